@@ -10,9 +10,10 @@ use indexmap::{indexmap, IndexMap};
 use proc_macro2::{Ident, Span, TokenStream};
 use proc_macro_kwargs::parse::{NestedDict, NestedList};
 use proc_macro_kwargs::{MacroArg, MacroKeywordArgs};
-use quote::quote;
+use quote::{quote, quote_spanned, ToTokens};
 use syn::ext::IdentExt;
 use syn::parse::ParseStream;
+use syn::spanned::Spanned;
 use syn::{
     braced, parse_quote, Error, Expr, GenericParam, Generics, Lifetime, Path, Token, Type,
     TypeParamBound, WhereClause, WherePredicate,
@@ -34,11 +35,20 @@ pub struct MacroInput {
     /// This has unconstrained use of the parameters defined in `params`
     #[kwarg(rename = "target")]
     pub target_type: Type,
-    /// The generic parameters (both types and lifetimes) that we want to
+    /// The generic parameters (types, lifetimes, and consts) that we want to
     /// declare for each implementation
     ///
+    /// Const parameters are passed through unchanged,
+    /// so they can be used by `collected_type` and `NEEDS_COLLECT`.
+    ///
     /// This must not conflict with our internal generic names ;)
     params: NestedList<GenericParam>,
+    /// An additional where clause, which applies to every implementation
+    ///
+    /// This is in addition to the bounds for each specific trait,
+    /// and can be omitted if no extra bounds are needed.
+    #[kwarg(optional)]
+    where_clause: Option<BracedWhereClause>,
     /// Custom bounds provided for each
     ///
     /// All of these bounds are optional.
@@ -80,6 +90,12 @@ impl MacroInput {
     fn basic_generics(&self) -> Generics {
         let mut generics = Generics::default();
         generics.params.extend(self.params.iter().cloned());
+        if let Some(ref extra) = self.where_clause {
+            generics
+                .make_where_clause()
+                .predicates
+                .extend(extra.0.predicates.iter().cloned());
+        }
         generics
     }
     fn setup_collector_id_generics(&self, target: &mut Generics) -> syn::Result<Path> {
@@ -90,6 +106,8 @@ impl MacroInput {
                 target.params.push(GenericParam::Type(parse_quote!(
                     #collector_id_param: #zerogc_next_crate::CollectorId
                 )));
+                // const parameters must remain after the new type parameter
+                helpers::sort_params(target);
                 Ok(syn::Path::from(collector_id_param))
             }
             CollectorIdInfo::Specific { ref map } => {
@@ -152,7 +170,9 @@ impl MacroInput {
         let mut generics: syn::Generics = self.basic_generics();
         let collector_id = self.setup_collector_id_generics(&mut generics)?;
         {
-            let clause = self.bounds.where_clause_collect(&self.params.elements)?;
+            let clause = self
+                .bounds
+                .where_clause_collect(&self.params.elements, &collector_id)?;
             generics
                 .make_where_clause()
                 .predicates
@@ -163,16 +183,20 @@ impl MacroInput {
         let (impl_generics, _, where_clause) = generics.split_for_impl();
         let needs_collect_const = {
             let expr = &self.needs_collect;
-            quote!(const NEEDS_TRACE: bool = {
+            quote_spanned!(expr.span() => const NEEDS_TRACE: bool = {
                 // Import the trait so we can access `T::NEEDS_COLLECT`
                 use #zerogc_next_crate::Collect;
                 #expr
             };)
         };
         let collected_type = &self.collected_type;
+        // errors about the projected type should point at its definition
+        let collected_type_decl = quote_spanned! { collected_type.span() =>
+            type Collected<'newgc> = #collected_type;
+        };
         Ok(quote! {
             unsafe impl #impl_generics #zerogc_next_crate::Collect<#collector_id> for #target_type #where_clause {
-                type Collected<'newgc> = #collected_type;
+                #collected_type_decl
                 #needs_collect_const
 
                 #[inline] // TODO: Should this be unconditional?
@@ -197,6 +221,7 @@ impl CustomBounds {
     fn where_clause_collect(
         &self,
         generic_params: &[GenericParam],
+        collector_id: &Path,
     ) -> Result<WhereClause, syn::Error> {
         match self.collect {
             Some(TraitRequirements::Never { span }) => {
@@ -205,12 +230,12 @@ impl CustomBounds {
             Some(TraitRequirements::Always { span: _ }) => Ok(empty_clause()), // No requirements
             Some(TraitRequirements::Where(ref explicit)) => Ok(explicit.clone()),
             None => {
-                // generate the implicit requirements
+                // generate the implicit requirements, requiring each type param to be collectable
                 let zerogc_next_crate = zerogc_next_crate();
                 Ok(create_clause_with_default(
                     &self.collect,
                     generic_params,
-                    vec![parse_quote!(#zerogc_next_crate::Collect)],
+                    vec![parse_quote!(#zerogc_next_crate::Collect<#collector_id>)],
                 )
                 .expect("Already checked for TraitRequirements::Never"))
             }
@@ -258,6 +283,17 @@ impl MacroArg for TraitRequirements {
     }
 }
 
+/// A where clause wrapped in braces, like `{ where T: Clone }`
+#[derive(Clone, Debug)]
+pub struct BracedWhereClause(WhereClause);
+impl MacroArg for BracedWhereClause {
+    fn parse_macro_arg(input: ParseStream) -> syn::Result<Self> {
+        let inner;
+        syn::braced!(inner in input);
+        Ok(BracedWhereClause(inner.parse::<WhereClause>()?))
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub enum CollectorIdInfo {
     #[default]
@@ -400,12 +436,23 @@ fn create_clause_with_default_and_ignored(
                 }
                 if let GenericParam::Type(ref t) = param {
                     let ident = &t.ident;
+                    // unsatisfied bounds should point at the parameter, not the macro invocation
+                    let span = ident.span();
                     where_clause
                         .predicates
                         .push(WherePredicate::Type(syn::PredicateType {
                             bounded_ty: parse_quote!(#ident),
-                            colon_token: Default::default(),
-                            bounds: default_bounds.iter().cloned().collect(),
+                            colon_token: Token![:](span),
+                            bounds: default_bounds
+                                .iter()
+                                .map(|bound| {
+                                    syn::parse2::<syn::TypeParamBound>(helpers::respan(
+                                        bound.to_token_stream(),
+                                        span,
+                                    ))
+                                    .expect("Respanning preserves bound")
+                                })
+                                .collect(),
                             lifetimes: None,
                         }))
                 }
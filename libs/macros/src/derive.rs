@@ -0,0 +1,411 @@
+//! The implementation of `#[derive(Collect)]`
+//!
+//! The collector id can be specified with `#[collect(collector_id = Path)]`.
+//! The placement hint can be specified with `#[collect(placement = Old)]`.
+//! Otherwise, it is taken from a type parameter named `Id` if present,
+//! or else the type implements `Collect` for every collector.
+//! The `'gc` lifetime (if any) is replaced by `'newgc` in the collected type,
+//! and each type parameter `T` is replaced by `T::Collected<'newgc>`.
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::spanned::Spanned;
+use syn::{
+    parse_quote, parse_quote_spanned, Attribute, Data, DeriveInput, Error, Fields, GenericArgument,
+    GenericParam, Member, Path, Token, Type,
+};
+
+use crate::helpers::{self, zerogc_next_crate};
+
+/// How a field is treated by the collector
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum FieldMode {
+    /// The field is traced
+    Trace,
+    /// `#[collect(skip)]`: The field is not traced,
+    /// so it must be `NullCollect` and must not mention the `'gc` lifetime
+    Skip,
+    /// `#[collect(unsafe_skip)]`: The field is not traced,
+    /// without checking that it is `NullCollect` or independent of `'gc`
+    UnsafeSkip,
+}
+
+struct FieldInfo {
+    member: Member,
+    ty: Type,
+    mode: FieldMode,
+}
+impl FieldInfo {
+    fn parse_all(fields: &Fields) -> syn::Result<Vec<FieldInfo>> {
+        let mut errors = Vec::new();
+        let mut result = Vec::new();
+        for (index, field) in fields.iter().enumerate() {
+            let mut mode = FieldMode::Trace;
+            for attr in &field.attrs {
+                if !attr.path().is_ident("collect") {
+                    continue;
+                }
+                let res = attr.parse_nested_meta(|meta| {
+                    let new_mode = if meta.path.is_ident("skip") {
+                        FieldMode::Skip
+                    } else if meta.path.is_ident("unsafe_skip") {
+                        FieldMode::UnsafeSkip
+                    } else {
+                        return Err(meta.error("Unknown `collect` attribute"));
+                    };
+                    if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+                        return Err(meta.error("Expected `skip` or `unsafe_skip` without a value"));
+                    }
+                    if mode != FieldMode::Trace {
+                        return Err(meta.error("Field is already skipped"));
+                    }
+                    mode = new_mode;
+                    Ok(())
+                });
+                if let Err(e) = res {
+                    errors.push(e);
+                }
+            }
+            let member = match field.ident {
+                Some(ref ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(index.into()),
+            };
+            result.push(FieldInfo {
+                member,
+                ty: field.ty.clone(),
+                mode,
+            });
+        }
+        helpers::combine_errors(errors)?;
+        Ok(result)
+    }
+}
+
+/// The attributes on the type itself
+#[derive(Default)]
+struct ContainerAttrs {
+    /// `#[collect(collector_id = Path)]`
+    collector_id: Option<Path>,
+    /// `#[collect(placement = Old)]` or `#[collect(placement = Young)]`
+    placement: Option<Ident>,
+}
+impl ContainerAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<ContainerAttrs> {
+        let mut result = ContainerAttrs::default();
+        for attr in attrs {
+            if !attr.path().is_ident("collect") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("collector_id") {
+                    if result.collector_id.is_some() {
+                        return Err(meta.error("Duplicate `collector_id`"));
+                    }
+                    result.collector_id = Some(meta.value()?.parse::<Path>()?);
+                    Ok(())
+                } else if meta.path.is_ident("placement") {
+                    if result.placement.is_some() {
+                        return Err(meta.error("Duplicate `placement`"));
+                    }
+                    let placement = meta.value()?.parse::<Ident>()?;
+                    if placement != "Old" && placement != "Young" {
+                        return Err(Error::new(
+                            placement.span(),
+                            "Expected `placement = Old` or `placement = Young`",
+                        ));
+                    }
+                    result.placement = Some(placement);
+                    Ok(())
+                } else {
+                    Err(meta.error("Unknown `collect` attribute"))
+                }
+            })?;
+        }
+        Ok(result)
+    }
+}
+
+/// Check if the tokens mention the specified lifetime
+fn mentions_lifetime(tokens: TokenStream, lifetime: &Ident) -> bool {
+    let mut prev_apostrophe = false;
+    for tt in tokens {
+        match tt {
+            TokenTree::Punct(ref p) if p.as_char() == '\'' => {
+                prev_apostrophe = true;
+                continue;
+            }
+            TokenTree::Ident(ref ident) if prev_apostrophe && ident == lifetime => return true,
+            TokenTree::Group(ref group) if mentions_lifetime(group.stream(), lifetime) => {
+                return true
+            }
+            _ => {}
+        }
+        prev_apostrophe = false;
+    }
+    false
+}
+
+/// Check if the tokens mention any of the specified type parameters
+fn mentions_type_param(tokens: TokenStream, params: &[Ident]) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        TokenTree::Ident(ref ident) => params.contains(ident),
+        TokenTree::Group(ref group) => mentions_type_param(group.stream(), params),
+        _ => false,
+    })
+}
+
+/// A pattern and the fields it binds, for either a struct or an enum variant
+struct Variant {
+    path: TokenStream,
+    fields: Vec<FieldInfo>,
+}
+impl Variant {
+    fn binding(index: usize) -> Ident {
+        format_ident!("__field_{}", index)
+    }
+
+    fn traced_fields(&self) -> impl Iterator<Item = (usize, &FieldInfo)> + '_ {
+        self.fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| field.mode == FieldMode::Trace)
+    }
+
+    fn expand_arm(&self, collector_id: &TokenStream) -> TokenStream {
+        let zerogc_next_crate = zerogc_next_crate();
+        let path = &self.path;
+        let members = self.traced_fields().map(|(_, field)| &field.member);
+        let bindings = self
+            .traced_fields()
+            .map(|(index, _)| Self::binding(index))
+            .collect::<Vec<_>>();
+        // errors about each field should point at its type
+        let trace_fields = self
+            .traced_fields()
+            .zip(&bindings)
+            .map(|((_, field), binding)| {
+                let ty = &field.ty;
+                quote_spanned! { ty.span() =>
+                    if <#ty as #zerogc_next_crate::Collect<#collector_id>>::NEEDS_COLLECT {
+                        <#ty as #zerogc_next_crate::Collect<#collector_id>>::collect_inplace(
+                            std::ptr::NonNull::from(#binding),
+                            context,
+                        );
+                    }
+                }
+            });
+        quote! {
+            #path { #(#members: #bindings,)* .. } => {
+                #(#trace_fields)*
+            }
+        }
+    }
+}
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let zerogc_next_crate = zerogc_next_crate();
+    let name = &input.ident;
+    let variants = match input.data {
+        Data::Struct(ref data) => vec![Variant {
+            path: quote!(Self),
+            fields: FieldInfo::parse_all(&data.fields)?,
+        }],
+        Data::Enum(ref data) => data
+            .variants
+            .iter()
+            .map(|variant| {
+                let variant_name = &variant.ident;
+                Ok(Variant {
+                    path: quote!(Self::#variant_name),
+                    fields: FieldInfo::parse_all(&variant.fields)?,
+                })
+            })
+            .collect::<syn::Result<Vec<_>>>()?,
+        Data::Union(ref data) => {
+            return Err(Error::new(
+                data.union_token.span,
+                "Unions cannot derive `Collect`",
+            ))
+        }
+    };
+    let gc_lifetime = input
+        .generics
+        .lifetimes()
+        .find(|def| def.lifetime.ident == "gc");
+    // check skipped fields are independent of 'gc
+    if let Some(gc_lifetime) = gc_lifetime {
+        let mut errors = Vec::new();
+        for field in variants.iter().flat_map(|variant| &variant.fields) {
+            if field.mode == FieldMode::Skip
+                && mentions_lifetime(field.ty.to_token_stream(), &gc_lifetime.lifetime.ident)
+            {
+                errors.push(Error::new_spanned(
+                    &field.ty,
+                    "Skipped field must not depend on 'gc (use `unsafe_skip` to override)",
+                ));
+            }
+        }
+        helpers::combine_errors(errors)?;
+    }
+    let container_attrs = ContainerAttrs::parse(&input.attrs)?;
+    let explicit_collector_id = container_attrs.collector_id;
+    let own_collector_id = input
+        .generics
+        .type_params()
+        .any(|param| param.ident == "Id");
+    let collector_id = match explicit_collector_id {
+        Some(ref path) => quote!(#path),
+        None if own_collector_id => quote!(Id),
+        None => quote!(AnyCollectorId),
+    };
+    // determine the generics of the impl
+    let mut generics = input.generics.clone();
+    if explicit_collector_id.is_none() && !own_collector_id {
+        generics.params.push(GenericParam::Type(parse_quote!(
+            #collector_id: #zerogc_next_crate::CollectorId
+        )));
+        helpers::sort_params(&mut generics);
+    }
+    let type_params = input
+        .generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .filter(|ident| ident != "Id")
+        .collect::<Vec<_>>();
+    let mut null_collect_generics = generics.clone();
+    // bounding field types directly would only apply to 'gc, not 'newgc
+    {
+        let where_clause = generics.make_where_clause();
+        for param in &type_params {
+            where_clause
+                .predicates
+                .push(parse_quote!(#param: #zerogc_next_crate::Collect<#collector_id>));
+        }
+        // skipped fields are independent of 'gc, so generic ones can be bounded directly
+        for field in variants.iter().flat_map(|variant| &variant.fields) {
+            if field.mode == FieldMode::Skip
+                && mentions_type_param(field.ty.to_token_stream(), &type_params)
+            {
+                let ty = &field.ty;
+                where_clause
+                    .predicates
+                    .push(parse_quote_spanned!(ty.span() =>
+                        #ty: #zerogc_next_crate::NullCollect<#collector_id>
+                    ));
+            }
+        }
+    }
+    // NullCollect is implemented whenever every field is NullCollect,
+    // including fields which skip tracing unsafely
+    {
+        let where_clause = null_collect_generics.make_where_clause();
+        for param in &type_params {
+            where_clause
+                .predicates
+                .push(parse_quote!(#param: #zerogc_next_crate::NullCollect<#collector_id>));
+        }
+        for field in variants.iter().flat_map(|variant| &variant.fields) {
+            let ty = &field.ty;
+            // higher-ranked to avoid errors from bounds which are trivially false
+            where_clause.predicates.push(parse_quote!(
+                for<'__trivial> #ty: #zerogc_next_crate::NullCollect<#collector_id>
+            ));
+        }
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (null_impl_generics, _, null_where_clause) = null_collect_generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    // the collected type replaces 'gc and each type parameter
+    let collected_args = input
+        .generics
+        .params
+        .iter()
+        .map(|param| -> GenericArgument {
+            match param {
+                GenericParam::Lifetime(def) if def.lifetime.ident == "gc" => {
+                    parse_quote!('newgc)
+                }
+                GenericParam::Lifetime(def) => GenericArgument::Lifetime(def.lifetime.clone()),
+                GenericParam::Type(param) if param.ident == "Id" => parse_quote!(Id),
+                GenericParam::Type(param) => {
+                    let ident = &param.ident;
+                    parse_quote!(<#ident as #zerogc_next_crate::Collect<#collector_id>>::Collected<'newgc>)
+                }
+                GenericParam::Const(param) => {
+                    let ident = &param.ident;
+                    parse_quote!(#ident)
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    let collected_type = if collected_args.is_empty() {
+        quote!(#name)
+    } else {
+        quote!(#name<#(#collected_args),*>)
+    };
+    // concrete skipped fields are checked in the body, so errors point at the field itself
+    let skip_assertions = variants
+        .iter()
+        .flat_map(|variant| &variant.fields)
+        .filter(|field| {
+            field.mode == FieldMode::Skip
+                && !mentions_type_param(field.ty.to_token_stream(), &type_params)
+        })
+        .map(|field| {
+            let ty = &field.ty;
+            quote_spanned! { ty.span() =>
+                __assert_null_collect::<#ty, #collector_id>();
+            }
+        })
+        .collect::<Vec<_>>();
+    let skip_assertions = if skip_assertions.is_empty() {
+        None
+    } else {
+        Some(quote! {
+            fn __assert_null_collect<T, Id>()
+            where
+                T: #zerogc_next_crate::NullCollect<Id> + ?Sized,
+                Id: #zerogc_next_crate::CollectorId,
+            {
+            }
+            #(#skip_assertions)*
+        })
+    };
+    let traced_types = variants
+        .iter()
+        .flat_map(|variant| variant.traced_fields().map(|(_, field)| &field.ty));
+    let arms = variants
+        .iter()
+        .map(|variant| variant.expand_arm(&collector_id));
+    let body = if variants.is_empty() {
+        // an empty enum can never be constructed
+        quote!(match *target.as_ptr() {})
+    } else {
+        quote!(match &mut *target.as_ptr() { #(#arms)* })
+    };
+    let placement_const = container_attrs.placement.map(|placement| {
+        quote! {
+            const PLACEMENT: #zerogc_next_crate::PlacementHint = #zerogc_next_crate::PlacementHint::#placement;
+        }
+    });
+    Ok(quote! {
+        unsafe impl #impl_generics #zerogc_next_crate::Collect<#collector_id> for #name #ty_generics #where_clause {
+            type Collected<'newgc> = #collected_type;
+            const NEEDS_COLLECT: bool = false #(|| <#traced_types as #zerogc_next_crate::Collect<#collector_id>>::NEEDS_COLLECT)*;
+            #placement_const
+
+            #[inline]
+            #[allow(unused_variables)] // possible if there are no traced fields
+            unsafe fn collect_inplace(
+                target: std::ptr::NonNull<Self>,
+                context: &mut #zerogc_next_crate::context::CollectContext<'_, #collector_id>,
+            ) {
+                #skip_assertions
+                if !<Self as #zerogc_next_crate::Collect<#collector_id>>::NEEDS_COLLECT {
+                    return;
+                }
+                #body
+            }
+        }
+        unsafe impl #null_impl_generics #zerogc_next_crate::NullCollect<#collector_id> for #name #ty_generics #null_where_clause {}
+    })
+}
@@ -1,5 +1,5 @@
 //! Helpers for macros, potentially shared across implementations.
-use proc_macro2::TokenStream;
+use proc_macro2::{Span, TokenStream, TokenTree};
 use quote::quote;
 use syn::spanned::Spanned;
 use syn::{Error, GenericArgument, Generics, Type};
@@ -105,6 +105,25 @@ pub fn rewrite_type(
     Ok(target)
 }
 
+/// Replace the span of every token,
+/// so that errors in generated code point at the relevant input.
+pub fn respan(tokens: TokenStream, span: Span) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|mut tt| {
+            if let TokenTree::Group(ref group) = tt {
+                let mut new_group =
+                    proc_macro2::Group::new(group.delimiter(), respan(group.stream(), span));
+                new_group.set_span(span);
+                tt = TokenTree::Group(new_group);
+            } else {
+                tt.set_span(span);
+            }
+            tt
+        })
+        .collect()
+}
+
 /// This refers to the zerogc_next crate,
 /// equivalent to `$crate` for proc_macros
 pub fn zerogc_next_crate() -> TokenStream {
@@ -113,7 +132,7 @@ pub fn zerogc_next_crate() -> TokenStream {
      *
      * Instead we re-export `extern crate self as zerogc_next` at the root of the zerogc_next crate.
      */
-    quote!(zerogc_next::)
+    quote!(zerogc_next)
 }
 
 // Sort the parameters so that lifetime parameters come before
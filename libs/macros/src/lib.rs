@@ -1,4 +1,5 @@
 mod collect_impl;
+mod derive;
 pub(crate) mod helpers;
 
 #[proc_macro]
@@ -9,3 +10,34 @@ pub fn unsafe_collect_impl(input: proc_macro::TokenStream) -> proc_macro::TokenS
         .unwrap_or_else(|e| e.to_compile_error());
     res.into()
 }
+
+/// Derive `Collect` for a struct or enum, tracing each of its fields.
+///
+/// Fields can be excluded from tracing with `#[collect(skip)]`,
+/// which requires their type to be `NullCollect` and independent of the `'gc` lifetime.
+/// Use `#[collect(unsafe_skip)]` to override these checks.
+///
+/// Skipping a field which could contain garbage collected pointers is an error:
+/// ```compile_fail,E0277
+/// # use std::cell::RefCell;
+/// # use zerog_next_macros::Collect;
+/// # use zerogc_next::{CollectorId, GcString};
+/// #[derive(Collect)]
+/// struct Node<'gc, Id: CollectorId> {
+///     name: GcString<'gc, Id>,
+///     #[collect(skip)]
+///     cache: RefCell<Vec<u32>>,
+/// }
+/// ```
+///
+/// If every field is `NullCollect`, the type also implements `NullCollect`.
+///
+/// The collector id can be fixed with `#[collect(collector_id = Path)]` on the type itself.
+/// Types known to be long-lived can be allocated directly in the old generation
+/// with `#[collect(placement = Old)]`, which overrides `Collect::PLACEMENT`.
+#[proc_macro_derive(Collect, attributes(collect))]
+pub fn derive_collect(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let parsed = syn::parse_macro_input!(input as syn::DeriveInput);
+    let res = derive::expand(&parsed).unwrap_or_else(|e| e.to_compile_error());
+    res.into()
+}
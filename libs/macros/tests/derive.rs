@@ -0,0 +1,8 @@
+#[test]
+fn skip() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/skip.rs");
+    cases.compile_fail("tests/ui/skip_gc_lifetime.rs");
+    cases.compile_fail("tests/ui/skip_twice.rs");
+    cases.compile_fail("tests/ui/skip_value.rs");
+}
@@ -0,0 +1,33 @@
+use zerog_next_macros::Collect;
+use zerogc_next::{CollectorId, GarbageCollector, Gc, GcString};
+
+#[derive(Collect)]
+struct Node<'gc, Id: CollectorId> {
+    name: GcString<'gc, Id>,
+    #[collect(skip)]
+    label: String,
+}
+
+#[derive(Collect)]
+struct Pair<'gc, Id: CollectorId> {
+    left: Gc<'gc, Node<'gc, Id>, Id>,
+    right: Gc<'gc, Node<'gc, Id>, Id>,
+}
+
+fn main() {
+    GarbageCollector::scoped(|gc| {
+        let left = gc.alloc(Node {
+            name: gc.alloc_str("left"),
+            label: "x".into(),
+        });
+        let right = gc.alloc(Node {
+            name: gc.alloc_str("right"),
+            label: "y".into(),
+        });
+        let pair = gc.root(gc.alloc(Pair { left, right }));
+        gc.force_collect();
+        let pair = pair.resolve(gc);
+        assert_eq!(pair.left.label, "x");
+        assert_eq!(pair.right.name.as_str(), "right");
+    });
+}
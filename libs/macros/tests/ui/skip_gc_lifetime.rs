@@ -0,0 +1,11 @@
+use zerog_next_macros::Collect;
+use zerogc_next::{CollectorId, Gc};
+
+#[derive(Collect)]
+struct Node<'gc, Id: CollectorId> {
+    value: Gc<'gc, u32, Id>,
+    #[collect(skip)]
+    parent: Gc<'gc, u32, Id>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use zerog_next_macros::Collect;
+
+#[derive(Collect)]
+struct Node {
+    value: u32,
+    #[collect(skip, unsafe_skip)]
+    cache: Vec<u32>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use zerog_next_macros::Collect;
+
+#[derive(Collect)]
+struct Node {
+    value: u32,
+    #[collect(skip = true)]
+    cache: Vec<u32>,
+}
+
+fn main() {}
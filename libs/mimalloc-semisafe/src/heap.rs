@@ -54,6 +54,18 @@ impl MimallocHeap {
         self.raw.as_ptr()
     }
 
+    /// Free memory allocated by a heap, without access to the heap itself.
+    ///
+    /// Unlike allocation, mimalloc supports freeing memory from any thread.
+    ///
+    /// ## Safety
+    /// The pointer must have been allocated by a [`MimallocHeap`] with the specified layout.
+    /// The heap must not be destroyed until this call returns.
+    #[inline]
+    pub unsafe fn deallocate_detached(ptr: NonNull<u8>, layout: Layout) {
+        sys::mi_free_size_aligned(ptr.as_ptr() as *mut c_void, layout.size(), layout.align())
+    }
+
     #[inline]
     unsafe fn alloc_from_raw_ptr(ptr: *mut u8, size: usize) -> Result<NonNull<[u8]>, AllocError> {
         if ptr.is_null() {
@@ -0,0 +1,313 @@
+//! Arrays allocated in the GC heap.
+//!
+//! The length of an array is stored in its header,
+//! so a [`GcArray`] is only a single pointer wide.
+//!
+//! Like regular objects, array elements may be over-aligned.
+//! This allows storing SIMD vectors from [`core::arch`] in the GC heap.
+
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::ops::{Bound, Deref, RangeBounds};
+use std::ptr::NonNull;
+
+use crate::cell::GcCell;
+use crate::context::layout::GcArrayHeader;
+use crate::context::{GcArrayLayoutError, TryReserveError};
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector};
+
+mod bytes;
+mod slice;
+mod string;
+
+pub use self::bytes::{GcBytes, GcPinnedBytes};
+pub use self::slice::GcSlice;
+pub use self::string::GcString;
+pub(crate) use self::string::{FixedWriter, LengthCounter};
+
+/// Resolve a range relative to a view of length `len`,
+/// panicking if it is out of bounds.
+#[track_caller]
+fn resolve_range(range: impl RangeBounds<usize>, len: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.checked_add(1).expect("range start overflow"),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.checked_add(1).expect("range end overflow"),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    assert!(
+        start <= end && end <= len,
+        "range {start}..{end} out of bounds for length {len}"
+    );
+    (start, end - start)
+}
+
+/// A pointer to an array of elements allocated in the GC heap.
+///
+/// This points to the first element of the array.
+pub struct GcArray<'gc, T, Id: CollectorId> {
+    ptr: NonNull<T>,
+    marker: PhantomData<*const T>,
+    collect_marker: PhantomData<&'gc GarbageCollector<Id>>,
+}
+impl<'gc, T, Id: CollectorId> GcArray<'gc, T, Id> {
+    #[inline]
+    pub fn id(&self) -> Id {
+        match unsafe { Id::summon_singleton() } {
+            None => self.header().main_header().id(),
+            Some(id) => id,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn header(&self) -> &'gc GcArrayHeader<Id> {
+        unsafe { GcArrayHeader::from_value_ptr(self.ptr.cast()) }
+    }
+
+    /// The number of elements in the array.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.header().len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &'gc [T] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len()) }
+    }
+
+    /// A raw pointer to the first element of the array.
+    ///
+    /// This is invalidated if the array moves during a collection.
+    #[inline(always)]
+    pub fn as_ptr(&self) -> NonNull<T> {
+        self.ptr
+    }
+
+    /// View a range of the array's elements, without copying.
+    ///
+    /// ## Panics
+    /// If the range is out of bounds.
+    #[inline]
+    #[track_caller]
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> GcSlice<'gc, T, Id> {
+        GcSlice::from_array(*self).slice(range)
+    }
+
+    crate::utils::unsafe_api! {
+        /// Create an array from a pointer to its first element.
+        ///
+        /// Only public with the `unsafe-api` feature.
+        ///
+        /// ## Safety
+        /// The pointer must have come from [`GcArray::as_ptr`],
+        /// and must be valid for the lifetime `'gc`.
+        #[inline(always)]
+        pub unsafe fn from_raw_ptr(ptr: NonNull<T>) -> Self {
+            GcArray {
+                ptr,
+                marker: PhantomData,
+                collect_marker: PhantomData,
+            }
+        }
+    }
+}
+impl<'gc, T: Collect<Id>, Id: CollectorId> GcArray<'gc, T, Id> {
+    /// Check that the heap has room to grow this array by `additional` elements,
+    /// returning a recoverable error if it does not.
+    ///
+    /// This mirrors [`Vec::try_reserve`].
+    /// Arrays grow by copying into a new allocation,
+    /// so this checks for room to allocate an entire array of the new length.
+    /// See [`GarbageCollector::try_reserve`] for details.
+    #[inline]
+    pub fn try_reserve(
+        &self,
+        collector: &GarbageCollector<Id>,
+        additional: usize,
+    ) -> Result<(), TryReserveError> {
+        let new_len = self
+            .len()
+            .checked_add(additional)
+            .ok_or(TryReserveError::InvalidLayout(
+                GcArrayLayoutError::ArraySizeOverflow,
+            ))?;
+        collector.try_reserve_array::<T>(new_len)
+    }
+}
+impl<'gc, T: Collect<Id>, Id: CollectorId> GcArray<'gc, GcCell<T>, Id> {
+    /// Replace the element at the specified index, performing the write barrier.
+    ///
+    /// ## Panics
+    /// If the index is out of bounds, or if the array is [frozen](crate::Gc::freeze).
+    #[inline]
+    #[track_caller]
+    pub fn set(&self, collector: &'gc GarbageCollector<Id>, index: usize, value: T) {
+        let len = self.len();
+        assert!(index < len, "index {index} out of bounds for length {len}");
+        self.begin_mutation(collector);
+        unsafe {
+            self.as_slice()[index].set_unchecked(value);
+            self.write_barrier_range(collector, index, 1);
+        }
+    }
+
+    /// Copy a range of elements to another position in the array,
+    /// which may overlap the source, like [`slice::copy_within`].
+    ///
+    /// This is a single `memmove`, followed by the write barrier for each element.
+    ///
+    /// ## Panics
+    /// If either range is out of bounds, or if the array is [frozen](crate::Gc::freeze).
+    #[track_caller]
+    pub fn copy_within(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+        src: impl RangeBounds<usize>,
+        dest: usize,
+    ) where
+        T: Copy,
+    {
+        let len = self.len();
+        let (start, count) = resolve_range(src, len);
+        assert!(
+            dest <= len - count,
+            "destination {dest} out of bounds for {count} elements and length {len}"
+        );
+        self.begin_mutation(collector);
+        unsafe {
+            let elements = self.as_ptr().cast::<T>();
+            std::ptr::copy(
+                elements.add(start).as_ptr(),
+                elements.add(dest).as_ptr(),
+                count,
+            );
+            self.write_barrier_range(collector, dest, count);
+        }
+    }
+
+    /// Set every element of the array to the specified value.
+    ///
+    /// ## Panics
+    /// If the array is [frozen](crate::Gc::freeze).
+    #[track_caller]
+    pub fn fill(&self, collector: &'gc GarbageCollector<Id>, value: T)
+    where
+        T: Copy,
+    {
+        self.begin_mutation(collector);
+        unsafe {
+            let elements = self.as_ptr().cast::<T>();
+            std::slice::from_raw_parts_mut(elements.as_ptr(), self.len()).fill(value);
+            self.write_barrier_range(collector, 0, self.len());
+        }
+    }
+
+    /// Copy every element from a slice of the same length,
+    /// using a single `memcpy`.
+    ///
+    /// ## Panics
+    /// If the lengths differ, or if the array is [frozen](crate::Gc::freeze).
+    #[track_caller]
+    pub fn copy_from_slice(&self, collector: &'gc GarbageCollector<Id>, src: &[T])
+    where
+        T: Copy,
+    {
+        assert_eq!(self.len(), src.len(), "source slice length mismatch");
+        self.begin_mutation(collector);
+        unsafe {
+            let elements = self.as_ptr().cast::<T>();
+            std::ptr::copy_nonoverlapping(src.as_ptr(), elements.as_ptr(), src.len());
+            self.write_barrier_range(collector, 0, src.len());
+        }
+    }
+
+    /// Clone every element from a slice of the same length,
+    /// dropping the previous elements.
+    ///
+    /// ## Panics
+    /// If the lengths differ, or if the array is [frozen](crate::Gc::freeze).
+    #[track_caller]
+    pub fn clone_from_slice(&self, collector: &'gc GarbageCollector<Id>, src: &[T])
+    where
+        T: Clone,
+    {
+        assert_eq!(self.len(), src.len(), "source slice length mismatch");
+        self.begin_mutation(collector);
+        for (index, value) in src.iter().enumerate() {
+            unsafe {
+                self.as_slice()[index].set_unchecked(value.clone());
+            }
+        }
+        unsafe { self.write_barrier_range(collector, 0, src.len()) }
+    }
+
+    /// ## Safety
+    /// The elements must have just been written with valid values.
+    #[inline]
+    unsafe fn write_barrier_range(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+        start: usize,
+        count: usize,
+    ) {
+        if T::NEEDS_COLLECT {
+            for index in start..start + count {
+                collector.write_barrier_array(*self, index);
+            }
+        }
+    }
+
+    /// Check that the array can be mutated,
+    /// rescanning it if marking is in progress.
+    #[inline]
+    #[track_caller]
+    fn begin_mutation(&self, collector: &'gc GarbageCollector<Id>) {
+        assert_eq!(self.id(), collector.id());
+        let header = self.header().main_header();
+        assert!(!header.is_frozen(), "Cannot mutate a frozen object");
+        if T::NEEDS_COLLECT {
+            collector.record_mutation(NonNull::from(header));
+        }
+    }
+}
+unsafe impl<'gc, Id: CollectorId, T: Collect<Id>> Collect<Id> for GcArray<'gc, T, Id> {
+    type Collected<'newgc> = GcArray<'newgc, T::Collected<'newgc>, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        if Id::SINGLETON.is_none() && target.as_ref().id() != context.id() {
+            return;
+        }
+        context.trace_gc_array_mut(target)
+    }
+}
+impl<'gc, T, Id: CollectorId> Deref for GcArray<'gc, T, Id> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+impl<'gc, T, Id: CollectorId> Copy for GcArray<'gc, T, Id> {}
+impl<'gc, T, Id: CollectorId> Clone for GcArray<'gc, T, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'gc, T: Debug, Id: CollectorId> Debug for GcArray<'gc, T, Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
@@ -0,0 +1,183 @@
+//! Byte buffers managed by the garbage collector.
+
+use std::fmt::{Debug, Formatter};
+use std::ops::{Deref, RangeBounds};
+use std::ptr::NonNull;
+
+use crate::array::{resolve_range, GcArray};
+use crate::context::RawPin;
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector};
+
+/// A buffer of bytes allocated in the GC heap.
+///
+/// This is a view into an underlying [`GcArray<u8>`](GcArray),
+/// so [subslicing](GcBytes::slice) does not copy.
+pub struct GcBytes<'gc, Id: CollectorId> {
+    array: GcArray<'gc, u8, Id>,
+    start: usize,
+    len: usize,
+}
+impl<'gc, Id: CollectorId> GcBytes<'gc, Id> {
+    /// View the entirety of the specified array.
+    #[inline]
+    pub fn from_array(array: GcArray<'gc, u8, Id>) -> Self {
+        GcBytes {
+            array,
+            start: 0,
+            len: array.len(),
+        }
+    }
+
+    /// The underlying array, which may be larger than this view.
+    #[inline]
+    pub fn array(&self) -> GcArray<'gc, u8, Id> {
+        self.array
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &'gc [u8] {
+        &self.array.as_slice()[self.start..][..self.len]
+    }
+
+    /// A raw pointer to the start of the bytes.
+    ///
+    /// This is invalidated if the bytes move during a collection,
+    /// so use [`GcBytes::pin`] to get a stable pointer.
+    #[inline]
+    pub fn as_ptr(&self) -> NonNull<u8> {
+        unsafe { self.array.as_ptr().add(self.start) }
+    }
+
+    /// Take a sub-slice of these bytes, without copying.
+    ///
+    /// ## Panics
+    /// If the range is out of bounds.
+    #[inline]
+    #[track_caller]
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+        let (start, len) = resolve_range(range, self.len);
+        GcBytes {
+            array: self.array,
+            start: self.start + start,
+            len,
+        }
+    }
+
+    /// Pin the underlying array, giving a stable pointer to the bytes.
+    ///
+    /// See [`GarbageCollector::pin`] for details.
+    #[inline]
+    pub fn pin(&self, collector: &'gc GarbageCollector<Id>) -> GcPinnedBytes<Id> {
+        assert_eq!(self.array.id(), collector.id());
+        GcPinnedBytes {
+            raw: unsafe { collector.pin_raw(NonNull::from(self.array.header().main_header())) },
+            start: self.start,
+            len: self.len,
+        }
+    }
+}
+unsafe impl<'gc, Id: CollectorId> Collect<Id> for GcBytes<'gc, Id> {
+    type Collected<'newgc> = GcBytes<'newgc, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        GcArray::collect_inplace(NonNull::from(&(*target.as_ptr()).array), context);
+    }
+}
+impl<'gc, Id: CollectorId> Deref for GcBytes<'gc, Id> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+impl<'gc, Id: CollectorId> Copy for GcBytes<'gc, Id> {}
+impl<'gc, Id: CollectorId> Clone for GcBytes<'gc, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'gc, Id: CollectorId> Debug for GcBytes<'gc, Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("GcBytes").field(&self.as_slice()).finish()
+    }
+}
+
+/// A pinned and rooted view of [`GcBytes`],
+/// whose address remains stable across collections.
+///
+/// This is suitable for passing buffers to foreign code,
+/// such as I/O routines.
+pub struct GcPinnedBytes<Id: CollectorId> {
+    raw: RawPin<Id>,
+    start: usize,
+    len: usize,
+}
+impl<Id: CollectorId> GcPinnedBytes<Id> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// A stable pointer to the start of the bytes.
+    #[inline]
+    pub fn as_ptr(&self) -> NonNull<u8> {
+        unsafe {
+            self.raw
+                .header()
+                .assume_array_header()
+                .array_value_ptr()
+                .add(self.start)
+        }
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &'_ [u8] {
+        unsafe { std::slice::from_raw_parts(self.as_ptr().as_ptr(), self.len) }
+    }
+
+    /// Resolve the pinned bytes into an ordinary [`GcBytes`].
+    #[inline]
+    pub fn resolve<'gc>(&self, collector: &'gc GarbageCollector<Id>) -> GcBytes<'gc, Id> {
+        assert_eq!(self.raw.header().id(), collector.id());
+        let array_ptr = unsafe { self.raw.header().assume_array_header().array_value_ptr() };
+        GcBytes {
+            array: unsafe { GcArray::from_raw_ptr(array_ptr) },
+            start: self.start,
+            len: self.len,
+        }
+    }
+}
+impl<Id: CollectorId> Deref for GcPinnedBytes<Id> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+impl<Id: CollectorId> Debug for GcPinnedBytes<Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("GcPinnedBytes")
+            .field(&self.as_slice())
+            .finish()
+    }
+}
@@ -0,0 +1,117 @@
+//! Views of a range of elements in a GC array.
+
+use std::fmt::{Debug, Formatter};
+use std::ops::{Deref, RangeBounds};
+use std::ptr::NonNull;
+
+use crate::array::{resolve_range, GcArray};
+use crate::{Collect, CollectContext, CollectorId};
+
+/// A view of a range of elements in an underlying [`GcArray`].
+///
+/// This keeps the entire array alive,
+/// and [subslicing](GcSlice::slice) does not copy.
+/// This is useful for substrings and subarrays in parsers,
+/// which would otherwise need to copy into a fresh array.
+pub struct GcSlice<'gc, T, Id: CollectorId> {
+    array: GcArray<'gc, T, Id>,
+    start: usize,
+    len: usize,
+}
+impl<'gc, T, Id: CollectorId> GcSlice<'gc, T, Id> {
+    /// View the entirety of the specified array.
+    #[inline]
+    pub fn from_array(array: GcArray<'gc, T, Id>) -> Self {
+        GcSlice {
+            array,
+            start: 0,
+            len: array.len(),
+        }
+    }
+
+    /// The underlying array, which may be larger than this view.
+    #[inline]
+    pub fn array(&self) -> GcArray<'gc, T, Id> {
+        self.array
+    }
+
+    /// The index in the underlying array where this view starts.
+    #[inline]
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &'gc [T] {
+        &self.array.as_slice()[self.start..][..self.len]
+    }
+
+    /// A raw pointer to the first element of the view.
+    ///
+    /// This is invalidated if the array moves during a collection.
+    #[inline]
+    pub fn as_ptr(&self) -> NonNull<T> {
+        unsafe { self.array.as_ptr().add(self.start) }
+    }
+
+    /// Take a sub-slice of this view, without copying.
+    ///
+    /// ## Panics
+    /// If the range is out of bounds.
+    #[inline]
+    #[track_caller]
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+        let (start, len) = resolve_range(range, self.len);
+        GcSlice {
+            array: self.array,
+            start: self.start + start,
+            len,
+        }
+    }
+}
+unsafe impl<'gc, Id: CollectorId, T: Collect<Id>> Collect<Id> for GcSlice<'gc, T, Id> {
+    type Collected<'newgc> = GcSlice<'newgc, T::Collected<'newgc>, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        // the range is relative to the array, so it remains valid after moving
+        GcArray::collect_inplace(NonNull::from(&(*target.as_ptr()).array), context);
+    }
+}
+impl<'gc, T, Id: CollectorId> Deref for GcSlice<'gc, T, Id> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+impl<'gc, T, Id: CollectorId> Copy for GcSlice<'gc, T, Id> {}
+impl<'gc, T, Id: CollectorId> Clone for GcSlice<'gc, T, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'gc, T, Id: CollectorId> From<GcArray<'gc, T, Id>> for GcSlice<'gc, T, Id> {
+    #[inline]
+    fn from(array: GcArray<'gc, T, Id>) -> Self {
+        GcSlice::from_array(array)
+    }
+}
+impl<'gc, T: Debug, Id: CollectorId> Debug for GcSlice<'gc, T, Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
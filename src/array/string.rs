@@ -0,0 +1,205 @@
+//! Strings allocated in the GC heap.
+
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::str::Utf8Error;
+
+use crate::array::GcArray;
+use crate::context::{GcArrayLayoutError, TryReserveError};
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector};
+
+/// An immutable UTF-8 string allocated in the GC heap.
+///
+/// This is a [`GcArray<u8>`](GcArray) which is known to contain valid UTF-8,
+/// so it derefs directly to a [`str`].
+pub struct GcString<'gc, Id: CollectorId> {
+    array: GcArray<'gc, u8, Id>,
+    /// The length which was validated,
+    /// since the array may later be [extended](crate::GarbageCollector::try_extend_array_with).
+    len: usize,
+}
+impl<'gc, Id: CollectorId> GcString<'gc, Id> {
+    /// Interpret an array of bytes as a string,
+    /// returning an error if it is not valid UTF-8.
+    #[inline]
+    pub fn from_utf8(array: GcArray<'gc, u8, Id>) -> Result<Self, Utf8Error> {
+        std::str::from_utf8(array.as_slice())?;
+        Ok(GcString {
+            array,
+            len: array.len(),
+        })
+    }
+
+    /// Interpret an array of bytes as a string, without checking that it is valid UTF-8.
+    ///
+    /// ## Safety
+    /// The array must contain valid UTF-8.
+    #[inline]
+    pub unsafe fn from_utf8_unchecked(array: GcArray<'gc, u8, Id>) -> Self {
+        GcString {
+            array,
+            len: array.len(),
+        }
+    }
+
+    /// The underlying array of UTF-8 bytes, which may be longer than the string.
+    #[inline]
+    pub fn array(&self) -> GcArray<'gc, u8, Id> {
+        self.array
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &'gc str {
+        unsafe { std::str::from_utf8_unchecked(&self.array.as_slice()[..self.len]) }
+    }
+
+    /// Check that the heap has room to grow this string by `additional` bytes,
+    /// returning a recoverable error if it does not.
+    ///
+    /// This mirrors [`String::try_reserve`].
+    /// Strings are immutable, so this checks for room to allocate an entire string of the new length.
+    /// See [`GarbageCollector::try_reserve`] for details.
+    #[inline]
+    pub fn try_reserve(
+        &self,
+        collector: &GarbageCollector<Id>,
+        additional: usize,
+    ) -> Result<(), TryReserveError> {
+        let new_len = self
+            .len
+            .checked_add(additional)
+            .ok_or(TryReserveError::InvalidLayout(
+                GcArrayLayoutError::ArraySizeOverflow,
+            ))?;
+        collector.try_reserve_array::<u8>(new_len)
+    }
+}
+unsafe impl<'gc, Id: CollectorId> Collect<Id> for GcString<'gc, Id> {
+    type Collected<'newgc> = GcString<'newgc, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        GcArray::collect_inplace(NonNull::from(&(*target.as_ptr()).array), context);
+    }
+}
+impl<'gc, Id: CollectorId> Deref for GcString<'gc, Id> {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+impl<'gc, Id: CollectorId> Copy for GcString<'gc, Id> {}
+impl<'gc, Id: CollectorId> Clone for GcString<'gc, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'gc, Id: CollectorId> PartialEq for GcString<'gc, Id> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+impl<'gc, Id: CollectorId> Eq for GcString<'gc, Id> {}
+impl<'gc, Id: CollectorId> PartialEq<str> for GcString<'gc, Id> {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+impl<'gc, Id: CollectorId> PartialEq<&str> for GcString<'gc, Id> {
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+impl<'gc, Id: CollectorId> Hash for GcString<'gc, Id> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+impl<'gc, Id: CollectorId> Display for GcString<'gc, Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self.as_str(), f)
+    }
+}
+impl<'gc, Id: CollectorId> Debug for GcString<'gc, Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// Allocate a [`GcString`] by formatting arguments directly into GC memory.
+///
+/// This expands to a call to [`GarbageCollector::alloc_fmt`](crate::GarbageCollector::alloc_fmt),
+/// taking the collector followed by the same arguments as [`format!`].
+///
+/// ```
+/// # use zerogc_next::{alloc_format, GarbageCollector};
+/// GarbageCollector::scoped(|gc| {
+///     let x = 5;
+///     let s = alloc_format!(gc, "x={}", x);
+///     assert_eq!(s, "x=5");
+/// });
+/// ```
+#[macro_export]
+macro_rules! alloc_format {
+    ($collector:expr, $($arg:tt)*) => {
+        ($collector).alloc_fmt(::std::format_args!($($arg)*))
+    };
+}
+
+/// Measures the length of formatted output, without writing it anywhere.
+pub(crate) struct LengthCounter(pub usize);
+impl fmt::Write for LengthCounter {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// Writes formatted output into a fixed-size buffer,
+/// failing instead of overflowing it.
+pub(crate) struct FixedWriter {
+    ptr: NonNull<u8>,
+    capacity: usize,
+    len: usize,
+}
+impl FixedWriter {
+    /// ## Safety
+    /// The pointer must be valid for writing `capacity` bytes.
+    #[inline]
+    pub unsafe fn new(ptr: NonNull<u8>, capacity: usize) -> Self {
+        FixedWriter {
+            ptr,
+            capacity,
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+impl fmt::Write for FixedWriter {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.len() > self.capacity - self.len {
+            return Err(fmt::Error);
+        }
+        unsafe {
+            std::ptr::copy_nonoverlapping(s.as_ptr(), self.ptr.as_ptr().add(self.len), s.len());
+        }
+        self.len += s.len();
+        Ok(())
+    }
+}
@@ -0,0 +1,387 @@
+//! Mutable cells which can be stored in the GC heap.
+//!
+//! Storing a GC pointer into an existing object requires a [write barrier](GarbageCollector::write_barrier).
+//! These cells perform the barrier automatically,
+//! so they can be used without any unsafe code.
+
+use std::cell::{BorrowError, BorrowMutError, Cell, OnceCell, Ref, RefCell, RefMut};
+use std::fmt::{Debug, Formatter};
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+use crate::collect::IsGcPointer;
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector, Gc, NullCollect};
+
+/// A [`RefCell`] which can be stored in a GC object.
+///
+/// Borrows are checked dynamically, exactly like a regular `RefCell`.
+/// Mutable borrows must go through the containing [`Gc`] pointer,
+/// so that the write barrier can be performed when the borrow ends.
+pub struct GcRefCell<T> {
+    value: RefCell<T>,
+}
+impl<T> GcRefCell<T> {
+    #[inline]
+    pub fn new(value: T) -> Self {
+        GcRefCell {
+            value: RefCell::new(value),
+        }
+    }
+
+    /// Immutably borrow the value.
+    ///
+    /// ## Panics
+    /// If the value is currently mutably borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn borrow(&self) -> Ref<'_, T> {
+        self.value.borrow()
+    }
+
+    /// Immutably borrow the value,
+    /// returning an error if it is currently mutably borrowed.
+    #[inline]
+    pub fn try_borrow(&self) -> Result<Ref<'_, T>, BorrowError> {
+        self.value.try_borrow()
+    }
+
+    /// Mutably borrow the value,
+    /// performing the write barrier once the borrow is released.
+    ///
+    /// If marking is in progress, the containing object is also rescanned.
+    ///
+    /// ## Panics
+    /// If the value is currently borrowed, or if the containing object is [frozen](Gc::freeze).
+    #[inline]
+    #[track_caller]
+    pub fn borrow_mut<'a, 'gc, Id: CollectorId>(
+        this: &'a Gc<'gc, Self, Id>,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> GcRefMut<'a, 'gc, T, Id>
+    where
+        T: Collect<Id>,
+    {
+        match Self::try_borrow_mut(this, collector) {
+            Ok(borrow) => borrow,
+            Err(cause) => panic!("{cause}"),
+        }
+    }
+
+    /// Mutably borrow the value,
+    /// returning an error if it is currently borrowed.
+    ///
+    /// See [`Self::borrow_mut`] for details.
+    #[track_caller]
+    pub fn try_borrow_mut<'a, 'gc, Id: CollectorId>(
+        this: &'a Gc<'gc, Self, Id>,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> Result<GcRefMut<'a, 'gc, T, Id>, BorrowMutError>
+    where
+        T: Collect<Id>,
+    {
+        assert!(!this.is_frozen(collector), "Cannot mutate a frozen object");
+        let inner = this.value.try_borrow_mut()?;
+        // only a successful borrow can mutate the value
+        if T::NEEDS_COLLECT {
+            collector.record_mutation(NonNull::from(this.header()));
+        }
+        Ok(GcRefMut {
+            inner,
+            container: *this,
+            collector,
+        })
+    }
+
+    /// Get a mutable reference to the value.
+    ///
+    /// This needs no write barrier, as unique access implies the cell is not in the GC heap.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+unsafe impl<Id: CollectorId, T: Collect<Id>> Collect<Id> for GcRefCell<T> {
+    type Collected<'newgc> = GcRefCell<T::Collected<'newgc>>;
+    const NEEDS_COLLECT: bool = T::NEEDS_COLLECT;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        // borrows cannot outlive 'gc, so the value is never borrowed during a collection
+        T::collect_inplace(
+            NonNull::new_unchecked(target.as_ref().value.as_ptr()),
+            context,
+        );
+    }
+}
+unsafe impl<Id: CollectorId, T: NullCollect<Id>> NullCollect<Id> for GcRefCell<T> {}
+impl<T: Debug> Debug for GcRefCell<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GcRefCell")
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+/// A mutable borrow of a [`GcRefCell`],
+/// which performs the write barrier when dropped.
+pub struct GcRefMut<'a, 'gc, T: Collect<Id>, Id: CollectorId> {
+    inner: RefMut<'a, T>,
+    container: Gc<'gc, GcRefCell<T>, Id>,
+    collector: &'gc GarbageCollector<Id>,
+}
+impl<T: Collect<Id>, Id: CollectorId> Deref for GcRefMut<'_, '_, T, Id> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+impl<T: Collect<Id>, Id: CollectorId> DerefMut for GcRefMut<'_, '_, T, Id> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+impl<T: Collect<Id>, Id: CollectorId> Drop for GcRefMut<'_, '_, T, Id> {
+    #[inline]
+    fn drop(&mut self) {
+        if T::NEEDS_COLLECT {
+            unsafe {
+                let field = NonNull::new_unchecked(self.container.value.as_ptr());
+                self.collector.write_barrier(self.container, field);
+            }
+        }
+    }
+}
+
+/// A cell which can be initialized once, and can be stored in a GC object.
+///
+/// This is useful for lazily computed caches
+/// inside objects which are otherwise immutable.
+/// Initialization must go through the containing [`Gc`] pointer,
+/// so that the write barrier can be performed.
+pub struct GcOnceCell<T> {
+    value: OnceCell<T>,
+}
+impl<T> GcOnceCell<T> {
+    #[inline]
+    pub const fn new() -> Self {
+        GcOnceCell {
+            value: OnceCell::new(),
+        }
+    }
+
+    /// Get the value, or `None` if the cell is uninitialized.
+    #[inline]
+    pub fn get(&self) -> Option<&T> {
+        self.value.get()
+    }
+
+    /// Get the value, initializing it with the specified closure if needed.
+    ///
+    /// ## Panics
+    /// If the closure recursively initializes the cell,
+    /// or if the containing object is [frozen](Gc::freeze) and the cell is uninitialized.
+    #[track_caller]
+    pub fn get_or_init<'a, 'gc, Id: CollectorId>(
+        this: &'a Gc<'gc, Self, Id>,
+        collector: &'gc GarbageCollector<Id>,
+        func: impl FnOnce() -> T,
+    ) -> &'a T
+    where
+        T: Collect<Id>,
+    {
+        match Self::get_or_try_init(
+            this,
+            collector,
+            || Ok::<T, std::convert::Infallible>(func()),
+        ) {
+            Ok(value) => value,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Get the value, initializing it with a fallible closure if needed.
+    ///
+    /// If the closure returns an error, the cell remains uninitialized.
+    ///
+    /// See [`Self::get_or_init`] for details.
+    #[track_caller]
+    pub fn get_or_try_init<'a, 'gc, Id: CollectorId, E>(
+        this: &'a Gc<'gc, Self, Id>,
+        collector: &'gc GarbageCollector<Id>,
+        func: impl FnOnce() -> Result<T, E>,
+    ) -> Result<&'a T, E>
+    where
+        T: Collect<Id>,
+    {
+        if let Some(value) = this.value.get() {
+            return Ok(value);
+        }
+        assert!(!this.is_frozen(collector), "Cannot mutate a frozen object");
+        let value = func()?;
+        assert!(
+            this.value.set(value).is_ok(),
+            "Reentrant initialization of GcOnceCell"
+        );
+        let value = this.value.get().unwrap();
+        if T::NEEDS_COLLECT {
+            unsafe {
+                collector.write_barrier(*this, NonNull::from(value));
+            }
+        }
+        Ok(value)
+    }
+
+    /// Get a mutable reference to the value, if initialized.
+    ///
+    /// This needs no write barrier, as unique access implies the cell is not in the GC heap.
+    #[inline]
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.value.get_mut()
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> Option<T> {
+        self.value.into_inner()
+    }
+}
+impl<T> Default for GcOnceCell<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+unsafe impl<Id: CollectorId, T: Collect<Id>> Collect<Id> for GcOnceCell<T> {
+    type Collected<'newgc> = GcOnceCell<T::Collected<'newgc>>;
+    const NEEDS_COLLECT: bool = T::NEEDS_COLLECT;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        // an uninitialized cell has nothing to trace
+        if let Some(value) = (*target.as_ptr()).value.get_mut() {
+            T::collect_inplace(NonNull::from(value), context);
+        }
+    }
+}
+unsafe impl<Id: CollectorId, T: NullCollect<Id>> NullCollect<Id> for GcOnceCell<T> {}
+impl<T: Debug> Debug for GcOnceCell<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GcOnceCell")
+            .field("value", &self.value.get())
+            .finish()
+    }
+}
+
+/// A [`Cell`] which can be stored in a GC object.
+///
+/// Values are copied in and out, so the cell is never borrowed.
+/// This is mainly useful for the elements of a [`GcArray`](crate::GcArray),
+/// which can then be [replaced](crate::GcArray::set) in place.
+/// Like the other cells, setting must go through the containing [`Gc`] pointer,
+/// so that the write barrier can be performed.
+#[repr(transparent)]
+pub struct GcCell<T> {
+    value: Cell<T>,
+}
+impl<T> GcCell<T> {
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        GcCell {
+            value: Cell::new(value),
+        }
+    }
+
+    #[inline]
+    pub fn get(&self) -> T
+    where
+        T: Copy,
+    {
+        self.value.get()
+    }
+
+    /// Replace the value, performing the write barrier.
+    ///
+    /// ## Panics
+    /// If the containing object is [frozen](Gc::freeze).
+    #[inline]
+    #[track_caller]
+    pub fn set<'gc, Id: CollectorId>(
+        this: &Gc<'gc, Self, Id>,
+        collector: &'gc GarbageCollector<Id>,
+        value: T,
+    ) where
+        T: Collect<Id>,
+    {
+        assert!(!this.is_frozen(collector), "Cannot mutate a frozen object");
+        if T::NEEDS_COLLECT {
+            collector.record_mutation(NonNull::from(this.header()));
+        }
+        this.value.set(value);
+        if T::NEEDS_COLLECT {
+            unsafe {
+                let field = NonNull::new_unchecked(this.value.as_ptr());
+                collector.write_barrier(*this, field);
+            }
+        }
+    }
+
+    /// Replace the value without any barrier.
+    ///
+    /// ## Safety
+    /// The caller must perform the barriers for the containing object.
+    #[inline]
+    pub(crate) unsafe fn set_unchecked(&self, value: T) {
+        self.value.set(value);
+    }
+
+    /// A raw pointer to the value.
+    #[inline]
+    pub(crate) fn as_ptr(&self) -> *mut T {
+        self.value.as_ptr()
+    }
+
+    /// Get a mutable reference to the value.
+    ///
+    /// This needs no write barrier, as unique access implies the cell is not in the GC heap.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+impl<T: Default> Default for GcCell<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+unsafe impl<Id: CollectorId, T: Collect<Id>> Collect<Id> for GcCell<T> {
+    type Collected<'newgc> = GcCell<T::Collected<'newgc>>;
+    const NEEDS_COLLECT: bool = T::NEEDS_COLLECT;
+    // the cell is transparent, so an array of cells has the same layout as an array of values
+    const IS_GC_POINTER: IsGcPointer = T::IS_GC_POINTER;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        T::collect_inplace(NonNull::new_unchecked(target.as_ref().as_ptr()), context);
+    }
+}
+unsafe impl<Id: CollectorId, T: NullCollect<Id>> NullCollect<Id> for GcCell<T> {}
+impl<T: Copy + Debug> Debug for GcCell<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GcCell")
+            .field("value", &self.value.get())
+            .finish()
+    }
+}
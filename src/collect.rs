@@ -13,8 +13,74 @@ mod primitives;
 pub unsafe trait Collect<Id: CollectorId> {
     type Collected<'newgc>: Collect<Id>;
     const NEEDS_COLLECT: bool;
+    /// Whether this type is exactly a [`Gc`](crate::Gc) pointer,
+    /// allowing arrays of it to be traced without an indirect call per element.
+    ///
+    /// This is sealed, since [`IsGcPointer`] can only be created within this crate.
+    #[doc(hidden)]
+    const IS_GC_POINTER: IsGcPointer = IsGcPointer::NO;
+    /// Whether [`Collect::on_moved`] must be called whenever the collector moves this value.
+    const NEEDS_MOVE_HOOK: bool = false;
+    /// Where newly allocated values of this type should be placed.
+    ///
+    /// Types which are known to be long-lived can use [`PlacementHint::Old`]
+    /// to skip the young generation entirely.
+    const PLACEMENT: PlacementHint = PlacementHint::Young;
 
     unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>);
+
+    /// Fix up address-dependent state after the collector moves this value,
+    /// like pointers to itself or registrations with external systems.
+    ///
+    /// This is only called if [`Collect::NEEDS_MOVE_HOOK`] is set.
+    /// It is invoked in the middle of a collection whenever the value is evacuated,
+    /// including promotion into the old generation and compaction.
+    /// It is also invoked for each element of a moved array.
+    ///
+    /// ## Safety
+    /// The value has already been copied to `new_ptr`,
+    /// but its children have not necessarily been traced yet,
+    /// so any GC pointers it contains must not be dereferenced.
+    /// The old location may have been overwritten by the collector,
+    /// so `old_ptr` must only be used as an address.
+    #[inline]
+    unsafe fn on_moved(old_ptr: NonNull<Self>, new_ptr: NonNull<Self>) {
+        let _ = (old_ptr, new_ptr);
+    }
+}
+
+/// The value of [`Collect::IS_GC_POINTER`],
+/// which can only be created within this crate.
+///
+/// Implementations outside this crate can only keep the default,
+/// or forward the value of a type they wrap transparently.
+#[doc(hidden)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IsGcPointer(bool);
+impl IsGcPointer {
+    pub(crate) const YES: Self = IsGcPointer(true);
+    pub(crate) const NO: Self = IsGcPointer(false);
+
+    #[inline]
+    pub const fn get(self) -> bool {
+        self.0
+    }
+}
+
+/// A hint for which generation newly allocated values of a type should be placed in.
+///
+/// Most objects die young, so by default values are allocated in the young generation.
+/// Values which are known to survive for a long time (like interned strings or global tables)
+/// would otherwise be copied out of the young generation at least once before being promoted.
+///
+/// This only applies to regular objects, as arrays are placed according to their size.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum PlacementHint {
+    /// Allocate in the young generation, if it has room.
+    #[default]
+    Young,
+    /// Allocate directly in the old generation, bypassing the young generation.
+    Old,
 }
 
 pub unsafe trait NullCollect<Id: CollectorId>: Collect<Id> {}
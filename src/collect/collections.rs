@@ -9,11 +9,7 @@ unsafe impl<Id: CollectorId, T: Collect<Id>> Collect<Id> for Vec<T> {
 
     #[inline]
     unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
-        if Self::NEEDS_COLLECT {
-            for val in target.as_ref().iter() {
-                T::collect_inplace(NonNull::from(val), context);
-            }
-        }
+        context.trace_slice(NonNull::from(target.as_ref().as_slice()));
     }
 }
 
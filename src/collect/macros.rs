@@ -1,9 +1,31 @@
 #[macro_export]
 macro_rules! static_null_trace {
     ($($target:ident),*) => {
-        $($crate::static_null_trace!(@single $target);)*
+        $crate::collect_trivial!($($target),*);
     };
-    (@single $target:ident) => {
+}
+
+/// Implement [`Collect`](crate::Collect) and [`NullCollect`](crate::NullCollect)
+/// for a list of plain-old-data types, which never contain GC pointers.
+///
+/// The types must be `'static`, and are projected to themselves.
+/// Unlike [`static_null_trace!`], arbitrary types like paths and generic instantiations are accepted.
+///
+/// ```
+/// # use zerogc_next::collect_trivial;
+/// struct Opcode(u8);
+/// mod span {
+///     pub struct Span(pub u32, pub u32);
+/// }
+/// struct Flags<const N: usize>([u8; N]);
+/// collect_trivial!(Opcode, span::Span, Flags<4>);
+/// ```
+#[macro_export]
+macro_rules! collect_trivial {
+    ($($target:ty),* $(,)?) => {
+        $($crate::collect_trivial!(@single $target);)*
+    };
+    (@single $target:ty) => {
         unsafe impl<Id: $crate::CollectorId> $crate::Collect<Id> for $target {
             type Collected<'newgc> = Self;
             const NEEDS_COLLECT: bool = {
@@ -18,7 +40,134 @@ macro_rules! static_null_trace {
     };
 }
 
+/// Implement [`Collect`](crate::Collect) for a newtype wrapper by forwarding to its single field.
+///
+/// [`NullCollect`](crate::NullCollect) is also implemented whenever the inner type implements it.
+///
+/// A wrapper without lifetimes implements `Collect` for every collector id supported by the inner type.
+/// A wrapper with a `'gc` lifetime must specify its collector id,
+/// and the inner type must be the same once `'gc` is replaced by `'newgc`.
+///
+/// ```
+/// # use zerogc_next::{collect_newtype, Gc, ScopedId};
+/// struct Index(u32);
+/// collect_newtype!(Index(u32));
+///
+/// struct Node<'gc>(Gc<'gc, u32, ScopedId>);
+/// collect_newtype!(Node<'gc>(Gc<'gc, u32, ScopedId>) for ScopedId);
+/// ```
+#[macro_export]
+macro_rules! collect_newtype {
+    ($wrapper:ident($inner:ty)) => {
+        unsafe impl<Id: $crate::CollectorId> $crate::Collect<Id> for $wrapper
+        where
+            $inner: $crate::Collect<Id>,
+        {
+            type Collected<'newgc> = Self;
+            const NEEDS_COLLECT: bool = {
+                $crate::collect::macros::helpers::assert_static_lifetime::<Self>();
+                <$inner as $crate::Collect<Id>>::NEEDS_COLLECT
+            };
+
+            #[inline]
+            unsafe fn collect_inplace(
+                target: std::ptr::NonNull<Self>,
+                context: &mut $crate::context::CollectContext<'_, Id>,
+            ) {
+                <$inner as $crate::Collect<Id>>::collect_inplace(
+                    std::ptr::NonNull::new_unchecked(std::ptr::addr_of_mut!((*target.as_ptr()).0)),
+                    context,
+                );
+            }
+        }
+        unsafe impl<Id: $crate::CollectorId> $crate::NullCollect<Id> for $wrapper where
+            $inner: $crate::NullCollect<Id>
+        {
+        }
+    };
+    ($wrapper:ident<$gc:lifetime>($inner:ty) for $id:ty) => {
+        unsafe impl<$gc> $crate::Collect<$id> for $wrapper<$gc> {
+            type Collected<'newgc> = $wrapper<'newgc>;
+            const NEEDS_COLLECT: bool = <$inner as $crate::Collect<$id>>::NEEDS_COLLECT;
+
+            #[inline]
+            unsafe fn collect_inplace(
+                target: std::ptr::NonNull<Self>,
+                context: &mut $crate::context::CollectContext<'_, $id>,
+            ) {
+                <$inner as $crate::Collect<$id>>::collect_inplace(
+                    std::ptr::NonNull::new_unchecked(std::ptr::addr_of_mut!((*target.as_ptr()).0)),
+                    context,
+                );
+            }
+        }
+        // higher-ranked to avoid errors from bounds which are trivially false
+        unsafe impl<$gc> $crate::NullCollect<$id> for $wrapper<$gc> where
+            for<'__trivial> $inner: $crate::NullCollect<$id>
+        {
+        }
+    };
+}
+
+/// Statically assert that a type implements [`Collect`](crate::Collect) as expected.
+///
+/// This checks the value of [`NEEDS_COLLECT`](crate::Collect::NEEDS_COLLECT),
+/// and optionally that the `Collected<'static>` projection is the expected type.
+/// Any mismatch is a compile-time error.
+///
+/// The collector id defaults to [`ScopedId`](crate::context::ScopedId),
+/// and can be overridden with `$target as Collect<$id>`.
+///
+/// ```
+/// # use zerogc_next::{assert_collect, Gc, ScopedId};
+/// assert_collect!(u32, needs_collect = false, collected = u32);
+/// assert_collect!(
+///     Vec<Gc<'static, u32, ScopedId>>,
+///     needs_collect = true,
+///     collected = Vec<Gc<'static, u32, ScopedId>>,
+/// );
+/// ```
+#[macro_export]
+macro_rules! assert_collect {
+    ($target:ty as Collect<$id:ty>, needs_collect = $needs_collect:expr $(, collected = $collected:ty)? $(,)?) => {
+        const _: () = {
+            assert!(
+                $crate::collect::macros::helpers::needs_collect::<$target, $id>() == $needs_collect,
+                concat!("Unexpected value of NEEDS_COLLECT for ", stringify!($target)),
+            );
+            $(
+                $crate::collect::macros::helpers::assert_same_type::<
+                    <$target as $crate::Collect<$id>>::Collected<'static>,
+                    $collected,
+                >(std::marker::PhantomData);
+            )?
+        };
+    };
+    ($target:ty, needs_collect = $needs_collect:expr $(, collected = $collected:ty)? $(,)?) => {
+        $crate::assert_collect!($target as Collect<$crate::context::ScopedId>, needs_collect = $needs_collect $(, collected = $collected)?);
+    };
+}
+
 #[doc(hidden)]
 pub mod helpers {
+    use std::marker::PhantomData;
+
+    use crate::{Collect, CollectorId};
+
     pub const fn assert_static_lifetime<T: ?Sized + 'static>() {}
+
+    #[inline]
+    pub const fn needs_collect<T: Collect<Id>, Id: CollectorId>() -> bool {
+        T::NEEDS_COLLECT
+    }
+
+    /// Only type checks if `Actual` and `Expected` are the same type.
+    pub const fn assert_same_type<Actual, Expected>(_marker: PhantomData<Actual>)
+    where
+        Actual: SameType<Expected>,
+    {
+    }
+
+    pub trait SameType<T> {}
+    impl<T> SameType<T> for T {}
 }
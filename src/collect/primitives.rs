@@ -1,3 +1,58 @@
 use crate::static_null_trace;
 
-static_null_trace!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, char, bool, String);
+static_null_trace!(
+    i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64, char, bool, String
+);
+
+#[cfg(feature = "half")]
+mod half_floats {
+    use crate::static_null_trace;
+    use half::{bf16, f16};
+
+    static_null_trace!(f16, bf16);
+}
+
+#[cfg(target_arch = "x86_64")]
+mod simd {
+    use crate::static_null_trace;
+    use std::arch::x86_64::{
+        __m128, __m128d, __m128i, __m256, __m256d, __m256i, __m512, __m512d, __m512i,
+    };
+
+    static_null_trace!(
+        __m128, __m128d, __m128i, __m256, __m256d, __m256i, __m512, __m512d, __m512i
+    );
+}
+
+#[cfg(target_arch = "aarch64")]
+mod simd {
+    use crate::static_null_trace;
+    use std::arch::aarch64::{
+        float32x2_t, float32x4_t, float64x1_t, float64x2_t, int16x4_t, int16x8_t, int32x2_t,
+        int32x4_t, int64x1_t, int64x2_t, int8x16_t, int8x8_t, uint16x4_t, uint16x8_t, uint32x2_t,
+        uint32x4_t, uint64x1_t, uint64x2_t, uint8x16_t, uint8x8_t,
+    };
+
+    static_null_trace!(
+        float32x2_t,
+        float32x4_t,
+        float64x1_t,
+        float64x2_t,
+        int8x8_t,
+        int8x16_t,
+        int16x4_t,
+        int16x8_t,
+        int32x2_t,
+        int32x4_t,
+        int64x1_t,
+        int64x2_t,
+        uint8x8_t,
+        uint8x16_t,
+        uint16x4_t,
+        uint16x8_t,
+        uint32x2_t,
+        uint32x4_t,
+        uint64x1_t,
+        uint64x2_t
+    );
+}
@@ -0,0 +1,63 @@
+//! Collections whose storage is allocated in the GC heap.
+//!
+//! Unlike the standard collections, these need no destructor,
+//! so they are moved and reclaimed like any other GC object.
+
+use crate::{Collect, CollectorId, GarbageCollector, GcArray};
+
+pub mod btree_map;
+pub mod hash_map;
+pub mod persistent_map;
+pub mod persistent_vec;
+
+pub use self::btree_map::GcBTreeMap;
+pub use self::hash_map::GcHashMap;
+pub use self::persistent_map::GcPersistentMap;
+pub use self::persistent_vec::GcPersistentVec;
+
+/// Copy a slice into a new array, inserting an element at the specified index.
+fn copy_insert<'gc, T: Collect<Id> + Clone, Id: CollectorId>(
+    collector: &'gc GarbageCollector<Id>,
+    slice: &[T],
+    index: usize,
+    value: T,
+) -> GcArray<'gc, T, Id> {
+    let mut value = Some(value);
+    collector.alloc_array_with(slice.len() + 1, |i| match i.cmp(&index) {
+        std::cmp::Ordering::Less => slice[i].clone(),
+        std::cmp::Ordering::Equal => value.take().unwrap(),
+        std::cmp::Ordering::Greater => slice[i - 1].clone(),
+    })
+}
+
+/// Copy a slice into a new array, replacing the element at the specified index.
+fn copy_replace<'gc, T: Collect<Id> + Clone, Id: CollectorId>(
+    collector: &'gc GarbageCollector<Id>,
+    slice: &[T],
+    index: usize,
+    value: T,
+) -> GcArray<'gc, T, Id> {
+    let mut value = Some(value);
+    collector.alloc_array_with(slice.len(), |i| {
+        if i == index {
+            value.take().unwrap()
+        } else {
+            slice[i].clone()
+        }
+    })
+}
+
+/// Copy a slice into a new array, removing the element at the specified index.
+fn copy_remove<'gc, T: Collect<Id> + Clone, Id: CollectorId>(
+    collector: &'gc GarbageCollector<Id>,
+    slice: &[T],
+    index: usize,
+) -> GcArray<'gc, T, Id> {
+    collector.alloc_array_with(slice.len() - 1, |i| {
+        if i < index {
+            slice[i].clone()
+        } else {
+            slice[i + 1].clone()
+        }
+    })
+}
@@ -0,0 +1,681 @@
+//! An ordered map whose nodes are GC objects.
+//!
+//! Each node is a [`GcRefCell`], so mutating a node performs the write barrier automatically.
+//! Nodes are split on the way down during insertion,
+//! and refilled on the way down during removal,
+//! so neither operation needs to revisit a parent.
+
+use std::borrow::Borrow;
+use std::cell::{Ref, RefCell};
+use std::fmt::{Debug, Formatter};
+use std::ops::{Bound, RangeBounds};
+use std::ptr::NonNull;
+
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector, Gc, GcRefCell};
+
+/// The minimum number of entries in a node other than the root.
+const MIN_LEN: usize = 5;
+/// The maximum number of entries in a node.
+const CAPACITY: usize = 2 * MIN_LEN + 1;
+
+type NodeRef<'gc, K, V, Id> = Gc<'gc, GcRefCell<Node<'gc, K, V, Id>>, Id>;
+
+struct Node<'gc, K, V, Id: CollectorId> {
+    len: usize,
+    leaf: bool,
+    /// The entries in ascending order, where only the first `len` are present.
+    entries: [Option<(K, V)>; CAPACITY],
+    /// The children of an internal node, where only the first `len + 1` are present.
+    children: [Option<NodeRef<'gc, K, V, Id>>; CAPACITY + 1],
+}
+impl<'gc, K, V, Id: CollectorId> Node<'gc, K, V, Id> {
+    fn new(leaf: bool) -> Self {
+        Node {
+            len: 0,
+            leaf,
+            entries: std::array::from_fn(|_| None),
+            children: std::array::from_fn(|_| None),
+        }
+    }
+
+    #[inline]
+    fn entry(&self, index: usize) -> &(K, V) {
+        self.entries[index].as_ref().expect("Missing entry")
+    }
+
+    #[inline]
+    fn child(&self, index: usize) -> NodeRef<'gc, K, V, Id> {
+        self.children[index].expect("Missing child")
+    }
+
+    /// Find the index of the specified key,
+    /// or else the index of the child which would contain it.
+    fn search<Q>(&self, key: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.entries[..self.len].binary_search_by(|entry| {
+            let (entry_key, _) = entry.as_ref().expect("Missing entry");
+            entry_key.borrow().cmp(key)
+        })
+    }
+
+    fn insert_entry(&mut self, index: usize, entry: (K, V)) {
+        assert!(self.len < CAPACITY);
+        self.entries[index..].rotate_right(1);
+        self.entries[index] = Some(entry);
+        self.len += 1;
+    }
+
+    fn remove_entry(&mut self, index: usize) -> (K, V) {
+        let entry = self.entries[index].take().expect("Missing entry");
+        self.entries[index..].rotate_left(1);
+        self.len -= 1;
+        entry
+    }
+
+    /// Insert a child, shifting later children back.
+    ///
+    /// This does not change the length of the node,
+    /// so it is paired with [`Self::insert_entry`].
+    fn insert_child(&mut self, index: usize, child: NodeRef<'gc, K, V, Id>) {
+        assert!(self.children[CAPACITY].is_none());
+        self.children[index..].rotate_right(1);
+        self.children[index] = Some(child);
+    }
+
+    fn remove_child(&mut self, index: usize) -> NodeRef<'gc, K, V, Id> {
+        let child = self.children[index].take().expect("Missing child");
+        self.children[index..].rotate_left(1);
+        child
+    }
+}
+unsafe impl<'gc, Id: CollectorId, K: Collect<Id>, V: Collect<Id>> Collect<Id>
+    for Node<'gc, K, V, Id>
+{
+    type Collected<'newgc> = Node<'newgc, K::Collected<'newgc>, V::Collected<'newgc>, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        let node = &mut *target.as_ptr();
+        if K::NEEDS_COLLECT || V::NEEDS_COLLECT {
+            for (key, value) in node.entries.iter_mut().flatten() {
+                K::collect_inplace(NonNull::from(key), context);
+                V::collect_inplace(NonNull::from(value), context);
+            }
+        }
+        for child in &mut node.children {
+            context.trace_option(NonNull::from(child));
+        }
+    }
+}
+
+/// Borrow a node for as long as the map is borrowed.
+#[inline]
+fn node_ref<'a, 'gc: 'a, K: Collect<Id>, V: Collect<Id>, Id: CollectorId>(
+    node: NodeRef<'gc, K, V, Id>,
+) -> &'a GcRefCell<Node<'gc, K, V, Id>> {
+    // the collector cannot run during 'gc, so the node outlives the borrow
+    unsafe { node.as_raw_ptr().as_ref() }
+}
+
+/// Ensure the specified child has more than the minimum number of entries,
+/// so that an entry can be removed from it.
+///
+/// Returns the index of the child, which changes if it is merged with its left sibling.
+fn fill_child<'gc, K: Collect<Id>, V: Collect<Id>, Id: CollectorId>(
+    parent: &mut Node<'gc, K, V, Id>,
+    index: usize,
+    collector: &'gc GarbageCollector<Id>,
+) -> usize {
+    let child = parent.child(index);
+    if GcRefCell::borrow(&child).len > MIN_LEN {
+        return index;
+    }
+    if index > 0 && GcRefCell::borrow(&parent.child(index - 1)).len > MIN_LEN {
+        // rotate the last entry of the left sibling through the parent
+        let left = parent.child(index - 1);
+        let mut left_data = GcRefCell::borrow_mut(&left, collector);
+        let mut child_data = GcRefCell::borrow_mut(&child, collector);
+        let left_len = left_data.len;
+        let entry = left_data.remove_entry(left_len - 1);
+        let separator = parent.entries[index - 1].replace(entry).unwrap();
+        child_data.insert_entry(0, separator);
+        if !left_data.leaf {
+            let moved = left_data.remove_child(left_len);
+            child_data.insert_child(0, moved);
+        }
+        index
+    } else if index < parent.len && GcRefCell::borrow(&parent.child(index + 1)).len > MIN_LEN {
+        // rotate the first entry of the right sibling through the parent
+        let right = parent.child(index + 1);
+        let mut right_data = GcRefCell::borrow_mut(&right, collector);
+        let mut child_data = GcRefCell::borrow_mut(&child, collector);
+        let entry = right_data.remove_entry(0);
+        let separator = parent.entries[index].replace(entry).unwrap();
+        let child_len = child_data.len;
+        child_data.insert_entry(child_len, separator);
+        if !right_data.leaf {
+            let moved = right_data.remove_child(0);
+            child_data.insert_child(child_len + 1, moved);
+        }
+        index
+    } else if index < parent.len {
+        merge_children(parent, index, collector);
+        index
+    } else {
+        merge_children(parent, index - 1, collector);
+        index - 1
+    }
+}
+
+/// Merge the specified child with its right sibling and the entry separating them.
+///
+/// Both children must have the minimum number of entries.
+fn merge_children<'gc, K: Collect<Id>, V: Collect<Id>, Id: CollectorId>(
+    parent: &mut Node<'gc, K, V, Id>,
+    index: usize,
+    collector: &'gc GarbageCollector<Id>,
+) {
+    let separator = parent.remove_entry(index);
+    let right = parent.remove_child(index + 1);
+    let left = parent.child(index);
+    let mut left_data = GcRefCell::borrow_mut(&left, collector);
+    // the right node is now unreachable, so its entries are moved out
+    let mut right_data = GcRefCell::borrow_mut(&right, collector);
+    let base = left_data.len + 1;
+    debug_assert!(base + right_data.len <= CAPACITY);
+    left_data.entries[base - 1] = Some(separator);
+    for offset in 0..right_data.len {
+        left_data.entries[base + offset] = right_data.entries[offset].take();
+    }
+    for offset in 0..=right_data.len {
+        left_data.children[base + offset] = right_data.children[offset].take();
+    }
+    left_data.len = base + right_data.len;
+    right_data.len = 0;
+}
+
+/// Split the specified full child in two, moving its median entry into the parent.
+fn split_child<'gc, K: Collect<Id>, V: Collect<Id>, Id: CollectorId>(
+    parent: &mut Node<'gc, K, V, Id>,
+    index: usize,
+    collector: &'gc GarbageCollector<Id>,
+) {
+    let child = parent.child(index);
+    let mut child_data = GcRefCell::borrow_mut(&child, collector);
+    debug_assert_eq!(child_data.len, CAPACITY);
+    let mut sibling = Node::new(child_data.leaf);
+    for offset in 0..MIN_LEN {
+        sibling.entries[offset] = child_data.entries[MIN_LEN + 1 + offset].take();
+    }
+    for offset in 0..=MIN_LEN {
+        sibling.children[offset] = child_data.children[MIN_LEN + 1 + offset].take();
+    }
+    sibling.len = MIN_LEN;
+    let median = child_data.entries[MIN_LEN].take().unwrap();
+    child_data.len = MIN_LEN;
+    drop(child_data);
+    let sibling = collector.alloc(GcRefCell::new(sibling));
+    parent.insert_entry(index, median);
+    parent.insert_child(index + 1, sibling);
+}
+
+/// Remove the first or last entry in the subtree rooted at the specified node,
+/// which must have more than the minimum number of entries.
+fn remove_extreme<'gc, K: Collect<Id>, V: Collect<Id>, Id: CollectorId>(
+    mut node: NodeRef<'gc, K, V, Id>,
+    last: bool,
+    collector: &'gc GarbageCollector<Id>,
+) -> (K, V) {
+    loop {
+        let mut data = GcRefCell::borrow_mut(&node, collector);
+        if data.leaf {
+            let index = if last { data.len - 1 } else { 0 };
+            return data.remove_entry(index);
+        }
+        let index = if last { data.len } else { 0 };
+        let index = fill_child(&mut data, index, collector);
+        let next = data.child(index);
+        drop(data);
+        node = next;
+    }
+}
+
+/// The position of an entry in the tree.
+type Position<'gc, K, V, Id> = (NodeRef<'gc, K, V, Id>, usize);
+
+/// Find the path to the first entry satisfying the specified lower bound.
+///
+/// Each node on the path is paired with the index of its next entry.
+fn seek<'gc, K, V, Id, Q>(
+    root: Option<NodeRef<'gc, K, V, Id>>,
+    bound: Bound<&Q>,
+) -> Vec<Position<'gc, K, V, Id>>
+where
+    K: Collect<Id> + Borrow<Q>,
+    V: Collect<Id>,
+    Id: CollectorId,
+    Q: ?Sized + Ord,
+{
+    let mut path = Vec::new();
+    let mut next = root;
+    while let Some(node) = next {
+        let data = GcRefCell::borrow(&node);
+        let (index, found) = match bound {
+            Bound::Unbounded => (0, false),
+            Bound::Included(key) => match data.search(key) {
+                Ok(index) => (index, true),
+                Err(index) => (index, false),
+            },
+            Bound::Excluded(key) => match data.search(key) {
+                Ok(index) => (index + 1, false),
+                Err(index) => (index, false),
+            },
+        };
+        path.push((node, index));
+        // an exact match precedes the rest of its subtree
+        next = if found || data.leaf {
+            None
+        } else {
+            Some(data.child(index))
+        };
+    }
+    path
+}
+
+/// Advance along the path returned by [`seek`], returning the position of the next entry.
+fn advance<'gc, K: Collect<Id>, V: Collect<Id>, Id: CollectorId>(
+    path: &mut Vec<Position<'gc, K, V, Id>>,
+) -> Option<Position<'gc, K, V, Id>> {
+    loop {
+        let (node, index) = *path.last()?;
+        let data = GcRefCell::borrow(&node);
+        if index < data.len {
+            path.last_mut().unwrap().1 += 1;
+            if !data.leaf {
+                // descend to the first entry after this one
+                let mut next = data.child(index + 1);
+                loop {
+                    path.push((next, 0));
+                    let next_data = GcRefCell::borrow(&next);
+                    if next_data.leaf {
+                        break;
+                    }
+                    let child = next_data.child(0);
+                    drop(next_data);
+                    next = child;
+                }
+            }
+            return Some((node, index));
+        }
+        path.pop();
+    }
+}
+
+struct Root<'gc, K, V, Id: CollectorId> {
+    node: Option<NodeRef<'gc, K, V, Id>>,
+    len: usize,
+}
+unsafe impl<'gc, Id: CollectorId, K: Collect<Id>, V: Collect<Id>> Collect<Id>
+    for Root<'gc, K, V, Id>
+{
+    type Collected<'newgc> = Root<'newgc, K::Collected<'newgc>, V::Collected<'newgc>, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        context.trace_option(NonNull::from(&mut (*target.as_ptr()).node));
+    }
+}
+
+/// An ordered map whose nodes are allocated in the GC heap.
+///
+/// This is a B-tree like [`std::collections::BTreeMap`],
+/// but its nodes are traced, moved, and reclaimed like any other object,
+/// instead of being freed by the destructor of the containing object.
+///
+/// Mutations must go through the containing [`Gc`] pointer,
+/// so that the write barrier can be performed.
+pub struct GcBTreeMap<'gc, K, V, Id: CollectorId> {
+    root: RefCell<Root<'gc, K, V, Id>>,
+}
+impl<'gc, K, V, Id: CollectorId> GcBTreeMap<'gc, K, V, Id> {
+    /// Create an empty map, which does not allocate until the first insertion.
+    #[inline]
+    pub const fn new() -> Self {
+        GcBTreeMap {
+            root: RefCell::new(Root { node: None, len: 0 }),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.root.borrow().len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+impl<'gc, K: Collect<Id> + Ord, V: Collect<Id>, Id: CollectorId> GcBTreeMap<'gc, K, V, Id> {
+    /// Get the value corresponding to the specified key.
+    ///
+    /// The node containing the value cannot be mutated while it is borrowed.
+    ///
+    /// ## Panics
+    /// If the map is currently being mutated,
+    /// which is only possible from the `Ord` implementation of a key.
+    pub fn get<Q>(&self, key: &Q) -> Option<Ref<'_, V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let mut next = self.root.borrow().node;
+        while let Some(node) = next {
+            let data = node_ref(node).borrow();
+            match data.search(key) {
+                Ok(index) => return Some(Ref::map(data, |data| &data.entry(index).1)),
+                Err(_) if data.leaf => return None,
+                Err(index) => next = Some(data.child(index)),
+            }
+        }
+        None
+    }
+
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Iterate over clones of the entries in the map, in ascending order.
+    ///
+    /// The map cannot be mutated until the iterator is dropped.
+    #[inline]
+    pub fn iter(&self) -> Range<'_, 'gc, K, V, Id>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.range::<K, _>(..)
+    }
+
+    /// Iterate over clones of the entries whose keys lie in the specified range,
+    /// in ascending order.
+    ///
+    /// The map cannot be mutated until the iterator is dropped.
+    ///
+    /// ## Panics
+    /// If the start of the range is greater than its end,
+    /// or if they are equal and both excluded.
+    #[track_caller]
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, 'gc, K, V, Id>
+    where
+        K: Borrow<Q> + Clone,
+        V: Clone,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        match (range.start_bound(), range.end_bound()) {
+            (Bound::Excluded(start), Bound::Excluded(end)) if start == end => {
+                panic!("Range start and end are equal and excluded")
+            }
+            (
+                Bound::Included(start) | Bound::Excluded(start),
+                Bound::Included(end) | Bound::Excluded(end),
+            ) if start > end => panic!("Range start is greater than range end"),
+            _ => {}
+        }
+        let root = self.root.borrow();
+        let path = seek(root.node, range.start_bound());
+        // the end is the first entry after the range
+        let end = match range.end_bound() {
+            Bound::Included(key) => advance(&mut seek(root.node, Bound::Excluded(key))),
+            Bound::Excluded(key) => advance(&mut seek(root.node, Bound::Included(key))),
+            Bound::Unbounded => None,
+        };
+        Range {
+            _root: root,
+            path,
+            end,
+        }
+    }
+
+    /// Insert a key-value pair into the map, returning the previous value (if any).
+    ///
+    /// If the key is already present, the existing key is kept.
+    ///
+    /// ## Panics
+    /// If the map is currently borrowed, or if the containing object is [frozen](Gc::freeze).
+    #[track_caller]
+    pub fn insert(
+        this: &Gc<'gc, Self, Id>,
+        collector: &'gc GarbageCollector<Id>,
+        key: K,
+        value: V,
+    ) -> Option<V> {
+        assert!(!this.is_frozen(collector), "Cannot mutate a frozen object");
+        let mut root = this.root.borrow_mut();
+        let mut node = match root.node {
+            Some(node) if GcRefCell::borrow(&node).len < CAPACITY => node,
+            old_root => {
+                // grow the tree by splitting the root (if any)
+                let mut new_root = Node::new(old_root.is_none());
+                if let Some(old_root) = old_root {
+                    new_root.children[0] = Some(old_root);
+                    split_child(&mut new_root, 0, collector);
+                }
+                let new_root = collector.alloc(GcRefCell::new(new_root));
+                root.node = Some(new_root);
+                Self::root_written(this, collector);
+                new_root
+            }
+        };
+        loop {
+            let mut data = GcRefCell::borrow_mut(&node, collector);
+            let mut index = match data.search(&key) {
+                Ok(index) => {
+                    return Some(std::mem::replace(
+                        &mut data.entries[index].as_mut().unwrap().1,
+                        value,
+                    ))
+                }
+                Err(index) if data.leaf => {
+                    data.insert_entry(index, (key, value));
+                    root.len += 1;
+                    return None;
+                }
+                Err(index) => index,
+            };
+            if GcRefCell::borrow(&data.child(index)).len == CAPACITY {
+                split_child(&mut data, index, collector);
+                match data.entry(index).0.cmp(&key) {
+                    std::cmp::Ordering::Less => index += 1,
+                    std::cmp::Ordering::Equal => {
+                        return Some(std::mem::replace(
+                            &mut data.entries[index].as_mut().unwrap().1,
+                            value,
+                        ))
+                    }
+                    std::cmp::Ordering::Greater => {}
+                }
+            }
+            let next = data.child(index);
+            drop(data);
+            node = next;
+        }
+    }
+
+    /// Remove a key from the map, returning its value (if any).
+    ///
+    /// ## Panics
+    /// If the map is currently borrowed, or if the containing object is [frozen](Gc::freeze).
+    #[track_caller]
+    pub fn remove<Q>(
+        this: &Gc<'gc, Self, Id>,
+        collector: &'gc GarbageCollector<Id>,
+        key: &Q,
+    ) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        assert!(!this.is_frozen(collector), "Cannot mutate a frozen object");
+        let mut root = this.root.borrow_mut();
+        let root_node = root.node?;
+        let mut node = root_node;
+        // every node below the root is refilled before descending into it
+        let removed = loop {
+            let mut data = GcRefCell::borrow_mut(&node, collector);
+            let next = match data.search(key) {
+                Ok(index) if data.leaf => break Some(data.remove_entry(index)),
+                Err(_) if data.leaf => break None,
+                Ok(index) => {
+                    // replace the entry with its predecessor or successor if possible
+                    if GcRefCell::borrow(&data.child(index)).len > MIN_LEN {
+                        let entry = remove_extreme(data.child(index), true, collector);
+                        break data.entries[index].replace(entry);
+                    } else if GcRefCell::borrow(&data.child(index + 1)).len > MIN_LEN {
+                        let entry = remove_extreme(data.child(index + 1), false, collector);
+                        break data.entries[index].replace(entry);
+                    }
+                    // otherwise merge the entry into the child, and remove it from there
+                    merge_children(&mut data, index, collector);
+                    data.child(index)
+                }
+                Err(index) => {
+                    let index = fill_child(&mut data, index, collector);
+                    data.child(index)
+                }
+            };
+            drop(data);
+            node = next;
+        };
+        if removed.is_some() {
+            root.len -= 1;
+        }
+        // shrink the tree if the root has been emptied
+        let root_data = GcRefCell::borrow(&root_node);
+        if root_data.len == 0 {
+            root.node = if root_data.leaf {
+                None
+            } else {
+                Some(root_data.child(0))
+            };
+            drop(root_data);
+            Self::root_written(this, collector);
+        } else {
+            drop(root_data);
+        }
+        drop(root);
+        removed.map(|(_, value)| value)
+    }
+
+    /// Remove every entry from the map.
+    ///
+    /// ## Panics
+    /// If the map is currently borrowed, or if the containing object is [frozen](Gc::freeze).
+    #[track_caller]
+    pub fn clear(this: &Gc<'gc, Self, Id>, collector: &'gc GarbageCollector<Id>) {
+        assert!(!this.is_frozen(collector), "Cannot mutate a frozen object");
+        let old_root = std::mem::replace(&mut *this.root.borrow_mut(), Root { node: None, len: 0 });
+        // drop the entries now, instead of whenever the nodes are collected
+        if std::mem::needs_drop::<(K, V)>() {
+            let mut pending = old_root.node.into_iter().collect::<Vec<_>>();
+            while let Some(node) = pending.pop() {
+                let mut data = GcRefCell::borrow_mut(&node, collector);
+                pending.extend(data.children.iter().flatten().copied());
+                let entries = std::mem::replace(&mut data.entries, std::array::from_fn(|_| None));
+                data.len = 0;
+                drop(data);
+                drop(entries);
+            }
+        }
+    }
+
+    /// Perform the write barrier after the root node has changed.
+    #[inline]
+    fn root_written(this: &Gc<'gc, Self, Id>, collector: &'gc GarbageCollector<Id>) {
+        collector.record_mutation(NonNull::from(this.header()));
+        unsafe {
+            collector.write_barrier(*this, NonNull::new_unchecked(this.root.as_ptr()));
+        }
+    }
+}
+unsafe impl<'gc, Id: CollectorId, K: Collect<Id>, V: Collect<Id>> Collect<Id>
+    for GcBTreeMap<'gc, K, V, Id>
+{
+    type Collected<'newgc> = GcBTreeMap<'newgc, K::Collected<'newgc>, V::Collected<'newgc>, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        // borrows cannot outlive 'gc, so the root is never borrowed during a collection
+        Root::collect_inplace(
+            NonNull::new_unchecked(target.as_ref().root.as_ptr()),
+            context,
+        );
+    }
+}
+impl<'gc, K, V, Id: CollectorId> Default for GcBTreeMap<'gc, K, V, Id> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<'gc, K, V, Id> Debug for GcBTreeMap<'gc, K, V, Id>
+where
+    K: Collect<Id> + Ord + Debug,
+    V: Collect<Id> + Debug,
+    Id: CollectorId,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let root = self.root.borrow();
+        let mut path = seek::<K, V, Id, K>(root.node, Bound::Unbounded);
+        let mut map = f.debug_map();
+        while let Some((node, index)) = advance(&mut path) {
+            let data = GcRefCell::borrow(&node);
+            let (key, value) = data.entry(index);
+            map.entry(key, value);
+        }
+        map.finish()
+    }
+}
+
+/// An iterator over clones of the entries in a range of a [`GcBTreeMap`].
+///
+/// The map is borrowed until the iterator is dropped.
+pub struct Range<'a, 'gc, K, V, Id: CollectorId> {
+    _root: Ref<'a, Root<'gc, K, V, Id>>,
+    path: Vec<Position<'gc, K, V, Id>>,
+    /// The position of the first entry after the range,
+    /// or `None` if the range continues to the end of the map.
+    end: Option<Position<'gc, K, V, Id>>,
+}
+impl<'a, 'gc, K, V, Id> Iterator for Range<'a, 'gc, K, V, Id>
+where
+    K: Collect<Id> + Clone,
+    V: Collect<Id> + Clone,
+    Id: CollectorId,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        let (node, index) = advance(&mut self.path)?;
+        if let Some((end_node, end_index)) = self.end {
+            if std::ptr::eq(&*node, &*end_node) && index == end_index {
+                self.path.clear();
+                return None;
+            }
+        }
+        let entry = GcRefCell::borrow(&node).entry(index).clone();
+        Some(entry)
+    }
+}
@@ -0,0 +1,460 @@
+//! A hash map whose table is a GC array.
+//!
+//! The table uses linear probing,
+//! and removes entries by shifting their successors back instead of leaving tombstones.
+
+use std::borrow::Borrow;
+use std::cell::{Ref, RefCell};
+use std::collections::hash_map::RandomState;
+use std::fmt::{Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+use std::ptr::NonNull;
+
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector, Gc, GcArray};
+
+/// The number of buckets in the smallest allocated table.
+const MIN_BUCKETS: usize = 8;
+
+struct Entry<K, V> {
+    /// The hash of the key, cached so the table can grow without rehashing.
+    hash: u64,
+    key: K,
+    value: V,
+}
+
+/// A slot in the table, which is either empty or holds a single entry.
+struct Bucket<K, V> {
+    entry: Option<Entry<K, V>>,
+}
+unsafe impl<Id: CollectorId, K: Collect<Id>, V: Collect<Id>> Collect<Id> for Bucket<K, V> {
+    type Collected<'newgc> = Bucket<K::Collected<'newgc>, V::Collected<'newgc>>;
+    const NEEDS_COLLECT: bool = K::NEEDS_COLLECT || V::NEEDS_COLLECT;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        if let Some(ref mut entry) = (*target.as_ptr()).entry {
+            if K::NEEDS_COLLECT {
+                K::collect_inplace(NonNull::from(&mut entry.key), context);
+            }
+            if V::NEEDS_COLLECT {
+                V::collect_inplace(NonNull::from(&mut entry.value), context);
+            }
+        }
+    }
+}
+
+struct RawTable<'gc, K, V, Id: CollectorId> {
+    /// The buckets, whose length is always a power of two.
+    ///
+    /// This is `None` until the first insertion.
+    buckets: Option<GcArray<'gc, Bucket<K, V>, Id>>,
+    len: usize,
+}
+impl<'gc, K, V, Id: CollectorId> RawTable<'gc, K, V, Id> {
+    #[inline]
+    const fn new() -> Self {
+        RawTable {
+            buckets: None,
+            len: 0,
+        }
+    }
+
+    #[inline]
+    fn num_buckets(&self) -> usize {
+        self.buckets.map_or(0, |buckets| buckets.len())
+    }
+
+    /// A pointer to the specified bucket.
+    ///
+    /// Buckets are only accessed through the table,
+    /// so borrowing the table guards its buckets.
+    #[inline]
+    fn bucket_ptr(&self, index: usize) -> *mut Bucket<K, V> {
+        let buckets = self.buckets.expect("Table is unallocated");
+        assert!(index < buckets.len());
+        unsafe { buckets.as_ptr().as_ptr().add(index) }
+    }
+
+    #[inline]
+    fn entry(&self, index: usize) -> &Option<Entry<K, V>> {
+        unsafe { &(*self.bucket_ptr(index)).entry }
+    }
+
+    #[inline]
+    fn entry_mut(&mut self, index: usize) -> &mut Option<Entry<K, V>> {
+        unsafe { &mut (*self.bucket_ptr(index)).entry }
+    }
+
+    fn entries(&self) -> impl Iterator<Item = &Entry<K, V>> + '_ {
+        (0..self.num_buckets()).filter_map(move |index| self.entry(index).as_ref())
+    }
+
+    /// Find the index of the bucket holding the specified key.
+    fn find<Q>(&self, hash: u64, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq,
+    {
+        let mask = self.buckets?.len() - 1;
+        let mut index = hash as usize & mask;
+        // the load factor guarantees an empty bucket, so this terminates
+        loop {
+            match *self.entry(index) {
+                None => return None,
+                Some(ref entry) if entry.hash == hash && entry.key.borrow() == key => {
+                    return Some(index)
+                }
+                Some(_) => index = (index + 1) & mask,
+            }
+        }
+    }
+
+    /// Insert an entry whose key is not already present,
+    /// returning the index of its bucket.
+    ///
+    /// The table must have room for another entry.
+    fn insert_unique(&mut self, entry: Entry<K, V>) -> usize {
+        let mask = self.num_buckets() - 1;
+        let mut index = entry.hash as usize & mask;
+        while self.entry(index).is_some() {
+            index = (index + 1) & mask;
+        }
+        *self.entry_mut(index) = Some(entry);
+        self.len += 1;
+        index
+    }
+
+    /// Remove the entry in the specified bucket,
+    /// shifting back any entries which probed past it.
+    fn remove(&mut self, index: usize) -> Entry<K, V> {
+        let mask = self.num_buckets() - 1;
+        let removed = self.entry_mut(index).take().expect("Bucket is empty");
+        self.len -= 1;
+        let mut hole = index;
+        let mut index = (index + 1) & mask;
+        while let Some(ref entry) = *self.entry(index) {
+            let ideal = entry.hash as usize & mask;
+            // the entry can fill the hole if the hole lies between its ideal bucket and its current one
+            if index.wrapping_sub(ideal) & mask >= index.wrapping_sub(hole) & mask {
+                let moved = self.entry_mut(index).take();
+                *self.entry_mut(hole) = moved;
+                hole = index;
+            }
+            index = (index + 1) & mask;
+        }
+        removed
+    }
+
+    /// Move every entry into a newly allocated table with the specified number of buckets.
+    ///
+    /// The old buckets are left empty, and are reclaimed by the next collection.
+    fn resize(&mut self, collector: &'gc GarbageCollector<Id>, num_buckets: usize)
+    where
+        K: Collect<Id>,
+        V: Collect<Id>,
+    {
+        debug_assert!(num_buckets.is_power_of_two());
+        let buckets = collector.alloc_array_with(num_buckets, |_| Bucket { entry: None });
+        let mut old = std::mem::replace(
+            self,
+            RawTable {
+                buckets: Some(buckets),
+                len: 0,
+            },
+        );
+        for index in 0..old.num_buckets() {
+            if let Some(entry) = old.entry_mut(index).take() {
+                self.insert_unique(entry);
+            }
+        }
+    }
+}
+unsafe impl<'gc, Id: CollectorId, K: Collect<Id>, V: Collect<Id>> Collect<Id>
+    for RawTable<'gc, K, V, Id>
+{
+    type Collected<'newgc> = RawTable<'newgc, K::Collected<'newgc>, V::Collected<'newgc>, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        if let Some(ref mut buckets) = (*target.as_ptr()).buckets {
+            context.trace_gc_array_mut(NonNull::from(buckets));
+        }
+    }
+}
+
+/// A hash map which is allocated entirely in the GC heap.
+///
+/// The table is a [`GcArray`] of buckets,
+/// so the map is traced, moved, and reclaimed like any other object,
+/// and needs no destructor unless its keys or values do.
+/// By contrast, a `HashMap` stored in a GC object keeps its table in the malloc heap,
+/// which must be freed by the destructor of the object.
+///
+/// Keys must hash by value rather than by address,
+/// as objects may move during a collection.
+///
+/// Mutations must go through the containing [`Gc`] pointer,
+/// so that the write barrier can be performed.
+pub struct GcHashMap<'gc, K, V, Id: CollectorId> {
+    table: RefCell<RawTable<'gc, K, V, Id>>,
+    hash_builder: RandomState,
+}
+impl<'gc, K, V, Id: CollectorId> GcHashMap<'gc, K, V, Id> {
+    /// Create an empty map, which does not allocate until the first insertion.
+    #[inline]
+    pub fn new() -> Self {
+        GcHashMap {
+            table: RefCell::new(RawTable::new()),
+            hash_builder: RandomState::new(),
+        }
+    }
+
+    /// Create an empty map, with room for at least the specified number of entries.
+    pub fn with_capacity(collector: &'gc GarbageCollector<Id>, capacity: usize) -> Self
+    where
+        K: Collect<Id>,
+        V: Collect<Id>,
+    {
+        let mut table = RawTable::new();
+        if capacity > 0 {
+            table.resize(collector, Self::buckets_for(capacity));
+        }
+        GcHashMap {
+            table: RefCell::new(table),
+            hash_builder: RandomState::new(),
+        }
+    }
+
+    /// The number of buckets needed to hold the specified number of entries.
+    fn buckets_for(len: usize) -> usize {
+        let min_buckets = len.checked_mul(4).expect("Capacity overflow").div_ceil(3);
+        min_buckets.next_power_of_two().max(MIN_BUCKETS)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.table.borrow().len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of entries the map can hold without reallocating its table.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.table.borrow().num_buckets() / 4 * 3
+    }
+
+    /// Get the value corresponding to the specified key.
+    ///
+    /// The map cannot be mutated while the value is borrowed.
+    ///
+    /// ## Panics
+    /// If the map is currently being mutated,
+    /// which is only possible from the `Hash` or `Eq` implementation of a key.
+    pub fn get<Q>(&self, key: &Q) -> Option<Ref<'_, V>>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        let hash = self.hash_builder.hash_one(key);
+        Ref::filter_map(self.table.borrow(), |table| {
+            let index = table.find(hash, key)?;
+            table.entry(index).as_ref().map(|entry| &entry.value)
+        })
+        .ok()
+    }
+
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        let hash = self.hash_builder.hash_one(key);
+        self.table.borrow().find(hash, key).is_some()
+    }
+
+    /// Iterate over clones of the entries in the map, in an unspecified order.
+    ///
+    /// The map cannot be mutated until the iterator is dropped.
+    pub fn iter(&self) -> Iter<'_, 'gc, K, V, Id>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let table = self.table.borrow();
+        Iter {
+            remaining: table.len,
+            table,
+            index: 0,
+        }
+    }
+
+    /// Insert a key-value pair into the map, returning the previous value (if any).
+    ///
+    /// If the key is already present, the existing key is kept.
+    ///
+    /// ## Panics
+    /// If the map is currently borrowed, or if the containing object is [frozen](Gc::freeze).
+    #[track_caller]
+    pub fn insert(
+        this: &Gc<'gc, Self, Id>,
+        collector: &'gc GarbageCollector<Id>,
+        key: K,
+        value: V,
+    ) -> Option<V>
+    where
+        K: Collect<Id> + Hash + Eq,
+        V: Collect<Id>,
+    {
+        assert!(!this.is_frozen(collector), "Cannot mutate a frozen object");
+        let hash = this.hash_builder.hash_one(&key);
+        let mut table = this.table.borrow_mut();
+        if let Some(index) = table.find(hash, &key) {
+            let entry = table.entry_mut(index).as_mut().unwrap();
+            let old_value = std::mem::replace(&mut entry.value, value);
+            Self::bucket_written(&table, collector, index);
+            drop(table);
+            return Some(old_value);
+        }
+        if (table.len + 1) * 4 > table.num_buckets() * 3 {
+            let num_buckets = (table.num_buckets() * 2).max(MIN_BUCKETS);
+            table.resize(collector, num_buckets);
+            collector.record_mutation(NonNull::from(this.header()));
+            unsafe {
+                collector.write_barrier(*this, NonNull::new_unchecked(this.table.as_ptr()));
+            }
+        }
+        let index = table.insert_unique(Entry { hash, key, value });
+        Self::bucket_written(&table, collector, index);
+        None
+    }
+
+    /// Remove a key from the map, returning its value (if any).
+    ///
+    /// ## Panics
+    /// If the map is currently borrowed, or if the containing object is [frozen](Gc::freeze).
+    #[track_caller]
+    pub fn remove<Q>(
+        this: &Gc<'gc, Self, Id>,
+        collector: &'gc GarbageCollector<Id>,
+        key: &Q,
+    ) -> Option<V>
+    where
+        K: Collect<Id> + Borrow<Q>,
+        V: Collect<Id>,
+        Q: ?Sized + Hash + Eq,
+    {
+        assert!(!this.is_frozen(collector), "Cannot mutate a frozen object");
+        let hash = this.hash_builder.hash_one(key);
+        let mut table = this.table.borrow_mut();
+        let index = table.find(hash, key)?;
+        let entry = table.remove(index);
+        // later entries may have been shifted into the bucket
+        Self::bucket_written(&table, collector, index);
+        drop(table);
+        Some(entry.value)
+    }
+
+    /// Remove every entry from the map, releasing its table.
+    ///
+    /// ## Panics
+    /// If the map is currently borrowed, or if the containing object is [frozen](Gc::freeze).
+    #[track_caller]
+    pub fn clear(this: &Gc<'gc, Self, Id>, collector: &'gc GarbageCollector<Id>)
+    where
+        K: Collect<Id>,
+        V: Collect<Id>,
+    {
+        assert!(!this.is_frozen(collector), "Cannot mutate a frozen object");
+        let mut old = std::mem::replace(&mut *this.table.borrow_mut(), RawTable::new());
+        // drop the entries now, instead of whenever the table is collected
+        if std::mem::needs_drop::<Entry<K, V>>() {
+            for index in 0..old.num_buckets() {
+                drop(old.entry_mut(index).take());
+            }
+        }
+    }
+
+    /// Perform the write barrier after the specified bucket has been written.
+    #[inline]
+    fn bucket_written(
+        table: &RawTable<'gc, K, V, Id>,
+        collector: &'gc GarbageCollector<Id>,
+        index: usize,
+    ) where
+        K: Collect<Id>,
+        V: Collect<Id>,
+    {
+        if <Bucket<K, V> as Collect<Id>>::NEEDS_COLLECT {
+            let buckets = table.buckets.unwrap();
+            collector.record_mutation(NonNull::from(buckets.header().main_header()));
+            unsafe {
+                collector.write_barrier_array(buckets, index);
+            }
+        }
+    }
+}
+unsafe impl<'gc, Id: CollectorId, K: Collect<Id>, V: Collect<Id>> Collect<Id>
+    for GcHashMap<'gc, K, V, Id>
+{
+    type Collected<'newgc> = GcHashMap<'newgc, K::Collected<'newgc>, V::Collected<'newgc>, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        // borrows cannot outlive 'gc, so the table is never borrowed during a collection
+        RawTable::collect_inplace(
+            NonNull::new_unchecked(target.as_ref().table.as_ptr()),
+            context,
+        );
+    }
+}
+impl<'gc, K, V, Id: CollectorId> Default for GcHashMap<'gc, K, V, Id> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<'gc, K: Debug, V: Debug, Id: CollectorId> Debug for GcHashMap<'gc, K, V, Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let table = self.table.borrow();
+        f.debug_map()
+            .entries(table.entries().map(|entry| (&entry.key, &entry.value)))
+            .finish()
+    }
+}
+
+/// An iterator over clones of the entries in a [`GcHashMap`].
+///
+/// The map is borrowed until the iterator is dropped.
+pub struct Iter<'a, 'gc, K, V, Id: CollectorId> {
+    table: Ref<'a, RawTable<'gc, K, V, Id>>,
+    index: usize,
+    remaining: usize,
+}
+impl<'a, 'gc, K: Clone, V: Clone, Id: CollectorId> Iterator for Iter<'a, 'gc, K, V, Id> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        while self.index < self.table.num_buckets() {
+            let index = self.index;
+            self.index += 1;
+            if let Some(ref entry) = *self.table.entry(index) {
+                self.remaining -= 1;
+                return Some((entry.key.clone(), entry.value.clone()));
+            }
+        }
+        None
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+impl<'a, 'gc, K: Clone, V: Clone, Id: CollectorId> ExactSizeIterator for Iter<'a, 'gc, K, V, Id> {}
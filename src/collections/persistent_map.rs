@@ -0,0 +1,499 @@
+//! A persistent hash map, which shares structure between versions.
+//!
+//! This is a hash array mapped trie (HAMT).
+//! Each level consumes five bits of the hash,
+//! and each node stores only its present slots in a single immutable [`GcArray`],
+//! using a bitmap to find the slot for each hash.
+//! Updates copy the path from the root to the affected entry,
+//! and share every other node with the original map.
+
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::fmt::{Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+use std::ptr::NonNull;
+
+use super::{copy_insert, copy_remove, copy_replace};
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector, GcArray};
+
+/// The number of hash bits consumed by each level of the trie.
+const BITS: u32 = 5;
+const MASK: u64 = (1 << BITS) - 1;
+/// Nodes at or below this shift have consumed the entire hash,
+/// so they hold a list of entries with identical hashes instead of using a bitmap.
+const HASH_BITS: u32 = u64::BITS;
+
+/// The bit corresponding to a hash in the bitmap of a node.
+#[inline]
+fn hash_bit(hash: u64, shift: u32) -> u32 {
+    1 << ((hash >> shift) & MASK)
+}
+
+/// The index of the slot corresponding to a bit in the bitmap of a node.
+#[inline]
+fn slot_index(bitmap: u32, bit: u32) -> usize {
+    (bitmap & (bit - 1)).count_ones() as usize
+}
+
+enum Slot<'gc, K, V, Id: CollectorId> {
+    Entry {
+        hash: u64,
+        key: K,
+        value: V,
+    },
+    /// A subtree holding the entries whose hashes share the bits consumed so far.
+    Node {
+        bitmap: u32,
+        slots: GcArray<'gc, Slot<'gc, K, V, Id>, Id>,
+    },
+}
+impl<'gc, K, V, Id: CollectorId> Slot<'gc, K, V, Id> {
+    /// The value of this entry, if it has the specified key.
+    #[inline]
+    fn matching_value<Q>(&self, hash: u64, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq,
+    {
+        match *self {
+            Slot::Entry {
+                hash: entry_hash,
+                key: ref entry_key,
+                ref value,
+            } if entry_hash == hash && entry_key.borrow() == key => Some(value),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn entry_hash(&self) -> u64 {
+        match *self {
+            Slot::Entry { hash, .. } => hash,
+            Slot::Node { .. } => unreachable!("Expected an entry"),
+        }
+    }
+}
+impl<'gc, K: Clone, V: Clone, Id: CollectorId> Clone for Slot<'gc, K, V, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        match *self {
+            Slot::Entry {
+                hash,
+                ref key,
+                ref value,
+            } => Slot::Entry {
+                hash,
+                key: key.clone(),
+                value: value.clone(),
+            },
+            Slot::Node { bitmap, slots } => Slot::Node { bitmap, slots },
+        }
+    }
+}
+unsafe impl<'gc, Id: CollectorId, K: Collect<Id>, V: Collect<Id>> Collect<Id>
+    for Slot<'gc, K, V, Id>
+{
+    type Collected<'newgc> = Slot<'newgc, K::Collected<'newgc>, V::Collected<'newgc>, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        match *target.as_ptr() {
+            Slot::Entry {
+                ref mut key,
+                ref mut value,
+                ..
+            } => {
+                if K::NEEDS_COLLECT {
+                    K::collect_inplace(NonNull::from(key), context);
+                }
+                if V::NEEDS_COLLECT {
+                    V::collect_inplace(NonNull::from(value), context);
+                }
+            }
+            Slot::Node { ref mut slots, .. } => context.trace_gc_array_mut(NonNull::from(slots)),
+        }
+    }
+}
+
+/// A node of the trie, as its bitmap and slots.
+type Node<'gc, K, V, Id> = (u32, GcArray<'gc, Slot<'gc, K, V, Id>, Id>);
+
+/// Insert an entry into a copy of the specified node,
+/// returning the new node and whether the entry was added rather than replaced.
+fn insert_node<'gc, K, V, Id>(
+    collector: &'gc GarbageCollector<Id>,
+    bitmap: u32,
+    slots: &'gc [Slot<'gc, K, V, Id>],
+    shift: u32,
+    entry: Slot<'gc, K, V, Id>,
+) -> (Node<'gc, K, V, Id>, bool)
+where
+    K: Collect<Id> + Eq + Clone,
+    V: Collect<Id> + Clone,
+    Id: CollectorId,
+{
+    let Slot::Entry { hash, ref key, .. } = entry else {
+        unreachable!("Expected an entry")
+    };
+    if shift >= HASH_BITS {
+        let existing = slots
+            .iter()
+            .position(|slot| slot.matching_value(hash, key).is_some());
+        return match existing {
+            Some(index) => (
+                (bitmap, copy_replace(collector, slots, index, entry)),
+                false,
+            ),
+            None => (
+                (bitmap, copy_insert(collector, slots, slots.len(), entry)),
+                true,
+            ),
+        };
+    }
+    let bit = hash_bit(hash, shift);
+    let index = slot_index(bitmap, bit);
+    if bitmap & bit == 0 {
+        return (
+            (bitmap | bit, copy_insert(collector, slots, index, entry)),
+            true,
+        );
+    }
+    let (replacement, added) = match slots[index] {
+        Slot::Node {
+            bitmap: child_bitmap,
+            slots: child_slots,
+        } => {
+            let ((child_bitmap, child_slots), added) = insert_node(
+                collector,
+                child_bitmap,
+                child_slots.as_slice(),
+                shift + BITS,
+                entry,
+            );
+            let child = Slot::Node {
+                bitmap: child_bitmap,
+                slots: child_slots,
+            };
+            (child, added)
+        }
+        ref existing if existing.matching_value(hash, key).is_some() => (entry, false),
+        ref existing => (pair(collector, existing.clone(), entry, shift + BITS), true),
+    };
+    (
+        (bitmap, copy_replace(collector, slots, index, replacement)),
+        added,
+    )
+}
+
+/// Create a node holding two entries with different keys.
+fn pair<'gc, K, V, Id>(
+    collector: &'gc GarbageCollector<Id>,
+    first: Slot<'gc, K, V, Id>,
+    second: Slot<'gc, K, V, Id>,
+    shift: u32,
+) -> Slot<'gc, K, V, Id>
+where
+    K: Collect<Id>,
+    V: Collect<Id>,
+    Id: CollectorId,
+{
+    let (bitmap, mut slots) = if shift >= HASH_BITS {
+        (0, [Some(first), Some(second)])
+    } else {
+        let first_bit = hash_bit(first.entry_hash(), shift);
+        let second_bit = hash_bit(second.entry_hash(), shift);
+        if first_bit == second_bit {
+            let mut child = Some(pair(collector, first, second, shift + BITS));
+            return Slot::Node {
+                bitmap: first_bit,
+                slots: collector.alloc_array_with(1, |_| child.take().unwrap()),
+            };
+        } else if first_bit < second_bit {
+            (first_bit | second_bit, [Some(first), Some(second)])
+        } else {
+            (first_bit | second_bit, [Some(second), Some(first)])
+        }
+    };
+    Slot::Node {
+        bitmap,
+        slots: collector.alloc_array_with(2, |i| slots[i].take().unwrap()),
+    }
+}
+
+/// The result of removing a key from a node,
+/// as the remaining node (or `None` if it is now empty) and the removed value.
+type Removed<'gc, K, V, Id> = (Option<Node<'gc, K, V, Id>>, &'gc V);
+
+/// Remove a key from a copy of the specified node,
+/// returning `None` if the key is not present.
+fn remove_node<'gc, K, V, Id, Q>(
+    collector: &'gc GarbageCollector<Id>,
+    bitmap: u32,
+    slots: &'gc [Slot<'gc, K, V, Id>],
+    shift: u32,
+    hash: u64,
+    key: &Q,
+) -> Option<Removed<'gc, K, V, Id>>
+where
+    K: Collect<Id> + Borrow<Q> + Clone,
+    V: Collect<Id> + Clone,
+    Id: CollectorId,
+    Q: ?Sized + Eq,
+{
+    let index = if shift >= HASH_BITS {
+        slots
+            .iter()
+            .position(|slot| slot.matching_value(hash, key).is_some())?
+    } else {
+        let bit = hash_bit(hash, shift);
+        if bitmap & bit == 0 {
+            return None;
+        }
+        slot_index(bitmap, bit)
+    };
+    let (replacement, value) = match slots[index] {
+        Slot::Node {
+            bitmap: child_bitmap,
+            slots: child_slots,
+        } => {
+            let (child, value) = remove_node(
+                collector,
+                child_bitmap,
+                child_slots.as_slice(),
+                shift + BITS,
+                hash,
+                key,
+            )?;
+            // a node holding a single entry is replaced by the entry
+            let replacement =
+                child.map(
+                    |(child_bitmap, child_slots)| match *child_slots.as_slice() {
+                        [ref entry @ Slot::Entry { .. }] => entry.clone(),
+                        _ => Slot::Node {
+                            bitmap: child_bitmap,
+                            slots: child_slots,
+                        },
+                    },
+                );
+            (replacement, value)
+        }
+        ref entry => (None, entry.matching_value(hash, key)?),
+    };
+    let remaining = match replacement {
+        Some(slot) => Some((bitmap, copy_replace(collector, slots, index, slot))),
+        None if slots.len() == 1 => None,
+        None => {
+            let bitmap = if shift >= HASH_BITS {
+                bitmap
+            } else {
+                bitmap & !hash_bit(hash, shift)
+            };
+            Some((bitmap, copy_remove(collector, slots, index)))
+        }
+    };
+    Some((remaining, value))
+}
+
+/// An immutable hash map, whose updates share structure with the original.
+///
+/// Every update returns a new map in `O(log n)` time,
+/// copying only the nodes along the path to the modified entry.
+/// Since nodes are never mutated, entries can be borrowed for the entire lifetime `'gc`,
+/// and old versions remain valid for as long as they are reachable.
+///
+/// Keys must hash by value rather than by address,
+/// as objects may move during a collection.
+/// Maps derived from each other share the same hasher.
+pub struct GcPersistentMap<'gc, K, V, Id: CollectorId> {
+    root: Option<Node<'gc, K, V, Id>>,
+    len: usize,
+    hash_builder: RandomState,
+}
+impl<'gc, K, V, Id: CollectorId> GcPersistentMap<'gc, K, V, Id> {
+    #[inline]
+    pub fn new() -> Self {
+        GcPersistentMap {
+            root: None,
+            len: 0,
+            hash_builder: RandomState::new(),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+impl<'gc, K: 'gc, V: 'gc, Id: CollectorId> GcPersistentMap<'gc, K, V, Id> {
+    /// Get the value corresponding to the specified key.
+    pub fn get<Q>(&self, key: &Q) -> Option<&'gc V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        let hash = self.hash_builder.hash_one(key);
+        let (mut bitmap, root) = self.root?;
+        let mut slots = root.as_slice();
+        let mut shift = 0;
+        loop {
+            let slot = if shift >= HASH_BITS {
+                return slots.iter().find_map(|slot| slot.matching_value(hash, key));
+            } else {
+                let bit = hash_bit(hash, shift);
+                if bitmap & bit == 0 {
+                    return None;
+                }
+                &slots[slot_index(bitmap, bit)]
+            };
+            match *slot {
+                Slot::Entry { .. } => return slot.matching_value(hash, key),
+                Slot::Node {
+                    bitmap: child_bitmap,
+                    slots: child_slots,
+                } => {
+                    bitmap = child_bitmap;
+                    slots = child_slots.as_slice();
+                    shift += BITS;
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Iterate over the entries of the map, in an unspecified order.
+    #[inline]
+    pub fn iter(&self) -> Iter<'gc, K, V, Id> {
+        Iter {
+            stack: self
+                .root
+                .map(|(_, slots)| slots.as_slice().iter())
+                .into_iter()
+                .collect(),
+            remaining: self.len,
+        }
+    }
+}
+impl<'gc, K, V, Id> GcPersistentMap<'gc, K, V, Id>
+where
+    K: Collect<Id> + Hash + Eq + Clone + 'gc,
+    V: Collect<Id> + Clone + 'gc,
+    Id: CollectorId,
+{
+    /// Return a copy of this map with the specified key-value pair inserted,
+    /// replacing the previous value (if any).
+    pub fn insert(&self, collector: &'gc GarbageCollector<Id>, key: K, value: V) -> Self {
+        let hash = self.hash_builder.hash_one(&key);
+        let entry = Slot::Entry { hash, key, value };
+        let (bitmap, slots) = self.root.map_or((0, &[] as &[_]), |(bitmap, slots)| {
+            (bitmap, slots.as_slice())
+        });
+        let (root, added) = insert_node(collector, bitmap, slots, 0, entry);
+        GcPersistentMap {
+            root: Some(root),
+            len: self.len + usize::from(added),
+            hash_builder: self.hash_builder.clone(),
+        }
+    }
+
+    /// Return a copy of this map without the specified key,
+    /// along with the removed value.
+    ///
+    /// Returns `None` if the key is not present.
+    pub fn remove<Q>(&self, collector: &'gc GarbageCollector<Id>, key: &Q) -> Option<(Self, &'gc V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        let hash = self.hash_builder.hash_one(key);
+        let (bitmap, slots) = self.root?;
+        let (root, value) = remove_node(collector, bitmap, slots.as_slice(), 0, hash, key)?;
+        let map = GcPersistentMap {
+            root,
+            len: self.len - 1,
+            hash_builder: self.hash_builder.clone(),
+        };
+        Some((map, value))
+    }
+}
+unsafe impl<'gc, Id: CollectorId, K: Collect<Id>, V: Collect<Id>> Collect<Id>
+    for GcPersistentMap<'gc, K, V, Id>
+{
+    type Collected<'newgc> =
+        GcPersistentMap<'newgc, K::Collected<'newgc>, V::Collected<'newgc>, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        if let Some((_, ref mut slots)) = (*target.as_ptr()).root {
+            context.trace_gc_array_mut(NonNull::from(slots));
+        }
+    }
+}
+impl<'gc, K, V, Id: CollectorId> Clone for GcPersistentMap<'gc, K, V, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        GcPersistentMap {
+            root: self.root,
+            len: self.len,
+            hash_builder: self.hash_builder.clone(),
+        }
+    }
+}
+impl<'gc, K, V, Id: CollectorId> Default for GcPersistentMap<'gc, K, V, Id> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<'gc, K: Debug + 'gc, V: Debug + 'gc, Id: CollectorId> Debug
+    for GcPersistentMap<'gc, K, V, Id>
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// An iterator over the entries of a [`GcPersistentMap`].
+pub struct Iter<'gc, K, V, Id: CollectorId> {
+    /// The remaining slots of each node along the path to the next entry.
+    stack: Vec<std::slice::Iter<'gc, Slot<'gc, K, V, Id>>>,
+    remaining: usize,
+}
+impl<'gc, K, V, Id: CollectorId> Iterator for Iter<'gc, K, V, Id> {
+    type Item = (&'gc K, &'gc V);
+
+    fn next(&mut self) -> Option<(&'gc K, &'gc V)> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(Slot::Entry { key, value, .. }) => {
+                    self.remaining -= 1;
+                    return Some((key, value));
+                }
+                Some(Slot::Node { slots, .. }) => self.stack.push(slots.as_slice().iter()),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+impl<'gc, K, V, Id: CollectorId> ExactSizeIterator for Iter<'gc, K, V, Id> {}
@@ -0,0 +1,350 @@
+//! A persistent vector, which shares structure between versions.
+//!
+//! Elements are stored in a trie with 32-way branching,
+//! where each node is a single immutable [`GcArray`].
+//! Updates copy the path from the root to the affected leaf,
+//! and share every other node with the original vector.
+
+use std::fmt::{Debug, Formatter};
+use std::ptr::NonNull;
+
+use super::{copy_insert, copy_remove, copy_replace};
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector, GcArray};
+
+/// The number of index bits consumed by each level of the trie.
+const BITS: u32 = 5;
+const MASK: usize = (1 << BITS) - 1;
+
+enum Slot<'gc, T, Id: CollectorId> {
+    Branch(GcArray<'gc, Slot<'gc, T, Id>, Id>),
+    Value(T),
+}
+impl<'gc, T, Id: CollectorId> Slot<'gc, T, Id> {
+    #[inline]
+    fn branch(&self) -> GcArray<'gc, Slot<'gc, T, Id>, Id> {
+        match *self {
+            Slot::Branch(children) => children,
+            Slot::Value(_) => unreachable!("Expected a branch"),
+        }
+    }
+
+    #[inline]
+    fn value(&self) -> &T {
+        match *self {
+            Slot::Value(ref value) => value,
+            Slot::Branch(_) => unreachable!("Expected a value"),
+        }
+    }
+}
+impl<'gc, T: Clone, Id: CollectorId> Clone for Slot<'gc, T, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        match *self {
+            Slot::Branch(children) => Slot::Branch(children),
+            Slot::Value(ref value) => Slot::Value(value.clone()),
+        }
+    }
+}
+unsafe impl<'gc, Id: CollectorId, T: Collect<Id>> Collect<Id> for Slot<'gc, T, Id> {
+    type Collected<'newgc> = Slot<'newgc, T::Collected<'newgc>, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        match *target.as_ptr() {
+            Slot::Branch(ref mut children) => context.trace_gc_array_mut(NonNull::from(children)),
+            Slot::Value(ref mut value) => {
+                if T::NEEDS_COLLECT {
+                    T::collect_inplace(NonNull::from(value), context);
+                }
+            }
+        }
+    }
+}
+
+/// An immutable vector, whose updates share structure with the original.
+///
+/// Every update returns a new vector in `O(log n)` time,
+/// copying only the nodes along the path to the modified element.
+/// Since nodes are never mutated, elements can be borrowed for the entire lifetime `'gc`,
+/// and old versions remain valid for as long as they are reachable.
+///
+/// The vector itself is only a few words, so it can be copied freely.
+pub struct GcPersistentVec<'gc, T, Id: CollectorId> {
+    root: Option<GcArray<'gc, Slot<'gc, T, Id>, Id>>,
+    /// The number of index bits consumed below the root.
+    shift: u32,
+    len: usize,
+}
+impl<'gc, T, Id: CollectorId> GcPersistentVec<'gc, T, Id> {
+    #[inline]
+    pub const fn new() -> Self {
+        GcPersistentVec {
+            root: None,
+            shift: 0,
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The leaf node containing the specified index.
+    fn leaf(&self, index: usize) -> &'gc [Slot<'gc, T, Id>] {
+        debug_assert!(index < self.len);
+        let mut node = self.root.expect("Vector is empty").as_slice();
+        let mut shift = self.shift;
+        while shift > 0 {
+            node = node[(index >> shift) & MASK].branch().as_slice();
+            shift -= BITS;
+        }
+        node
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&'gc T> {
+        if index < self.len {
+            Some(self.leaf(index)[index & MASK].value())
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn first(&self) -> Option<&'gc T> {
+        self.get(0)
+    }
+
+    #[inline]
+    pub fn last(&self) -> Option<&'gc T> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> Iter<'gc, T, Id> {
+        Iter {
+            vec: *self,
+            index: 0,
+            leaf: &[],
+        }
+    }
+}
+impl<'gc, T: Collect<Id> + Clone, Id: CollectorId> GcPersistentVec<'gc, T, Id> {
+    /// Return a copy of this vector with the specified element appended.
+    pub fn push(&self, collector: &'gc GarbageCollector<Id>, value: T) -> Self {
+        let index = self.len;
+        let (root, shift) = match self.root {
+            Some(root) if index == 1 << (self.shift + BITS) => {
+                // the trie is full, so add a level above the root
+                let path = Self::push_node(collector, None, self.shift, index, value);
+                let new_root = collector
+                    .alloc_array_with(2, |i| Slot::Branch(if i == 0 { root } else { path }));
+                (new_root, self.shift + BITS)
+            }
+            root => (
+                Self::push_node(collector, root, self.shift, index, value),
+                self.shift,
+            ),
+        };
+        GcPersistentVec {
+            root: Some(root),
+            shift,
+            len: index + 1,
+        }
+    }
+
+    /// Copy the path to the specified index, appending the value to its leaf.
+    fn push_node(
+        collector: &'gc GarbageCollector<Id>,
+        node: Option<GcArray<'gc, Slot<'gc, T, Id>, Id>>,
+        shift: u32,
+        index: usize,
+        value: T,
+    ) -> GcArray<'gc, Slot<'gc, T, Id>, Id> {
+        let slots = node.map_or(&[] as &[_], |node| node.as_slice());
+        if shift == 0 {
+            return copy_insert(collector, slots, slots.len(), Slot::Value(value));
+        }
+        let child_index = (index >> shift) & MASK;
+        let child = Self::push_node(
+            collector,
+            slots.get(child_index).map(Slot::branch),
+            shift - BITS,
+            index,
+            value,
+        );
+        if child_index < slots.len() {
+            copy_replace(collector, slots, child_index, Slot::Branch(child))
+        } else {
+            copy_insert(collector, slots, child_index, Slot::Branch(child))
+        }
+    }
+
+    /// Return a copy of this vector with the specified element replaced.
+    ///
+    /// ## Panics
+    /// If the index is out of bounds.
+    #[track_caller]
+    pub fn set(&self, collector: &'gc GarbageCollector<Id>, index: usize, value: T) -> Self {
+        assert!(
+            index < self.len,
+            "Index {index} out of bounds for length {}",
+            self.len
+        );
+        let root = Self::set_node(collector, self.root.unwrap(), self.shift, index, value);
+        GcPersistentVec {
+            root: Some(root),
+            ..*self
+        }
+    }
+
+    fn set_node(
+        collector: &'gc GarbageCollector<Id>,
+        node: GcArray<'gc, Slot<'gc, T, Id>, Id>,
+        shift: u32,
+        index: usize,
+        value: T,
+    ) -> GcArray<'gc, Slot<'gc, T, Id>, Id> {
+        let slots = node.as_slice();
+        let child_index = (index >> shift) & MASK;
+        let slot = if shift == 0 {
+            Slot::Value(value)
+        } else {
+            let child = slots[child_index].branch();
+            Slot::Branch(Self::set_node(collector, child, shift - BITS, index, value))
+        };
+        copy_replace(collector, slots, child_index, slot)
+    }
+
+    /// Return a copy of this vector without its last element,
+    /// along with the removed element.
+    ///
+    /// Returns `None` if the vector is empty.
+    pub fn pop(&self, collector: &'gc GarbageCollector<Id>) -> Option<(Self, &'gc T)> {
+        let index = self.len.checked_sub(1)?;
+        let last = self.get(index)?;
+        if index == 0 {
+            return Some((Self::new(), last));
+        }
+        let mut root = Self::pop_node(collector, self.root.unwrap(), self.shift, index)
+            .expect("Root is nonempty");
+        let mut shift = self.shift;
+        // remove levels which have only a single child
+        while shift > 0 && root.len() == 1 {
+            root = root.as_slice()[0].branch();
+            shift -= BITS;
+        }
+        let vec = GcPersistentVec {
+            root: Some(root),
+            shift,
+            len: index,
+        };
+        Some((vec, last))
+    }
+
+    /// Copy the path to the specified index, removing the last element of its leaf.
+    ///
+    /// Returns `None` if the node is left empty.
+    fn pop_node(
+        collector: &'gc GarbageCollector<Id>,
+        node: GcArray<'gc, Slot<'gc, T, Id>, Id>,
+        shift: u32,
+        index: usize,
+    ) -> Option<GcArray<'gc, Slot<'gc, T, Id>, Id>> {
+        let slots = node.as_slice();
+        let child_index = (index >> shift) & MASK;
+        debug_assert_eq!(child_index, slots.len() - 1);
+        let child = if shift == 0 {
+            None
+        } else {
+            let child = slots[child_index].branch();
+            Self::pop_node(collector, child, shift - BITS, index)
+        };
+        match child {
+            Some(child) => Some(copy_replace(
+                collector,
+                slots,
+                child_index,
+                Slot::Branch(child),
+            )),
+            None if child_index == 0 => None,
+            None => Some(copy_remove(collector, slots, child_index)),
+        }
+    }
+}
+unsafe impl<'gc, Id: CollectorId, T: Collect<Id>> Collect<Id> for GcPersistentVec<'gc, T, Id> {
+    type Collected<'newgc> = GcPersistentVec<'newgc, T::Collected<'newgc>, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        if let Some(ref mut root) = (*target.as_ptr()).root {
+            context.trace_gc_array_mut(NonNull::from(root));
+        }
+    }
+}
+impl<'gc, T, Id: CollectorId> Copy for GcPersistentVec<'gc, T, Id> {}
+impl<'gc, T, Id: CollectorId> Clone for GcPersistentVec<'gc, T, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'gc, T, Id: CollectorId> Default for GcPersistentVec<'gc, T, Id> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<'gc, T: Debug, Id: CollectorId> Debug for GcPersistentVec<'gc, T, Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+impl<'gc, T: 'gc, Id: CollectorId> IntoIterator for GcPersistentVec<'gc, T, Id> {
+    type Item = &'gc T;
+    type IntoIter = Iter<'gc, T, Id>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the elements of a [`GcPersistentVec`].
+pub struct Iter<'gc, T, Id: CollectorId> {
+    vec: GcPersistentVec<'gc, T, Id>,
+    index: usize,
+    /// The leaf containing the current index.
+    leaf: &'gc [Slot<'gc, T, Id>],
+}
+impl<'gc, T, Id: CollectorId> Iterator for Iter<'gc, T, Id> {
+    type Item = &'gc T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'gc T> {
+        if self.index >= self.vec.len {
+            return None;
+        }
+        if self.index & MASK == 0 {
+            self.leaf = self.vec.leaf(self.index);
+        }
+        let value = self.leaf[self.index & MASK].value();
+        self.index += 1;
+        Some(value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.vec.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+impl<'gc, T, Id: CollectorId> ExactSizeIterator for Iter<'gc, T, Id> {}
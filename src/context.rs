@@ -1,28 +1,112 @@
 use std::alloc::Layout;
 use std::cell::{Cell, RefCell};
-use std::error::Error;
-use std::fmt::Debug;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::fmt::{self, Debug};
 use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::num::NonZeroU64;
 use std::ptr::NonNull;
 use std::rc::{Rc, Weak};
 
+use arbitrary_int::u2;
 use bitbybit::bitenum;
 
+use crate::array::{FixedWriter, GcArray, GcBytes, GcString, LengthCounter};
+use crate::context::dynamic::{DynGc, DynGcType, DynGcTypeBuilder, DynTypeError, DynTypeRegistry};
+use crate::context::ephemeron::EphemeronTable;
+use crate::context::guardian::GuardianTable;
+use crate::context::handle_scope::ScopeSlots;
+use crate::context::immortal::{ImmortalAllocError, ImmortalSpace};
 use crate::context::layout::{
-    GcArrayHeader, GcArrayLayoutInfo, GcArrayTypeInfo, GcHeader, GcMarkBits, GcStateBits,
-    GcTypeInfo, HeaderMetadata, TraceFuncPtr,
+    GcArrayHeader, GcArrayKind, GcArrayLayoutInfo, GcArrayTypeInfo, GcHeader, GcMarkBits,
+    GcStateBits, GcTypeInfo, HeaderMetadata, MovedFuncPtr, TraceFuncPtr,
 };
-use crate::context::old::OldGenerationSpace;
+use crate::context::mutation::MutationLog;
+use crate::context::old::{OldAllocError, OldGenerationSpace};
+use crate::context::snapshot::SnapshotRegistry;
+use crate::context::stats::GcStatsCounters;
 use crate::context::young::{YoungAllocError, YoungGenerationSpace};
 use crate::gcptr::Gc;
-use crate::utils::AbortFailureGuard;
-use crate::Collect;
+use crate::utils::{AbortFailureGuard, CallbackSlot};
+use crate::{Collect, NullCollect, PlacementHint};
 
 mod alloc;
+mod any_handle;
+mod branded;
+mod classify;
+mod config;
+#[cfg(feature = "conservative-roots")]
+mod conservative;
+pub mod dynamic;
+mod ephemeron;
+mod expiry;
+#[cfg(feature = "fault-injection")]
+mod faults;
+mod finalize;
+mod guardian;
+mod handle_scope;
+mod identity;
+mod immortal;
+mod interner;
+#[cfg(feature = "jit-layout")]
+pub mod jit_layout;
 pub(crate) mod layout;
+mod mutation;
 mod old;
+#[cfg(feature = "rayon")]
+mod par;
+#[cfg(feature = "profile")]
+mod profile;
+mod region;
+#[cfg(feature = "record-replay")]
+mod replay;
+mod roots;
+mod sampling;
+mod scoped;
+mod snapshot;
+mod stats;
+#[cfg(feature = "debug-tombstones")]
+mod tombstone;
+mod typed;
+#[cfg(debug_assertions)]
+mod verify;
+mod weak;
 mod young;
 
+pub use self::any_handle::GcAnyHandle;
+pub use self::branded::{BrandedGc, GcScope};
+pub use self::classify::{HeapObject, HeapSpace};
+pub use self::config::{DropOrder, GcConfig, TraceFailurePolicy};
+#[cfg(feature = "conservative-roots")]
+pub use self::conservative::ConservativeRangeId;
+pub use self::ephemeron::GcWeakMap;
+pub use self::expiry::GcExpiring;
+#[cfg(feature = "fault-injection")]
+pub use self::faults::AllocFailStage;
+pub use self::guardian::Guardian;
+pub use self::handle_scope::{HandleScope, ScopedHandle};
+pub use self::interner::{GcInterner, Symbol};
+pub use self::layout::GcArrayLayoutError;
+pub use self::old::{OldPage, SizeClassStats};
+#[cfg(feature = "rayon")]
+pub use self::par::{ParShare, ParShared};
+#[cfg(feature = "profile")]
+pub use self::profile::{AllocProfile, AllocSite};
+pub use self::region::GcRegion;
+#[cfg(feature = "record-replay")]
+pub use self::replay::{AllocTrace, TraceEvent, TraceParseError};
+use self::roots::RootState;
+pub use self::roots::{RootInfo, RootKind, RootSlotStats};
+pub use self::sampling::AllocSample;
+pub use self::scoped::ScopedId;
+pub use self::snapshot::{SnapshotDecoder, SnapshotEncoder, SnapshotError, SnapshotType};
+pub use self::stats::{CollectionReason, CollectionReasonCounts, GcStats, GcStatsDelta};
+#[cfg(feature = "debug-tombstones")]
+pub use self::tombstone::Tombstone;
+pub use self::typed::TypedAlloc;
+pub use self::weak::WeakGcHandle;
+
 pub enum SingletonStatus {
     /// The singleton is thread-local.
     ///
@@ -96,10 +180,88 @@ enum CollectStage {
 pub(crate) struct CollectorState<Id: CollectorId> {
     collector_id: Id,
     mark_bits_inverted: Cell<bool>,
+    /// Allocate new objects as black, so they survive the collection in progress.
+    ///
+    /// This is needed for objects allocated in the middle of a cycle,
+    /// which would otherwise be freed because tracing never reached them.
+    allocate_black: Cell<bool>,
+    /// Set while marking is in progress, enabling the mutation log.
+    marking: Cell<bool>,
+    /// The objects of the region being evacuated by [`GarbageCollector::scoped_young`],
+    /// or `None` if the entire heap is being collected.
+    ///
+    /// This points to a set owned by the evacuation, which outlives every use.
+    evacuating_region: Cell<Option<NonNull<HashSet<NonNull<GcHeader<Id>>>>>>,
+}
+impl<Id: CollectorId> CollectorState<Id> {
+    /// The mark bits for a newly allocated object.
+    #[inline]
+    fn initial_mark_bits(&self) -> GcMarkBits {
+        if self.allocate_black.get() {
+            GcMarkBits::Black
+        } else {
+            GcMarkBits::White
+        }
+    }
+
+    /// Check if only a single region is being evacuated, instead of collecting the entire heap.
+    #[inline]
+    fn evacuating_region(&self) -> bool {
+        self.evacuating_region.get().is_some()
+    }
+
+    /// Check if an object lies outside the region being evacuated.
+    ///
+    /// Such objects are treated as live, and are never marked or moved.
+    /// This is always `false` when collecting the entire heap.
+    #[inline]
+    unsafe fn outside_evacuated_region(&self, header: NonNull<GcHeader<Id>>) -> bool {
+        match self.evacuating_region.get() {
+            Some(objects) => !objects.as_ref().contains(&header),
+            None => false,
+        }
+    }
 }
 
 struct GcRootBox<Id: CollectorId> {
     header: Cell<NonNull<GcHeader<Id>>>,
+    kind: RootKind,
+    /// Changed when the collector is dropped or poisoned, so the object can no longer be used.
+    state: Cell<RootState>,
+    /// Where the root was created, for [`GarbageCollector::iter_roots`].
+    #[cfg(debug_assertions)]
+    location: &'static std::panic::Location<'static>,
+}
+impl<Id: CollectorId> GcRootBox<Id> {
+    #[inline]
+    #[track_caller]
+    fn new(header: NonNull<GcHeader<Id>>, kind: RootKind) -> Self {
+        GcRootBox {
+            header: Cell::new(header),
+            kind,
+            state: Cell::new(RootState::Alive),
+            #[cfg(debug_assertions)]
+            location: std::panic::Location::caller(),
+        }
+    }
+
+    #[inline]
+    #[track_caller]
+    fn assert_usable(&self) {
+        self.state.get().assert_usable();
+    }
+
+    #[inline]
+    fn location(&self) -> Option<&'static std::panic::Location<'static>> {
+        #[cfg(debug_assertions)]
+        {
+            Some(self.location)
+        }
+        #[cfg(not(debug_assertions))]
+        {
+            None
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -108,6 +270,11 @@ struct GenerationSizes {
     old_generation_size: usize,
 }
 impl GenerationSizes {
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.young_generation_size + self.old_generation_size
+    }
+
     const INITIAL_COLLECT_THRESHOLD: Self = GenerationSizes {
         young_generation_size: 12 * 1024,
         old_generation_size: 12 * 1204,
@@ -118,29 +285,183 @@ impl GenerationSizes {
         self.young_generation_size >= threshold.young_generation_size
             || self.old_generation_size >= threshold.old_generation_size
     }
+
+    /// The threshold for the next collection,
+    /// given the sizes at the end of the last one.
+    ///
+    /// Each generation may double before collecting again,
+    /// but never below its initial threshold.
+    /// Otherwise a generation which is empty after collecting
+    /// (like the young generation after evacuation)
+    /// would meet its threshold immediately, and every call to collect would collect again.
+    #[inline]
+    pub fn next_threshold(&self) -> GenerationSizes {
+        GenerationSizes {
+            young_generation_size: (self.young_generation_size * 2)
+                .max(Self::INITIAL_COLLECT_THRESHOLD.young_generation_size),
+            old_generation_size: (self.old_generation_size * 2)
+                .max(Self::INITIAL_COLLECT_THRESHOLD.old_generation_size),
+        }
+    }
 }
 
 pub struct GarbageCollector<Id: CollectorId> {
     state: CollectorState<Id>,
     young_generation: YoungGenerationSpace<Id>,
     old_generation: OldGenerationSpace<Id>,
+    immortal_space: ImmortalSpace<Id>,
     roots: RefCell<Vec<Weak<GcRootBox<Id>>>>,
-    last_collect_size: Option<GenerationSizes>,
+    /// The number of guards pinning each object, shared with the guards themselves.
+    pin_counts: Rc<PinCounts<Id>>,
+    handle_scopes: RefCell<Vec<Weak<ScopeSlots<Id>>>>,
+    ephemerons: RefCell<Vec<Weak<dyn EphemeronTable<Id>>>>,
+    guardians: RefCell<Vec<Weak<dyn GuardianTable<Id>>>>,
+    mutation_log: MutationLog<Id>,
+    /// The queue of gray objects, retained between collections to reuse its capacity.
+    gray_queue: Cell<VecDeque<NonNull<GcHeader<Id>>>>,
+    last_collect_size: Cell<Option<GenerationSizes>>,
+    /// The number of completed collections, which is never reset.
+    collection_count: Cell<u64>,
+    /// Bytes allocated since the last collection.
+    bytes_since_collect: Cell<usize>,
+    /// The value of `bytes_since_collect` at which [`GarbageCollector::should_collect`] returns `true`.
+    collect_watermark: Cell<usize>,
+    /// The value of `bytes_since_collect` at which the heap may exceed the [soft limit](GcConfig::soft_heap_limit).
+    soft_limit_watermark: Cell<usize>,
+    /// Set when the heap grows past the [soft limit](GcConfig::soft_heap_limit),
+    /// until the next collection.
+    soft_limit_exceeded: Cell<bool>,
+    soft_limit_callback: CallbackSlot<dyn FnMut(usize)>,
+    collection_recommended: Cell<bool>,
+    /// Set when an allocation is rejected by the [heap limit](GcConfig::heap_limit),
+    /// so the next call to [`GarbageCollector::collect`] will free up space.
+    heap_limit_exceeded: Cell<bool>,
+    trigger_callback: CallbackSlot<dyn FnMut(usize)>,
+    error_hook: CallbackSlot<dyn FnMut(&GcError)>,
+    sampler: self::sampling::AllocSampler,
+    /// Set for the duration of a collection,
+    /// so that it remains set if a destructor panics while sweeping.
+    poisoned: Cell<bool>,
+    trace_failure_policy: Cell<TraceFailurePolicy>,
+    /// Set for the duration of a collection,
+    /// to reject reentrant collections and allocations.
+    collecting: Cell<bool>,
+    /// The number of objects which are currently being initialized,
+    /// which would be corrupted by a collection.
+    initializing: Cell<usize>,
+    config: GcConfig,
+    stats: GcStatsCounters,
+    identity_hashes: self::identity::IdentityHashes<Id>,
+    finalizers: self::finalize::Finalizers<Id>,
+    dyn_types: DynTypeRegistry<Id>,
+    snapshots: SnapshotRegistry<Id>,
+    /// The region reused by [`GarbageCollector::scoped_young`], created on first use.
+    scoped_region: Cell<Option<GcRegion<Id>>>,
+    /// Set while the closure of [`GarbageCollector::scoped_young`] is running.
+    remembering: Cell<bool>,
+    /// Objects outside the scoped region which may refer into it,
+    /// because they were allocated or mutated while `remembering`.
+    ///
+    /// Every collection empties the region, so this is cleared as well.
+    remembered: RefCell<Vec<NonNull<GcHeader<Id>>>>,
+    #[cfg(feature = "debug-tombstones")]
+    tombstones: self::tombstone::TombstoneTracker<Id>,
+    #[cfg(feature = "profile")]
+    profile: self::profile::AllocProfiler,
+    #[cfg(feature = "record-replay")]
+    recorder: self::replay::TraceRecorder,
+    #[cfg(feature = "fault-injection")]
+    faults: self::faults::FaultInjector,
+    #[cfg(feature = "conservative-roots")]
+    conservative_roots: self::conservative::ConservativeRoots,
     collector_id: Id,
 }
 impl<Id: CollectorId> GarbageCollector<Id> {
-    pub unsafe fn with_id(id: Id) -> Self {
-        GarbageCollector {
+    crate::utils::unsafe_api! {
+        /// Create a garbage collector with the specified id.
+        ///
+        /// Only public with the `unsafe-api` feature.
+        /// Otherwise, use [`GarbageCollector::scoped`].
+        ///
+        /// ## Safety
+        /// The [`CollectorId`] must be unique.
+        pub unsafe fn with_id(id: Id) -> Self {
+            Self::with_config(id, GcConfig::default())
+        }
+    }
+
+    crate::utils::unsafe_api! {
+        /// Create a garbage collector with the specified id and configuration.
+        ///
+        /// Only public with the `unsafe-api` feature.
+        /// Otherwise, use [`GarbageCollector::scoped_with_config`].
+        ///
+        /// ## Safety
+        /// The same requirements as [`Self::with_id`] apply.
+        /// The [`CollectorId`] must be unique.
+        pub unsafe fn with_config(id: Id, config: GcConfig) -> Self {
+            Self::new_unchecked(id, config)
+        }
+    }
+
+    unsafe fn new_unchecked(id: Id, config: GcConfig) -> Self {
+        let collector = GarbageCollector {
             state: CollectorState {
                 collector_id: id,
                 mark_bits_inverted: Cell::new(false),
+                allocate_black: Cell::new(false),
+                marking: Cell::new(false),
+                evacuating_region: Cell::new(None),
             },
-            young_generation: YoungGenerationSpace::new(id),
+            young_generation: YoungGenerationSpace::new(id, &config),
             old_generation: OldGenerationSpace::new(id),
+            immortal_space: ImmortalSpace::new(id),
             roots: RefCell::new(Vec::new()),
-            last_collect_size: None,
+            pin_counts: Rc::new(PinCounts::new()),
+            handle_scopes: RefCell::new(Vec::new()),
+            ephemerons: RefCell::new(Vec::new()),
+            guardians: RefCell::new(Vec::new()),
+            mutation_log: MutationLog::new(),
+            gray_queue: Cell::new(VecDeque::new()),
+            last_collect_size: Cell::new(None),
+            collection_count: Cell::new(0),
+            bytes_since_collect: Cell::new(0),
+            collect_watermark: Cell::new(0),
+            soft_limit_watermark: Cell::new(usize::MAX),
+            soft_limit_exceeded: Cell::new(false),
+            soft_limit_callback: CallbackSlot::new(),
+            collection_recommended: Cell::new(false),
+            heap_limit_exceeded: Cell::new(false),
+            trigger_callback: CallbackSlot::new(),
+            error_hook: CallbackSlot::new(),
+            sampler: self::sampling::AllocSampler::new(),
+            poisoned: Cell::new(false),
+            trace_failure_policy: Cell::new(TraceFailurePolicy::Abort),
+            collecting: Cell::new(false),
+            initializing: Cell::new(0),
+            stats: GcStatsCounters::new(),
+            identity_hashes: self::identity::IdentityHashes::new(),
+            finalizers: self::finalize::Finalizers::new(),
+            dyn_types: DynTypeRegistry::new(),
+            snapshots: SnapshotRegistry::new(),
+            scoped_region: Cell::new(None),
+            remembering: Cell::new(false),
+            remembered: RefCell::new(Vec::new()),
+            #[cfg(feature = "debug-tombstones")]
+            tombstones: self::tombstone::TombstoneTracker::new(config.tombstone_capacity),
+            #[cfg(feature = "profile")]
+            profile: self::profile::AllocProfiler::new(),
+            #[cfg(feature = "record-replay")]
+            recorder: self::replay::TraceRecorder::new(),
+            #[cfg(feature = "fault-injection")]
+            faults: self::faults::FaultInjector::new(),
+            #[cfg(feature = "conservative-roots")]
+            conservative_roots: self::conservative::ConservativeRoots::new(),
+            config,
             collector_id: id,
-        }
+        };
+        collector.update_collect_watermark();
+        collector
     }
 
     #[inline]
@@ -148,177 +469,2524 @@ impl<Id: CollectorId> GarbageCollector<Id> {
         self.collector_id
     }
 
+    #[inline]
+    pub fn config(&self) -> &'_ GcConfig {
+        &self.config
+    }
+
+    /// The number of bytes that can be allocated before reaching the
+    /// [heap limit](GcConfig::heap_limit).
+    ///
+    /// Returns `None` if the heap is unlimited.
+    #[inline]
+    pub fn heap_headroom(&self) -> Option<usize> {
+        self.config
+            .heap_limit
+            .map(|limit| limit.saturating_sub(self.heap_size()))
+    }
+
+    /// Check that the heap has room for an additional `bytes`,
+    /// returning a recoverable error if it does not.
+    ///
+    /// This mirrors [`Vec::try_reserve`],
+    /// allowing growable containers to fail gracefully
+    /// instead of hitting a fatal allocation error mid-growth.
+    ///
+    /// Nothing is actually allocated,
+    /// so the reservation is only valid until the next allocation.
+    #[inline]
+    pub fn try_reserve(&self, bytes: usize) -> Result<(), TryReserveError> {
+        match self.heap_headroom() {
+            Some(remaining) if bytes > remaining => Err(TryReserveError::HeapLimitExceeded {
+                requested: bytes,
+                remaining,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that the heap has room for an array of `len` elements of type `T`,
+    /// including the space needed for the array's header.
+    ///
+    /// See [`Self::try_reserve`] for details.
+    #[inline]
+    pub fn try_reserve_array<T: Collect<Id>>(&self, len: usize) -> Result<(), TryReserveError> {
+        let layout_info = GcArrayLayoutInfo::<Id>::new(Layout::new::<T>(), len)
+            .map_err(TryReserveError::InvalidLayout)?;
+        self.try_reserve(layout_info.overall_layout().size())
+    }
+
+    /// Take a snapshot of the collector's cumulative statistics.
+    #[inline]
+    pub fn stats_snapshot(&self) -> GcStats {
+        self.stats.snapshot()
+    }
+
+    /// Compute the change in statistics since the `since` snapshot was taken.
+    ///
+    /// This is equivalent to `self.stats_snapshot().delta(since)`.
+    #[inline]
+    pub fn stats_delta(&self, since: &GcStats) -> GcStatsDelta {
+        self.stats_snapshot().delta(since)
+    }
+
+    /// Reset all statistics counters to zero.
+    ///
+    /// Deltas computed against snapshots taken before the reset are meaningless.
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+    }
+
+    /// Statistics on each size class of the old generation, ordered by block size.
+    ///
+    /// Small old objects are segregated into size classes,
+    /// and the memory of dead objects is reused by later objects of the same class.
+    /// Larger objects are not included.
+    /// Unlike the [statistics](Self::stats_snapshot), these are never reset.
+    pub fn old_size_class_stats(&self) -> Vec<SizeClassStats> {
+        self.old_generation.size_class_stats()
+    }
+
+    /// Describe each page of the old generation which contains objects, in address order.
+    ///
+    /// This shows how densely each page is occupied,
+    /// so fragmentation can be analyzed by external tools.
+    /// The pages are a snapshot, computed by scanning every old object.
+    ///
+    /// ## Panics
+    /// If called during a collection, for example by a destructor.
+    pub fn iter_old_pages(&self) -> impl Iterator<Item = OldPage> {
+        assert!(
+            !self.collecting.get(),
+            "Cannot inspect the heap during a collection"
+        );
+        unsafe { self.old_generation.iter_pages() }
+    }
+
+    /// The number of collections this collector has completed.
+    ///
+    /// Objects can only move during a collection,
+    /// so this acts as an epoch for caches keyed by object addresses:
+    /// any address recorded in the same epoch is still valid.
+    /// Unlike the [statistics](Self::stats_snapshot), this is never reset.
+    #[inline]
+    pub fn collection_count(&self) -> u64 {
+        self.collection_count.get()
+    }
+
     #[inline(always)]
+    #[track_caller]
     pub fn alloc<T: Collect<Id>>(&self, value: T) -> Gc<'_, T, Id> {
         self.alloc_with(|| value)
     }
 
+    /// Allocate a GC object, returning an error instead of panicking on failure.
+    ///
+    /// This fails if the allocation would exceed the [heap limit](GcConfig::heap_limit),
+    /// if memory is exhausted, if the collector is [poisoned](GcError::Poisoned),
+    /// or if a [collection is in progress](GcError::CollectionInProgress).
+    #[inline]
+    #[track_caller]
+    pub fn try_alloc<T: Collect<Id>>(&self, value: T) -> Result<Gc<'_, T, Id>, GcError> {
+        // checked first, since the collector is also poisoned during a collection
+        self.check_can_alloc()
+            .map_err(|error| self.report_error(error))?;
+        if self.poisoned.get() {
+            return Err(self.report_error(GcError::Poisoned));
+        }
+        unsafe {
+            let header = self.try_alloc_raw(&RegularAlloc::new::<T>(&self.state))?;
+            Ok(self.init_alloc(header, || value))
+        }
+    }
+
     /// Allocate a GC object, initializng it with the specified closure.
     #[inline(always)]
     #[track_caller]
     pub fn alloc_with<T: Collect<Id>>(&self, func: impl FnOnce() -> T) -> Gc<'_, T, Id> {
         unsafe {
-            let header = self.alloc_raw(&RegularAlloc {
-                state: &self.state,
-                type_info: GcTypeInfo::new::<T>(),
-            });
-            let initialization_guard = DestroyUninitValueGuard {
-                header,
-                old_generation: &self.old_generation,
-            };
-            let value_ptr = header.as_ref().regular_value_ptr().cast::<T>();
-            value_ptr.as_ptr().write(func());
+            let header = self.alloc_raw(&RegularAlloc::new::<T>(&self.state));
+            self.init_alloc(header, func)
+        }
+    }
+
+    /// Allocate a GC object for each of the values,
+    /// reserving a single contiguous block of the young generation for all of them.
+    ///
+    /// This is much cheaper than allocating each value separately,
+    /// which helps when creating many objects at once,
+    /// like the builtins of an interpreter during startup.
+    /// The objects are returned in the order of the values.
+    /// If they do not fit in a single block, each value is allocated individually.
+    ///
+    /// ## Panics
+    /// If allocation fails.
+    #[track_caller]
+    pub fn alloc_many<T, I>(&self, values: I) -> Vec<Gc<'_, T, Id>>
+    where
+        T: Collect<Id>,
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let values = values.into_iter();
+        let count = values.len();
+        unsafe { self.alloc_many_in(&RegularAlloc::new::<T>(&self.state), values, count) }
+    }
+
+    /// Allocate the values in a single block if there are exactly `count` of them,
+    /// allocating any others individually.
+    ///
+    /// ## Safety
+    /// The target must be for values of type `T`.
+    #[track_caller]
+    unsafe fn alloc_many_in<T: Collect<Id>>(
+        &self,
+        target: &RegularAlloc<'_, Id>,
+        mut values: impl Iterator<Item = T>,
+        count: usize,
+    ) -> Vec<Gc<'_, T, Id>> {
+        let mut result = Vec::with_capacity(count);
+        if count > 0 {
+            let batch = self
+                .try_alloc_batch_raw(target, count)
+                .unwrap_or_else(|error| self.oom(error));
+            if let Some(headers) = batch {
+                for header in headers {
+                    // stop early if the iterator misreported its length,
+                    // leaving the remaining objects uninitialized
+                    let Some(value) = values.next() else {
+                        break;
+                    };
+                    result.push(self.init_alloc(header, || value));
+                }
+            }
+        }
+        for value in values {
+            let header = self.alloc_raw(target);
+            result.push(self.init_alloc(header, || value));
+        }
+        result
+    }
+
+    /// Create a facade for allocating many objects of type `T`,
+    /// which resolves the type's metadata once instead of on every allocation.
+    #[inline]
+    pub fn typed<T: Collect<Id>>(&self) -> TypedAlloc<'_, T, Id> {
+        TypedAlloc::new(self, RegularAlloc::new::<T>(&self.state))
+    }
+
+    /// Allocate a GC object whose address never changes.
+    ///
+    /// The object is allocated directly in the old generation,
+    /// and is permanently [pinned](GcPin).
+    #[track_caller]
+    pub fn alloc_pinned<T: Collect<Id>>(&self, value: T) -> Gc<'_, T, Id> {
+        unsafe {
+            let target = RegularAlloc::new::<T>(&self.state);
+            let size = target.overall_layout().size();
+            self.check_can_alloc()
+                .and_then(|()| self.reserve_alloc(size))
+                .unwrap_or_else(|error| self.oom(error));
+            self.stats.record_alloc(size);
+            self.record_allocated_bytes(size);
+            let header = self.alloc_raw_fallback(&target);
+            self.record_alloc_hooks(header, size);
             header
                 .as_ref()
-                .update_state_bits(|state| state.with_value_initialized(true));
-            initialization_guard.defuse(); // successful initialization;
-            Gc::from_raw_ptr(value_ptr)
+                .update_state_bits(|state| state.with_pinned(true));
+            self.init_alloc(header, || value)
         }
     }
 
-    #[inline]
-    unsafe fn alloc_raw<T: RawAllocTarget<Id>>(&self, target: &T) -> NonNull<T::Header> {
-        match self.young_generation.alloc_raw(target) {
-            Ok(res) => res,
-            Err(YoungAllocError::SizeExceedsLimit) => self.alloc_raw_fallback(target),
-            Err(error @ YoungAllocError::OutOfMemory) => Self::oom(error),
+    /// Allocate an object which is never collected.
+    ///
+    /// Immortal objects are never marked, moved, or swept,
+    /// and live until the collector is destroyed.
+    /// This is suitable for constants like builtin strings.
+    ///
+    /// If the value contains GC pointers, it is traced like a root on every collection.
+    #[track_caller]
+    pub fn alloc_immortal<T: Collect<Id>>(&self, value: T) -> Gc<'_, T, Id> {
+        unsafe {
+            let target = RegularAlloc::new::<T>(&self.state);
+            self.check_can_alloc()
+                .and_then(|()| self.reserve_alloc(target.overall_layout().size()))
+                .unwrap_or_else(|error| self.oom(error));
+            let header = self.immortal_space.alloc_raw(&target).unwrap_or_else(
+                |ImmortalAllocError::OutOfMemory| {
+                    self.oom(GcError::AllocFailure {
+                        size: target.overall_layout().size(),
+                    })
+                },
+            );
+            self.stats.record_alloc(target.overall_layout().size());
+            self.record_allocated_bytes(target.overall_layout().size());
+            #[cfg(feature = "profile")]
+            self.profile.record_alloc(
+                header,
+                target.overall_layout().size(),
+                std::panic::Location::caller(),
+            );
+            #[cfg(feature = "record-replay")]
+            self.recorder.record_alloc(
+                header.as_ref().type_info().type_name(),
+                target.overall_layout().size(),
+            );
+            self.sample_alloc(header, target.overall_layout().size());
+            self.init_alloc(header, || value)
         }
     }
 
-    #[cold]
-    unsafe fn alloc_raw_fallback<T: RawAllocTarget<Id>>(&self, target: &T) -> NonNull<T::Header> {
-        self.old_generation
-            .alloc_raw(target)
-            .unwrap_or_else(|err| Self::oom(err))
+    /// Allocate a GC object, initializing it with a fallible closure.
+    ///
+    /// If the closure returns an error,
+    /// the reserved memory is reclaimed and the error is returned.
+    #[inline(always)]
+    #[track_caller]
+    pub fn alloc_try_with<T: Collect<Id>, E>(
+        &self,
+        func: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Gc<'_, T, Id>, E> {
+        unsafe {
+            let header = self.alloc_raw(&RegularAlloc::new::<T>(&self.state));
+            self.try_init_alloc(header, func)
+        }
     }
 
-    #[cold]
-    #[inline(never)]
-    fn oom<E: Error>(error: E) -> ! {
-        panic!("Fatal allocation error: {error}")
+    /// Allocate a GC object, constructing it directly in GC memory.
+    ///
+    /// This avoids constructing large values on the stack and then copying them into the heap.
+    /// The closure must initialize the slot and return the resulting reference,
+    /// as given by [`MaybeUninit::write`] or [`MaybeUninit::assume_init_mut`].
+    ///
+    /// ## Panics
+    /// If the returned reference does not point to the provided slot.
+    #[inline(always)]
+    #[track_caller]
+    pub fn alloc_emplace<T: Collect<Id>>(
+        &self,
+        func: impl for<'a> FnOnce(&'a mut MaybeUninit<T>) -> &'a mut T,
+    ) -> Gc<'_, T, Id> {
+        unsafe {
+            let header = self.alloc_raw(&RegularAlloc::new::<T>(&self.state));
+            let res = self.try_init_alloc_in_place(header, |value_ptr: NonNull<T>| {
+                let slot = &mut *value_ptr.as_ptr().cast::<MaybeUninit<T>>();
+                let initialized = NonNull::from(func(slot));
+                assert_eq!(initialized, value_ptr, "Must initialize the provided slot");
+                Ok::<(), Infallible>(())
+            });
+            match res {
+                Ok(value) => value,
+                Err(infallible) => match infallible {},
+            }
+        }
     }
 
+    /// The young generation's allocation cursor,
+    /// for JIT compilers which inline the allocation fast path.
+    ///
+    /// The region behind the cursor is empty until the first [slow path](Self::jit_alloc_slow).
+    #[cfg(feature = "jit-layout")]
     #[inline]
-    pub fn root<'gc, T: Collect<Id>>(
-        &'gc self,
-        val: Gc<'gc, T, Id>,
-    ) -> GcHandle<T::Collected<'static>, Id> {
-        let mut roots = self.roots.borrow_mut();
-        let root = Rc::new(GcRootBox {
-            header: Cell::new(NonNull::from(val.header())),
-        });
-        roots.push(Rc::downgrade(&root));
-        drop(roots); // drop refcell guard
-        GcHandle {
-            ptr: root,
-            id: self.id(),
-            marker: PhantomData,
+    pub fn allocation_cursor(&self) -> jit_layout::AllocationCursor {
+        unsafe { self.young_generation.allocation_cursor() }
+    }
+
+    /// How generated code allocates objects of type `T` using the [allocation cursor](Self::allocation_cursor).
+    ///
+    /// Returns `None` if `T` must always take the [slow path](Self::jit_alloc_slow),
+    /// because it needs to be dropped, is over-aligned, is too large for the young generation,
+    /// or is [placed](PlacementHint::Old) directly in the old generation.
+    #[cfg(feature = "jit-layout")]
+    pub fn jit_alloc_template<T: Collect<Id>>(&self) -> Option<jit_layout::JitAllocTemplate> {
+        let target = RegularAlloc::new::<T>(&self.state);
+        let size = target.overall_layout().size();
+        if target.needs_drop()
+            || target.header_offset() != 0
+            || target.placement() == PlacementHint::Old
+            || size > self.config.young_object_limit
+        {
+            return None;
         }
+        let state_bits = target
+            .init_state_bits(GenerationId::Young)
+            .with_value_initialized(true);
+        Some(jit_layout::JitAllocTemplate::new(
+            state_bits,
+            target.header_metadata(),
+            self.id(),
+            size,
+        ))
     }
 
-    #[inline]
-    pub fn collect(&mut self) {
-        if self.needs_collection() {
-            self.force_collect();
+    /// The slow path of inline allocation,
+    /// which allocates an object of type `T` normally and then refills the [allocation cursor](Self::allocation_cursor).
+    ///
+    /// ## Safety
+    /// The returned value is uninitialized, but the collector considers it initialized.
+    /// It must be fully initialized before anything which can collect, or drop the collector.
+    #[cfg(feature = "jit-layout")]
+    #[cfg_attr(any(feature = "debug-tombstones", feature = "profile"), track_caller)]
+    pub unsafe fn jit_alloc_slow<T: Collect<Id>>(&self) -> NonNull<T> {
+        let header = self.alloc_raw(&RegularAlloc::new::<T>(&self.state));
+        header
+            .as_ref()
+            .update_state_bits(|state| state.with_value_initialized(true));
+        self.refill_allocation_cursor();
+        header.as_ref().regular_value_ptr().cast()
+    }
+
+    #[cfg(feature = "jit-layout")]
+    fn refill_allocation_cursor(&self) {
+        let size = self.config.young_object_limit;
+        if self.config.heap_limit.is_some() && self.try_reserve(size).is_err() {
+            // keep taking the slow path, which reports the limit
+            return;
+        }
+        if let Ok(size) = unsafe { self.young_generation.refill_allocation_cursor() } {
+            self.record_allocated_bytes(size);
         }
     }
 
-    #[cold]
-    pub fn force_collect(&mut self) {
-        // mark roots
-        let mut context = CollectContext {
-            garbage_collector: self,
-            id: self.collector_id,
+    /// Initialize a freshly allocated object with the specified closure.
+    #[inline(always)]
+    unsafe fn init_alloc<T: Collect<Id>>(
+        &self,
+        header: NonNull<GcHeader<Id>>,
+        func: impl FnOnce() -> T,
+    ) -> Gc<'_, T, Id> {
+        match self.try_init_alloc(header, || Ok::<T, Infallible>(func())) {
+            Ok(value) => value,
+            Err(infallible) => match infallible {},
+        }
+    }
+
+    /// Initialize a freshly allocated object with a fallible closure.
+    ///
+    /// On failure, the guard destroys the uninitialized object.
+    #[inline(always)]
+    unsafe fn try_init_alloc<T: Collect<Id>, E>(
+        &self,
+        header: NonNull<GcHeader<Id>>,
+        func: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Gc<'_, T, Id>, E> {
+        self.try_init_alloc_in_place(header, |value_ptr: NonNull<T>| {
+            value_ptr.as_ptr().write(func()?);
+            Ok(())
+        })
+    }
+
+    /// Initialize a freshly allocated object by writing directly to its value pointer.
+    ///
+    /// The closure must fully initialize the value if it returns successfully.
+    #[inline(always)]
+    unsafe fn try_init_alloc_in_place<T: Collect<Id>, E>(
+        &self,
+        header: NonNull<GcHeader<Id>>,
+        func: impl FnOnce(NonNull<T>) -> Result<(), E>,
+    ) -> Result<Gc<'_, T, Id>, E> {
+        let initialization_guard = self.uninit_guard(header);
+        let value_ptr = header.as_ref().regular_value_ptr().cast::<T>();
+        func(value_ptr)?;
+        header
+            .as_ref()
+            .update_state_bits(|state| state.with_value_initialized(true));
+        initialization_guard.defuse(); // successful initialization;
+        Ok(Gc::from_raw_ptr(value_ptr))
+    }
+
+    #[inline]
+    fn uninit_guard(&self, header: NonNull<GcHeader<Id>>) -> DestroyUninitValueGuard<'_, Id> {
+        self.initializing.set(self.initializing.get() + 1);
+        let guard = DestroyUninitValueGuard {
+            header,
+            old_generation: &self.old_generation,
+            initializing: &self.initializing,
+            #[cfg(feature = "debug-tombstones")]
+            tombstones: &self.tombstones,
+            #[cfg(feature = "debug-tombstones")]
+            gc_number: self.stats.collections() + 1,
         };
-        let failure_guard = AbortFailureGuard::new("GC failure to trace is fatal");
-        let mut roots = self.roots.borrow_mut();
-        roots.retain(|root| {
-            match root.upgrade() {
-                Some(root) => {
-                    let new_header = unsafe { context.collect_gcheader(root.header.get()) };
-                    root.header.set(new_header);
-                    true // keep live root
-                }
-                None => false, // delete dead root
-            }
-        });
-        drop(roots); // release guard
-                     // tracing failure is fatal, but sweeping fatal is fine
-        failure_guard.defuse();
-        // now sweep
-        unsafe {
-            self.young_generation.sweep(&self.state);
-            self.old_generation.sweep(&self.state);
+        #[cfg(feature = "fault-injection")]
+        if self
+            .faults
+            .should_fail(self::faults::AllocFailStage::Initialization)
+        {
+            // the guard reclaims the object while unwinding
+            panic!("Injected failure initializing {}", unsafe {
+                header.as_ref().type_info().type_name()
+            });
         }
-        // touch roots to verify validity
-        #[cfg(debug_assertions)]
-        for root in self.roots.get_mut().iter() {
-            unsafe {
-                assert!(!root
-                    .upgrade()
-                    .unwrap()
-                    .header
-                    .get()
-                    .as_ref()
-                    .state_bits
-                    .get()
-                    .forwarded());
+        guard
+    }
+
+    /// Allocate an array of `len` elements,
+    /// initializing each element by calling `func` with its index.
+    ///
+    /// ## Panics
+    /// If the size of the array overflows,
+    /// or if `func` panics (after dropping the initialized elements).
+    #[track_caller]
+    pub fn alloc_array_with<T: Collect<Id>>(
+        &self,
+        len: usize,
+        mut func: impl FnMut(usize) -> T,
+    ) -> GcArray<'_, T, Id> {
+        unsafe {
+            let header = self.alloc_raw_array::<T>(len, GcArrayTypeInfo::new::<T>());
+            let initialization_guard = self.uninit_guard(header.cast());
+            let elements_ptr = header.as_ref().array_value_ptr().cast::<T>();
+            let mut elements_guard = DropInitElementsGuard {
+                elements_ptr,
+                initialized: 0,
+            };
+            for index in 0..len {
+                elements_ptr.as_ptr().add(index).write(func(index));
+                elements_guard.initialized = index + 1;
             }
+            std::mem::forget(elements_guard);
+            header
+                .as_ref()
+                .main_header
+                .update_state_bits(|state| state.with_value_initialized(true));
+            initialization_guard.defuse();
+            GcArray::from_raw_ptr(elements_ptr)
         }
+    }
+
+    /// Attempt to grow an array to `new_len` elements without moving it,
+    /// initializing the new elements with the specified closure.
+    ///
+    /// Returns `false` if there is no room to grow the array in place,
+    /// in which case the caller must allocate a new array instead.
+    /// This succeeds if the array is the most recent young array,
+    /// or if the new elements fit within the padding at the end of the allocation.
+    /// Repeatedly growing the most recent array is cheap,
+    /// as long as no other array is allocated in between.
+    ///
+    /// ## Panics
+    /// If the array is [frozen](Gc::freeze) or `new_len` is less than the current length.
+    pub fn try_extend_array_with<'gc, T: Collect<Id>>(
+        &'gc self,
+        array: GcArray<'gc, T, Id>,
+        new_len: usize,
+        mut func: impl FnMut(usize) -> T,
+    ) -> bool {
+        assert_eq!(array.id(), self.id());
+        let header = NonNull::from(array.header());
+        unsafe {
+            let main_header = &header.as_ref().main_header;
+            assert!(!main_header.is_frozen(), "Cannot mutate a frozen object");
+            let old_len = header.as_ref().len();
+            assert!(new_len >= old_len, "Cannot shrink an array");
+            if main_header.state_bits.get().generation() != GenerationId::Young {
+                return false;
+            }
+            let old_size = header.as_ref().layout_info().overall_layout().size();
+            let Ok(new_layout) = GcArrayLayoutInfo::<Id>::new(Layout::new::<T>(), new_len) else {
+                return false;
+            };
+            let added = new_layout.overall_layout().size().saturating_sub(old_size);
+            if self.try_reserve(added).is_err()
+                || !self.young_generation.try_extend_in_place(header, new_len)
+            {
+                return false;
+            }
+            self.record_allocated_bytes(added);
+            // only expose each element once it is initialized, in case the closure panics
+            (*header.as_ptr()).len_elements = old_len;
+            self.initializing.set(self.initializing.get() + 1);
+            let _initializing = scopeguard::guard((), |()| {
+                self.initializing.set(self.initializing.get() - 1);
+            });
+            let elements_ptr = header.as_ref().array_value_ptr().cast::<T>();
+            for index in old_len..new_len {
+                elements_ptr.as_ptr().add(index).write(func(index));
+                (*header.as_ptr()).len_elements = index + 1;
+            }
+            true
+        }
+    }
+
+    /// Allocate an array by cloning the elements of a slice.
+    #[track_caller]
+    pub fn alloc_array_from_slice<T: Collect<Id> + Clone>(&self, src: &[T]) -> GcArray<'_, T, Id> {
+        self.alloc_array_with(src.len(), |index| src[index].clone())
+    }
+
+    /// Allocate an array by copying the elements of a slice.
+    ///
+    /// This is faster than [`Self::alloc_array_from_slice`],
+    /// because the elements are copied in bulk.
+    /// This makes it suitable for large numeric arrays,
+    /// including those of over-aligned SIMD types.
+    #[track_caller]
+    pub fn alloc_array_copied<T: NullCollect<Id> + Copy>(&self, src: &[T]) -> GcArray<'_, T, Id> {
+        unsafe {
+            // elements are never traced, even if `T::NEEDS_COLLECT` is overly conservative
+            let header =
+                self.alloc_raw_array::<T>(src.len(), GcArrayTypeInfo::new_null_collect::<T>());
+            let elements_ptr = header.as_ref().array_value_ptr().cast::<T>();
+            std::ptr::copy_nonoverlapping(src.as_ptr(), elements_ptr.as_ptr(), src.len());
+            header
+                .as_ref()
+                .main_header
+                .update_state_bits(|state| state.with_value_initialized(true));
+            GcArray::from_raw_ptr(elements_ptr)
+        }
+    }
+
+    /// Allocate a buffer of bytes, copying from the specified slice.
+    #[track_caller]
+    pub fn alloc_bytes(&self, src: &[u8]) -> GcBytes<'_, Id> {
+        GcBytes::from_array(self.alloc_array_copied(src))
+    }
+
+    /// Allocate a zero-filled buffer of bytes.
+    #[track_caller]
+    pub fn alloc_bytes_zeroed(&self, len: usize) -> GcBytes<'_, Id> {
+        GcBytes::from_array(self.alloc_array_with(len, |_| 0u8))
+    }
+
+    /// Allocate a string, copying from the specified slice.
+    #[track_caller]
+    pub fn alloc_str(&self, src: &str) -> GcString<'_, Id> {
+        unsafe { GcString::from_utf8_unchecked(self.alloc_array_copied(src.as_bytes())) }
+    }
+
+    /// Allocate a string by formatting the specified arguments,
+    /// usually invoked through the [`alloc_format!`](crate::alloc_format) macro.
+    ///
+    /// The output is written directly into GC memory, without an intermediate [`String`].
+    /// To do so, the arguments are formatted twice:
+    /// once to measure the length, and once to write the bytes.
+    /// If the two passes disagree, the output is formatted into a [`String`] instead.
+    ///
+    /// ## Panics
+    /// If a formatting trait implementation returns an error, just like [`format!`].
+    #[track_caller]
+    pub fn alloc_fmt(&self, args: fmt::Arguments<'_>) -> GcString<'_, Id> {
+        if let Some(src) = args.as_str() {
+            return self.alloc_str(src);
+        }
+        let mut counter = LengthCounter(0);
+        fmt::write(&mut counter, args)
+            .expect("a formatting trait implementation returned an error");
+        let len = counter.0;
+        unsafe {
+            let header = self.alloc_raw_array::<u8>(len, GcArrayTypeInfo::new_null_collect::<u8>());
+            // formatting may panic or allocate
+            let initialization_guard = self.uninit_guard(header.cast());
+            let bytes_ptr = header.as_ref().array_value_ptr().cast::<u8>();
+            let mut writer = FixedWriter::new(bytes_ptr, len);
+            if fmt::write(&mut writer, args).is_err() || writer.len() != len {
+                // output changed between passes, so reclaim the array and fall back
+                drop(initialization_guard);
+                return self.alloc_str(&fmt::format(args));
+            }
+            header
+                .as_ref()
+                .main_header
+                .update_state_bits(|state| state.with_value_initialized(true));
+            initialization_guard.defuse();
+            GcString::from_utf8_unchecked(GcArray::from_raw_ptr(bytes_ptr))
+        }
+    }
+
+    #[inline]
+    #[cfg_attr(any(feature = "debug-tombstones", feature = "profile"), track_caller)]
+    unsafe fn alloc_raw_array<T: Collect<Id>>(
+        &self,
+        len: usize,
+        type_info: &'static GcArrayTypeInfo<Id>,
+    ) -> NonNull<GcArrayHeader<Id>> {
+        let layout_info = GcArrayLayoutInfo::new(Layout::new::<T>(), len)
+            .unwrap_or_else(|_| panic!("capacity overflow"));
+        self.alloc_raw(&ArrayAlloc {
+            type_info,
+            layout_info,
+            state: &self.state,
+        })
+    }
+
+    crate::utils::unsafe_api! {
+        /// Register a type whose layout is only known at runtime.
+        ///
+        /// The type information is never freed.
+        /// Only public with the `unsafe-api` feature.
+        ///
+        /// ## Safety
+        /// The drop and trace functions of the type must be valid
+        /// for a zero-initialized value and any value subsequently written to it.
+        pub unsafe fn register_dyn_type(
+            &self,
+            builder: DynGcTypeBuilder<Id>,
+        ) -> Result<DynGcType<Id>, DynTypeError> {
+            self.dyn_types.register(builder)
+        }
+    }
+
+    /// Lookup a [dynamic type](DynGcType) by its name.
+    pub fn dyn_type_by_name(&self, name: &str) -> Option<DynGcType<Id>> {
+        self.dyn_types.lookup(name)
+    }
+
+    /// Allocate an object of a dynamic type.
+    ///
+    /// The value is initialized to all zeroes,
+    /// so all of its slots are initially `None`.
+    #[track_caller]
+    pub fn alloc_dyn(&self, ty: DynGcType<Id>) -> DynGc<'_, Id> {
+        unsafe {
+            let header = self.alloc_raw(&RegularAlloc {
+                state: &self.state,
+                type_info: ty.type_info(),
+            });
+            let value_ptr = header.as_ref().regular_value_ptr();
+            value_ptr.as_ptr().write_bytes(0, ty.layout().size());
+            header
+                .as_ref()
+                .update_state_bits(|state| state.with_value_initialized(true));
+            DynGc::from_raw_ptr(value_ptr)
+        }
+    }
+
+    #[inline]
+    #[cfg_attr(any(feature = "debug-tombstones", feature = "profile"), track_caller)]
+    unsafe fn alloc_raw<T: RawAllocTarget<Id>>(&self, target: &T) -> NonNull<T::Header> {
+        self.try_alloc_raw_unreported(target)
+            .unwrap_or_else(|error| self.oom(error))
+    }
+
+    #[inline]
+    #[cfg_attr(any(feature = "debug-tombstones", feature = "profile"), track_caller)]
+    unsafe fn try_alloc_raw<T: RawAllocTarget<Id>>(
+        &self,
+        target: &T,
+    ) -> Result<NonNull<T::Header>, GcError> {
+        self.try_alloc_raw_unreported(target)
+            .map_err(|error| self.report_error(error))
+    }
+
+    /// Allocate an object without passing failures to the [error hook](Self::set_error_hook).
+    #[inline]
+    #[cfg_attr(any(feature = "debug-tombstones", feature = "profile"), track_caller)]
+    unsafe fn try_alloc_raw_unreported<T: RawAllocTarget<Id>>(
+        &self,
+        target: &T,
+    ) -> Result<NonNull<T::Header>, GcError> {
+        self.check_can_alloc()?;
+        let size = target.overall_layout().size();
+        self.reserve_alloc(size)?;
+        self.stats.record_alloc(size);
+        self.record_allocated_bytes(size);
+        // the young generation is reset wholesale at the end of the cycle,
+        // so black objects must be allocated in the old generation
+        let header = if self.state.allocate_black.get() || target.placement() == PlacementHint::Old
+        {
+            self.try_alloc_raw_fallback(target)?
+        } else {
+            match self.try_alloc_young(target) {
+                Ok(res) => res,
+                Err(YoungAllocError::SizeExceedsLimit) => self.try_alloc_raw_fallback(target)?,
+                Err(YoungAllocError::OutOfMemory) => return Err(GcError::AllocFailure { size }),
+            }
+        };
+        self.record_alloc_hooks(header.cast(), size);
+        Ok(header)
+    }
+
+    /// Reserve room for an allocation of `size` bytes under the heap limit.
+    ///
+    /// On failure, the next call to [`Self::collect`] is forced to free up space.
+    #[inline]
+    fn reserve_alloc(&self, size: usize) -> Result<(), GcError> {
+        if self.config.heap_limit.is_some() {
+            if let Err(error) = self.try_reserve(size) {
+                self.heap_limit_exceeded.set(true);
+                self.collect_watermark.set(0);
+                return Err(error.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Pass a new object to the debugging and profiling hooks which observe every allocation.
+    #[inline]
+    #[cfg_attr(any(feature = "debug-tombstones", feature = "profile"), track_caller)]
+    unsafe fn record_alloc_hooks(&self, header: NonNull<GcHeader<Id>>, size: usize) {
+        #[cfg(feature = "debug-tombstones")]
+        self.tombstones
+            .record_alloc(header, size, std::panic::Location::caller());
+        #[cfg(feature = "profile")]
+        self.profile
+            .record_alloc(header, size, std::panic::Location::caller());
+        #[cfg(feature = "record-replay")]
+        self.recorder
+            .record_alloc(header.as_ref().type_info().type_name(), size);
+        self.sample_alloc(header, size);
+    }
+
+    /// Allocate a contiguous block of young objects for [`Self::alloc_many`],
+    /// returning `None` if they must be allocated individually.
+    #[cfg_attr(any(feature = "debug-tombstones", feature = "profile"), track_caller)]
+    unsafe fn try_alloc_batch_raw(
+        &self,
+        target: &RegularAlloc<'_, Id>,
+        count: usize,
+    ) -> Result<Option<Vec<NonNull<GcHeader<Id>>>>, GcError> {
+        self.check_can_alloc()?;
+        // black objects must be allocated in the old generation
+        if self.state.allocate_black.get() || target.placement() == PlacementHint::Old {
+            return Ok(None);
+        }
+        #[cfg(feature = "fault-injection")]
+        if self.faults.young_failure().is_some() {
+            return Ok(None);
+        }
+        let size = target.overall_layout().size();
+        let Some(total) = size.checked_mul(count) else {
+            return Ok(None);
+        };
+        self.reserve_alloc(total)?;
+        let headers = match self.young_generation.alloc_batch(target, count) {
+            Ok(headers) => headers,
+            Err(YoungAllocError::SizeExceedsLimit) => return Ok(None),
+            Err(YoungAllocError::OutOfMemory) => return Err(GcError::AllocFailure { size: total }),
+        };
+        self.record_allocated_bytes(total);
+        for &header in &headers {
+            self.stats.record_alloc(size);
+            self.record_alloc_hooks(header, size);
+            self.remember_young(header);
+        }
+        Ok(Some(headers))
+    }
+
+    #[inline]
+    unsafe fn sample_alloc(&self, header: NonNull<GcHeader<Id>>, size: usize) {
+        if self.sampler.tick() {
+            self.sampler.sample(AllocSample {
+                type_name: header.as_ref().type_info().type_name(),
+                size,
+                array: header.as_ref().state_bits.get().array(),
+            });
+        }
+    }
+
+    /// Check that objects can be allocated,
+    /// failing if a destructor or other callback allocates in the middle of a collection.
+    ///
+    /// The heap is in an inconsistent state while sweeping,
+    /// so new objects cannot be safely added to it.
+    #[inline]
+    fn check_can_alloc(&self) -> Result<(), GcError> {
+        if self.collecting.get() {
+            Err(GcError::CollectionInProgress)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    unsafe fn try_alloc_young<T: RawAllocTarget<Id>>(
+        &self,
+        target: &T,
+    ) -> Result<NonNull<T::Header>, YoungAllocError> {
+        #[cfg(feature = "fault-injection")]
+        if let Some(error) = self.faults.young_failure() {
+            return Err(error);
+        }
+        let header = self.young_generation.alloc_raw(target)?;
+        self.remember_young(header.cast());
+        Ok(header)
+    }
+
+    #[inline]
+    fn record_allocated_bytes(&self, size: usize) {
+        let allocated = self.bytes_since_collect.get().saturating_add(size);
+        self.bytes_since_collect.set(allocated);
+        if let Some(threshold) = self.config.collect_trigger_bytes {
+            if allocated >= threshold && !self.collection_recommended.get() {
+                self.trigger_collection_recommended(allocated);
+            }
+        }
+        if allocated >= self.soft_limit_watermark.get() {
+            self.check_soft_limit();
+        }
+    }
+
+    #[cold]
+    fn check_soft_limit(&self) {
+        let Some(limit) = self.config.soft_heap_limit else {
+            return;
+        };
+        let size = self.heap_size();
+        if size < limit {
+            // the watermark assumed every byte was allocated in the heap
+            let allocated = self.bytes_since_collect.get();
+            self.soft_limit_watermark
+                .set(allocated.saturating_add(limit - size));
+            return;
+        }
+        self.soft_limit_exceeded.set(true);
+        self.soft_limit_watermark.set(usize::MAX);
+        self.collect_watermark.set(0);
+        self.soft_limit_callback.call(|callback| callback(size));
+    }
+
+    /// Check if the heap has grown past the [soft limit](GcConfig::soft_heap_limit)
+    /// since the last collection.
+    ///
+    /// This is reset by every collection.
+    #[inline]
+    pub fn soft_limit_exceeded(&self) -> bool {
+        self.soft_limit_exceeded.get()
+    }
+
+    /// Set a callback which is invoked when the heap grows past the [soft limit](GcConfig::soft_heap_limit),
+    /// replacing any previous callback.
+    ///
+    /// The callback receives the size of the heap in bytes.
+    /// It is invoked at most once per collection cycle, in the middle of an allocation,
+    /// so it cannot collect directly and should instead schedule a call to [`Self::collect`].
+    /// If the live objects still exceed the soft limit after a collection,
+    /// it is invoked again by the next allocation.
+    pub fn set_soft_limit_callback(&self, callback: impl FnMut(usize) + 'static) {
+        self.soft_limit_callback.set(Some(Box::new(callback)));
+    }
+
+    #[cold]
+    fn trigger_collection_recommended(&self, allocated: usize) {
+        self.collection_recommended.set(true);
+        self.collect_watermark.set(0);
+        self.trigger_callback.call(|callback| callback(allocated));
+    }
+
+    /// Check if enough bytes have been allocated since the last collection
+    /// to exceed the [collection trigger](GcConfig::collect_trigger_bytes).
+    ///
+    /// This is reset by every collection.
+    #[inline]
+    pub fn collection_recommended(&self) -> bool {
+        self.collection_recommended.get()
+    }
+
+    /// Set a callback which is invoked when a collection becomes [recommended](Self::collection_recommended),
+    /// replacing any previous callback.
+    ///
+    /// The callback receives the number of bytes allocated since the last collection.
+    /// It is invoked at most once per collection cycle, in the middle of an allocation,
+    /// so it cannot collect directly and should instead schedule a call to [`Self::collect`].
+    pub fn set_trigger_callback(&self, callback: impl FnMut(usize) + 'static) {
+        self.trigger_callback.set(Some(Box::new(callback)));
+    }
+
+    /// Set a callback which observes every error reported by the collector,
+    /// replacing any previous hook.
+    ///
+    /// The hook is invoked before an error is returned by a fallible API like [`Self::try_alloc`],
+    /// and before the panic of an infallible API like [`Self::alloc`],
+    /// so hosts can log failures or flush crash reports.
+    /// It may be invoked in the middle of an allocation, so it cannot collect.
+    pub fn set_error_hook(&self, hook: impl FnMut(&GcError) + 'static) {
+        self.error_hook.set(Some(Box::new(hook)));
+    }
+
+    /// Remove the [error hook](Self::set_error_hook).
+    pub fn clear_error_hook(&self) {
+        self.error_hook.set(None);
+    }
+
+    /// Pass an error to the [error hook](Self::set_error_hook), then return it.
+    #[cold]
+    pub(crate) fn report_error(&self, error: GcError) -> GcError {
+        self.error_hook.call(|hook| hook(&error));
+        error
+    }
+
+    /// Set a callback which is invoked for every `rate`-th allocation,
+    /// replacing any previous sampler.
+    ///
+    /// This is cheap enough to leave enabled in production,
+    /// so hosts can build continuous memory profiling
+    /// without the overhead of the `profile` feature.
+    /// The callback is invoked in the middle of an allocation,
+    /// before the object is initialized, so it cannot collect.
+    pub fn set_alloc_sampler(&self, rate: NonZeroU64, callback: impl FnMut(AllocSample) + 'static) {
+        self.sampler.set(rate, Box::new(callback));
+    }
+
+    /// Stop sampling allocations, removing the [sampler](Self::set_alloc_sampler).
+    pub fn clear_alloc_sampler(&self) {
+        self.sampler.clear();
+    }
+
+    #[cold]
+    unsafe fn alloc_raw_fallback<T: RawAllocTarget<Id>>(&self, target: &T) -> NonNull<T::Header> {
+        self.try_alloc_raw_fallback(target)
+            .unwrap_or_else(|error| self.oom(error))
+    }
+
+    #[cold]
+    unsafe fn try_alloc_raw_fallback<T: RawAllocTarget<Id>>(
+        &self,
+        target: &T,
+    ) -> Result<NonNull<T::Header>, GcError> {
+        #[cfg(feature = "fault-injection")]
+        if self
+            .faults
+            .should_fail(self::faults::AllocFailStage::OldGeneration)
+        {
+            return Err(GcError::AllocFailure {
+                size: target.overall_layout().size(),
+            });
+        }
+        let header =
+            self.old_generation
+                .alloc_raw(target)
+                .map_err(|OldAllocError::OutOfMemory| GcError::AllocFailure {
+                    size: target.overall_layout().size(),
+                })?;
+        self.remember(header.cast());
+        Ok(header)
+    }
+
+    /// Check if the object belongs to this collector.
+    #[inline]
+    pub fn contains<T: Collect<Id>>(&self, gc: Gc<'_, T, Id>) -> bool {
+        gc.id() == self.id()
+    }
+
+    /// Find the object containing the specified address,
+    /// returning `None` if the address is not inside any object in the heap.
+    ///
+    /// This searches every young, old, and immortal object,
+    /// so it takes time proportional to the size of the heap.
+    /// It is intended for integrating with conservative components,
+    /// where an address may or may not be a pointer into the heap.
+    /// The object may be garbage which has not yet been collected,
+    /// and may be freed or moved by the next collection unless it is rooted.
+    ///
+    /// Only available with the `classify-ptr` feature,
+    /// which tracks every new object as it is allocated.
+    #[cfg(feature = "classify-ptr")]
+    pub fn classify_ptr<T: ?Sized>(&self, ptr: *const T) -> Option<HeapObject> {
+        self::classify::find_object(self, crate::utils::addr(ptr))
+    }
+
+    /// Check if the address is inside an object in the heap.
+    ///
+    /// See [`Self::classify_ptr`] for details.
+    #[inline]
+    #[cfg(feature = "classify-ptr")]
+    pub fn contains_ptr<T: ?Sized>(&self, ptr: *const T) -> bool {
+        self.classify_ptr(ptr).is_some()
+    }
+
+    /// Find the most recently freed object containing the specified address.
+    ///
+    /// This is useful to diagnose stale pointers,
+    /// but is only available with the `debug-tombstones` feature.
+    /// Only the last [`GcConfig::tombstone_capacity`] objects are retained.
+    #[cfg(feature = "debug-tombstones")]
+    pub fn find_tombstone<T: ?Sized>(&self, ptr: *const T) -> Option<Tombstone> {
+        self.tombstones.find(crate::utils::addr(ptr))
+    }
+
+    /// Summaries of recently freed objects, from oldest to newest.
+    #[cfg(feature = "debug-tombstones")]
+    pub fn tombstones(&self) -> Vec<Tombstone> {
+        self.tombstones.all()
+    }
+
+    /// A report of where GC memory has been allocated,
+    /// since the collector was created or the profile was last [reset](Self::reset_alloc_profile).
+    ///
+    /// Only available with the `profile` feature.
+    #[cfg(feature = "profile")]
+    pub fn alloc_profile(&self) -> AllocProfile {
+        self.profile.report()
+    }
+
+    /// Discard the allocations recorded by the [profile](Self::alloc_profile).
+    #[cfg(feature = "profile")]
+    pub fn reset_alloc_profile(&self) {
+        self.profile.reset();
+    }
+
+    /// Begin recording every allocation and collection,
+    /// discarding any recording already in progress.
+    ///
+    /// The recording can be [replayed](AllocTrace::replay) against a fresh collector,
+    /// in order to deterministically reproduce a failure.
+    /// Only available with the `record-replay` feature.
+    #[cfg(feature = "record-replay")]
+    pub fn start_recording(&self) {
+        self.recorder.start();
+    }
+
+    /// Stop recording, returning the recorded events.
+    ///
+    /// Returns `None` if no recording is in progress.
+    #[cfg(feature = "record-replay")]
+    pub fn finish_recording(&self) -> Option<AllocTrace> {
+        self.recorder.finish()
+    }
+
+    /// Force the next `count` allocations which reach the specified stage to fail,
+    /// replacing any failures which were previously injected.
+    ///
+    /// This is intended for testing how a program handles running out of memory.
+    /// Fallible methods like [`Self::try_alloc`] return an error,
+    /// while the other allocation methods panic.
+    /// Only available with the `fault-injection` feature.
+    #[cfg(feature = "fault-injection")]
+    pub fn inject_alloc_failures(&self, stage: AllocFailStage, count: usize) {
+        self.faults.inject(stage, count);
+    }
+
+    /// Cancel any remaining [injected failures](Self::inject_alloc_failures).
+    #[cfg(feature = "fault-injection")]
+    pub fn clear_alloc_failures(&self) {
+        self.faults.clear();
+    }
+
+    /// The number of [injected failures](Self::inject_alloc_failures) which have not yet occurred.
+    #[cfg(feature = "fault-injection")]
+    pub fn remaining_alloc_failures(&self) -> usize {
+        self.faults.remaining()
+    }
+
+    /// Pass an allocation failure to the [error hook](Self::set_error_hook), then panic.
+    #[cold]
+    #[inline(never)]
+    #[track_caller]
+    fn oom(&self, error: GcError) -> ! {
+        let error = self.report_error(error);
+        panic!("Fatal allocation error: {error}")
+    }
+
+    /// Run a parallel pass over GC data using a [`rayon::scope`].
+    ///
+    /// Worker threads get read-only access to the `shared` pointers,
+    /// which can be any [`ParShare`] pointers like a [`Gc`] with a `Sync` value.
+    /// Because the closure must be `Send`, it cannot capture the collector itself,
+    /// so nothing can be allocated, pinned, or collected for the duration of the scope.
+    ///
+    /// Only available with the `rayon` feature.
+    #[cfg(feature = "rayon")]
+    pub fn par_scope<'gc, P: ParShare + 'gc, R: Send>(
+        &'gc self,
+        shared: P,
+        func: impl for<'scope> FnOnce(&rayon::Scope<'scope>, ParShared<'scope, P>) -> R + Send,
+    ) -> R {
+        // SAFETY: The collector cannot be captured by the closure
+        let shared = unsafe { ParShared::new(shared) };
+        rayon::scope(move |scope| func(scope, shared))
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn root<'gc, T: Collect<Id>>(
+        &'gc self,
+        val: Gc<'gc, T, Id>,
+    ) -> GcHandle<T::Collected<'static>, Id> {
+        unsafe { self.root_raw(NonNull::from(val.header())) }
+    }
+
+    /// Root the object with the specified header.
+    ///
+    /// ## Safety
+    /// The header must be a live object of type `T`.
+    #[track_caller]
+    pub(crate) unsafe fn root_raw<T: Collect<Id>>(
+        &self,
+        header: NonNull<GcHeader<Id>>,
+    ) -> GcHandle<T, Id> {
+        let mut roots = self.roots.borrow_mut();
+        let root = Rc::new(GcRootBox::new(header, RootKind::Handle));
+        roots.push(Rc::downgrade(&root));
+        drop(roots); // drop refcell guard
+        GcHandle {
+            ptr: root,
+            id: self.id(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Create a separate nursery, whose objects can be [discarded](Self::discard_region)
+    /// independently of the rest of the young generation.
+    ///
+    /// Regions are never freed, so they should be reused.
+    pub fn create_region(&self) -> GcRegion<Id> {
+        GcRegion {
+            index: self.young_generation.create_region(),
+            id: self.id(),
+        }
+    }
+
+    /// Allocate young objects in the specified region for the duration of the closure.
+    ///
+    /// Objects which are too large for the young generation,
+    /// or which are [placed](PlacementHint::Old) directly in the old generation,
+    /// are allocated in the old generation as usual.
+    pub fn in_region<R>(&self, region: GcRegion<Id>, func: impl FnOnce() -> R) -> R {
+        assert_eq!(region.id, self.id());
+        let previous = self.young_generation.set_active_region(Some(region.index));
+        let _restore = scopeguard::guard((), |()| {
+            self.young_generation.set_active_region(previous);
+        });
+        func()
+    }
+
+    /// Allocate young objects in a dedicated region for the duration of the closure,
+    /// then collect the region as soon as the closure returns.
+    ///
+    /// This suits request-scoped work, where most allocations die with the request.
+    /// Objects from the region which are reachable from roots,
+    /// including any [handles](GcHandle) in the returned value, are evacuated to the main heap,
+    /// and the rest of the region is freed at once.
+    /// Garbage from the scope never lingers in the young generation until the next collection.
+    ///
+    /// While the closure runs, the collector remembers every object outside the region
+    /// which is allocated or mutated, since only those can refer into the region.
+    /// On exit, only the roots and the remembered objects are traced,
+    /// so the cost is proportional to the live objects of the scope rather than the entire heap.
+    /// If an object in the region is [pinned](GcPin),
+    /// the region cannot be evacuated by itself and the entire heap is collected instead.
+    /// Nothing is collected if nothing was allocated in the region.
+    ///
+    /// The closure is not given access to the collector's lifetime,
+    /// so only roots can carry objects out of the scope.
+    pub fn scoped_young<R>(&mut self, func: impl FnOnce(&Self) -> R) -> R {
+        let region = match self.scoped_region.get() {
+            Some(region) => region,
+            None => {
+                let region = self.create_region();
+                self.scoped_region.set(Some(region));
+                region
+            }
+        };
+        if self.region_allocated_bytes(region) > 0 {
+            // left behind by a panic, so nothing is known about what refers into it
+            self.collect_for(CollectionReason::ScopeExit);
+        }
+        #[cfg(feature = "jit-layout")]
+        let jit_cursor = self.young_generation.jit_cursor();
+        self.remembering.set(true);
+        let result = {
+            let _remembering = scopeguard::guard((), |()| self.remembering.set(false));
+            self.in_region(region, || func(self))
+        };
+        let remembered = std::mem::take(self.remembered.get_mut());
+        if self.region_allocated_bytes(region) > 0 {
+            // objects allocated by generated code are never remembered
+            #[cfg(feature = "jit-layout")]
+            let evacuated = self.young_generation.jit_cursor() == jit_cursor
+                && self.try_evacuate_region(region, &remembered);
+            #[cfg(not(feature = "jit-layout"))]
+            let evacuated = self.try_evacuate_region(region, &remembered);
+            if !evacuated {
+                self.collect_for(CollectionReason::ScopeExit);
+            }
+        }
+        result
+    }
+
+    /// Evacuate the live objects of the [scoped region](Self::scoped_young) into the main eden arena,
+    /// then free the rest of the region at once.
+    ///
+    /// Only the roots and the `remembered` objects, which may refer into the region, are traced.
+    /// Every other object is treated as live, and its side tables are left alone.
+    /// Returns `false` without collecting anything if an object in the region is pinned,
+    /// in which case the entire heap must be collected instead.
+    fn try_evacuate_region(
+        &self,
+        region: GcRegion<Id>,
+        remembered: &[NonNull<GcHeader<Id>>],
+    ) -> bool {
+        self.check_can_collect();
+        self.collecting.set(true);
+        let _collecting = scopeguard::guard((), |()| self.collecting.set(false));
+        #[cfg(feature = "conservative-roots")]
+        let conservative_pins = unsafe { self.conservative_roots.pin_referenced(self) };
+        let objects = unsafe { self.young_generation.region_objects(region.index) }
+            .iter()
+            .copied()
+            .collect::<HashSet<_>>();
+        if objects
+            .iter()
+            .any(|header| unsafe { header.as_ref().is_pinned() })
+        {
+            return false;
+        }
+        #[cfg(feature = "record-replay")]
+        self.recorder.record_collect(CollectionReason::ScopeExit);
+        self.poisoned.set(true);
+        self.state
+            .evacuating_region
+            .set(Some(NonNull::from(&objects)));
+        let _evacuating = scopeguard::guard((), |()| self.state.evacuating_region.set(None));
+        unsafe {
+            self.trace_heap(remembered);
+            self.young_generation.discard_region(region.index);
+        }
+        #[cfg(debug_assertions)]
+        unsafe {
+            self::verify::verify_heap(self);
+        }
+        self.stats.record_collection(CollectionReason::ScopeExit);
+        self.collection_count
+            .set(self.collection_count.get().wrapping_add(1));
+        self.poisoned.set(false);
+        #[cfg(feature = "conservative-roots")]
+        drop(conservative_pins);
+        true
+    }
+
+    /// The number of bytes allocated in the specified region since it was last emptied.
+    #[inline]
+    pub fn region_allocated_bytes(&self, region: GcRegion<Id>) -> usize {
+        assert_eq!(region.id, self.id());
+        self.young_generation.region_allocated_bytes(region.index)
+    }
+
+    /// Free every object in the specified region at once, running their destructors,
+    /// without tracing the rest of the heap.
+    ///
+    /// This is much cheaper than a collection, which would evacuate the region's live objects.
+    ///
+    /// Objects in the region are treated as if a collection had found them dead:
+    /// [weak handles](WeakGcHandle) to them are cleared, their entries in a [`GcWeakMap`]
+    /// and their registrations with a [`Guardian`] are removed,
+    /// and their [finalizers](Self::add_finalizer) are queued.
+    /// Unlike a collection, guardians never resurrect objects from a discarded region.
+    ///
+    /// ## Safety
+    /// No object in the region may be reachable from outside of it,
+    /// whether from roots, other objects, or [`Gc`] pointers held by the caller.
+    /// This includes [handle scopes](HandleScope), the values of a [`GcWeakMap`],
+    /// unexpired [`GcExpiring`] references, and objects queued by a [`Guardian`].
+    /// With `debug_assertions`, this panics if a root, a handle scope,
+    /// an unexpired reference, or the queue of a guardian refers to an object in the region.
+    pub unsafe fn discard_region(&self, region: GcRegion<Id>) {
+        assert_eq!(region.id, self.id());
+        assert!(
+            !self.collecting.get(),
+            "Cannot discard a region during a collection"
+        );
+        let objects = self
+            .young_generation
+            .region_objects(region.index)
+            .iter()
+            .copied()
+            .collect::<HashSet<_>>();
+        #[cfg(debug_assertions)]
+        {
+            for root in self.roots.borrow().iter().filter_map(Weak::upgrade) {
+                assert!(
+                    !objects.contains(&root.header.get()),
+                    "Cannot discard a region containing a rooted object"
+                );
+            }
+            for slots in self.handle_scopes.borrow().iter().filter_map(Weak::upgrade) {
+                assert!(
+                    slots
+                        .headers()
+                        .iter()
+                        .all(|header| !objects.contains(header)),
+                    "Cannot discard a region containing an object in a handle scope"
+                );
+            }
+        }
+        if !objects.is_empty() {
+            self.forget_discarded(&objects);
+        }
+        self.young_generation.discard_region(region.index);
+    }
+
+    /// Remove objects which are about to be freed without a collection from every side table,
+    /// so none of them can be used after the object is freed.
+    fn forget_discarded(&self, discarded: &HashSet<NonNull<GcHeader<Id>>>) {
+        for table in self.ephemerons.borrow().iter().filter_map(Weak::upgrade) {
+            table.forget(discarded);
+        }
+        for guardian in self.guardians.borrow().iter().filter_map(Weak::upgrade) {
+            guardian.forget(discarded);
+        }
+        self.identity_hashes.forget(discarded);
+        self.finalizers.forget(discarded);
+        self.mutation_log.forget(discarded);
+        self.remembered
+            .borrow_mut()
+            .retain(|header| !discarded.contains(header));
+        #[cfg(feature = "debug-tombstones")]
+        self.tombstones
+            .record_discarded(discarded, self.stats.collections() + 1);
+    }
+
+    /// Every root currently registered with this collector,
+    /// including [handles](GcHandle), [pins](GcPin), and the slots of [handle scopes](HandleScope).
+    ///
+    /// This is a snapshot, so roots created or dropped while iterating are not reflected.
+    /// Objects kept alive in other ways, like immortal objects or the queues of guardians, are not included.
+    pub fn iter_roots(&self) -> impl Iterator<Item = RootInfo> + '_ {
+        let mut roots = Vec::new();
+        for root in self.roots.borrow().iter().filter_map(Weak::upgrade) {
+            roots.push(unsafe { RootInfo::new(root.kind, root.header.get(), root.location()) });
+        }
+        for slots in self.handle_scopes.borrow().iter().filter_map(Weak::upgrade) {
+            for header in slots.headers() {
+                roots.push(unsafe { RootInfo::new(RootKind::HandleScope, header, None) });
+            }
+        }
+        roots.into_iter()
+    }
+
+    /// The number of slots in the list of [handles](GcHandle) and [pins](GcPin),
+    /// including stale slots whose root has been dropped.
+    ///
+    /// Stale slots are only removed by a collection or by [`Self::compact_roots`],
+    /// so this grows with the number of roots created since then.
+    #[inline]
+    pub fn roots_len(&self) -> usize {
+        self.roots.borrow().len()
+    }
+
+    /// Count the live and stale slots in the list of roots.
+    ///
+    /// This scans the entire list.
+    pub fn root_slot_stats(&self) -> RootSlotStats {
+        let roots = self.roots.borrow();
+        let live = roots.iter().filter(|root| root.strong_count() > 0).count();
+        RootSlotStats {
+            live,
+            stale: roots.len() - live,
+        }
+    }
+
+    /// Remove the stale slots from the list of roots without collecting,
+    /// returning the number of slots removed.
+    ///
+    /// This bounds the growth of the list for hosts which create many short-lived roots
+    /// between collections.
+    pub fn compact_roots(&self) -> usize {
+        let mut roots = self.roots.borrow_mut();
+        let original_len = roots.len();
+        roots.retain(|root| root.strong_count() > 0);
+        let live = roots.len();
+        roots.shrink_to(live * 2);
+        original_len - live
+    }
+
+    /// Register a type which can be saved in a [heap snapshot](Self::snapshot).
+    ///
+    /// Registering the same type again does nothing.
+    pub fn register_snapshot_type<T: SnapshotType<Id>>(&self) -> Result<(), SnapshotError> {
+        self.snapshots.register_type::<T>()
+    }
+
+    /// Register a root which is saved in [heap snapshots](Self::snapshot),
+    /// replacing any existing root with the same name.
+    ///
+    /// The root keeps its object alive until it is unregistered.
+    pub fn register_snapshot_root<T: SnapshotType<Id>>(
+        &self,
+        name: impl Into<String>,
+        handle: GcHandle<T, Id>,
+    ) {
+        assert_eq!(handle.id, self.id());
+        self.snapshots.register_root(name.into(), handle);
+    }
+
+    /// Unregister the snapshot root with the specified name,
+    /// returning whether it existed.
+    pub fn unregister_snapshot_root(&self, name: &str) -> bool {
+        self.snapshots.unregister_root(name)
+    }
+
+    /// Get the snapshot root with the specified name,
+    /// or `None` if there is no such root of type `T`.
+    pub fn snapshot_root<T: SnapshotType<Id>>(&self, name: &str) -> Option<GcHandle<T, Id>> {
+        self.snapshots.root(name)
+    }
+
+    /// Save every object reachable from the [snapshot roots](Self::register_snapshot_root).
+    ///
+    /// The type of every saved object must be [registered](Self::register_snapshot_type).
+    pub fn snapshot(&self, out: &mut impl std::io::Write) -> Result<(), SnapshotError> {
+        self.snapshots.snapshot(self, out)
+    }
+
+    /// Restore the objects saved by [`Self::snapshot`],
+    /// registering the saved roots with this collector.
+    ///
+    /// The objects are allocated afresh,
+    /// so a snapshot can be restored into a different collector.
+    /// Existing roots with the same names are replaced, and other roots are left untouched.
+    pub fn restore(&self, input: &mut impl std::io::Read) -> Result<(), SnapshotError> {
+        self.snapshots.restore(self, input)
+    }
+
+    /// Create a [`HandleScope`] for rooting many temporary objects at once.
+    #[inline]
+    pub fn handle_scope(&self) -> HandleScope<'static, Id> {
+        HandleScope::new(self, None)
+    }
+
+    #[inline]
+    pub(crate) fn register_handle_scope(&self, slots: Weak<ScopeSlots<Id>>) {
+        self.handle_scopes.borrow_mut().push(slots);
+    }
+
+    #[inline]
+    pub(crate) fn register_ephemerons(&self, table: Weak<dyn EphemeronTable<Id>>) {
+        self.ephemerons.borrow_mut().push(table);
+    }
+
+    #[inline]
+    pub(crate) fn register_guardian(&self, table: Weak<dyn GuardianTable<Id>>) {
+        self.guardians.borrow_mut().push(table);
+    }
+
+    /// Record that a GC pointer has been stored into a field of an existing object.
+    ///
+    /// Unsafe code implementing its own mutable cells must call this after every such store.
+    /// Leaving [`Self::scoped_young`] relies on it to find the objects referring into its region,
+    /// while other collections trace the entire heap.
+    /// Future incremental or generational modes will also rely on it.
+    ///
+    /// ## Panics
+    /// If the container is [frozen](Gc::freeze).
+    ///
+    /// ## Safety
+    /// The field must lie within the value of `container`,
+    /// and must have just been written with a valid value.
+    #[inline(always)]
+    #[track_caller]
+    pub unsafe fn write_barrier<'gc, C: Collect<Id>, F: Collect<Id>>(
+        &'gc self,
+        container: Gc<'gc, C, Id>,
+        field: NonNull<F>,
+    ) {
+        debug_assert_eq!(container.id(), self.id());
+        assert!(
+            !container.header().is_frozen(),
+            "Cannot mutate a frozen object"
+        );
+        debug_assert!({
+            let start = crate::utils::addr(container.as_raw_ptr().as_ptr());
+            let field = crate::utils::addr(field.as_ptr());
+            field >= start && field + size_of::<F>() <= start + size_of::<C>()
+        });
+        self.remember(NonNull::from(container.header()));
+    }
+
+    /// Record that a GC pointer has been stored into the specified element of an array.
+    ///
+    /// See [`Self::write_barrier`] for details.
+    ///
+    /// ## Panics
+    /// If the array is [frozen](Gc::freeze).
+    ///
+    /// ## Safety
+    /// The element must have just been written with a valid value.
+    #[inline(always)]
+    #[track_caller]
+    pub unsafe fn write_barrier_array<'gc, T: Collect<Id>>(
+        &'gc self,
+        array: GcArray<'gc, T, Id>,
+        index: usize,
+    ) {
+        debug_assert_eq!(array.id(), self.id());
+        assert!(
+            !array.header().main_header().is_frozen(),
+            "Cannot mutate a frozen object"
+        );
+        debug_assert!(index < array.len());
+        self.remember(NonNull::from(array.header().main_header()));
+    }
+
+    /// Record that a GC pointer is about to be overwritten.
+    ///
+    /// This is a snapshot-at-the-beginning deletion barrier.
+    /// If marking is in progress, the old value is logged and kept alive,
+    /// so moving a pointer between objects cannot hide it from the marker.
+    /// Otherwise, this does nothing.
+    #[inline]
+    pub fn deletion_barrier<'gc, T: Collect<Id>>(&'gc self, old_value: Gc<'gc, T, Id>) {
+        debug_assert_eq!(old_value.id(), self.id());
+        if self.state.marking.get() {
+            self.mutation_log
+                .log_deleted(NonNull::from(old_value.header()));
+        }
+    }
+
+    /// Record that the specified object is about to be mutated in an unknown way,
+    /// so its children are rescanned if marking is in progress.
+    #[inline]
+    pub(crate) fn record_mutation(&self, header: NonNull<GcHeader<Id>>) {
+        // callers check before mutating, so the object is left untouched
+        debug_assert!(
+            !unsafe { header.as_ref() }.is_frozen(),
+            "Cannot mutate a frozen object"
+        );
+        if self.state.marking.get() {
+            self.mutation_log.log_mutated(header);
+        }
+        self.remember(header);
+    }
+
+    /// Record an object which may refer into the region of [`Self::scoped_young`],
+    /// if its closure is running.
+    #[inline]
+    fn remember(&self, header: NonNull<GcHeader<Id>>) {
+        if self.remembering.get() {
+            let mut remembered = self.remembered.borrow_mut();
+            // mutating the same object repeatedly is common, like pushing onto a vector
+            if remembered.last() != Some(&header) {
+                remembered.push(header);
+            }
+        }
+    }
+
+    /// Remember a newly allocated young object,
+    /// unless it was allocated in the region of [`Self::scoped_young`] itself.
+    #[inline]
+    fn remember_young(&self, header: NonNull<GcHeader<Id>>) {
+        if self.remembering.get()
+            && self.young_generation.active_region()
+                != self.scoped_region.get().map(|region| region.index)
+        {
+            self.remember(header);
+        }
+    }
+
+    /// Check if a collection is currently marking objects.
+    ///
+    /// Mutations performed while marking must use the [deletion barrier](Self::deletion_barrier).
+    #[inline]
+    pub fn is_marking(&self) -> bool {
+        self.state.marking.get()
+    }
+
+    /// Register a finalizer to run once the specified object has been collected.
+    ///
+    /// Finalizers do not run during the collection itself,
+    /// since objects cannot be allocated while sweeping.
+    /// Instead, they are queued once their object dies,
+    /// and run by the next call to [`Self::run_pending_finalizers`],
+    /// where they are free to allocate new objects.
+    ///
+    /// The object is already gone when the finalizer runs,
+    /// so the finalizer should capture any data it needs by value.
+    /// Immortal objects never die, so their finalizers never run.
+    pub fn add_finalizer<T: Collect<Id>>(
+        &self,
+        gc: Gc<'_, T, Id>,
+        finalizer: impl FnOnce(&GarbageCollector<Id>) + 'static,
+    ) {
+        assert_eq!(gc.id(), self.id());
+        self.finalizers
+            .register(NonNull::from(gc.header()), Box::new(finalizer));
+    }
+
+    /// The number of finalizers whose objects have been collected,
+    /// but which have not yet been run.
+    #[inline]
+    pub fn pending_finalizers(&self) -> usize {
+        self.finalizers.pending()
+    }
+
+    /// Run the finalizers of all collected objects, returning the number which ran.
+    ///
+    /// Finalizers are run in the order their objects were found dead.
+    /// This also delivers [notifications](WeakGcHandle::with_notifier) for weak handles.
+    /// Any finalizers queued while this is running are also run before returning.
+    pub fn run_pending_finalizers(&self) -> usize {
+        let mut count = 0;
+        while let Some(finalizer) = self.finalizers.pop_pending() {
+            finalizer(self);
+            count += 1;
+        }
+        count
+    }
+
+    /// Get the identity hash of the specified object, assigning one if needed.
+    #[inline]
+    pub(crate) fn identity_hash_raw(&self, header: NonNull<GcHeader<Id>>) -> u64 {
+        self.identity_hashes.get_or_assign(header)
+    }
+
+    /// Freeze the specified object, marking it as immutable.
+    ///
+    /// If `deep` is set, everything reachable from the object is also frozen.
+    pub(crate) unsafe fn freeze_raw(&self, header: NonNull<GcHeader<Id>>, deep: bool) {
+        if deep {
+            let mut context = CollectContext {
+                garbage_collector: self,
+                id: self.collector_id,
+                freezing: true,
+                gray_queue: VecDeque::new(),
+                #[cfg(debug_assertions)]
+                verify_live: None,
+            };
+            context.freeze_gcheader(header);
+        } else {
+            header
+                .as_ref()
+                .update_state_bits(|state| state.with_frozen(true));
+        }
+    }
+
+    /// Create a reference which keeps the specified object alive
+    /// until it survives `max_age` collections without being touched.
+    ///
+    /// See [`GcExpiring`] for details.
+    #[inline]
+    pub fn expiring<'gc, T: Collect<Id>>(
+        &'gc self,
+        val: Gc<'gc, T, Id>,
+        max_age: u32,
+    ) -> GcExpiring<T::Collected<'static>, Id> {
+        assert_eq!(val.id(), self.id());
+        GcExpiring::new(self, NonNull::from(val.header()), max_age)
+    }
+
+    /// Pin the specified object, preventing it from moving until the guard is dropped.
+    ///
+    /// The pinned object is also rooted,
+    /// so its address remains valid across collections.
+    /// Pins are counted, so an object may be pinned by multiple guards at once,
+    /// and remains pinned until every guard has been dropped.
+    #[inline]
+    #[track_caller]
+    pub fn pin<'gc, T: Collect<Id>>(
+        &'gc self,
+        val: Gc<'gc, T, Id>,
+    ) -> GcPin<T::Collected<'static>, Id> {
+        GcPin {
+            raw: unsafe { self.pin_raw(NonNull::from(val.header())) },
+            marker: PhantomData,
+        }
+    }
+
+    /// Pin and root the object with the specified header,
+    /// which may be either an array or a regular object.
+    #[track_caller]
+    pub(crate) unsafe fn pin_raw(&self, header: NonNull<GcHeader<Id>>) -> RawPin<Id> {
+        let counts = self
+            .pin_counts
+            .acquire(header)
+            .then(|| Rc::clone(&self.pin_counts));
+        let root = Rc::new(GcRootBox::new(header, RootKind::Pin));
+        self.roots.borrow_mut().push(Rc::downgrade(&root));
+        RawPin { root, counts }
+    }
+
+    /// The number of [guards](GcPin) currently pinning the specified object.
+    ///
+    /// This does not include objects which are pinned permanently,
+    /// like those from [`Self::alloc_pinned`].
+    #[inline]
+    pub(crate) fn pin_count_raw(&self, header: NonNull<GcHeader<Id>>) -> usize {
+        self.pin_counts.get(header)
+    }
+
+    /// Scan a range of memory for pointers into the heap at the start of every collection,
+    /// pinning and rooting each referenced object until the collection finishes.
+    ///
+    /// This is intended for quick prototyping and interop with C code,
+    /// where precisely rooting every pointer is inconvenient.
+    /// Every aligned word which points inside an object is treated as a reference to it,
+    /// even if it is actually an integer, so garbage may be retained.
+    /// Only available with the `conservative-roots` feature.
+    ///
+    /// ## Safety
+    /// The range must remain readable until it is [unregistered](Self::unregister_conservative_range).
+    #[cfg(feature = "conservative-roots")]
+    pub unsafe fn register_conservative_range(
+        &self,
+        start: *const u8,
+        len: usize,
+    ) -> ConservativeRangeId {
+        self.conservative_roots.register_fixed(start, len)
+    }
+
+    /// Conservatively scan the stack of the current thread at the start of every collection,
+    /// from the position of the collection up to the specified base.
+    ///
+    /// The base should be the address of a local variable in a function
+    /// which outlives every use of the collector, like `main`.
+    /// Pointers which are only held in registers are not found,
+    /// so the stack scan is only an approximation.
+    /// See [`Self::register_conservative_range`] for details.
+    ///
+    /// ## Safety
+    /// The frame containing the base must remain on the stack until unregistered,
+    /// and every collection must happen on this thread, below the base.
+    #[cfg(feature = "conservative-roots")]
+    pub unsafe fn register_conservative_stack(&self, base: *const u8) -> ConservativeRangeId {
+        self.conservative_roots.register_stack(base)
+    }
+
+    /// Stop scanning a range registered for conservative scanning,
+    /// returning `false` if it was already unregistered.
+    #[cfg(feature = "conservative-roots")]
+    pub fn unregister_conservative_range(&self, id: ConservativeRangeId) -> bool {
+        self.conservative_roots.unregister(id)
+    }
+
+    #[inline]
+    pub fn collect(&mut self) {
+        // SAFETY: No GC pointers can be live while the collector is borrowed mutably
+        unsafe { self.collect_shared() }
+    }
+
+    /// Collect garbage if needed, without requiring a mutable reference to the collector.
+    ///
+    /// This is useful when the collector is shared with the code which allocates,
+    /// for example by storing it in an [`Rc`].
+    /// A reentrant call from a destructor or while initializing an object will panic.
+    ///
+    /// ## Safety
+    /// Collection moves and frees objects, invalidating any [`Gc`] pointers.
+    /// The caller must ensure that no GC pointers are live,
+    /// except those which are reachable from [handles](GcHandle) and other roots.
+    #[inline]
+    pub unsafe fn collect_shared(&self) {
+        if let Some(reason) = self.pending_collection_reason() {
+            self.collect_for(reason);
+        } else {
+            // recover from a spurious result of `should_collect`
+            self.update_collect_watermark();
+        }
+    }
+
+    /// Check if the next call to [`Self::collect`] is likely to perform a collection.
+    ///
+    /// This is a single comparison against a cached watermark,
+    /// so hosts can cheaply poll it at their own safepoints,
+    /// like the back-edges of an interpreter loop.
+    /// It never misses a pending collection,
+    /// but may conservatively return `true` when none is needed,
+    /// in which case calling [`Self::collect`] recomputes the watermark.
+    /// Use [`Self::pending_collection_reason`] for an exact answer.
+    #[inline(always)]
+    pub fn should_collect(&self) -> bool {
+        self.bytes_since_collect.get() >= self.collect_watermark.get()
+    }
+
+    /// The reason the next call to [`Self::collect`] would perform a collection,
+    /// or `None` if no collection is needed.
+    #[inline]
+    pub fn pending_collection_reason(&self) -> Option<CollectionReason> {
+        if self.heap_limit_exceeded.get() {
+            Some(CollectionReason::HeapLimit)
+        } else if self.needs_collection() {
+            Some(CollectionReason::SizeThreshold)
+        } else if self.soft_limit_exceeded() {
+            Some(CollectionReason::SoftLimit)
+        } else if self.collection_recommended() {
+            Some(CollectionReason::Trigger)
+        } else {
+            None
+        }
+    }
+
+    /// Collect garbage if needed, returning an error instead of panicking
+    /// if the collector is [poisoned](GcError::Poisoned).
+    #[inline]
+    pub fn try_collect(&mut self) -> Result<(), GcError> {
+        if self.poisoned.get() {
+            return Err(self.report_error(GcError::Poisoned));
+        }
+        self.collect();
+        Ok(())
+    }
+
+    /// Check if a panic during a previous collection left the collector
+    /// in an inconsistent state.
+    ///
+    /// Once poisoned, all further collections will fail.
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.get()
+    }
+
+    /// Set what happens when tracing panics in the middle of a collection.
+    ///
+    /// By default, the process is aborted.
+    ///
+    /// ## Safety
+    /// With [`TraceFailurePolicy::Panic`], the caller must not use the collector
+    /// for anything except dropping it once a collection panics,
+    /// since the heap is left in an inconsistent state.
+    /// Invalidating the roots only catches some misuse,
+    /// and objects reachable in other ways, like through [interned symbols](GcInterner),
+    /// may refer to moved objects.
+    pub unsafe fn set_trace_failure_policy(&self, policy: TraceFailurePolicy) {
+        self.trace_failure_policy.set(policy);
+    }
+
+    /// Prevent every outstanding root and handle scope from being used again.
+    fn invalidate_roots(&self, state: RootState) {
+        for root in self.roots.borrow().iter().filter_map(Weak::upgrade) {
+            root.state.set(state);
+        }
+        for slots in self.handle_scopes.borrow().iter().filter_map(Weak::upgrade) {
+            slots.invalidate(state);
+        }
+    }
+
+    /// Apply the [`TraceFailurePolicy`] after tracing panics.
+    #[cold]
+    fn trace_failed(&self) {
+        self.report_error(GcError::TraceFailure);
+        match self.trace_failure_policy.get() {
+            TraceFailurePolicy::Abort => {
+                AbortFailureGuard::new(GcError::TraceFailure).fail();
+            }
+            TraceFailurePolicy::Panic => {
+                // the collector stays poisoned, and the panic continues unwinding
+                self.invalidate_roots(RootState::Poisoned);
+            }
+        }
+    }
+
+    /// Perform a full collection which also compacts the old generation.
+    ///
+    /// Unpinned live objects in the old generation are evacuated into a fresh heap,
+    /// updating references to them just like objects evacuated from the young generation.
+    /// This is much slower than a regular collection,
+    /// but avoids fragmentation in long-running programs.
+    ///
+    /// Pinned objects are never moved,
+    /// so the memory of the previous heap is only released once they have all died.
+    #[cold]
+    pub fn compact(&mut self) {
+        self.check_can_collect();
+        unsafe {
+            self.old_generation.begin_compaction();
+        }
+        self.collect_for(CollectionReason::Compaction);
+    }
+
+    #[cold]
+    pub fn force_collect(&mut self) {
+        // SAFETY: No GC pointers can be live while the collector is borrowed mutably
+        unsafe { self.force_collect_shared() }
+    }
+
+    /// Unconditionally collect garbage,
+    /// without requiring a mutable reference to the collector.
+    ///
+    /// ## Safety
+    /// The same requirements as [`Self::collect_shared`] apply.
+    #[cold]
+    pub unsafe fn force_collect_shared(&self) {
+        self.collect_for(CollectionReason::Explicit);
+    }
+
+    /// Check that a collection can begin,
+    /// panicking if it would be reentrant or the collector is poisoned.
+    #[track_caller]
+    fn check_can_collect(&self) {
+        assert!(
+            !self.collecting.get(),
+            "Cannot collect while a collection is already in progress"
+        );
+        assert!(
+            self.initializing.get() == 0,
+            "Cannot collect while an object is being initialized"
+        );
+        if self.poisoned.get() {
+            panic!("{}", self.report_error(GcError::Poisoned));
+        }
+    }
+
+    /// Perform a collection.
+    ///
+    /// This only requires a shared reference,
+    /// relying on [`Self::check_can_collect`] to reject reentrant collections.
+    #[cold]
+    fn collect_for(&self, reason: CollectionReason) {
+        self.check_can_collect();
+        self.collecting.set(true);
+        let _collecting = scopeguard::guard((), |()| self.collecting.set(false));
+        #[cfg(feature = "record-replay")]
+        self.recorder.record_collect(reason);
+        // pinned for the duration of this collection
+        #[cfg(feature = "conservative-roots")]
+        let conservative_pins = unsafe { self.conservative_roots.pin_referenced(self) };
+        // every region is emptied, including the scoped region
+        self.remembered.borrow_mut().clear();
+        self.poisoned.set(true);
+        self.state.allocate_black.set(true);
+        unsafe { self.trace_heap(&[]) };
+        // now sweep
+        unsafe {
+            self.young_generation.run_destructors(&self.state);
+            if self.config.drop_order == DropOrder::DropBeforeFree {
+                self.old_generation.run_destructors(&self.state);
+            }
+            self.young_generation.sweep();
+            self.old_generation.sweep(&self.state);
+            if self.old_generation.is_compacting() {
+                self.old_generation.finish_compaction();
+            }
+        }
+        // touch roots to verify validity
+        #[cfg(debug_assertions)]
+        for root in self.roots.borrow().iter() {
+            unsafe {
+                assert!(!root
+                    .upgrade()
+                    .unwrap()
+                    .header
+                    .get()
+                    .as_ref()
+                    .state_bits
+                    .get()
+                    .forwarded());
+            }
+        }
+        #[cfg(debug_assertions)]
+        unsafe {
+            self::verify::verify_heap(self);
+        }
+
+        self.state.allocate_black.set(false);
+        // invert meaning of the mark bits
+        self.state
+            .mark_bits_inverted
+            .set(!self.state.mark_bits_inverted.get());
+        // count size to trigger next gc
+        self.last_collect_size.set(Some(self.current_size()));
+        self.bytes_since_collect.set(0);
+        self.collection_recommended.set(false);
+        self.heap_limit_exceeded.set(false);
+        self.soft_limit_exceeded.set(false);
+        self.update_collect_watermark();
+        self.stats.record_collection(reason);
+        self.collection_count
+            .set(self.collection_count.get().wrapping_add(1));
+        self.poisoned.set(false);
+        #[cfg(feature = "conservative-roots")]
+        drop(conservative_pins);
+    }
+
+    /// Mark every object reachable from the roots, evacuating young objects,
+    /// then update the side tables of the objects which moved or died.
+    ///
+    /// The `remembered` objects are traced like roots,
+    /// which is only needed when [evacuating a single region](Self::try_evacuate_region).
+    ///
+    /// ## Safety
+    /// Must only be called at the start of a collection, before sweeping.
+    unsafe fn trace_heap(&self, remembered: &[NonNull<GcHeader<Id>>]) {
+        self.state.marking.set(true);
+        // mark roots
+        let mut context = CollectContext {
+            garbage_collector: self,
+            id: self.collector_id,
+            freezing: false,
+            gray_queue: self.gray_queue.take(),
+            #[cfg(debug_assertions)]
+            verify_live: None,
+        };
+        let failure_guard = scopeguard::guard((), |()| self.trace_failed());
+        let mut roots = self.roots.borrow_mut();
+        roots.retain(|root| {
+            match root.upgrade() {
+                Some(root) => {
+                    let new_header = unsafe { context.collect_any_gcheader(root.header.get()) };
+                    root.header.set(new_header);
+                    true // keep live root
+                }
+                None => false, // delete dead root
+            }
+        });
+        drop(roots); // release guard
+        self.handle_scopes
+            .borrow_mut()
+            .retain(|slots| match slots.upgrade() {
+                Some(slots) => {
+                    unsafe { slots.trace(&mut context) };
+                    true
+                }
+                None => false, // scope has been dropped
+            });
+        let guardians = {
+            let mut tables = self.guardians.borrow_mut();
+            tables.retain(|table| table.strong_count() > 0);
+            tables.iter().filter_map(Weak::upgrade).collect::<Vec<_>>()
+        };
+        unsafe {
+            for guardian in &guardians {
+                guardian.trace_queue(&mut context);
+            }
+            for &header in remembered {
+                context.trace_remembered(header);
+            }
+            self.immortal_space.trace_roots(&mut context);
+            self.mutation_log.drain(&mut context);
+            context.drain_gray_queue();
+        }
+        // trace ephemerons until reaching a fixpoint
+        let ephemerons = {
+            let mut tables = self.ephemerons.borrow_mut();
+            tables.retain(|table| table.strong_count() > 0);
+            tables.iter().filter_map(Weak::upgrade).collect::<Vec<_>>()
+        };
+        loop {
+            loop {
+                let mut progress = false;
+                for table in &ephemerons {
+                    progress |= unsafe { table.trace_reachable(&mut context) };
+                }
+                progress |= unsafe { self.mutation_log.drain(&mut context) };
+                unsafe { context.drain_gray_queue() };
+                if !progress {
+                    break;
+                }
+            }
+            // resurrect unreachable objects registered with guardians,
+            // then trace everything they reference to reach a new fixpoint
+            let mut resurrected = false;
+            for guardian in &guardians {
+                resurrected |= unsafe { guardian.resurrect(&mut context) };
+            }
+            if !resurrected {
+                break;
+            }
+            unsafe { context.drain_gray_queue() };
+        }
+        drop(guardians);
+        self.state.marking.set(false);
+        self.gray_queue.set(std::mem::take(&mut context.gray_queue));
+        // tracing failure is fatal, but sweeping fatal is fine
+        scopeguard::ScopeGuard::into_inner(failure_guard);
+        for table in &ephemerons {
+            unsafe { table.sweep(&self.state) };
+        }
+        drop(ephemerons);
+        unsafe { self.identity_hashes.process_marked(&self.state) };
+        unsafe { self.finalizers.process_marked(&self.state) };
+        #[cfg(feature = "debug-tombstones")]
+        unsafe {
+            self.tombstones
+                .process_marked(&self.state, self.stats.collections() + 1);
+        }
+    }
+
+    #[inline]
+    fn current_size(&self) -> GenerationSizes {
+        GenerationSizes {
+            old_generation_size: self.old_generation.allocated_bytes(),
+            young_generation_size: self.young_generation.allocated_bytes(),
+        }
+    }
+
+    /// The number of bytes held by the heap, which counts towards the heap limits.
+    ///
+    /// Unlike the [current size](Self::current_size),
+    /// this includes the free blocks the old generation retains for reuse
+    /// and the objects in the immortal space.
+    #[inline]
+    fn heap_size(&self) -> usize {
+        self.current_size().total()
+            + self.old_generation.free_list_bytes()
+            + self.immortal_space.allocated_bytes()
+    }
+
+    #[inline]
+    fn threshold_size(&self) -> GenerationSizes {
+        match self.last_collect_size.get() {
+            None => GenerationSizes::INITIAL_COLLECT_THRESHOLD,
+            Some(last_sizes) => last_sizes.next_threshold(),
+        }
+    }
+
+    #[inline]
+    fn needs_collection(&self) -> bool {
+        self.current_size()
+            .meets_either_threshold(self.threshold_size())
+    }
+
+    /// Recompute the watermark used by [`Self::should_collect`].
+    ///
+    /// Each allocation grows only one generation,
+    /// so no size threshold can be met before allocating
+    /// the smallest remaining headroom of either generation.
+    fn update_collect_watermark(&self) {
+        let allocated = self.bytes_since_collect.get();
+        let watermark = if self.pending_collection_reason().is_some() {
+            0
+        } else {
+            let current = self.current_size();
+            let threshold = self.threshold_size();
+            let headroom = threshold
+                .young_generation_size
+                .saturating_sub(current.young_generation_size)
+                .min(
+                    threshold
+                        .old_generation_size
+                        .saturating_sub(current.old_generation_size),
+                );
+            let watermark = allocated.saturating_add(headroom);
+            match self.config.collect_trigger_bytes {
+                Some(trigger) => watermark.min(trigger),
+                None => watermark,
+            }
+        };
+        self.collect_watermark.set(watermark);
+        if let (Some(limit), false) = (self.config.soft_heap_limit, self.soft_limit_exceeded()) {
+            let size = self.heap_size();
+            self.soft_limit_watermark
+                .set(allocated.saturating_add(limit.saturating_sub(size)));
+        }
+    }
+}
+impl<Id: CollectorId> Drop for GarbageCollector<Id> {
+    fn drop(&mut self) {
+        // outstanding roots would otherwise dangle once the heap is freed
+        self.invalidate_roots(RootState::CollectorDropped);
+    }
+}
+
+/// An error returned by [`GarbageCollector::try_reserve`] and related methods.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum TryReserveError {
+    #[error("Reserving {requested} bytes exceeds heap limit ({remaining} bytes remaining)")]
+    HeapLimitExceeded { requested: usize, remaining: usize },
+    #[error("Invalid array layout: {0}")]
+    InvalidLayout(#[source] GcArrayLayoutError),
+}
+
+/// An error returned by the fallible APIs of the collector,
+/// such as [`GarbageCollector::try_alloc`] or [`GcHandle::try_resolve`].
+///
+/// Every error is also passed to the [error hook](GarbageCollector::set_error_hook),
+/// including those which cause an infallible API to panic.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum GcError {
+    /// Memory was exhausted by either generation.
+    #[error("Failed to allocate {size} bytes")]
+    AllocFailure { size: usize },
+    /// The allocation was rejected by the [heap limit](GcConfig::heap_limit).
+    #[error("Allocating {requested} bytes exceeds heap limit ({remaining} bytes remaining)")]
+    HeapLimit { requested: usize, remaining: usize },
+    /// An object or handle was used with a collector other than the one which owns it.
+    #[error("Object belongs to a different collector")]
+    WrongCollector,
+    /// A panic occurred during a previous collection,
+    /// leaving the collector in an inconsistent state.
+    #[error("Collector poisoned by a panic during a previous collection")]
+    Poisoned,
+    /// The handle no longer refers to a live object,
+    /// possibly because its collector has been dropped.
+    #[error("Invalid handle")]
+    InvalidHandle,
+    /// An object was allocated while a collection was in progress,
+    /// typically by the destructor of an object being swept.
+    #[error("Cannot allocate while a collection is in progress (was a destructor allocating?)")]
+    CollectionInProgress,
+    /// Tracing panicked in the middle of a collection,
+    /// leaving the heap in an inconsistent state.
+    ///
+    /// This is only passed to the [error hook](GarbageCollector::set_error_hook)
+    /// before applying the [`TraceFailurePolicy`].
+    #[error("GC failure to trace is fatal")]
+    TraceFailure,
+    /// The layout of an array could not be computed,
+    /// typically because its size overflows.
+    #[error("Invalid array layout: {0}")]
+    InvalidLayout(#[source] GcArrayLayoutError),
+}
+impl From<TryReserveError> for GcError {
+    fn from(error: TryReserveError) -> Self {
+        match error {
+            TryReserveError::HeapLimitExceeded {
+                requested,
+                remaining,
+            } => GcError::HeapLimit {
+                requested,
+                remaining,
+            },
+            TryReserveError::InvalidLayout(error) => GcError::InvalidLayout(error),
+        }
+    }
+}
+
+pub struct GcHandle<T: Collect<Id>, Id: CollectorId> {
+    ptr: Rc<GcRootBox<Id>>,
+    id: Id,
+    marker: PhantomData<T>,
+}
+impl<T: Collect<Id>, Id: CollectorId> GcHandle<T, Id> {
+    /// Resolve this handle into a [`Gc`] smart-pointer.
+    ///
+    /// ## Safety
+    /// Even if this handle is dropped, the value will live until the next collection.
+    /// This makes it valid for `'gc`.
+    ///
+    /// ## Panics
+    /// If the handle belongs to a different collector,
+    /// or is [no longer valid](Self::is_valid).
+    #[inline]
+    #[track_caller]
+    pub fn resolve<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> Gc<'gc, T::Collected<'gc>, Id> {
+        self.ptr.assert_usable();
+        assert_eq!(self.id, collector.id());
+        // reload from GcRootBox in case pointer moved
+        unsafe { Gc::from_raw_ptr(self.ptr.header.get().as_ref().regular_value_ptr().cast()) }
+    }
+
+    /// Check if the handle can still be used.
+    ///
+    /// Once the collector is dropped, the object is freed along with the rest of its heap,
+    /// and any attempt to use the handle will panic.
+    /// The same applies once the collector is [poisoned](GarbageCollector::is_poisoned)
+    /// by a [tracing failure](TraceFailurePolicy::Panic).
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.ptr.state.get() == RootState::Alive
+    }
+
+    /// Resolve this handle, returning an error if it belongs to a different collector,
+    /// or is [no longer valid](Self::is_valid).
+    ///
+    /// See [`Self::resolve`] for details.
+    #[inline]
+    pub fn try_resolve<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> Result<Gc<'gc, T::Collected<'gc>, Id>, GcError> {
+        if let Some(error) = self.ptr.state.get().error() {
+            return Err(collector.report_error(error));
+        }
+        if self.id != collector.id() {
+            return Err(collector.report_error(GcError::WrongCollector));
+        }
+        Ok(self.resolve(collector))
+    }
+
+    /// Clone the value out of the handle, without access to the collector.
+    ///
+    /// This is only supported for [singleton](CollectorId::SINGLETON) collectors,
+    /// because other collectors could have been destroyed while the handle is alive.
+    /// The value must not contain GC pointers, which could otherwise escape.
+    ///
+    /// ## Panics
+    /// If the collector is not a singleton,
+    /// or if the object is in an invalid state.
+    pub fn get_cloned(&self) -> T
+    where
+        T: Clone + NullCollect<Id>,
+    {
+        assert!(
+            Id::SINGLETON.is_some(),
+            "Only supported for singleton collectors"
+        );
+        self.ptr.assert_usable();
+        unsafe {
+            let header = self.ptr.header.get().as_ref();
+            let state_bits = header.state_bits.get();
+            assert!(
+                state_bits.value_initialized() && !state_bits.forwarded(),
+                "Invalid object state: {header:?}"
+            );
+            debug_assert_eq!(header.id(), self.id);
+            header.regular_value_ptr().cast::<T>().as_ref().clone()
+        }
+    }
+
+    /// Create a weak handle to the same object,
+    /// which does not keep it alive.
+    #[inline]
+    pub fn downgrade(&self, collector: &GarbageCollector<Id>) -> WeakGcHandle<T, Id> {
+        self.ptr.assert_usable();
+        assert_eq!(self.id, collector.id());
+        WeakGcHandle::new(collector, self.ptr.header.get())
+    }
+
+    /// Erase the type of this handle,
+    /// so it can be stored alongside handles to other types.
+    ///
+    /// The type can be recovered with [`GcAnyHandle::downcast`].
+    #[inline]
+    pub fn erase(self) -> GcAnyHandle<Id>
+    where
+        T: 'static,
+    {
+        GcAnyHandle::new(self)
+    }
+
+    /// Create a handle to an object derived from this one,
+    /// such as the target of one of its fields.
+    ///
+    /// The new handle is rooted independently of this one.
+    #[inline]
+    pub fn map_root<'gc, U: Collect<Id>>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+        func: impl FnOnce(Gc<'gc, T::Collected<'gc>, Id>) -> Gc<'gc, U, Id>,
+    ) -> GcHandle<U::Collected<'static>, Id> {
+        collector.root(func(self.resolve(collector)))
+    }
+}
+/// Cloning a handle shares the same root,
+/// so the clones always resolve to the same object.
+impl<T: Collect<Id>, Id: CollectorId> Clone for GcHandle<T, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        GcHandle {
+            ptr: Rc::clone(&self.ptr),
+            id: self.id,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// A guard which prevents an object from moving,
+/// created by [`GarbageCollector::pin`] or [`Gc::pin`].
+///
+/// The object is rooted for as long as the guard is held,
+/// so the pointer returned by [`GcPin::as_ptr`] remains valid across collections.
+///
+/// This is necessary to pass GC memory to foreign code,
+/// which is unaware objects could move.
+pub struct GcPin<T: Collect<Id>, Id: CollectorId> {
+    raw: RawPin<Id>,
+    marker: PhantomData<T>,
+}
+impl<T: Collect<Id>, Id: CollectorId> GcPin<T, Id> {
+    /// A stable pointer to the pinned value.
+    #[inline]
+    pub fn as_ptr(&self) -> NonNull<T> {
+        self.raw.header().regular_value_ptr().cast()
+    }
 
-        // invert meaning of the mark bits
-        self.state
-            .mark_bits_inverted
-            .set(!self.state.mark_bits_inverted.get());
-        // count size to trigger next gc
-        self.last_collect_size = Some(self.current_size());
+    /// Resolve the pinned object into a [`Gc`] smart-pointer.
+    #[inline]
+    #[track_caller]
+    pub fn resolve<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> Gc<'gc, T::Collected<'gc>, Id> {
+        assert_eq!(self.raw.header().id(), collector.id());
+        unsafe { Gc::from_raw_ptr(self.as_ptr().cast()) }
     }
 
+    /// Check if the pin can still be used.
+    ///
+    /// See [`GcHandle::is_valid`] for details.
     #[inline]
-    fn current_size(&self) -> GenerationSizes {
-        GenerationSizes {
-            old_generation_size: self.old_generation.allocated_bytes(),
-            young_generation_size: self.young_generation.allocated_bytes(),
-        }
+    pub fn is_valid(&self) -> bool {
+        self.raw.root.state.get() == RootState::Alive
     }
 
+    /// Resolve the pinned object, returning an error if it belongs to a different collector,
+    /// or is [no longer valid](Self::is_valid).
     #[inline]
-    fn threshold_size(&self) -> GenerationSizes {
-        match self.last_collect_size {
-            None => GenerationSizes::INITIAL_COLLECT_THRESHOLD,
-            Some(last_sizes) => GenerationSizes {
-                young_generation_size: last_sizes.young_generation_size * 2,
-                old_generation_size: last_sizes.old_generation_size * 2,
-            },
+    pub fn try_resolve<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> Result<Gc<'gc, T::Collected<'gc>, Id>, GcError> {
+        if let Some(error) = self.raw.root.state.get().error() {
+            return Err(collector.report_error(error));
         }
+        if self.raw.header().id() != collector.id() {
+            return Err(collector.report_error(GcError::WrongCollector));
+        }
+        Ok(self.resolve(collector))
     }
+}
 
+/// An untyped guard which keeps an object pinned and rooted.
+///
+/// Unpins the object on drop, unless it was already pinned.
+pub(crate) struct RawPin<Id: CollectorId> {
+    root: Rc<GcRootBox<Id>>,
+    /// The counts to release when dropped,
+    /// or `None` if the object is pinned permanently.
+    counts: Option<Rc<PinCounts<Id>>>,
+}
+impl<Id: CollectorId> RawPin<Id> {
     #[inline]
-    fn needs_collection(&self) -> bool {
-        self.current_size()
-            .meets_either_threshold(self.threshold_size())
+    #[track_caller]
+    pub fn header(&self) -> &'_ GcHeader<Id> {
+        self.root.assert_usable();
+        // pinned objects never move, so the header is stable
+        unsafe { self.root.header.get().as_ref() }
+    }
+}
+impl<Id: CollectorId> Drop for RawPin<Id> {
+    fn drop(&mut self) {
+        // the object was freed along with its collector, or may be inconsistent
+        if self.root.state.get() != RootState::Alive {
+            return;
+        }
+        if let Some(ref counts) = self.counts {
+            unsafe { counts.release(self.root.header.get()) }
+        }
     }
 }
 
-pub struct GcHandle<T: Collect<Id>, Id: CollectorId> {
-    ptr: Rc<GcRootBox<Id>>,
-    id: Id,
-    marker: PhantomData<T>,
+/// Counts the guards pinning each object,
+/// so the object is only unpinned once the last guard is dropped.
+///
+/// Pinned objects never move, so their headers can be used as keys.
+struct PinCounts<Id: CollectorId> {
+    counts: RefCell<HashMap<NonNull<GcHeader<Id>>, usize>>,
 }
-impl<T: Collect<Id>, Id: CollectorId> GcHandle<T, Id> {
-    /// Resolve this handle into a [`Gc`] smart-pointer.
-    ///
-    /// ## Safety
-    /// Even if this handle is dropped, the value will live until the next collection.
-    /// This makes it valid for `'gc`.
+impl<Id: CollectorId> PinCounts<Id> {
+    fn new() -> Self {
+        PinCounts {
+            counts: RefCell::new(HashMap::new()),
+        }
+    }
+
     #[inline]
-    pub fn resolve<'gc>(
-        &self,
-        collector: &'gc GarbageCollector<Id>,
-    ) -> Gc<'gc, T::Collected<'gc>, Id> {
-        assert_eq!(self.id, collector.id());
-        // reload from GcRootBox in case pointer moved
-        unsafe { Gc::from_raw_ptr(self.ptr.header.get().as_ref().regular_value_ptr().cast()) }
+    fn get(&self, header: NonNull<GcHeader<Id>>) -> usize {
+        self.counts.borrow().get(&header).copied().unwrap_or(0)
+    }
+
+    /// Pin the object, returning `false` if it was already pinned permanently
+    /// and so does not need to be counted.
+    unsafe fn acquire(&self, header: NonNull<GcHeader<Id>>) -> bool {
+        let mut counts = self.counts.borrow_mut();
+        match counts.get_mut(&header) {
+            Some(count) => *count += 1,
+            None if header.as_ref().is_pinned() => return false,
+            None => {
+                header
+                    .as_ref()
+                    .update_state_bits(|state| state.with_pinned(true));
+                counts.insert(header, 1);
+            }
+        }
+        true
+    }
+
+    /// Release a pin, unpinning the object once the count reaches zero.
+    unsafe fn release(&self, header: NonNull<GcHeader<Id>>) {
+        let mut counts = self.counts.borrow_mut();
+        let count = counts.get_mut(&header).expect("object should be pinned");
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(&header);
+            header
+                .as_ref()
+                .update_state_bits(|state| state.with_pinned(false));
+        }
     }
 }
 
@@ -329,23 +2997,53 @@ unsafe trait RawAllocTarget<Id: CollectorId> {
     fn needs_drop(&self) -> bool;
     unsafe fn init_header(&self, header_ptr: NonNull<Self::Header>, base_header: GcHeader<Id>);
     fn overall_layout(&self) -> Layout;
+    /// The padding between the start of the allocation and the header.
+    #[inline]
+    fn header_offset(&self) -> usize {
+        0
+    }
     #[inline]
     fn init_state_bits(&self, gen: GenerationId) -> GcStateBits {
         GcStateBits::builder()
             .with_forwarded(false)
             .with_generation(gen)
             .with_array(Self::ARRAY)
-            .with_raw_mark_bits(GcMarkBits::White.to_raw(self.collector_state()))
+            .with_raw_mark_bits(
+                self.collector_state()
+                    .initial_mark_bits()
+                    .to_raw(self.collector_state()),
+            )
             .with_value_initialized(false)
+            .with_pinned(false)
+            .with_frozen(false)
+            .with_immortal(false)
+            .with_age(u2::new(0))
+            .with_deep_frozen(false)
             .build()
     }
 
+    /// Which generation the allocation should be placed in, if it fits in the young generation.
+    #[inline]
+    fn placement(&self) -> PlacementHint {
+        PlacementHint::Young
+    }
+
     fn collector_state(&self) -> &'_ CollectorState<Id>;
 }
+#[derive(Clone, Copy)]
 struct RegularAlloc<'a, Id: CollectorId> {
     state: &'a CollectorState<Id>,
     type_info: &'static GcTypeInfo<Id>,
 }
+impl<'a, Id: CollectorId> RegularAlloc<'a, Id> {
+    #[inline]
+    fn new<T: Collect<Id>>(state: &'a CollectorState<Id>) -> Self {
+        RegularAlloc {
+            state,
+            type_info: GcTypeInfo::new::<T>(),
+        }
+    }
+}
 unsafe impl<Id: CollectorId> RawAllocTarget<Id> for RegularAlloc<'_, Id> {
     const ARRAY: bool = false;
     type Header = GcHeader<Id>;
@@ -369,12 +3067,17 @@ unsafe impl<Id: CollectorId> RawAllocTarget<Id> for RegularAlloc<'_, Id> {
 
     #[inline]
     fn overall_layout(&self) -> Layout {
-        unsafe {
-            Layout::from_size_align_unchecked(
-                self.type_info.layout.overall_layout().size(),
-                GcHeader::<Id>::FIXED_ALIGNMENT,
-            )
-        }
+        self.type_info.layout.overall_layout()
+    }
+
+    #[inline]
+    fn header_offset(&self) -> usize {
+        self.type_info.layout.header_offset()
+    }
+
+    #[inline]
+    fn placement(&self) -> PlacementHint {
+        self.type_info.placement
     }
 
     #[inline]
@@ -420,6 +3123,11 @@ unsafe impl<Id: CollectorId> RawAllocTarget<Id> for ArrayAlloc<'_, Id> {
         self.layout_info.overall_layout()
     }
 
+    #[inline]
+    fn header_offset(&self) -> usize {
+        self.layout_info.header_offset()
+    }
+
     #[inline]
     fn collector_state(&self) -> &'_ CollectorState<Id> {
         self.state
@@ -436,6 +3144,21 @@ enum GenerationId {
 pub struct CollectContext<'newgc, Id: CollectorId> {
     id: Id,
     garbage_collector: &'newgc GarbageCollector<Id>,
+    /// Indicates this context is freezing objects instead of collecting them.
+    ///
+    /// Objects never move while freezing,
+    /// so pointers are never updated.
+    freezing: bool,
+    /// Objects which have been marked, but whose children have not yet been traced.
+    ///
+    /// These are processed in FIFO order,
+    /// so that promoted objects are laid out breadth-first like a Cheney-style copying collector.
+    gray_queue: VecDeque<NonNull<GcHeader<Id>>>,
+    /// The set of live objects, if this context is verifying the heap instead of collecting it.
+    ///
+    /// Pointers are checked against the set, and never updated.
+    #[cfg(debug_assertions)]
+    verify_live: Option<std::collections::HashSet<NonNull<GcHeader<Id>>>>,
 }
 impl<'newgc, Id: CollectorId> CollectContext<'newgc, Id> {
     #[inline]
@@ -446,11 +3169,129 @@ impl<'newgc, Id: CollectorId> CollectContext<'newgc, Id> {
     #[inline]
     pub unsafe fn trace_gc_ptr_mut<T: Collect<Id>>(&mut self, target: NonNull<Gc<'_, T, Id>>) {
         let target = target.as_ptr();
+        if self.freezing {
+            return self.freeze_gcheader(NonNull::from(target.read().header()));
+        }
         target
             .cast::<Gc<'newgc, T::Collected<'newgc>, Id>>()
             .write(self.collect_gc_ptr(target.read()));
     }
 
+    /// Trace a pointer to a [dynamically typed](DynGcType) object,
+    /// updating it in place.
+    ///
+    /// ## Safety
+    /// The target must point to a valid `DynGc`,
+    /// and must only be traced once per collection.
+    #[inline]
+    pub unsafe fn trace_dyn_gc_mut(&mut self, target: NonNull<DynGc<'_, Id>>) {
+        let target = target.as_ptr();
+        if self.freezing {
+            return self.freeze_gcheader(NonNull::from(target.read().header()));
+        }
+        let new_header = self.collect_gcheader(NonNull::from(target.read().header()));
+        target
+            .cast::<DynGc<'newgc, Id>>()
+            .write(DynGc::from_raw_ptr(new_header.as_ref().regular_value_ptr()));
+    }
+
+    /// Trace a pointer to a [`GcArray`], updating it in place.
+    ///
+    /// ## Safety
+    /// The target must point to a valid `GcArray`,
+    /// and must only be traced once per collection.
+    #[inline]
+    pub unsafe fn trace_gc_array_mut<T: Collect<Id>>(
+        &mut self,
+        target: NonNull<GcArray<'_, T, Id>>,
+    ) {
+        let target = target.as_ptr();
+        if self.freezing {
+            let header = target.read().header().main_header();
+            return self.freeze_gcheader(NonNull::from(header));
+        }
+        let new_header = self.collect_array_header(NonNull::from(target.read().header()));
+        target
+            .cast::<GcArray<'newgc, T::Collected<'newgc>, Id>>()
+            .write(GcArray::from_raw_ptr(
+                new_header.as_ref().array_value_ptr().cast(),
+            ));
+    }
+
+    /// Trace an optional [`Gc`] pointer, updating it in place.
+    ///
+    /// ## Safety
+    /// The target must point to a valid `Option<Gc>`,
+    /// and must only be traced once per collection.
+    #[inline]
+    pub unsafe fn trace_option<T: Collect<Id>>(&mut self, target: NonNull<Option<Gc<'_, T, Id>>>) {
+        if let Some(ptr) = &mut *target.as_ptr() {
+            self.trace_gc_ptr_mut(NonNull::from(ptr));
+        }
+    }
+
+    /// Trace every element of a slice, updating them in place.
+    ///
+    /// This is a no-op if the elements don't [need collection](Collect::NEEDS_COLLECT).
+    ///
+    /// ## Safety
+    /// The target must point to a valid slice,
+    /// and must only be traced once per collection.
+    #[inline]
+    pub unsafe fn trace_slice<T: Collect<Id>>(&mut self, target: NonNull<[T]>) {
+        if T::NEEDS_COLLECT {
+            let elements = target.cast::<T>();
+            for index in 0..target.len() {
+                T::collect_inplace(elements.add(index), self);
+            }
+        }
+    }
+
+    /// Trace each of the specified fields, updating them in place.
+    ///
+    /// ## Safety
+    /// Each field must point to a valid value,
+    /// and must only be traced once per collection.
+    #[inline]
+    pub unsafe fn trace_fields<T: Collect<Id>>(
+        &mut self,
+        fields: impl IntoIterator<Item = NonNull<T>>,
+    ) {
+        if T::NEEDS_COLLECT {
+            for field in fields {
+                T::collect_inplace(field, self);
+            }
+        }
+    }
+
+    /// Freeze an object and everything reachable from it.
+    ///
+    /// Objects which are already deep frozen are skipped,
+    /// as their children must have already been frozen.
+    /// Objects which were only frozen shallowly still have their children traced.
+    #[cold]
+    unsafe fn freeze_gcheader(&mut self, header: NonNull<GcHeader<Id>>) {
+        debug_assert!(self.freezing);
+        debug_assert!(!header.as_ref().state_bits.get().forwarded());
+        if header.as_ref().is_deep_frozen() {
+            return;
+        }
+        // marked before tracing the children, so cycles terminate
+        header
+            .as_ref()
+            .update_state_bits(|state| state.with_frozen(true).with_deep_frozen(true));
+        if let Some(trace_func) = header.as_ref().type_info().trace_func {
+            #[cfg(not(miri))]
+            stacker::maybe_grow(
+                4096,       // 4KB
+                128 * 1024, // 128KB
+                || self.trace_children(header, trace_func),
+            );
+            #[cfg(miri)]
+            self.trace_children(header, trace_func);
+        }
+    }
+
     #[inline]
     unsafe fn collect_gc_ptr<'gc, T: Collect<Id>>(
         &mut self,
@@ -464,19 +3305,52 @@ impl<'newgc, Id: CollectorId> CollectContext<'newgc, Id> {
         )
     }
 
-    #[cfg_attr(not(debug_assertions), inline)]
+    #[inline]
     #[must_use]
     unsafe fn collect_gcheader(&mut self, header: NonNull<GcHeader<Id>>) -> NonNull<GcHeader<Id>> {
+        debug_assert!(
+            !header.as_ref().state_bits.get().array(),
+            "Incorrectly marked as an array"
+        );
+        self.collect_any_gcheader(header)
+    }
+
+    #[inline]
+    #[must_use]
+    pub(crate) unsafe fn collect_array_header(
+        &mut self,
+        header: NonNull<GcArrayHeader<Id>>,
+    ) -> NonNull<GcArrayHeader<Id>> {
+        debug_assert!(
+            header.as_ref().main_header.state_bits.get().array(),
+            "Incorrectly marked as a regular object"
+        );
+        self.collect_any_gcheader(header.cast()).cast()
+    }
+
+    /// Collect a header which could be either an array or a regular object.
+    #[cfg_attr(not(debug_assertions), inline)]
+    #[must_use]
+    unsafe fn collect_any_gcheader(
+        &mut self,
+        header: NonNull<GcHeader<Id>>,
+    ) -> NonNull<GcHeader<Id>> {
+        #[cfg(debug_assertions)]
+        if let Some(ref live) = self.verify_live {
+            return self::verify::verify_pointer(live, header);
+        }
         let mark_bits: GcMarkBits;
         {
             let header = header.as_ref();
             assert_eq!(header.collector_id, self.id, "Mismatched collector ids");
-            debug_assert!(
-                !header.state_bits.get().array(),
-                "Incorrectly marked as an array"
-            );
+            if header.state_bits.get().immortal() {
+                return NonNull::from(header); // implicitly black
+            }
             if header.state_bits.get().forwarded() {
-                debug_assert_eq!(header.state_bits.get().generation(), GenerationId::Young);
+                debug_assert!(
+                    header.state_bits.get().generation() == GenerationId::Young
+                        || self.garbage_collector.old_generation.is_compacting()
+                );
                 debug_assert_eq!(
                     header
                         .state_bits
@@ -485,7 +3359,7 @@ impl<'newgc, Id: CollectorId> CollectContext<'newgc, Id> {
                         .resolve(&self.garbage_collector.state),
                     GcMarkBits::Black
                 );
-                return header.metadata.forward_ptr;
+                return header.forward_ptr();
             }
             mark_bits = header
                 .state_bits
@@ -494,11 +3368,38 @@ impl<'newgc, Id: CollectorId> CollectContext<'newgc, Id> {
                 .resolve(&self.garbage_collector.state);
         }
         match mark_bits {
+            GcMarkBits::White
+                if self
+                    .garbage_collector
+                    .state
+                    .outside_evacuated_region(header) =>
+            {
+                header
+            }
             GcMarkBits::White => self.fallback_collect_gc_header(header),
             GcMarkBits::Black => header,
         }
     }
 
+    /// Trace the children of an object outside the region being evacuated,
+    /// which may refer into the region, without marking or moving the object itself.
+    unsafe fn trace_remembered(&mut self, header: NonNull<GcHeader<Id>>) {
+        let state_bits = header.as_ref().state_bits.get();
+        debug_assert!(!state_bits.forwarded());
+        // failed initialization leaves objects which must not be traced
+        if !state_bits.value_initialized()
+            || !self
+                .garbage_collector
+                .state
+                .outside_evacuated_region(header)
+        {
+            return;
+        }
+        if let Some(trace_func) = header.as_ref().type_info().trace_func {
+            self.trace_children(header, trace_func);
+        }
+    }
+
     #[cold]
     unsafe fn fallback_collect_gc_header(
         &mut self,
@@ -528,64 +3429,126 @@ impl<'newgc, Id: CollectorId> CollectContext<'newgc, Id> {
                     .with_raw_mark_bits(GcMarkBits::Black.to_raw(&self.garbage_collector.state))
             });
             prev_generation = header.state_bits.get().generation();
-            type_info = header.metadata.type_info;
+            type_info = header.type_info();
         }
+        let pinned = header_ptr.as_ref().state_bits.get().pinned();
+        // compaction evacuates unpinned old objects into a fresh heap
+        let compact_old = prev_generation == GenerationId::Old
+            && !pinned
+            && self.garbage_collector.old_generation.is_compacting();
         let forwarded_ptr = match prev_generation {
-            GenerationId::Young => {
+            GenerationId::Young if pinned => {
+                // pinned objects are excluded from evacuation
+                self.garbage_collector
+                    .young_generation
+                    .retain_in_place(header_ptr);
+                header_ptr
+            }
+            GenerationId::Old if !compact_old => {
+                // no copying needed for oldgen
+                self.garbage_collector.old_generation.mark(header_ptr);
+                header_ptr
+            }
+            GenerationId::Young | GenerationId::Old => 'evacuate: {
                 let array_value_size: Option<usize>;
-                // reallocate in oldgen
+                let age = header_ptr.as_ref().state_bits.get().age().value();
+                // objects evacuated from a single region stay young, as the old generation is not swept
+                let evacuating_region = self.garbage_collector.state.evacuating_region();
+                let promote = compact_old
+                    || (age >= self.garbage_collector.young_generation.age_threshold()
+                        && !evacuating_region);
+                // reallocate in oldgen, or a survivor space if the object is too young
                 let copied_ptr = if array {
                     let array_type_info = type_info.assume_array_info();
                     debug_assert!(std::ptr::eq(
                         array_type_info,
-                        header_ptr.as_ref().metadata.array_type_info
+                        header_ptr.as_ref().array_type_info()
                     ));
                     let array_layout = GcArrayLayoutInfo::new_unchecked(
                         array_type_info.element_type_info.layout.value_layout(),
                         header_ptr.cast::<GcArrayHeader<Id>>().as_ref().len_elements,
                     );
                     array_value_size = Some(array_layout.value_layout().size());
-                    self.garbage_collector
-                        .old_generation
-                        .alloc_raw(&ArrayAlloc {
+                    self.alloc_evacuated(
+                        &ArrayAlloc {
                             layout_info: array_layout,
                             type_info: array_type_info,
                             state: &self.garbage_collector.state,
-                        })
-                        .map(NonNull::cast::<GcHeader<Id>>)
+                        },
+                        prev_generation,
+                        promote,
+                    )
+                    .map(NonNull::cast::<GcHeader<Id>>)
                 } else {
                     array_value_size = None;
-                    self.garbage_collector
-                        .old_generation
-                        .alloc_raw(&RegularAlloc {
+                    self.alloc_evacuated(
+                        &RegularAlloc {
                             type_info,
                             state: &self.garbage_collector.state,
-                        })
-                }
-                .unwrap_or_else(|_| {
-                    // TODO: This panic is fatal, will cause an abort
-                    panic!("Oldgen alloc failure")
-                });
+                        },
+                        prev_generation,
+                        promote,
+                    )
+                };
+                let Some(copied_ptr) = copied_ptr else {
+                    self.retain_unevacuated(header_ptr, prev_generation);
+                    break 'evacuate header_ptr;
+                };
                 copied_ptr
                     .as_ref()
                     .state_bits
                     .set(header_ptr.as_ref().state_bits.get());
-                copied_ptr.as_ref().update_state_bits(|bits| {
-                    debug_assert!(!bits.forwarded());
-                    bits.with_generation(GenerationId::Old)
-                        .with_value_initialized(true)
-                });
-                header_ptr
+                #[cfg(feature = "user-word")]
+                copied_ptr
                     .as_ref()
-                    .update_state_bits(|bits| bits.with_forwarded(true));
-                (&mut *header_ptr.as_ptr()).metadata.forward_ptr = copied_ptr.cast();
-                // determine if drop is needed from header_ptr, avoiding an indirection to type_info
-                let needs_drop = header_ptr.as_ref().alloc_info.nontrivial_drop_index < u32::MAX;
-                debug_assert_eq!(needs_drop, type_info.drop_func.is_some());
-                if needs_drop {
-                    self.garbage_collector
-                        .young_generation
-                        .remove_destruction_queue(header_ptr, &self.garbage_collector.state);
+                    .user_word
+                    .set(header_ptr.as_ref().user_word.get());
+                if promote {
+                    if !compact_old {
+                        self.garbage_collector.stats.record_promotion(if array {
+                            header_ptr
+                                .cast::<GcArrayHeader<Id>>()
+                                .as_ref()
+                                .layout_info()
+                                .overall_layout()
+                                .size()
+                        } else {
+                            type_info.layout.overall_layout().size()
+                        });
+                    }
+                    copied_ptr.as_ref().update_state_bits(|bits| {
+                        debug_assert!(!bits.forwarded());
+                        bits.with_generation(GenerationId::Old)
+                            .with_value_initialized(true)
+                    });
+                    self.garbage_collector.old_generation.mark(copied_ptr);
+                } else {
+                    copied_ptr.as_ref().update_state_bits(|bits| {
+                        debug_assert!(!bits.forwarded());
+                        let bits = bits.with_age(u2::new(age + 1)).with_value_initialized(true);
+                        if evacuating_region {
+                            // the mark bits are not inverted afterwards
+                            bits.with_raw_mark_bits(
+                                GcMarkBits::White.to_raw(&self.garbage_collector.state),
+                            )
+                        } else {
+                            bits
+                        }
+                    });
+                }
+                if !compact_old {
+                    // determine if drop is needed from header_ptr, avoiding an indirection to type_info
+                    let drop_index = header_ptr.as_ref().alloc_info.nontrivial_drop_index;
+                    let needs_drop = drop_index != YoungGenerationSpace::<Id>::NO_DROP_INDEX;
+                    debug_assert!(
+                        drop_index == YoungGenerationSpace::<Id>::RETAINED_INDEX
+                            || needs_drop == type_info.drop_func.is_some()
+                    );
+                    if needs_drop {
+                        self.garbage_collector
+                            .young_generation
+                            .remove_destruction_queue(header_ptr, &self.garbage_collector.state);
+                    }
                 }
                 // NOTE: Copy uninitialized bytes is safe here, as long as they are not read in dest
                 if array {
@@ -618,32 +3581,146 @@ impl<'newgc, Id: CollectorId> CollectContext<'newgc, Id> {
                             type_info.layout.value_layout().size(),
                         );
                 }
+                if let Some(moved_func) = type_info.moved_func {
+                    Self::notify_moved(header_ptr, copied_ptr, type_info, moved_func);
+                }
+                // forward only after copying, as the forwarding pointer may overwrite the value
+                header_ptr
+                    .as_ref()
+                    .update_state_bits(|bits| bits.with_forwarded(true));
+                GcHeader::set_forward_ptr(header_ptr, copied_ptr);
                 copied_ptr
             }
-            GenerationId::Old => header_ptr, // no copying needed for oldgen
         };
         /*
-         * finally, trace the value
+         * finally, queue the value to be traced
          * this needs to come after forwarding and switching the mark bit
          * so we can properly update self-referential pointers
          */
-        if let Some(trace_func) = type_info.trace_func {
-            /*
-             * NOTE: Cannot have aliasing &mut header references during this recursion
-             * The parameters to maybe_grow are completely arbitrary right now.
-             */
-            #[cfg(not(miri))]
-            stacker::maybe_grow(
-                4096,       // 4KB
-                128 * 1024, // 128KB
-                || self.trace_children(forwarded_ptr, trace_func),
-            );
-            #[cfg(miri)]
-            self.trace_children(forwarded_ptr, trace_func);
+        if type_info.trace_func.is_some() {
+            // start loading the value, so it is hopefully cached once dequeued
+            crate::utils::prefetch_read(forwarded_ptr.as_ref().regular_value_ptr().as_ptr());
+            self.gray_queue.push_back(forwarded_ptr);
         }
         forwarded_ptr
     }
 
+    /// Invoke the [move hook](Collect::on_moved) of a value which has just been copied,
+    /// or of each element if it is an array.
+    #[cold]
+    unsafe fn notify_moved(
+        old_header: NonNull<GcHeader<Id>>,
+        new_header: NonNull<GcHeader<Id>>,
+        type_info: &'static GcTypeInfo<Id>,
+        moved_func: MovedFuncPtr,
+    ) {
+        if old_header.as_ref().state_bits.get().array() {
+            let old_array = old_header.cast::<GcArrayHeader<Id>>();
+            let new_array = new_header.cast::<GcArrayHeader<Id>>();
+            let old_elements = old_array.as_ref().array_value_ptr().cast::<u8>();
+            let new_elements = new_array.as_ref().array_value_ptr().cast::<u8>();
+            let element_size = type_info.layout.value_layout().size();
+            for index in 0..old_array.as_ref().len() {
+                let offset = index * element_size;
+                moved_func(
+                    old_elements.add(offset).cast(),
+                    new_elements.add(offset).cast(),
+                );
+            }
+        } else {
+            moved_func(
+                old_header.as_ref().regular_value_ptr().cast(),
+                new_header.as_ref().regular_value_ptr().cast(),
+            );
+        }
+    }
+
+    /// Allocate the destination of an evacuated object.
+    ///
+    /// Young objects are copied into the old generation or a survivor space,
+    /// or into the main eden arena when evacuating a single region,
+    /// while old objects are only evacuated by compaction.
+    #[inline]
+    unsafe fn alloc_evacuated<T: RawAllocTarget<Id>>(
+        &self,
+        target: &T,
+        prev_generation: GenerationId,
+        promote: bool,
+    ) -> Option<NonNull<T::Header>> {
+        #[cfg(feature = "fault-injection")]
+        if self
+            .garbage_collector
+            .faults
+            .should_fail(self::faults::AllocFailStage::Evacuation)
+        {
+            return None;
+        }
+        if prev_generation == GenerationId::Old {
+            self.garbage_collector.old_generation.evacuate(target).ok()
+        } else if promote {
+            self.garbage_collector.old_generation.alloc_raw(target).ok()
+        } else if self.garbage_collector.state.evacuating_region() {
+            self.garbage_collector
+                .young_generation
+                .alloc_eden(target)
+                .ok()
+        } else {
+            self.garbage_collector
+                .young_generation
+                .alloc_survivor(target)
+                .ok()
+        }
+    }
+
+    /// Leave a marked object in place, because there was no room to evacuate it.
+    ///
+    /// Like a pinned object, it keeps its arena or heap alive until it dies.
+    /// A compacting collection still succeeds, just without compacting this object.
+    /// Objects in a scoped region cannot be left behind,
+    /// since the region is freed without sweeping,
+    /// so this is a fatal allocation error.
+    #[cold]
+    unsafe fn retain_unevacuated(
+        &self,
+        header_ptr: NonNull<GcHeader<Id>>,
+        prev_generation: GenerationId,
+    ) {
+        match prev_generation {
+            GenerationId::Old => self.garbage_collector.old_generation.mark(header_ptr),
+            GenerationId::Young if !self.garbage_collector.state.evacuating_region() => self
+                .garbage_collector
+                .young_generation
+                .retain_in_place(header_ptr),
+            GenerationId::Young => {
+                let size = if header_ptr.as_ref().state_bits.get().array() {
+                    header_ptr
+                        .cast::<GcArrayHeader<Id>>()
+                        .as_ref()
+                        .layout_info()
+                        .overall_layout()
+                        .size()
+                } else {
+                    header_ptr
+                        .as_ref()
+                        .type_info()
+                        .layout
+                        .overall_layout()
+                        .size()
+                };
+                self.garbage_collector.oom(GcError::AllocFailure { size })
+            }
+        }
+    }
+
+    /// Trace the children of all gray objects,
+    /// including any objects which become gray in the process.
+    unsafe fn drain_gray_queue(&mut self) {
+        while let Some(header) = self.gray_queue.pop_front() {
+            let trace_func = header.as_ref().type_info().trace_func;
+            self.trace_children(header, trace_func.unwrap_unchecked());
+        }
+    }
+
     #[inline]
     unsafe fn trace_children(
         &mut self,
@@ -666,13 +3743,59 @@ impl<'newgc, Id: CollectorId> CollectContext<'newgc, Id> {
         header: NonNull<GcArrayHeader<Id>>,
         trace_func: TraceFuncPtr<Id>,
     ) {
-        let type_info = header.as_ref().main_header.metadata.type_info;
+        let type_info = header.as_ref().main_header.type_info();
         debug_assert_eq!(type_info.trace_func, Some(trace_func));
         let array_header = header.cast::<GcArrayHeader<Id>>();
+        if array_header.as_ref().main_header.array_type_info().kind() == GcArrayKind::GcPointers {
+            return self.trace_gc_pointer_elements(array_header);
+        }
         for element in array_header.as_ref().iter_elements() {
             trace_func(element.cast::<()>(), self);
         }
     }
+
+    /// Trace an array whose elements are [`Gc`] pointers,
+    /// as a tight loop instead of an indirect call per element.
+    unsafe fn trace_gc_pointer_elements(&mut self, header: NonNull<GcArrayHeader<Id>>) {
+        let elements = header.as_ref().array_value_ptr().cast::<NonNull<u8>>();
+        for index in 0..header.as_ref().len() {
+            let slot = elements.add(index).as_ptr();
+            let element_header = slot
+                .read()
+                .sub(GcHeader::<Id>::REGULAR_VALUE_OFFSET)
+                .cast::<GcHeader<Id>>();
+            // matches the check in the `Collect` impl for `Gc`
+            if Id::SINGLETON.is_none() && element_header.as_ref().id() != self.id {
+                continue;
+            }
+            if self.freezing {
+                self.freeze_gcheader(element_header);
+            } else {
+                slot.write(
+                    self.collect_gcheader(element_header)
+                        .as_ref()
+                        .regular_value_ptr(),
+                );
+            }
+        }
+    }
+}
+
+/// A RAII guard to drop the initialized prefix of a partially initialized array.
+struct DropInitElementsGuard<T> {
+    elements_ptr: NonNull<T>,
+    initialized: usize,
+}
+impl<T> Drop for DropInitElementsGuard<T> {
+    #[cold]
+    fn drop(&mut self) {
+        unsafe {
+            std::ptr::drop_in_place(std::ptr::slice_from_raw_parts_mut(
+                self.elements_ptr.as_ptr(),
+                self.initialized,
+            ));
+        }
+    }
 }
 
 /// A RAII guard to destroy an uninitialized GC allocation.
@@ -682,6 +3805,11 @@ impl<'newgc, Id: CollectorId> CollectContext<'newgc, Id> {
 struct DestroyUninitValueGuard<'a, Id: CollectorId> {
     header: NonNull<GcHeader<Id>>,
     old_generation: &'a OldGenerationSpace<Id>,
+    initializing: &'a Cell<usize>,
+    #[cfg(feature = "debug-tombstones")]
+    tombstones: &'a self::tombstone::TombstoneTracker<Id>,
+    #[cfg(feature = "debug-tombstones")]
+    gc_number: u64,
 }
 impl<'a, Id: CollectorId> DestroyUninitValueGuard<'a, Id> {
     #[inline]
@@ -690,6 +3818,7 @@ impl<'a, Id: CollectorId> DestroyUninitValueGuard<'a, Id> {
             unsafe { self.header.as_ref().state_bits.get().value_initialized() },
             "Value not initialized"
         );
+        self.initializing.set(self.initializing.get() - 1);
         std::mem::forget(self);
     }
 }
@@ -697,14 +3826,22 @@ impl<Id: CollectorId> Drop for DestroyUninitValueGuard<'_, Id> {
     #[cold]
     fn drop(&mut self) {
         // should only be called on failure
+        self.initializing.set(self.initializing.get() - 1);
         unsafe {
             assert!(
                 !self.header.as_ref().state_bits.get().value_initialized(),
                 "Value successfully initialized but guard not defused"
             );
+            if self.header.as_ref().is_immortal() {
+                // immortal memory is never reclaimed, and uninitialized objects are skipped
+                return;
+            }
             match self.header.as_ref().state_bits.get().generation() {
                 GenerationId::Old => {
                     // old-gen needs an explicit free
+                    #[cfg(feature = "debug-tombstones")]
+                    self.tombstones
+                        .record_early_free(self.header, self.gc_number);
                     self.old_generation.destroy_uninit_object(self.header);
                 }
                 GenerationId::Young => {
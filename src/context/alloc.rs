@@ -33,6 +33,25 @@ impl<A: Allocator> CountingAlloc<A> {
     pub fn allocated_bytes(&self) -> usize {
         self.allocated_bytes.get()
     }
+
+    /// Reset the count of allocated bytes,
+    /// after the underlying allocator has freed everything in bulk.
+    #[inline]
+    pub fn reset_count(&mut self) {
+        self.allocated_bytes.set(0);
+    }
+
+    /// Count bytes handed out from a block which was allocated
+    /// directly from the [underlying allocator](Self::as_inner).
+    #[inline]
+    pub fn count_bytes(&self, bytes: usize) {
+        self.allocated_bytes.set(self.allocated_bytes.get() + bytes);
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> A {
+        self.alloc
+    }
 }
 
 unsafe impl<A: Allocator> Allocator for CountingAlloc<A> {
@@ -0,0 +1,124 @@
+//! Handles whose type has been erased,
+//! so handles to different types can be stored in a single collection.
+
+use std::any::TypeId;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use crate::context::roots::RootState;
+use crate::context::{GcHandle, GcRootBox};
+use crate::{Collect, CollectorId, GarbageCollector, Gc};
+
+/// A [`GcHandle`] whose type has been erased,
+/// created by [`GcHandle::erase`].
+///
+/// The handle remembers the type of its object,
+/// so it can be [downcast](Self::downcast) back to a typed pointer.
+/// Like a typed handle, the object is kept alive until the handle is dropped.
+pub struct GcAnyHandle<Id: CollectorId> {
+    ptr: Rc<GcRootBox<Id>>,
+    id: Id,
+    type_id: TypeId,
+    type_name: &'static str,
+}
+impl<Id: CollectorId> GcAnyHandle<Id> {
+    #[inline]
+    pub(super) fn new<T: Collect<Id> + 'static>(handle: GcHandle<T, Id>) -> Self {
+        GcAnyHandle {
+            ptr: handle.ptr,
+            id: handle.id,
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+
+    /// The [`TypeId`] of the object, with its GC lifetime erased to `'static`.
+    #[inline]
+    pub fn value_type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// The name of the object's type, for diagnostics.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Check if the object has the specified type,
+    /// given with its GC lifetime erased to `'static`.
+    #[inline]
+    pub fn is<T: Collect<Id> + 'static>(&self) -> bool {
+        self.type_id == TypeId::of::<T>()
+    }
+
+    /// Check if the handle can still be used.
+    ///
+    /// See [`GcHandle::is_valid`] for details.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.ptr.state.get() == RootState::Alive
+    }
+
+    /// Resolve the handle into a [`Gc`] pointer of the specified type,
+    /// returning `None` if the object has a different type.
+    ///
+    /// ## Panics
+    /// If the handle belongs to a different collector,
+    /// or is [no longer valid](Self::is_valid).
+    #[inline]
+    #[track_caller]
+    pub fn downcast<'gc, T: Collect<Id> + 'static>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> Option<Gc<'gc, T::Collected<'gc>, Id>> {
+        self.ptr.assert_usable();
+        assert_eq!(self.id, collector.id());
+        if !self.is::<T>() {
+            return None;
+        }
+        // reload from GcRootBox in case pointer moved
+        Some(unsafe { Gc::from_raw_ptr(self.ptr.header.get().as_ref().regular_value_ptr().cast()) })
+    }
+
+    /// Convert back into a typed handle,
+    /// returning the original handle if the object has a different type.
+    #[inline]
+    pub fn downcast_handle<T: Collect<Id> + 'static>(self) -> Result<GcHandle<T, Id>, Self> {
+        if self.is::<T>() {
+            Ok(GcHandle {
+                ptr: self.ptr,
+                id: self.id,
+                marker: PhantomData,
+            })
+        } else {
+            Err(self)
+        }
+    }
+}
+/// Cloning a handle shares the same root,
+/// so the clones always resolve to the same object.
+impl<Id: CollectorId> Clone for GcAnyHandle<Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        GcAnyHandle {
+            ptr: Rc::clone(&self.ptr),
+            id: self.id,
+            type_id: self.type_id,
+            type_name: self.type_name,
+        }
+    }
+}
+impl<T: Collect<Id> + 'static, Id: CollectorId> From<GcHandle<T, Id>> for GcAnyHandle<Id> {
+    #[inline]
+    fn from(handle: GcHandle<T, Id>) -> Self {
+        GcAnyHandle::new(handle)
+    }
+}
+impl<Id: CollectorId> Debug for GcAnyHandle<Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GcAnyHandle")
+            .field("type_name", &self.type_name)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,166 @@
+//! Branded collectors, which distinguish pointers from different collectors at compile time.
+//!
+//! Every [`GcScope`] is given a unique, invariant `'brand` lifetime.
+//! Pointers allocated by the scope carry the brand in their type,
+//! so passing them to a different scope is a type error
+//! instead of a runtime id check.
+
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ptr::NonNull;
+
+use crate::context::{GarbageCollector, GcConfig, GcHandle, GcPin, ScopedId};
+use crate::{Collect, CollectContext, Gc};
+
+/// An invariant lifetime, which can never be unified with another brand.
+type Brand<'brand> = PhantomData<fn(&'brand ()) -> &'brand ()>;
+
+impl GarbageCollector<ScopedId> {
+    /// Run the specified closure with a fresh branded collector,
+    /// which is destroyed once the closure returns.
+    ///
+    /// Unlike [`GarbageCollector::scoped`],
+    /// pointers from different scopes have distinct types.
+    #[inline]
+    pub fn scope<R>(func: impl for<'brand> FnOnce(&mut GcScope<'brand>) -> R) -> R {
+        Self::scope_with_config(GcConfig::default(), func)
+    }
+
+    /// Run the specified closure with a fresh branded collector,
+    /// using the specified configuration.
+    ///
+    /// See [`Self::scope`] for details.
+    pub fn scope_with_config<R>(
+        config: GcConfig,
+        func: impl for<'brand> FnOnce(&mut GcScope<'brand>) -> R,
+    ) -> R {
+        GarbageCollector::scoped_with_config(config, |collector| {
+            func(&mut GcScope {
+                collector,
+                brand: PhantomData,
+            })
+        })
+    }
+}
+
+/// A garbage collector with a unique `'brand` lifetime,
+/// created by [`GarbageCollector::scope`].
+pub struct GcScope<'brand> {
+    collector: &'brand mut GarbageCollector<ScopedId>,
+    brand: Brand<'brand>,
+}
+impl<'brand> GcScope<'brand> {
+    /// The underlying collector, for APIs which are not branded.
+    #[inline]
+    pub fn collector(&self) -> &GarbageCollector<ScopedId> {
+        self.collector
+    }
+
+    /// Mutable access to the underlying collector,
+    /// which is needed to run a collection.
+    #[inline]
+    pub fn collector_mut(&mut self) -> &mut GarbageCollector<ScopedId> {
+        self.collector
+    }
+
+    /// Allocate a branded GC object.
+    #[inline]
+    #[track_caller]
+    pub fn alloc<T: Collect<ScopedId>>(&self, value: T) -> BrandedGc<'_, 'brand, T> {
+        BrandedGc {
+            gc: self.collector.alloc(value),
+            brand: PhantomData,
+        }
+    }
+
+    /// Brand a pointer, checking that it belongs to this collector.
+    ///
+    /// This is the only runtime check needed,
+    /// as the brand is preserved by all other operations.
+    #[inline]
+    pub fn brand<'gc, T: Collect<ScopedId>>(
+        &'gc self,
+        gc: Gc<'gc, T, ScopedId>,
+    ) -> BrandedGc<'gc, 'brand, T> {
+        assert_eq!(gc.id(), self.collector.id());
+        BrandedGc {
+            gc,
+            brand: PhantomData,
+        }
+    }
+
+    /// Resolve a handle into a branded pointer.
+    ///
+    /// ## Panics
+    /// If the handle belongs to a different collector.
+    #[inline]
+    pub fn resolve<T: Collect<ScopedId>>(
+        &self,
+        handle: &GcHandle<T, ScopedId>,
+    ) -> BrandedGc<'_, 'brand, T::Collected<'_>> {
+        BrandedGc {
+            gc: handle.resolve(self.collector()),
+            brand: PhantomData,
+        }
+    }
+
+    /// Root the specified object, as in [`GarbageCollector::root`].
+    ///
+    /// No id check is needed, because the brand guarantees a matching collector.
+    #[inline]
+    pub fn root<'gc, T: Collect<ScopedId>>(
+        &'gc self,
+        val: BrandedGc<'gc, 'brand, T>,
+    ) -> GcHandle<T::Collected<'static>, ScopedId> {
+        unsafe { self.collector.root_raw(NonNull::from(val.gc.header())) }
+    }
+
+    /// Pin the specified object, as in [`GarbageCollector::pin`].
+    #[inline]
+    pub fn pin<'gc, T: Collect<ScopedId>>(
+        &'gc self,
+        val: BrandedGc<'gc, 'brand, T>,
+    ) -> GcPin<T::Collected<'static>, ScopedId> {
+        self.collector.pin(val.gc)
+    }
+}
+
+/// A [`Gc`] pointer which is statically known to belong to the [`GcScope`] with the same brand.
+pub struct BrandedGc<'gc, 'brand, T> {
+    gc: Gc<'gc, T, ScopedId>,
+    brand: Brand<'brand>,
+}
+impl<'gc, 'brand, T> BrandedGc<'gc, 'brand, T> {
+    /// Discard the brand, returning a regular [`Gc`] pointer.
+    #[inline]
+    pub fn into_gc(self) -> Gc<'gc, T, ScopedId> {
+        self.gc
+    }
+}
+unsafe impl<'gc, 'brand, T: Collect<ScopedId>> Collect<ScopedId> for BrandedGc<'gc, 'brand, T> {
+    type Collected<'newgc> = BrandedGc<'newgc, 'brand, T::Collected<'newgc>>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, ScopedId>) {
+        <Gc<'gc, T, ScopedId> as Collect<ScopedId>>::collect_inplace(
+            NonNull::from(&mut (*target.as_ptr()).gc),
+            context,
+        )
+    }
+}
+impl<'gc, 'brand, T> Deref for BrandedGc<'gc, 'brand, T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        &self.gc
+    }
+}
+impl<'gc, 'brand, T> Copy for BrandedGc<'gc, 'brand, T> {}
+impl<'gc, 'brand, T> Clone for BrandedGc<'gc, 'brand, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
@@ -0,0 +1,134 @@
+//! Classifying arbitrary addresses against the objects in the heap.
+
+use std::ptr::NonNull;
+
+use crate::context::layout::GcHeader;
+use crate::context::GenerationId;
+use crate::CollectorId;
+#[cfg(any(feature = "classify-ptr", debug_assertions))]
+use crate::GarbageCollector;
+
+/// The space of the heap which contains an object.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HeapSpace {
+    /// The young generation, where new objects are allocated.
+    Young,
+    /// The old generation, containing promoted objects,
+    /// pinned objects, objects too large for the young generation,
+    /// and objects whose type is [placed](crate::PlacementHint::Old) there directly.
+    Old,
+    /// The space of [immortal](GarbageCollector::alloc_immortal) objects.
+    Immortal,
+}
+
+/// An object found by [`GarbageCollector::classify_ptr`].
+///
+/// The object may be garbage which has not yet been collected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HeapObject {
+    /// The space containing the object.
+    pub space: HeapSpace,
+    /// The address of the start of the object's allocation,
+    /// including its header.
+    pub address: usize,
+    /// The size of the object's allocation.
+    pub size: usize,
+    /// The name of the object's type.
+    pub type_name: &'static str,
+    /// Whether the object is an array.
+    pub array: bool,
+    /// Whether the object is currently [pinned](crate::GcPin),
+    /// so that its address will not change.
+    pub pinned: bool,
+}
+impl HeapObject {
+    pub(super) unsafe fn from_header<Id: CollectorId>(header: NonNull<GcHeader<Id>>) -> Self {
+        let (start, layout) = header.as_ref().allocation();
+        let state_bits = header.as_ref().state_bits.get();
+        HeapObject {
+            space: if header.as_ref().is_immortal() {
+                HeapSpace::Immortal
+            } else {
+                match state_bits.generation() {
+                    GenerationId::Young => HeapSpace::Young,
+                    GenerationId::Old => HeapSpace::Old,
+                }
+            },
+            address: crate::utils::addr(start.as_ptr()),
+            size: layout.size(),
+            type_name: header.as_ref().type_info().type_name(),
+            array: state_bits.array(),
+            pinned: header.as_ref().is_pinned(),
+        }
+    }
+
+    /// Check if the specified address falls within the object.
+    #[inline]
+    pub fn contains(&self, address: usize) -> bool {
+        address >= self.address && address - self.address < self.size
+    }
+}
+
+/// Every initialized object in the heap, including dead objects which have not yet been swept.
+///
+/// Must not be called during a collection.
+#[cfg(any(feature = "classify-ptr", debug_assertions))]
+pub(super) unsafe fn heap_objects<Id: CollectorId>(
+    collector: &GarbageCollector<Id>,
+) -> impl Iterator<Item = NonNull<GcHeader<Id>>> + '_ {
+    collector
+        .young_generation
+        .objects()
+        .chain(collector.old_generation.objects())
+        .chain(collector.immortal_space.objects())
+        .filter(|header| header.as_ref().state_bits.get().value_initialized())
+}
+
+/// Find the object containing the specified address by searching the entire heap.
+#[cfg(feature = "classify-ptr")]
+pub(crate) fn find_object<Id: CollectorId>(
+    collector: &GarbageCollector<Id>,
+    address: usize,
+) -> Option<HeapObject> {
+    unsafe {
+        heap_objects(collector)
+            .map(|header| HeapObject::from_header(header))
+            .find(|object| object.contains(address))
+    }
+}
+
+/// An index of the objects in the heap sorted by address,
+/// for classifying many addresses at once.
+///
+/// This is invalidated by any allocation or collection.
+#[cfg(feature = "conservative-roots")]
+pub(crate) struct HeapIndex<Id: CollectorId> {
+    /// The start and end address of each object's allocation, along with its header.
+    objects: Vec<(usize, usize, NonNull<GcHeader<Id>>)>,
+}
+#[cfg(feature = "conservative-roots")]
+impl<Id: CollectorId> HeapIndex<Id> {
+    pub unsafe fn build(collector: &GarbageCollector<Id>) -> Self {
+        let mut objects = heap_objects(collector)
+            .map(|header| {
+                let (start, layout) = header.as_ref().allocation();
+                let start = crate::utils::addr(start.as_ptr());
+                (start, start + layout.size(), header)
+            })
+            .collect::<Vec<_>>();
+        objects.sort_unstable_by_key(|&(start, _, _)| start);
+        HeapIndex { objects }
+    }
+
+    /// Find the header of the object containing the specified address.
+    pub fn find(&self, address: usize) -> Option<NonNull<GcHeader<Id>>> {
+        let index = self
+            .objects
+            .partition_point(|&(start, _, _)| start <= address)
+            .checked_sub(1)?;
+        let (_, end, header) = self.objects[index];
+        (address < end).then_some(header)
+    }
+}
@@ -0,0 +1,195 @@
+//! Configuration for a [`GarbageCollector`](crate::GarbageCollector)
+
+/// Configuration options for a [`GarbageCollector`](crate::GarbageCollector).
+///
+/// The [`Default`] configuration is suitable for most uses.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct GcConfig {
+    /// The maximum number of bytes the heap is allowed to occupy,
+    /// summed across both generations and the immortal space.
+    /// This includes dead objects which have not yet been swept,
+    /// and free blocks which the old generation retains for reuse.
+    ///
+    /// Exceeding this limit is a fatal allocation error,
+    /// unless it is checked ahead of time with a fallible API
+    /// like [`GarbageCollector::try_reserve`](crate::GarbageCollector::try_reserve).
+    ///
+    /// If this is `None`, the heap is unlimited.
+    pub heap_limit: Option<usize>,
+    /// The number of bytes the heap can occupy before a collection is requested,
+    /// summed across the heap like the [hard limit](Self::heap_limit).
+    ///
+    /// Unlike the [hard limit](Self::heap_limit), allocations past this limit still succeed.
+    /// Once the heap grows past it, [`GarbageCollector::should_collect`](crate::GarbageCollector::should_collect) returns `true`
+    /// and the [soft limit callback](crate::GarbageCollector::set_soft_limit_callback) is invoked,
+    /// so cooperative hosts can collect at a convenient point before reaching the hard limit.
+    ///
+    /// If this is `None`, there is no soft limit.
+    pub soft_heap_limit: Option<usize>,
+    /// The number of recently freed objects to retain summaries for.
+    ///
+    /// This is ignored unless the `debug-tombstones` feature is enabled.
+    /// See [`GarbageCollector::find_tombstone`](crate::GarbageCollector::find_tombstone).
+    pub tombstone_capacity: usize,
+    /// The number of bytes which can be allocated after a collection
+    /// before another collection is recommended.
+    ///
+    /// Once exceeded, [`GarbageCollector::collection_recommended`](crate::GarbageCollector::collection_recommended) returns `true`
+    /// and the [trigger callback](crate::GarbageCollector::set_trigger_callback) is invoked.
+    /// This decouples collection policy from the places which call [`GarbageCollector::collect`](crate::GarbageCollector::collect).
+    ///
+    /// If this is `None`, allocations are not tracked.
+    pub collect_trigger_bytes: Option<usize>,
+    /// The initial size of each chunk of the young generation.
+    ///
+    /// The young generation is composed of chained chunks,
+    /// so it grows as needed instead of spilling into the old generation.
+    pub young_chunk_size: usize,
+    /// The largest object which can be allocated in the young generation.
+    ///
+    /// Anything larger is allocated directly in the old generation.
+    /// This must not exceed the [chunk size](Self::young_chunk_size).
+    pub young_object_limit: usize,
+    /// The number of collections a young object must survive before it is promoted.
+    ///
+    /// Until then, surviving objects are copied between two survivor spaces
+    /// instead of occupying space in the old generation.
+    /// Zero promotes every object the first time it survives.
+    pub survivor_age_threshold: u8,
+    /// Whether the destructors of dead objects may observe each other.
+    ///
+    /// See [`DropOrder`] for details.
+    pub drop_order: DropOrder,
+}
+impl GcConfig {
+    /// Set the [heap limit](Self::heap_limit), returning the modified config.
+    #[inline]
+    pub fn with_heap_limit(mut self, limit: usize) -> Self {
+        self.heap_limit = Some(limit);
+        self
+    }
+
+    /// Set the [soft heap limit](Self::soft_heap_limit), returning the modified config.
+    #[inline]
+    pub fn with_soft_heap_limit(mut self, limit: usize) -> Self {
+        self.soft_heap_limit = Some(limit);
+        self
+    }
+
+    /// Set the [young chunk size](Self::young_chunk_size), returning the modified config.
+    ///
+    /// The [object limit](Self::young_object_limit) is reduced if it would exceed the chunk size.
+    #[inline]
+    pub fn with_young_chunk_size(mut self, bytes: usize) -> Self {
+        self.young_chunk_size = bytes;
+        self.young_object_limit = self.young_object_limit.min(bytes);
+        self
+    }
+
+    /// Set the [young object limit](Self::young_object_limit), returning the modified config.
+    ///
+    /// ## Panics
+    /// If the limit exceeds the [chunk size](Self::young_chunk_size).
+    #[inline]
+    pub fn with_young_object_limit(mut self, bytes: usize) -> Self {
+        assert!(
+            bytes <= self.young_chunk_size,
+            "Young object limit must not exceed the chunk size"
+        );
+        self.young_object_limit = bytes;
+        self
+    }
+
+    /// The largest supported [age threshold](Self::survivor_age_threshold).
+    pub const MAX_SURVIVOR_AGE: u8 = 3;
+
+    /// Set the [age threshold](Self::survivor_age_threshold), returning the modified config.
+    ///
+    /// ## Panics
+    /// If the threshold exceeds [`Self::MAX_SURVIVOR_AGE`].
+    #[inline]
+    pub fn with_survivor_age_threshold(mut self, age: u8) -> Self {
+        assert!(
+            age <= Self::MAX_SURVIVOR_AGE,
+            "Age threshold must not exceed {}",
+            Self::MAX_SURVIVOR_AGE
+        );
+        self.survivor_age_threshold = age;
+        self
+    }
+
+    /// Set the [drop order](Self::drop_order), returning the modified config.
+    #[inline]
+    pub fn with_drop_order(mut self, order: DropOrder) -> Self {
+        self.drop_order = order;
+        self
+    }
+
+    /// Set the [collection trigger](Self::collect_trigger_bytes), returning the modified config.
+    #[inline]
+    pub fn with_collect_trigger(mut self, bytes: usize) -> Self {
+        self.collect_trigger_bytes = Some(bytes);
+        self
+    }
+}
+impl Default for GcConfig {
+    #[inline]
+    fn default() -> Self {
+        GcConfig {
+            heap_limit: None,
+            soft_heap_limit: None,
+            tombstone_capacity: 1024,
+            collect_trigger_bytes: None,
+            young_chunk_size: 32 * 1024,
+            young_object_limit: 1024,
+            survivor_age_threshold: 1,
+            drop_order: DropOrder::default(),
+        }
+    }
+}
+
+/// How the destructors of dead objects are ordered relative to freeing their memory.
+///
+/// Within each generation, destructors run in an unspecified order,
+/// so a destructor must never assume another dead object has or has not been dropped.
+/// The young generation always runs every destructor before freeing any memory,
+/// and its objects are dropped before those of the old generation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum DropOrder {
+    /// Each old object is freed immediately after its destructor runs.
+    ///
+    /// This is the cheapest, since the heap is only traversed once,
+    /// but a destructor must not read any other dead object.
+    #[default]
+    Interleaved,
+    /// Run every destructor before freeing any dead object.
+    ///
+    /// A destructor may read the memory of other dead objects,
+    /// such as a sibling it holds a [`Gc`](crate::Gc) pointer to,
+    /// although that object may have already been dropped.
+    /// This costs an extra pass over the old generation.
+    DropBeforeFree,
+}
+
+/// What happens when tracing panics in the middle of a collection,
+/// set by [`GarbageCollector::set_trace_failure_policy`](crate::GarbageCollector::set_trace_failure_policy).
+///
+/// Tracing moves objects and updates pointers to them,
+/// so a panic leaves the heap in an inconsistent state which cannot be recovered.
+/// Either way, the [error hook](crate::GarbageCollector::set_error_hook)
+/// first receives [`GcError::TraceFailure`](crate::GcError::TraceFailure),
+/// so hosts can flush logs or crash reports.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum TraceFailurePolicy {
+    /// Abort the process.
+    #[default]
+    Abort,
+    /// Poison the collector and continue unwinding the panic.
+    ///
+    /// Every root is invalidated, as if the collector had been dropped,
+    /// and the collector must not be used for anything except dropping it.
+    Panic,
+}
@@ -0,0 +1,133 @@
+//! Conservative scanning of registered memory for pointers into the heap.
+//!
+//! Only available with the `conservative-roots` feature.
+
+use std::cell::{Cell, RefCell};
+use std::mem::size_of;
+use std::ptr::NonNull;
+
+use crate::context::classify::HeapIndex;
+use crate::context::layout::GcHeader;
+use crate::context::RawPin;
+use crate::{CollectorId, GarbageCollector};
+
+/// Identifies a range registered for conservative scanning,
+/// so that it can later be unregistered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ConservativeRangeId(u64);
+
+enum ScanRange {
+    /// A fixed range of memory.
+    Fixed { start: *const u8, len: usize },
+    /// The stack of the collector's thread,
+    /// from the position at the time of the collection up to the base.
+    Stack { base: *const u8 },
+}
+
+/// Memory ranges which are scanned for pointers at the start of every collection.
+pub(crate) struct ConservativeRoots {
+    ranges: RefCell<Vec<(ConservativeRangeId, ScanRange)>>,
+    next_id: Cell<u64>,
+}
+impl ConservativeRoots {
+    pub fn new() -> Self {
+        ConservativeRoots {
+            ranges: RefCell::new(Vec::new()),
+            next_id: Cell::new(0),
+        }
+    }
+
+    fn register(&self, range: ScanRange) -> ConservativeRangeId {
+        let id = ConservativeRangeId(self.next_id.get());
+        self.next_id.set(id.0 + 1);
+        self.ranges.borrow_mut().push((id, range));
+        id
+    }
+
+    pub fn register_fixed(&self, start: *const u8, len: usize) -> ConservativeRangeId {
+        self.register(ScanRange::Fixed { start, len })
+    }
+
+    pub fn register_stack(&self, base: *const u8) -> ConservativeRangeId {
+        self.register(ScanRange::Stack { base })
+    }
+
+    pub fn unregister(&self, id: ConservativeRangeId) -> bool {
+        let mut ranges = self.ranges.borrow_mut();
+        match ranges.iter().position(|&(existing, _)| existing == id) {
+            Some(index) => {
+                ranges.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pin every mortal object referenced by a word in the registered ranges.
+    ///
+    /// The objects remain pinned and rooted until the returned guards are dropped.
+    ///
+    /// ## Safety
+    /// Every registered range must be readable.
+    /// Must be called at the start of a collection, before anything is marked.
+    pub unsafe fn pin_referenced<Id: CollectorId>(
+        &self,
+        collector: &GarbageCollector<Id>,
+    ) -> Vec<RawPin<Id>> {
+        let ranges = self.ranges.borrow();
+        if ranges.is_empty() {
+            return Vec::new();
+        }
+        let index = HeapIndex::build(collector);
+        let mut found = Vec::new();
+        let stack_position = current_stack_position();
+        for (_, range) in ranges.iter() {
+            let (start, len) = match *range {
+                ScanRange::Fixed { start, len } => (start, len),
+                ScanRange::Stack { base } => {
+                    let base_address = crate::utils::addr(base);
+                    if stack_position <= base_address {
+                        // the stack grows downwards
+                        let len = base_address - stack_position;
+                        (base.wrapping_sub(len), len)
+                    } else {
+                        (base, stack_position - base_address)
+                    }
+                }
+            };
+            scan_words(start, len, |word| {
+                if let Some(header) = index.find(word) {
+                    found.push(header);
+                }
+            });
+        }
+        drop(ranges);
+        found.sort_unstable();
+        found.dedup();
+        found
+            .into_iter()
+            .filter(|header: &NonNull<GcHeader<Id>>| !header.as_ref().is_immortal())
+            .map(|header| collector.pin_raw(header))
+            .collect()
+    }
+}
+
+/// An address within the frame of a function which has returned,
+/// which is below the frames of every caller on a downward-growing stack.
+#[inline(never)]
+fn current_stack_position() -> usize {
+    let marker = 0u8;
+    crate::utils::addr(std::hint::black_box(&marker))
+}
+
+/// Call the function with every aligned word in the range.
+unsafe fn scan_words(start: *const u8, len: usize, mut func: impl FnMut(usize)) {
+    let misalignment = start.align_offset(std::mem::align_of::<usize>());
+    if misalignment >= len {
+        return;
+    }
+    let words = start.wrapping_add(misalignment).cast::<usize>();
+    for index in 0..(len - misalignment) / size_of::<usize>() {
+        func(std::ptr::read_volatile(words.wrapping_add(index)));
+    }
+}
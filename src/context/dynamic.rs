@@ -0,0 +1,383 @@
+//! Object types which are defined at runtime.
+//!
+//! Normally the type information for an object is derived from a static rust type.
+//! This is insufficient for class-based scripting languages,
+//! where the shape of an object is only known at runtime.
+
+use std::alloc::Layout;
+use std::cell::RefCell;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+use crate::context::layout::{
+    GcArrayKind, GcHeader, GcTypeInfo, GcTypeLayout, GcTypeName, TraceFuncPtr,
+};
+use crate::utils::LayoutExt;
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector, PlacementHint};
+
+/// A callback to trace the contents of a dynamically typed object.
+///
+/// The first argument is a pointer to the object's value.
+pub type DynTraceFunc<Id> = TraceFuncPtr<Id>;
+
+/// A callback to destroy the contents of a dynamically typed object.
+///
+/// The argument is a pointer to the object's value.
+pub type DynDropFunc = unsafe fn(*mut ());
+
+/// The runtime type information for a dynamically typed object.
+///
+/// The `GcTypeInfo` is stored as the first field
+/// to allow one-way pointer casts from `DynTypeInfo` -> `GcTypeInfo`,
+/// in the same way as [`GcArrayTypeInfo`](crate::context::layout::GcArrayTypeInfo).
+#[repr(C)]
+pub(crate) struct DynTypeInfo<Id: CollectorId> {
+    base: GcTypeInfo<Id>,
+    /// The offsets of the `Option<DynGc>` slots within the value.
+    slot_offsets: Box<[usize]>,
+    /// An additional user-defined trace function,
+    /// called after tracing the slots.
+    extra_trace_func: Option<DynTraceFunc<Id>>,
+}
+impl<Id: CollectorId> DynTypeInfo<Id> {
+    /// Recover the dynamic type information from a value pointer.
+    ///
+    /// ## Safety
+    /// The value must be a (non-forwarded) dynamically typed object.
+    #[inline]
+    unsafe fn from_value_ptr<'a>(value: NonNull<()>) -> &'a Self {
+        let header = &*(value.as_ptr() as *mut u8)
+            .sub(GcHeader::<Id>::REGULAR_VALUE_OFFSET)
+            .cast::<GcHeader<Id>>();
+        debug_assert!(!header.state_bits.get().forwarded());
+        &*(header.type_info() as *const GcTypeInfo<Id> as *const DynTypeInfo<Id>)
+    }
+}
+
+/// The trace function shared by all dynamic types.
+///
+/// Traces the slots, then invokes any user-defined trace function.
+unsafe fn trace_dyn_object<Id: CollectorId>(value: NonNull<()>, context: &mut CollectContext<Id>) {
+    let info = DynTypeInfo::<Id>::from_value_ptr(value);
+    for &offset in info.slot_offsets.iter() {
+        let slot = value
+            .cast::<u8>()
+            .add(offset)
+            .cast::<Option<DynGc<'_, Id>>>();
+        if slot.as_ref().is_some() {
+            context.trace_dyn_gc_mut(slot.cast::<DynGc<'_, Id>>());
+        }
+    }
+    if let Some(extra_trace_func) = info.extra_trace_func {
+        extra_trace_func(value, context);
+    }
+}
+
+/// A handle to a type which was registered at runtime.
+///
+/// Created by [`GarbageCollector::register_dyn_type`].
+pub struct DynGcType<Id: CollectorId> {
+    info: &'static DynTypeInfo<Id>,
+}
+impl<Id: CollectorId> DynGcType<Id> {
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        self.info.base.type_name()
+    }
+
+    /// The layout of the object's value, excluding the header.
+    #[inline]
+    pub fn layout(&self) -> Layout {
+        self.info.base.layout.value_layout()
+    }
+
+    /// The number of [`DynGc`] slots in the object.
+    #[inline]
+    pub fn slot_count(&self) -> usize {
+        self.info.slot_offsets.len()
+    }
+
+    /// The byte offsets of the [`DynGc`] slots within the value.
+    #[inline]
+    pub fn slot_offsets(&self) -> &'static [usize] {
+        &self.info.slot_offsets
+    }
+
+    #[inline]
+    pub(crate) fn type_info(&self) -> &'static GcTypeInfo<Id> {
+        &self.info.base
+    }
+}
+impl<Id: CollectorId> Copy for DynGcType<Id> {}
+impl<Id: CollectorId> Clone for DynGcType<Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<Id: CollectorId> PartialEq for DynGcType<Id> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.info, other.info)
+    }
+}
+impl<Id: CollectorId> Eq for DynGcType<Id> {}
+impl<Id: CollectorId> Debug for DynGcType<Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DynGcType")
+            .field("name", &self.name())
+            .field("layout", &self.layout())
+            .field("slot_offsets", &self.slot_offsets())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Describes a type to register with [`GarbageCollector::register_dyn_type`].
+///
+/// The type's value consists of raw bytes described by a [`Layout`],
+/// along with a set of "slots" at fixed offsets,
+/// each containing an `Option<DynGc>` which is traced automatically.
+/// Any other pointers need to be traced by a custom [trace function](Self::trace_func).
+pub struct DynGcTypeBuilder<Id: CollectorId> {
+    name: String,
+    layout: Layout,
+    slot_offsets: Vec<usize>,
+    trace_func: Option<DynTraceFunc<Id>>,
+    drop_func: Option<DynDropFunc>,
+}
+impl<Id: CollectorId> DynGcTypeBuilder<Id> {
+    pub fn new(name: impl Into<String>, layout: Layout) -> Self {
+        DynGcTypeBuilder {
+            name: name.into(),
+            layout,
+            slot_offsets: Vec::new(),
+            trace_func: None,
+            drop_func: None,
+        }
+    }
+
+    /// Add a slot containing an `Option<DynGc>` at the specified byte offset.
+    pub fn slot(mut self, offset: usize) -> Self {
+        self.slot_offsets.push(offset);
+        self
+    }
+
+    /// Add a custom function to trace the object, called after tracing the slots.
+    pub fn trace_func(mut self, func: DynTraceFunc<Id>) -> Self {
+        self.trace_func = Some(func);
+        self
+    }
+
+    /// Add a function to destroy the object after it becomes unreachable.
+    pub fn drop_func(mut self, func: DynDropFunc) -> Self {
+        self.drop_func = Some(func);
+        self
+    }
+
+    fn validate(&self) -> Result<(), DynTypeError> {
+        if self.layout.align() > GcHeader::<Id>::FIXED_ALIGNMENT {
+            return Err(DynTypeError::InvalidAlignment {
+                align: self.layout.align(),
+            });
+        }
+        if LayoutExt(GcHeader::<Id>::REGULAR_HEADER_LAYOUT)
+            .extend(self.layout)
+            .is_err()
+        {
+            return Err(DynTypeError::SizeOverflow);
+        }
+        let slot_layout = Layout::new::<Option<DynGc<'static, Id>>>();
+        for &offset in &self.slot_offsets {
+            if offset % slot_layout.align() != 0 {
+                return Err(DynTypeError::MisalignedSlot { offset });
+            }
+            match offset.checked_add(slot_layout.size()) {
+                Some(end) if end <= self.layout.size() => {}
+                _ => return Err(DynTypeError::SlotOutOfBounds { offset }),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An error registering a [`DynGcType`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum DynTypeError {
+    #[error("Alignment {align} exceeds maximum")]
+    InvalidAlignment { align: usize },
+    #[error("Size overflow for type layout")]
+    SizeOverflow,
+    #[error("Slot at offset {offset} is misaligned")]
+    MisalignedSlot { offset: usize },
+    #[error("Slot at offset {offset} is out of bounds")]
+    SlotOutOfBounds { offset: usize },
+    #[error("Type already registered with name {name:?}")]
+    DuplicateName { name: String },
+}
+
+/// The registry of runtime types for a collector.
+pub(crate) struct DynTypeRegistry<Id: CollectorId> {
+    types: RefCell<Vec<DynGcType<Id>>>,
+}
+impl<Id: CollectorId> DynTypeRegistry<Id> {
+    pub fn new() -> Self {
+        DynTypeRegistry {
+            types: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub unsafe fn register(
+        &self,
+        builder: DynGcTypeBuilder<Id>,
+    ) -> Result<DynGcType<Id>, DynTypeError> {
+        builder.validate()?;
+        if self.lookup(&builder.name).is_some() {
+            return Err(DynTypeError::DuplicateName { name: builder.name });
+        }
+        let needs_trace = !builder.slot_offsets.is_empty() || builder.trace_func.is_some();
+        let info = DynTypeInfo {
+            base: GcTypeInfo {
+                layout: GcTypeLayout::from_value_layout(builder.layout),
+                drop_func: builder.drop_func,
+                trace_func: if needs_trace {
+                    Some(trace_dyn_object::<Id> as TraceFuncPtr<Id>)
+                } else {
+                    None
+                },
+                moved_func: None,
+                type_name: GcTypeName::Dynamic(Box::leak(builder.name.into_boxed_str())),
+                array_kind: GcArrayKind::Regular,
+                placement: PlacementHint::Young,
+            },
+            slot_offsets: builder.slot_offsets.into_boxed_slice(),
+            extra_trace_func: builder.trace_func,
+        };
+        /*
+         * Objects may refer to the type info until the collector is dropped,
+         * so it needs to be 'static.
+         * This intentionally leaks the info (and its name).
+         */
+        let ty = DynGcType {
+            info: Box::leak(Box::new(info)),
+        };
+        self.types.borrow_mut().push(ty);
+        Ok(ty)
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<DynGcType<Id>> {
+        self.types
+            .borrow()
+            .iter()
+            .find(|ty| ty.name() == name)
+            .copied()
+    }
+}
+
+/// A pointer to an object of a [dynamic type](DynGcType).
+pub struct DynGc<'gc, Id: CollectorId> {
+    ptr: NonNull<u8>,
+    marker: PhantomData<&'gc GarbageCollector<Id>>,
+}
+impl<'gc, Id: CollectorId> DynGc<'gc, Id> {
+    #[inline]
+    pub(crate) fn header(&self) -> &'gc GcHeader<Id> {
+        unsafe {
+            &*(self.ptr.as_ptr().sub(GcHeader::<Id>::REGULAR_VALUE_OFFSET) as *mut GcHeader<Id>)
+        }
+    }
+
+    #[inline]
+    pub fn id(&self) -> Id {
+        match unsafe { Id::summon_singleton() } {
+            None => self.header().id(),
+            Some(id) => id,
+        }
+    }
+
+    #[inline]
+    pub fn dyn_type(&self) -> DynGcType<Id> {
+        DynGcType {
+            info: unsafe { DynTypeInfo::from_value_ptr(self.ptr.cast()) },
+        }
+    }
+
+    /// A pointer to the raw bytes of the object's value.
+    ///
+    /// Writing to slots through this pointer is allowed,
+    /// but should be done through [`Self::set_slot`] instead.
+    #[inline]
+    pub fn as_ptr(&self) -> NonNull<u8> {
+        self.ptr
+    }
+
+    #[inline]
+    fn slot_ptr(&self, index: usize) -> NonNull<Option<DynGc<'gc, Id>>> {
+        let offset = self.dyn_type().slot_offsets()[index];
+        unsafe { self.ptr.add(offset).cast() }
+    }
+
+    /// Get the value of the slot with the specified index.
+    ///
+    /// ## Panics
+    /// If the index is out of bounds.
+    #[inline]
+    pub fn slot(&self, index: usize) -> Option<DynGc<'gc, Id>> {
+        unsafe { self.slot_ptr(index).read() }
+    }
+
+    /// Set the value of the slot with the specified index.
+    ///
+    /// ## Panics
+    /// If the index is out of bounds, or the object is [frozen](crate::Gc::freeze).
+    #[inline]
+    pub fn set_slot(&self, index: usize, value: Option<DynGc<'gc, Id>>) {
+        assert!(!self.header().is_frozen(), "Cannot mutate a frozen object");
+        unsafe { self.slot_ptr(index).write(value) }
+    }
+
+    crate::utils::unsafe_api! {
+        /// Create a pointer from the raw value pointer, as returned by [`Self::as_ptr`].
+        ///
+        /// Only public with the `unsafe-api` feature.
+        ///
+        /// ## Safety
+        /// The pointer must refer to a live object of a dynamic type,
+        /// which is valid for the lifetime `'gc`.
+        #[inline(always)]
+        pub unsafe fn from_raw_ptr(ptr: NonNull<u8>) -> Self {
+            DynGc {
+                ptr,
+                marker: PhantomData,
+            }
+        }
+    }
+}
+unsafe impl<'gc, Id: CollectorId> Collect<Id> for DynGc<'gc, Id> {
+    type Collected<'newgc> = DynGc<'newgc, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        if Id::SINGLETON.is_none() && target.as_ref().id() != context.id() {
+            return;
+        }
+        context.trace_dyn_gc_mut(target)
+    }
+}
+impl<'gc, Id: CollectorId> Copy for DynGc<'gc, Id> {}
+impl<'gc, Id: CollectorId> Clone for DynGc<'gc, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'gc, Id: CollectorId> Debug for DynGc<'gc, Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DynGc")
+            .field("type", &self.dyn_type().name())
+            .field("ptr", &self.ptr)
+            .finish()
+    }
+}
@@ -0,0 +1,219 @@
+//! Ephemerons, which are weak-keyed associations between GC objects.
+//!
+//! The value of an ephemeron is only traced if its key is otherwise reachable.
+//! This requires a fixpoint after marking the roots,
+//! because tracing one value may make the key of another ephemeron reachable.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use crate::context::layout::{GcHeader, GcMarkBits};
+use crate::context::CollectorState;
+use crate::utils::transmute_arbitrary;
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector, Gc};
+
+/// A table of ephemerons, type-erased so the collector can process it.
+pub(crate) trait EphemeronTable<Id: CollectorId> {
+    /// Trace the values of all entries whose keys have been marked,
+    /// returning `true` if any new values were traced.
+    ///
+    /// ## Safety
+    /// Must only be called during the marking phase of a collection.
+    unsafe fn trace_reachable(&self, context: &mut CollectContext<'_, Id>) -> bool;
+
+    /// Remove all entries whose keys are dead,
+    /// and update the keys of all entries which were moved.
+    ///
+    /// ## Safety
+    /// Must be called after marking has reached a fixpoint, but before sweeping.
+    unsafe fn sweep(&self, state: &CollectorState<Id>);
+
+    /// Remove all entries whose keys are about to be freed without a collection,
+    /// like the objects of a [discarded region](GarbageCollector::discard_region).
+    fn forget(&self, discarded: &HashSet<NonNull<GcHeader<Id>>>);
+}
+
+/// Check if an object has been marked by the current collection.
+///
+/// Objects outside of a region being evacuated by itself are always considered marked.
+#[inline]
+pub(super) unsafe fn is_marked<Id: CollectorId>(
+    header: NonNull<GcHeader<Id>>,
+    state: &CollectorState<Id>,
+) -> bool {
+    let state_bits = header.as_ref().state_bits.get();
+    state_bits.forwarded()
+        || state_bits.immortal()
+        || state_bits.raw_mark_bits().resolve(state) == GcMarkBits::Black
+        || state.outside_evacuated_region(header)
+}
+
+struct EphemeronEntry<V> {
+    value: V,
+    /// Whether the value has been traced in the current collection.
+    traced: bool,
+}
+
+struct WeakMapTable<K, V, Id: CollectorId> {
+    entries: RefCell<HashMap<NonNull<GcHeader<Id>>, EphemeronEntry<V>>>,
+    marker: PhantomData<fn() -> K>,
+}
+impl<K, V: Collect<Id>, Id: CollectorId> EphemeronTable<Id> for WeakMapTable<K, V, Id> {
+    unsafe fn trace_reachable(&self, context: &mut CollectContext<'_, Id>) -> bool {
+        let state = &context.garbage_collector.state;
+        let mut progress = false;
+        for (&key, entry) in self.entries.borrow_mut().iter_mut() {
+            if !entry.traced && is_marked(key, state) {
+                entry.traced = true;
+                progress = true;
+                if V::NEEDS_COLLECT {
+                    V::collect_inplace(NonNull::from(&mut entry.value), context);
+                }
+            }
+        }
+        progress
+    }
+
+    unsafe fn sweep(&self, state: &CollectorState<Id>) {
+        let mut entries = self.entries.borrow_mut();
+        let mut survivors = HashMap::with_capacity(entries.len());
+        for (key, mut entry) in entries.drain() {
+            if !entry.traced {
+                debug_assert!(!is_marked(key, state));
+                continue; // key is dead, drop the value
+            }
+            entry.traced = false;
+            let state_bits = key.as_ref().state_bits.get();
+            let new_key = if state_bits.forwarded() {
+                key.as_ref().forward_ptr()
+            } else {
+                key
+            };
+            survivors.insert(new_key, entry);
+        }
+        *entries = survivors;
+    }
+
+    fn forget(&self, discarded: &HashSet<NonNull<GcHeader<Id>>>) {
+        self.entries
+            .borrow_mut()
+            .retain(|key, _| !discarded.contains(key));
+    }
+}
+
+/// A map whose keys are held weakly, using ephemeron semantics.
+///
+/// An entry is removed once its key is no longer reachable,
+/// and the value only keeps objects alive while its key is alive.
+/// This avoids leaks in caches keyed by GC objects,
+/// even if the value refers back to its key.
+///
+/// Keys are compared by identity.
+/// Like a [`GcHandle`](crate::GcHandle), the map itself is not part of the GC heap
+/// and so can be held across collections.
+pub struct GcWeakMap<K: Collect<Id>, V: Collect<Id>, Id: CollectorId> {
+    table: Rc<WeakMapTable<K, V, Id>>,
+    id: Id,
+}
+impl<K: Collect<Id> + 'static, V: Collect<Id> + 'static, Id: CollectorId> GcWeakMap<K, V, Id> {
+    /// Create an empty map, registering it with the specified collector.
+    pub fn new(collector: &GarbageCollector<Id>) -> Self {
+        let table = Rc::new(WeakMapTable {
+            entries: RefCell::new(HashMap::new()),
+            marker: PhantomData,
+        });
+        collector.register_ephemerons(Rc::downgrade(&table) as _);
+        GcWeakMap {
+            table,
+            id: collector.id(),
+        }
+    }
+}
+impl<K: Collect<Id>, V: Collect<Id>, Id: CollectorId> GcWeakMap<K, V, Id> {
+    /// The number of entries in the map.
+    ///
+    /// This includes entries whose keys have died since the last collection.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.table.entries.borrow().len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    fn key_header<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+        key: Gc<'gc, K::Collected<'gc>, Id>,
+    ) -> NonNull<GcHeader<Id>> {
+        assert_eq!(self.id, collector.id());
+        assert_eq!(key.id(), self.id);
+        NonNull::from(key.header())
+    }
+
+    /// Insert an entry into the map, returning the previous value for the key.
+    pub fn insert<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+        key: Gc<'gc, K::Collected<'gc>, Id>,
+        value: V::Collected<'gc>,
+    ) -> Option<V::Collected<'gc>> {
+        let key = self.key_header(collector, key);
+        let entry = EphemeronEntry {
+            value: unsafe { transmute_arbitrary::<V::Collected<'gc>, V>(value) },
+            traced: false,
+        };
+        self.table
+            .entries
+            .borrow_mut()
+            .insert(key, entry)
+            .map(|old| unsafe { transmute_arbitrary(old.value) })
+    }
+
+    /// Lookup the value associated with the specified key.
+    pub fn get<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+        key: Gc<'gc, K::Collected<'gc>, Id>,
+    ) -> Option<V::Collected<'gc>>
+    where
+        V: Clone,
+    {
+        let key = self.key_header(collector, key);
+        self.table
+            .entries
+            .borrow()
+            .get(&key)
+            .map(|entry| unsafe { transmute_arbitrary(entry.value.clone()) })
+    }
+
+    #[inline]
+    pub fn contains_key<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+        key: Gc<'gc, K::Collected<'gc>, Id>,
+    ) -> bool {
+        let key = self.key_header(collector, key);
+        self.table.entries.borrow().contains_key(&key)
+    }
+
+    /// Remove the entry for the specified key, returning its value.
+    pub fn remove<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+        key: Gc<'gc, K::Collected<'gc>, Id>,
+    ) -> Option<V::Collected<'gc>> {
+        let key = self.key_header(collector, key);
+        self.table
+            .entries
+            .borrow_mut()
+            .remove(&key)
+            .map(|entry| unsafe { transmute_arbitrary(entry.value) })
+    }
+}
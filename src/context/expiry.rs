@@ -0,0 +1,170 @@
+//! References which expire once their target goes unused for too many collections.
+
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use crate::context::ephemeron::{is_marked, EphemeronTable};
+use crate::context::layout::GcHeader;
+use crate::context::{CollectorState, GcError};
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector, Gc};
+
+struct ExpiringCell<Id: CollectorId> {
+    /// The target object, or `None` if the reference has expired.
+    header: Cell<Option<NonNull<GcHeader<Id>>>>,
+    /// The number of collections survived since the target was last touched.
+    age: Cell<u32>,
+    max_age: u32,
+    /// Whether the target has been traced in the current collection.
+    traced: Cell<bool>,
+}
+impl<Id: CollectorId> EphemeronTable<Id> for ExpiringCell<Id> {
+    unsafe fn trace_reachable(&self, context: &mut CollectContext<'_, Id>) -> bool {
+        match self.header.get() {
+            Some(header) if !self.traced.get() && self.age.get() < self.max_age => {
+                self.traced.set(true);
+                self.header.set(Some(context.collect_any_gcheader(header)));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    unsafe fn sweep(&self, state: &CollectorState<Id>) {
+        if state.evacuating_region() {
+            // only a collection of the entire heap ages the reference
+            if !self.traced.replace(false) {
+                if let Some(header) = self.header.get() {
+                    if !is_marked(header, state) {
+                        self.header.set(None);
+                    } else if header.as_ref().state_bits.get().forwarded() {
+                        self.header.set(Some(header.as_ref().forward_ptr()));
+                    }
+                }
+            }
+        } else if self.traced.replace(false) {
+            self.age.set(self.age.get() + 1);
+        } else {
+            // too old to be traced, so clear the reference
+            self.header.set(None);
+        }
+    }
+
+    fn forget(&self, discarded: &HashSet<NonNull<GcHeader<Id>>>) {
+        // the target is held strongly until it expires
+        debug_assert!(
+            !(self.age.get() < self.max_age
+                && self
+                    .header
+                    .get()
+                    .is_some_and(|header| discarded.contains(&header))),
+            "Cannot free the target of an unexpired reference"
+        );
+    }
+}
+
+/// A reference which keeps its target alive for a limited number of collections.
+///
+/// Each collection ages the reference,
+/// and [touching](GcExpiring::touch) it resets the age to zero.
+/// Once the target has survived `max_age` collections without being touched,
+/// the reference is cleared.
+/// If there are no other references to the target, it will then be collected.
+///
+/// This enables caches whose eviction is driven purely by GC activity.
+pub struct GcExpiring<T: Collect<Id>, Id: CollectorId> {
+    cell: Rc<ExpiringCell<Id>>,
+    id: Id,
+    marker: PhantomData<T>,
+}
+impl<T: Collect<Id>, Id: CollectorId> GcExpiring<T, Id> {
+    /// The number of collections the target has survived since it was last touched.
+    #[inline]
+    pub fn age(&self) -> u32 {
+        self.cell.age.get()
+    }
+
+    /// The number of untouched collections the target survives before the reference expires.
+    #[inline]
+    pub fn max_age(&self) -> u32 {
+        self.cell.max_age
+    }
+
+    /// Check if the reference has been cleared.
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        self.cell.header.get().is_none()
+    }
+
+    /// Reset the age of the reference, extending the life of its target.
+    ///
+    /// This has no effect if the reference has already expired.
+    #[inline]
+    pub fn touch(&self) {
+        self.cell.age.set(0);
+    }
+
+    /// Resolve the reference without touching it,
+    /// returning `None` if it has expired.
+    #[inline]
+    pub fn peek<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> Option<Gc<'gc, T::Collected<'gc>, Id>> {
+        assert_eq!(self.id, collector.id());
+        self.cell
+            .header
+            .get()
+            .map(|header| unsafe { Gc::from_raw_ptr(header.as_ref().regular_value_ptr().cast()) })
+    }
+
+    /// Resolve the reference and [touch](Self::touch) it,
+    /// returning an error if it has expired or belongs to a different collector.
+    #[inline]
+    pub fn try_get<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> Result<Gc<'gc, T::Collected<'gc>, Id>, GcError> {
+        if self.id != collector.id() {
+            return Err(collector.report_error(GcError::WrongCollector));
+        }
+        self.get(collector)
+            .ok_or_else(|| collector.report_error(GcError::InvalidHandle))
+    }
+
+    /// Resolve the reference and [touch](Self::touch) it,
+    /// returning `None` if it has expired.
+    #[inline]
+    pub fn get<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> Option<Gc<'gc, T::Collected<'gc>, Id>> {
+        self.touch();
+        self.peek(collector)
+    }
+}
+
+impl<T: Collect<Id>, Id: CollectorId> GcExpiring<T, Id> {
+    /// Create a reference to the object with the specified header,
+    /// registering it with the collector.
+    pub(crate) fn new(
+        collector: &GarbageCollector<Id>,
+        header: NonNull<GcHeader<Id>>,
+        max_age: u32,
+    ) -> Self {
+        let cell = Rc::new(ExpiringCell {
+            header: Cell::new(Some(header)),
+            age: Cell::new(0),
+            max_age,
+            traced: Cell::new(false),
+        });
+        collector.register_ephemerons(Rc::downgrade(&cell) as _);
+        GcExpiring {
+            cell,
+            id: collector.id(),
+            marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Injecting allocation failures, to test how programs handle running out of memory.
+//!
+//! Only available with the `fault-injection` feature.
+
+use std::cell::Cell;
+
+use crate::context::young::YoungAllocError;
+
+/// The stage of allocation where an injected failure occurs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AllocFailStage {
+    /// The young generation rejects the object as too large,
+    /// so it is spilled into the old generation instead.
+    ///
+    /// This does not fail the allocation by itself.
+    YoungSpill,
+    /// The young generation is out of memory,
+    /// failing the allocation with [`GcError::AllocFailure`](crate::GcError::AllocFailure).
+    YoungGeneration,
+    /// The old generation is out of memory,
+    /// failing the allocation with [`GcError::AllocFailure`](crate::GcError::AllocFailure).
+    ///
+    /// This only affects objects allocated in the old generation,
+    /// like pinned objects, objects spilled from the young generation,
+    /// and objects whose type has a [placement hint](crate::PlacementHint::Old).
+    OldGeneration,
+    /// Copying a live object during a collection runs out of memory.
+    ///
+    /// The object is left in place as if it were pinned, so the collection still succeeds.
+    /// When evacuating a [scoped region](crate::GarbageCollector::scoped_young),
+    /// this is instead a fatal allocation error.
+    Evacuation,
+    /// Memory is reserved successfully, but initializing the object panics.
+    ///
+    /// The reserved memory is reclaimed while unwinding,
+    /// just as if the initialization closure had panicked.
+    Initialization,
+}
+
+/// Fails a number of allocations at a chosen stage.
+pub(crate) struct FaultInjector {
+    stage: Cell<Option<AllocFailStage>>,
+    remaining: Cell<usize>,
+}
+impl FaultInjector {
+    pub fn new() -> Self {
+        FaultInjector {
+            stage: Cell::new(None),
+            remaining: Cell::new(0),
+        }
+    }
+
+    pub fn inject(&self, stage: AllocFailStage, count: usize) {
+        self.stage.set(Some(stage));
+        self.remaining.set(count);
+    }
+
+    pub fn clear(&self) {
+        self.stage.set(None);
+        self.remaining.set(0);
+    }
+
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.remaining.get()
+    }
+
+    /// Check if an allocation reaching the specified stage should fail,
+    /// consuming one of the remaining failures.
+    #[inline]
+    pub fn should_fail(&self, stage: AllocFailStage) -> bool {
+        if self.stage.get() != Some(stage) || self.remaining.get() == 0 {
+            return false;
+        }
+        let remaining = self.remaining.get() - 1;
+        self.remaining.set(remaining);
+        if remaining == 0 {
+            self.stage.set(None);
+        }
+        true
+    }
+
+    /// The error to return from the young generation, if a failure is injected there.
+    #[inline]
+    pub fn young_failure(&self) -> Option<YoungAllocError> {
+        if self.should_fail(AllocFailStage::YoungSpill) {
+            Some(YoungAllocError::SizeExceedsLimit)
+        } else if self.should_fail(AllocFailStage::YoungGeneration) {
+            Some(YoungAllocError::OutOfMemory)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Finalizers, which run after an object has been collected.
+//!
+//! Finalizers never run in the middle of a collection,
+//! where the heap is inconsistent and allocation is forbidden.
+//! Instead, they are queued once their object dies,
+//! and run by [`GarbageCollector::run_pending_finalizers`](crate::GarbageCollector::run_pending_finalizers).
+
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+use std::ptr::NonNull;
+
+use crate::context::ephemeron::is_marked;
+use crate::context::layout::GcHeader;
+use crate::context::CollectorState;
+use crate::{CollectorId, GarbageCollector};
+
+pub(crate) type Finalizer<Id> = Box<dyn FnOnce(&GarbageCollector<Id>)>;
+
+/// A finalizer whose object is still alive.
+struct Registered<Id: CollectorId> {
+    header: NonNull<GcHeader<Id>>,
+    finalizer: Finalizer<Id>,
+}
+
+pub(crate) struct Finalizers<Id: CollectorId> {
+    /// Finalizers whose objects were alive at the last collection.
+    registered: RefCell<Vec<Registered<Id>>>,
+    /// Finalizers whose objects have died, in the order they were found.
+    pending: RefCell<VecDeque<Finalizer<Id>>>,
+}
+impl<Id: CollectorId> Finalizers<Id> {
+    pub fn new() -> Self {
+        Finalizers {
+            registered: RefCell::new(Vec::new()),
+            pending: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    #[inline]
+    pub fn register(&self, header: NonNull<GcHeader<Id>>, finalizer: Finalizer<Id>) {
+        self.registered
+            .borrow_mut()
+            .push(Registered { header, finalizer });
+    }
+
+    /// Queue a finalizer to run immediately, as if its object had already died.
+    #[inline]
+    pub fn push_pending(&self, finalizer: Finalizer<Id>) {
+        self.pending.borrow_mut().push_back(finalizer);
+    }
+
+    #[inline]
+    pub fn pending(&self) -> usize {
+        self.pending.borrow().len()
+    }
+
+    /// Take the next pending finalizer,
+    /// releasing the queue so the finalizer is free to register more.
+    #[inline]
+    pub fn pop_pending(&self) -> Option<Finalizer<Id>> {
+        self.pending.borrow_mut().pop_front()
+    }
+
+    /// Follow objects which have been evacuated,
+    /// and queue the finalizers of those which have died.
+    ///
+    /// ## Safety
+    /// Must be called after marking has finished and before sweeping.
+    pub unsafe fn process_marked(&self, state: &CollectorState<Id>) {
+        let mut registered = self.registered.borrow_mut();
+        if registered.is_empty() {
+            return;
+        }
+        let mut pending = self.pending.borrow_mut();
+        let mut survivors = Vec::with_capacity(registered.len());
+        for mut entry in registered.drain(..) {
+            if !is_marked(entry.header, state) {
+                pending.push_back(entry.finalizer);
+                continue;
+            }
+            if entry.header.as_ref().state_bits.get().forwarded() {
+                entry.header = entry.header.as_ref().forward_ptr();
+            }
+            survivors.push(entry);
+        }
+        *registered = survivors;
+    }
+
+    /// Queue the finalizers of objects which are about to be freed without a collection.
+    pub fn forget(&self, discarded: &HashSet<NonNull<GcHeader<Id>>>) {
+        let mut registered = self.registered.borrow_mut();
+        let mut pending = self.pending.borrow_mut();
+        let mut survivors = Vec::with_capacity(registered.len());
+        for entry in registered.drain(..) {
+            if discarded.contains(&entry.header) {
+                pending.push_back(entry.finalizer);
+            } else {
+                survivors.push(entry);
+            }
+        }
+        *registered = survivors;
+    }
+}
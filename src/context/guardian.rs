@@ -0,0 +1,169 @@
+//! Guardians, which resurrect unreachable objects for post-mortem cleanup.
+//!
+//! This follows the guardians of Scheme:
+//! objects registered with a guardian are not freed once they become unreachable.
+//! Instead, they are resurrected into the guardian's queue,
+//! where the host can retrieve them and dispose of them explicitly.
+
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use crate::context::ephemeron::is_marked;
+use crate::context::layout::GcHeader;
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector, Gc};
+
+/// The objects of a guardian, type-erased so the collector can process it.
+pub(crate) trait GuardianTable<Id: CollectorId> {
+    /// Trace the resurrected objects which have not yet been retrieved,
+    /// which are strongly held by the guardian.
+    ///
+    /// ## Safety
+    /// Must only be called while marking the roots of a collection.
+    unsafe fn trace_queue(&self, context: &mut CollectContext<'_, Id>);
+
+    /// Resurrect all registered objects which have not been marked,
+    /// moving them into the queue and returning `true` if there were any.
+    ///
+    /// Registered objects which were marked are updated to follow evacuation.
+    ///
+    /// ## Safety
+    /// Must be called after marking has reached a fixpoint, but before sweeping.
+    unsafe fn resurrect(&self, context: &mut CollectContext<'_, Id>) -> bool;
+
+    /// Unregister all objects which are about to be freed without a collection,
+    /// like the objects of a [discarded region](GarbageCollector::discard_region).
+    ///
+    /// Queued objects are held strongly, so they must never be freed this way.
+    fn forget(&self, discarded: &HashSet<NonNull<GcHeader<Id>>>);
+}
+
+struct GuardianQueue<Id: CollectorId> {
+    /// Registered objects, which are held weakly.
+    registered: RefCell<Vec<NonNull<GcHeader<Id>>>>,
+    /// Resurrected objects waiting to be retrieved, in the order they were found dead.
+    queue: RefCell<VecDeque<NonNull<GcHeader<Id>>>>,
+}
+impl<Id: CollectorId> GuardianTable<Id> for GuardianQueue<Id> {
+    unsafe fn trace_queue(&self, context: &mut CollectContext<'_, Id>) {
+        for header in self.queue.borrow_mut().iter_mut() {
+            *header = context.collect_any_gcheader(*header);
+        }
+    }
+
+    unsafe fn resurrect(&self, context: &mut CollectContext<'_, Id>) -> bool {
+        let mut registered = self.registered.borrow_mut();
+        let mut queue = self.queue.borrow_mut();
+        let state = &context.garbage_collector.state;
+        // decide which objects are dead before resurrecting any of them,
+        // so objects reachable from another dead object are also queued
+        let (live, dead): (Vec<_>, Vec<_>) = registered
+            .drain(..)
+            .partition(|&header| is_marked(header, state));
+        for header in live {
+            registered.push(if header.as_ref().state_bits.get().forwarded() {
+                header.as_ref().forward_ptr()
+            } else {
+                header
+            });
+        }
+        let resurrected = !dead.is_empty();
+        for header in dead {
+            queue.push_back(context.collect_any_gcheader(header));
+        }
+        resurrected
+    }
+
+    fn forget(&self, discarded: &HashSet<NonNull<GcHeader<Id>>>) {
+        debug_assert!(
+            self.queue
+                .borrow()
+                .iter()
+                .all(|header| !discarded.contains(header)),
+            "Cannot free an object queued by a guardian"
+        );
+        self.registered
+            .borrow_mut()
+            .retain(|header| !discarded.contains(header));
+    }
+}
+
+/// A queue of objects which are resurrected once they become unreachable.
+///
+/// Objects [registered](Self::register) with the guardian are held weakly.
+/// Once a collection finds one of them unreachable,
+/// it is kept alive and moved into the guardian's queue instead of being freed.
+/// The host can then [retrieve](Self::pop) it to perform cleanup,
+/// in whatever order and at whatever time it chooses.
+/// An object is only resurrected once per registration,
+/// so it is freed normally once it becomes unreachable again.
+///
+/// Unlike [finalizers](GarbageCollector::add_finalizer),
+/// this gives access to the dead object itself.
+/// Like a [`GcHandle`](crate::GcHandle), the guardian itself is not part of the GC heap
+/// and so can be held across collections.
+/// Dropping the guardian forgets all of its objects, including those in the queue.
+pub struct Guardian<T: Collect<Id>, Id: CollectorId> {
+    table: Rc<GuardianQueue<Id>>,
+    id: Id,
+    marker: PhantomData<fn() -> T>,
+}
+impl<T: Collect<Id> + 'static, Id: CollectorId> Guardian<T, Id> {
+    /// Create an empty guardian, registering it with the specified collector.
+    pub fn new(collector: &GarbageCollector<Id>) -> Self {
+        let table = Rc::new(GuardianQueue {
+            registered: RefCell::new(Vec::new()),
+            queue: RefCell::new(VecDeque::new()),
+        });
+        collector.register_guardian(Rc::downgrade(&table) as _);
+        Guardian {
+            table,
+            id: collector.id(),
+            marker: PhantomData,
+        }
+    }
+}
+impl<T: Collect<Id>, Id: CollectorId> Guardian<T, Id> {
+    /// Register an object to be resurrected into the queue once it becomes unreachable.
+    ///
+    /// Registering the same object multiple times queues it multiple times.
+    pub fn register<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+        value: Gc<'gc, T::Collected<'gc>, Id>,
+    ) {
+        assert_eq!(self.id, collector.id());
+        assert_eq!(value.id(), self.id);
+        self.table
+            .registered
+            .borrow_mut()
+            .push(NonNull::from(value.header()));
+    }
+
+    /// Retrieve the next resurrected object, in the order they were found unreachable.
+    ///
+    /// The object is no longer held by the guardian,
+    /// so it will be freed once it becomes unreachable again.
+    pub fn pop<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> Option<Gc<'gc, T::Collected<'gc>, Id>> {
+        assert_eq!(self.id, collector.id());
+        let header = self.table.queue.borrow_mut().pop_front()?;
+        Some(unsafe { Gc::from_raw_ptr(header.as_ref().regular_value_ptr().cast()) })
+    }
+
+    /// The number of resurrected objects waiting to be [retrieved](Self::pop).
+    #[inline]
+    pub fn pending(&self) -> usize {
+        self.table.queue.borrow().len()
+    }
+
+    /// The number of registered objects which have not yet been found unreachable.
+    #[inline]
+    pub fn registered(&self) -> usize {
+        self.table.registered.borrow().len()
+    }
+}
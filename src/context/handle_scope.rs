@@ -0,0 +1,194 @@
+//! Handle scopes, which root many objects at once (like V8's `HandleScope`).
+
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use crate::context::layout::GcHeader;
+use crate::context::roots::RootState;
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector, Gc, GcHandle};
+
+/// The root slots owned by a [`HandleScope`].
+pub(crate) struct ScopeSlots<Id: CollectorId> {
+    headers: RefCell<Vec<NonNull<GcHeader<Id>>>>,
+    /// Changed when the collector is dropped or poisoned,
+    /// since the scope can outlive it.
+    state: Cell<RootState>,
+}
+impl<Id: CollectorId> ScopeSlots<Id> {
+    /// Prevent the handles of the scope from being resolved again.
+    #[inline]
+    pub(crate) fn invalidate(&self, state: RootState) {
+        self.state.set(state);
+    }
+
+    /// Trace every slot, updating them in place.
+    ///
+    /// ## Safety
+    /// Must only be called during the marking phase of a collection.
+    pub(crate) unsafe fn trace(&self, context: &mut CollectContext<'_, Id>) {
+        for header in self.headers.borrow_mut().iter_mut() {
+            *header = context.collect_any_gcheader(*header);
+        }
+    }
+
+    /// The objects in every slot.
+    pub(crate) fn headers(&self) -> Vec<NonNull<GcHeader<Id>>> {
+        self.headers.borrow().clone()
+    }
+
+    #[inline]
+    fn push(&self, header: NonNull<GcHeader<Id>>) -> usize {
+        let mut headers = self.headers.borrow_mut();
+        headers.push(header);
+        headers.len() - 1
+    }
+}
+
+/// A scope which roots many temporary objects at once.
+///
+/// Creating a [`GcHandle`] allocates a separate root for each handle.
+/// Handles created by a scope instead share a single growable list of slots,
+/// which is released all at once when the scope is dropped.
+///
+/// Scopes can be [nested](HandleScope::nested),
+/// and a single result can be [escaped](HandleScope::escape) into the parent scope.
+pub struct HandleScope<'parent, Id: CollectorId> {
+    slots: Rc<ScopeSlots<Id>>,
+    parent: Option<&'parent HandleScope<'parent, Id>>,
+    id: Id,
+}
+impl<'parent, Id: CollectorId> HandleScope<'parent, Id> {
+    pub(crate) fn new(
+        collector: &GarbageCollector<Id>,
+        parent: Option<&'parent HandleScope<'parent, Id>>,
+    ) -> Self {
+        let slots = Rc::new(ScopeSlots {
+            headers: RefCell::new(Vec::new()),
+            state: Cell::new(RootState::Alive),
+        });
+        collector.register_handle_scope(Rc::downgrade(&slots));
+        HandleScope {
+            slots,
+            parent,
+            id: collector.id(),
+        }
+    }
+
+    /// Create a child scope, whose handles are released before this scope's.
+    #[inline]
+    pub fn nested(&self, collector: &GarbageCollector<Id>) -> HandleScope<'_, Id> {
+        assert_eq!(self.id, collector.id());
+        HandleScope::new(collector, Some(self))
+    }
+
+    /// Root the specified object until this scope is dropped.
+    #[inline]
+    pub fn handle<'gc, T: Collect<Id>>(
+        &self,
+        val: Gc<'gc, T, Id>,
+    ) -> ScopedHandle<'_, T::Collected<'static>, Id> {
+        assert_eq!(self.id, val.id());
+        ScopedHandle {
+            slots: &self.slots,
+            index: self.slots.push(NonNull::from(val.header())),
+            id: self.id,
+            marker: PhantomData,
+        }
+    }
+
+    /// Root the target of a handle in the parent scope,
+    /// so it remains valid after this scope is dropped.
+    ///
+    /// ## Panics
+    /// If this scope has no parent.
+    /// Use [`ScopedHandle::to_handle`] to escape from a top-level scope.
+    pub fn escape<T: Collect<Id>>(
+        &self,
+        handle: ScopedHandle<'_, T, Id>,
+    ) -> ScopedHandle<'parent, T, Id> {
+        let parent = self.parent.expect("Cannot escape from a top-level scope");
+        let header = handle.header();
+        ScopedHandle {
+            slots: &parent.slots,
+            index: parent.slots.push(header),
+            id: handle.id,
+            marker: PhantomData,
+        }
+    }
+
+    /// The number of handles rooted by this scope.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.slots.headers.borrow().len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A handle created by a [`HandleScope`],
+/// which is valid for as long as the scope.
+///
+/// A scope can outlive its collector,
+/// so using the handle after the collector is dropped or
+/// [poisoned](GarbageCollector::is_poisoned) will panic.
+pub struct ScopedHandle<'scope, T: Collect<Id>, Id: CollectorId> {
+    slots: &'scope ScopeSlots<Id>,
+    index: usize,
+    id: Id,
+    marker: PhantomData<T>,
+}
+impl<'scope, T: Collect<Id>, Id: CollectorId> ScopedHandle<'scope, T, Id> {
+    #[inline]
+    fn header(&self) -> NonNull<GcHeader<Id>> {
+        self.slots.headers.borrow()[self.index]
+    }
+
+    /// Check that the handle can be used with the specified collector,
+    /// before its header is accessed.
+    #[inline]
+    #[track_caller]
+    fn checked_header(&self, collector: &GarbageCollector<Id>) -> NonNull<GcHeader<Id>> {
+        self.slots.state.get().assert_usable();
+        assert_eq!(self.id, collector.id());
+        self.header()
+    }
+
+    /// Resolve this handle into a [`Gc`] smart-pointer.
+    ///
+    /// ## Panics
+    /// If the handle belongs to a different collector,
+    /// or its collector has been dropped or poisoned.
+    #[inline]
+    #[track_caller]
+    pub fn resolve<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> Gc<'gc, T::Collected<'gc>, Id> {
+        let header = self.checked_header(collector);
+        unsafe { Gc::from_raw_ptr(header.as_ref().regular_value_ptr().cast()) }
+    }
+
+    /// Promote this into a regular [`GcHandle`],
+    /// which is independent of the scope.
+    ///
+    /// ## Panics
+    /// Under the same conditions as [`Self::resolve`].
+    #[inline]
+    #[track_caller]
+    pub fn to_handle(&self, collector: &GarbageCollector<Id>) -> GcHandle<T, Id> {
+        let header = self.checked_header(collector);
+        unsafe { collector.root_raw(header) }
+    }
+}
+impl<T: Collect<Id>, Id: CollectorId> Copy for ScopedHandle<'_, T, Id> {}
+impl<T: Collect<Id>, Id: CollectorId> Clone for ScopedHandle<'_, T, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
@@ -0,0 +1,77 @@
+//! Stable identity hashes, which survive objects being moved.
+//!
+//! Hashes are assigned lazily and stored in a side table keyed by header,
+//! which is updated after marking to follow evacuated objects.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::ptr::NonNull;
+
+use crate::context::ephemeron::is_marked;
+use crate::context::layout::GcHeader;
+use crate::context::CollectorState;
+use crate::CollectorId;
+
+pub(crate) struct IdentityHashes<Id: CollectorId> {
+    hashes: RefCell<HashMap<NonNull<GcHeader<Id>>, u64>>,
+    /// The number of hashes assigned so far.
+    ///
+    /// Hashes are derived from this counter,
+    /// so they are unique and do not depend on the address of the object.
+    counter: Cell<u64>,
+}
+impl<Id: CollectorId> IdentityHashes<Id> {
+    pub fn new() -> Self {
+        IdentityHashes {
+            hashes: RefCell::new(HashMap::new()),
+            counter: Cell::new(0),
+        }
+    }
+
+    /// Get the identity hash of the specified object, assigning one if needed.
+    pub fn get_or_assign(&self, header: NonNull<GcHeader<Id>>) -> u64 {
+        *self.hashes.borrow_mut().entry(header).or_insert_with(|| {
+            let count = self.counter.get().wrapping_add(1);
+            self.counter.set(count);
+            mix(count)
+        })
+    }
+
+    /// Follow objects which have been evacuated, and forget those which have died.
+    ///
+    /// ## Safety
+    /// Must be called after marking has finished and before sweeping.
+    pub unsafe fn process_marked(&self, state: &CollectorState<Id>) {
+        let mut hashes = self.hashes.borrow_mut();
+        if hashes.is_empty() {
+            return;
+        }
+        let mut survivors = HashMap::with_capacity(hashes.len());
+        for (header, hash) in hashes.drain() {
+            if !is_marked(header, state) {
+                continue; // dead
+            } else if header.as_ref().state_bits.get().forwarded() {
+                survivors.insert(header.as_ref().forward_ptr(), hash);
+            } else {
+                survivors.insert(header, hash);
+            }
+        }
+        *hashes = survivors;
+    }
+
+    /// Forget the hashes of objects which are about to be freed without a collection.
+    pub fn forget(&self, discarded: &HashSet<NonNull<GcHeader<Id>>>) {
+        self.hashes
+            .borrow_mut()
+            .retain(|header, _| !discarded.contains(header));
+    }
+}
+
+/// The finalizer of the SplitMix64 generator,
+/// spreading consecutive counters across all bits.
+#[inline]
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
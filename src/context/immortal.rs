@@ -0,0 +1,116 @@
+//! A space for immortal objects, which are never collected.
+
+use std::cell::{Cell, UnsafeCell};
+use std::ptr::NonNull;
+
+use bumpalo::Bump;
+
+use crate::context::layout::{AllocInfo, GcHeader};
+use crate::context::{CollectContext, GenerationId};
+use crate::CollectorId;
+
+/// An arena of objects which live until the collector is destroyed.
+///
+/// Immortal objects are implicitly black, so they are never marked or swept.
+/// However, they may still reference mortal objects,
+/// so any immortal objects which need tracing are treated as roots.
+pub struct ImmortalSpace<Id: CollectorId> {
+    arena: Bump,
+    /// Every immortal object.
+    objects: UnsafeCell<Vec<NonNull<GcHeader<Id>>>>,
+    /// Immortal objects whose children need to be traced on every collection.
+    traced_objects: UnsafeCell<Vec<NonNull<GcHeader<Id>>>>,
+    /// Immortal objects which need to be dropped when the space is destroyed.
+    drop_objects: UnsafeCell<Vec<NonNull<GcHeader<Id>>>>,
+    allocated_bytes: Cell<usize>,
+    collector_id: Id,
+}
+impl<Id: CollectorId> ImmortalSpace<Id> {
+    pub fn new(id: Id) -> Self {
+        ImmortalSpace {
+            arena: Bump::new(),
+            objects: UnsafeCell::new(Vec::new()),
+            traced_objects: UnsafeCell::new(Vec::new()),
+            drop_objects: UnsafeCell::new(Vec::new()),
+            allocated_bytes: Cell::new(0),
+            collector_id: id,
+        }
+    }
+
+    #[inline]
+    pub unsafe fn alloc_raw<T: super::RawAllocTarget<Id>>(
+        &self,
+        target: &T,
+    ) -> Result<NonNull<T::Header>, ImmortalAllocError> {
+        let overall_layout = target.overall_layout();
+        let Ok(raw_ptr) = self.arena.try_alloc_layout(overall_layout) else {
+            return Err(ImmortalAllocError::OutOfMemory);
+        };
+        self.allocated_bytes
+            .set(self.allocated_bytes.get() + overall_layout.size());
+        let header_ptr = raw_ptr.add(target.header_offset()).cast::<T::Header>();
+        target.init_header(
+            header_ptr,
+            GcHeader::new(
+                target
+                    .init_state_bits(GenerationId::Old)
+                    .with_immortal(true)
+                    .with_pinned(true),
+                AllocInfo {
+                    live_object_index: u32::MAX,
+                },
+                target.header_metadata(),
+                self.collector_id,
+            ),
+        );
+        let header = header_ptr.cast::<GcHeader<Id>>();
+        (*self.objects.get()).push(header);
+        if header.as_ref().type_info().trace_func.is_some() {
+            (*self.traced_objects.get()).push(header);
+        }
+        if target.needs_drop() {
+            (*self.drop_objects.get()).push(header);
+        }
+        Ok(header_ptr)
+    }
+
+    /// Trace the children of all immortal objects, as if they were roots.
+    pub unsafe fn trace_roots(&self, context: &mut CollectContext<'_, Id>) {
+        for &header in (*self.traced_objects.get()).iter() {
+            // failed initialization leaves objects in the list, but they must not be traced
+            if !header.as_ref().state_bits.get().value_initialized() {
+                continue;
+            }
+            let trace_func = header.as_ref().type_info().trace_func;
+            context.trace_children(header, trace_func.unwrap_unchecked());
+        }
+    }
+
+    #[inline]
+    #[cfg(any(feature = "classify-ptr", debug_assertions))]
+    pub unsafe fn objects(&self) -> impl Iterator<Item = NonNull<GcHeader<Id>>> + '_ {
+        (*self.objects.get()).iter().copied()
+    }
+
+    #[inline]
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocated_bytes.get()
+    }
+}
+impl<Id: CollectorId> Drop for ImmortalSpace<Id> {
+    fn drop(&mut self) {
+        for header in self.drop_objects.get_mut().iter() {
+            unsafe {
+                if header.as_ref().state_bits.get().value_initialized() {
+                    header.as_ref().invoke_destructor();
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImmortalAllocError {
+    #[error("Out of memory (immortal)")]
+    OutOfMemory,
+}
@@ -0,0 +1,221 @@
+//! Interned strings, which are stored in the GC heap.
+//!
+//! The symbol table holds its strings weakly,
+//! so unused symbols are reclaimed by the next collection.
+
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use crate::array::GcArray;
+use crate::context::ephemeron::{is_marked, EphemeronTable};
+use crate::context::layout::{GcArrayHeader, GcHeader};
+use crate::context::CollectorState;
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector};
+
+struct SymbolTable<Id: CollectorId> {
+    /// The headers of all interned strings, grouped by the hash of their contents.
+    buckets: RefCell<HashMap<u64, Vec<NonNull<GcHeader<Id>>>>>,
+    hasher: RandomState,
+}
+impl<Id: CollectorId> SymbolTable<Id> {
+    #[inline]
+    fn hash(&self, s: &str) -> u64 {
+        self.hasher.hash_one(s)
+    }
+
+    #[inline]
+    unsafe fn contents<'a>(header: NonNull<GcHeader<Id>>) -> &'a str {
+        let header = header.as_ref().assume_array_header();
+        std::str::from_utf8_unchecked(std::slice::from_raw_parts(
+            header.array_value_ptr().as_ptr(),
+            header.len(),
+        ))
+    }
+
+    fn lookup(&self, hash: u64, s: &str) -> Option<NonNull<GcHeader<Id>>> {
+        self.buckets.borrow().get(&hash).and_then(|bucket| {
+            bucket
+                .iter()
+                .copied()
+                .find(|&header| unsafe { Self::contents(header) } == s)
+        })
+    }
+}
+impl<Id: CollectorId> EphemeronTable<Id> for SymbolTable<Id> {
+    #[inline]
+    unsafe fn trace_reachable(&self, _context: &mut CollectContext<'_, Id>) -> bool {
+        false // symbols are never kept alive by the table
+    }
+
+    unsafe fn sweep(&self, state: &CollectorState<Id>) {
+        self.buckets.borrow_mut().retain(|_hash, bucket| {
+            bucket.retain_mut(|header| {
+                if !is_marked(*header, state) {
+                    return false;
+                }
+                if header.as_ref().state_bits.get().forwarded() {
+                    *header = header.as_ref().forward_ptr();
+                }
+                true
+            });
+            !bucket.is_empty()
+        });
+    }
+
+    fn forget(&self, discarded: &HashSet<NonNull<GcHeader<Id>>>) {
+        self.buckets.borrow_mut().retain(|_hash, bucket| {
+            bucket.retain(|header| !discarded.contains(header));
+            !bucket.is_empty()
+        });
+    }
+}
+
+/// A table of interned strings, which deduplicates strings on insertion.
+///
+/// Strings are held weakly,
+/// so a [`Symbol`] is reclaimed once it is no longer reachable.
+/// Interning the same string again afterwards gives a fresh symbol.
+pub struct GcInterner<Id: CollectorId> {
+    table: Rc<SymbolTable<Id>>,
+    id: Id,
+}
+impl<Id: CollectorId> GcInterner<Id> {
+    /// Create an empty interner, registering it with the specified collector.
+    pub fn new(collector: &GarbageCollector<Id>) -> Self {
+        let table = Rc::new(SymbolTable {
+            buckets: RefCell::new(HashMap::new()),
+            hasher: RandomState::new(),
+        });
+        collector.register_ephemerons(Rc::downgrade(&table) as _);
+        GcInterner {
+            table,
+            id: collector.id(),
+        }
+    }
+
+    /// The number of interned strings.
+    ///
+    /// This includes unused symbols which have not yet been collected.
+    pub fn len(&self) -> usize {
+        self.table.buckets.borrow().values().map(Vec::len).sum()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.table.buckets.borrow().is_empty()
+    }
+
+    /// Intern the specified string,
+    /// reusing the existing symbol if it has already been interned.
+    #[track_caller]
+    pub fn intern<'gc>(&self, collector: &'gc GarbageCollector<Id>, s: &str) -> Symbol<'gc, Id> {
+        assert_eq!(self.id, collector.id());
+        let hash = self.table.hash(s);
+        if let Some(header) = self.table.lookup(hash, s) {
+            return unsafe { Symbol::from_header(header) };
+        }
+        let array = collector.alloc_array_copied(s.as_bytes());
+        self.table
+            .buckets
+            .borrow_mut()
+            .entry(hash)
+            .or_default()
+            .push(NonNull::from(array.header().main_header()));
+        Symbol { array }
+    }
+
+    /// Lookup the symbol for the specified string,
+    /// returning `None` if it has not been interned.
+    pub fn get<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+        s: &str,
+    ) -> Option<Symbol<'gc, Id>> {
+        assert_eq!(self.id, collector.id());
+        self.table
+            .lookup(self.table.hash(s), s)
+            .map(|header| unsafe { Symbol::from_header(header) })
+    }
+}
+
+/// An interned string, created by [`GcInterner::intern`].
+///
+/// Because symbols are deduplicated,
+/// equality is a cheap pointer comparison.
+pub struct Symbol<'gc, Id: CollectorId> {
+    array: GcArray<'gc, u8, Id>,
+}
+impl<'gc, Id: CollectorId> Symbol<'gc, Id> {
+    #[inline]
+    unsafe fn from_header(header: NonNull<GcHeader<Id>>) -> Self {
+        let header = header.cast::<GcArrayHeader<Id>>();
+        Symbol {
+            array: GcArray::from_raw_ptr(header.as_ref().array_value_ptr()),
+        }
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &'gc str {
+        unsafe { std::str::from_utf8_unchecked(self.array.as_slice()) }
+    }
+
+    /// The underlying array of UTF-8 bytes.
+    #[inline]
+    pub fn as_array(&self) -> GcArray<'gc, u8, Id> {
+        self.array
+    }
+}
+unsafe impl<'gc, Id: CollectorId> Collect<Id> for Symbol<'gc, Id> {
+    type Collected<'newgc> = Symbol<'newgc, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        GcArray::collect_inplace(NonNull::from(&(*target.as_ptr()).array), context);
+    }
+}
+impl<'gc, Id: CollectorId> Deref for Symbol<'gc, Id> {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+impl<'gc, Id: CollectorId> PartialEq for Symbol<'gc, Id> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.array.as_ptr() == other.array.as_ptr()
+    }
+}
+impl<'gc, Id: CollectorId> Eq for Symbol<'gc, Id> {}
+impl<'gc, Id: CollectorId> Hash for Symbol<'gc, Id> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // hash contents, because the address changes if the symbol moves
+        self.as_str().hash(state)
+    }
+}
+impl<'gc, Id: CollectorId> Copy for Symbol<'gc, Id> {}
+impl<'gc, Id: CollectorId> Clone for Symbol<'gc, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'gc, Id: CollectorId> Debug for Symbol<'gc, Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Symbol").field(&self.as_str()).finish()
+    }
+}
+impl<'gc, Id: CollectorId> Display for Symbol<'gc, Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
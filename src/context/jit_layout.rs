@@ -0,0 +1,208 @@
+//! The layout of object headers, for JIT compilers which inline header checks.
+//!
+//! Generated code may read the header directly,
+//! for example to check mark bits in a write barrier
+//! or to follow a forwarding pointer.
+//! This module is a semi-stable contract for that:
+//! the values may change between releases,
+//! but they are checked against the real header at compile time,
+//! so they never silently disagree with the collector.
+//!
+//! All headers are [`HEADER_ALIGNMENT`] aligned.
+//! The state bits are a native-endian `u32` at [`HeaderLayout::STATE_BITS_OFFSET`],
+//! which should only be tested against the masks below.
+//! With the `compact-header` feature, the bits above [`STATE_BITS_USED`]
+//! hold a type index, which is not part of this contract.
+//!
+//! Allocation can also be inlined, using the [`AllocationCursor`]
+//! of the young generation together with a [`JitAllocTemplate`] for each type.
+
+use std::marker::PhantomData;
+use std::mem::{offset_of, size_of, MaybeUninit};
+use std::ptr::addr_of_mut;
+use std::ptr::NonNull;
+
+use arbitrary_int::u2;
+
+use crate::context::layout::{
+    AllocInfo, GcArrayHeader, GcHeader, GcRawMarkBits, GcStateBits, HeaderMetadata,
+};
+use crate::context::young::YoungGenerationSpace;
+use crate::context::GenerationId;
+use crate::{CollectorId, GarbageCollector};
+
+/// The alignment of every object header.
+pub const HEADER_ALIGNMENT: usize = GcHeader::<crate::ScopedId>::FIXED_ALIGNMENT;
+
+/// Set if the object has moved, and its forwarding pointer is valid.
+pub const FORWARDED_MASK: u32 = 1 << 0;
+/// Set if the object is in the old generation.
+pub const GENERATION_MASK: u32 = 1 << 1;
+/// Set if the object is an array, with a length following the header.
+pub const ARRAY_MASK: u32 = 1 << 2;
+/// The raw mark bit, whose meaning is given by [`black_mark_bits`].
+pub const MARK_MASK: u32 = 1 << 3;
+/// Set once the value has been fully initialized.
+pub const VALUE_INITIALIZED_MASK: u32 = 1 << 4;
+/// Set if the object must not be moved.
+pub const PINNED_MASK: u32 = 1 << 5;
+/// Set if the object is frozen, and must not be mutated.
+pub const FROZEN_MASK: u32 = 1 << 6;
+/// Set if the object is in the immortal space.
+pub const IMMORTAL_MASK: u32 = 1 << 7;
+/// The number of collections a young object has survived.
+pub const AGE_MASK: u32 = 0b11 << AGE_SHIFT;
+/// The position of the lowest bit of [`AGE_MASK`].
+pub const AGE_SHIFT: u32 = 8;
+/// Set if everything reachable from the object is also frozen.
+pub const DEEP_FROZEN_MASK: u32 = 1 << 10;
+/// The number of low bits used by the flags above.
+pub const STATE_BITS_USED: u32 = 11;
+
+const _: () = {
+    let empty = GcStateBits::DEFAULT;
+    assert!(empty.with_forwarded(true).raw_value() == FORWARDED_MASK);
+    assert!(empty.with_generation(GenerationId::Old).raw_value() == GENERATION_MASK);
+    assert!(empty.with_array(true).raw_value() == ARRAY_MASK);
+    assert!(empty.with_raw_mark_bits(GcRawMarkBits::Green).raw_value() == MARK_MASK);
+    assert!(empty.with_value_initialized(true).raw_value() == VALUE_INITIALIZED_MASK);
+    assert!(empty.with_pinned(true).raw_value() == PINNED_MASK);
+    assert!(empty.with_frozen(true).raw_value() == FROZEN_MASK);
+    assert!(empty.with_immortal(true).raw_value() == IMMORTAL_MASK);
+    assert!(empty.with_age(u2::new(3)).raw_value() == AGE_MASK);
+    assert!(empty.with_deep_frozen(true).raw_value() == DEEP_FROZEN_MASK);
+    assert!(DEEP_FROZEN_MASK >> STATE_BITS_USED == 0);
+    assert!(size_of::<GcStateBits>() == size_of::<u32>());
+};
+
+/// The byte offsets of header fields for objects of a particular collector.
+///
+/// Offsets are relative to the start of the header,
+/// which is the address of a [`Gc`](crate::Gc) minus [`Self::REGULAR_VALUE_OFFSET`].
+pub struct HeaderLayout<Id: CollectorId> {
+    marker: PhantomData<Id>,
+}
+impl<Id: CollectorId> HeaderLayout<Id> {
+    /// The offset of the `u32` state bits.
+    pub const STATE_BITS_OFFSET: usize = {
+        let offset = offset_of!(GcHeader<Id>, state_bits);
+        assert!(offset % std::mem::align_of::<u32>() == 0);
+        offset
+    };
+    /// The offset of the forwarding pointer,
+    /// which is only valid if [`FORWARDED_MASK`] is set.
+    ///
+    /// With the `compact-header` feature,
+    /// this overwrites the start of the old value.
+    #[cfg(not(feature = "compact-header"))]
+    pub const FORWARD_PTR_OFFSET: usize = offset_of!(GcHeader<Id>, metadata);
+    /// The offset of the forwarding pointer,
+    /// which is only valid if [`FORWARDED_MASK`] is set.
+    ///
+    /// With the `compact-header` feature,
+    /// this overwrites the start of the old value.
+    #[cfg(feature = "compact-header")]
+    pub const FORWARD_PTR_OFFSET: usize = GcHeader::<Id>::REGULAR_VALUE_OFFSET;
+    /// The offset of the [user word](crate::Gc::user_data).
+    #[cfg(feature = "user-word")]
+    pub const USER_WORD_OFFSET: usize = offset_of!(GcHeader<Id>, user_word);
+    /// The offset of a regular (non-array) value.
+    pub const REGULAR_VALUE_OFFSET: usize = GcHeader::<Id>::REGULAR_VALUE_OFFSET;
+    /// The offset of the `usize` length of an array.
+    pub const ARRAY_LEN_OFFSET: usize = offset_of!(GcArrayHeader<Id>, len_elements);
+    /// The offset of the first element of an array.
+    pub const ARRAY_VALUE_OFFSET: usize = GcHeader::<Id>::ARRAY_VALUE_OFFSET;
+}
+
+/// The raw value of [`MARK_MASK`] for objects marked live by the current collection.
+///
+/// Outside of a collection, every object has the opposite value.
+/// The meaning of the mark bit is flipped after every collection,
+/// so generated code must reload this after anything which can collect.
+pub fn black_mark_bits<Id: CollectorId>(collector: &GarbageCollector<Id>) -> u32 {
+    if collector.state.mark_bits_inverted.get() {
+        0
+    } else {
+        MARK_MASK
+    }
+}
+
+/// The addresses of the young generation's allocation cursor,
+/// for generated code which inlines the allocation fast path.
+///
+/// Generated code allocates an object of `size` bytes like this:
+/// ```text
+/// start = *cursor
+/// if limit - start < size: take the slow path
+/// *cursor = start + size
+/// copy the header template to start
+/// initialize the value at start + REGULAR_VALUE_OFFSET
+/// ```
+/// Both the size and header come from a [`JitAllocTemplate`],
+/// and the slow path is [`GarbageCollector::jit_alloc_slow`],
+/// which also refills the region.
+///
+/// Objects allocated this way are not seen by the [sampler](GarbageCollector::set_alloc_sampler)
+/// or by [`GarbageCollector::classify_ptr`] until they survive a collection.
+///
+/// The region is emptied by every collection,
+/// so the addresses remain valid for the lifetime of the collector,
+/// but the values behind them must be reloaded after anything which can collect.
+#[derive(Debug, Copy, Clone)]
+#[non_exhaustive]
+pub struct AllocationCursor {
+    /// The address of the next object to allocate.
+    pub cursor: NonNull<*mut u8>,
+    /// The end of the current region.
+    pub limit: NonNull<*mut u8>,
+}
+
+/// How generated code allocates an object of a particular type,
+/// given by [`GarbageCollector::jit_alloc_template`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct JitAllocTemplate {
+    /// The bytes of the header to copy to the start of each object.
+    ///
+    /// This embeds the current meaning of the [mark bit](black_mark_bits),
+    /// so it must be reloaded after every collection.
+    pub header: Box<[u8]>,
+    /// The overall size of each object, including its header.
+    ///
+    /// This is always a multiple of [`HEADER_ALIGNMENT`].
+    pub size: usize,
+}
+impl JitAllocTemplate {
+    pub(super) fn new<Id: CollectorId>(
+        state_bits: GcStateBits,
+        metadata: HeaderMetadata<Id>,
+        id: Id,
+        size: usize,
+    ) -> Self {
+        let header = GcHeader::new(
+            state_bits,
+            AllocInfo {
+                nontrivial_drop_index: YoungGenerationSpace::<Id>::NO_DROP_INDEX,
+            },
+            metadata,
+            id,
+        );
+        // copy field by field, so the padding is zeroed instead of uninitialized
+        let mut bytes = MaybeUninit::<GcHeader<Id>>::zeroed();
+        let ptr = bytes.as_mut_ptr();
+        unsafe {
+            addr_of_mut!((*ptr).state_bits).write(header.state_bits);
+            addr_of_mut!((*ptr).alloc_info).write(header.alloc_info);
+            #[cfg(not(feature = "compact-header"))]
+            addr_of_mut!((*ptr).metadata).write(header.metadata);
+            #[cfg(feature = "user-word")]
+            addr_of_mut!((*ptr).user_word).write(header.user_word);
+            addr_of_mut!((*ptr).collector_id).write(header.collector_id);
+            let bytes = std::slice::from_raw_parts(ptr.cast::<u8>(), size_of::<GcHeader<Id>>());
+            JitAllocTemplate {
+                header: bytes.into(),
+                size,
+            }
+        }
+    }
+}
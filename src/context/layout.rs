@@ -1,6 +1,7 @@
 use crate::context::{CollectorState, GenerationId};
 use crate::utils::LayoutExt;
-use crate::{Collect, CollectContext, CollectorId};
+use crate::{Collect, CollectContext, CollectorId, NullCollect, PlacementHint};
+use arbitrary_int::u2;
 use bitbybit::{bitenum, bitfield};
 use std::alloc::Layout;
 use std::cell::Cell;
@@ -11,15 +12,19 @@ use std::path::Iter;
 use std::ptr::NonNull;
 use std::thread::current;
 
+#[cfg(feature = "compact-header")]
+mod type_registry;
+
 /// The layout of a "regular" (non-array) type
 #[derive(Debug)]
 pub(crate) struct GcTypeLayout<Id: CollectorId> {
     /// The layout of the underlying value
     ///
-    /// INVARIANT: The maximum alignment is [`GcHeader::FIXED_ALIGNMENT`]
+    /// If this exceeds [`GcHeader::FIXED_ALIGNMENT`],
+    /// the allocation is padded before the header.
     value_layout: Layout,
-    /// The overall size of the value including the header
-    /// and trailing padding.
+    /// The overall size of the value including the header,
+    /// any padding before the header, and trailing padding.
     overall_size: usize,
     marker: PhantomData<&'static Id>,
 }
@@ -41,8 +46,36 @@ impl<Id: CollectorId> GcTypeLayout<Id> {
 
     #[inline]
     pub const fn overall_layout(&self) -> Layout {
-        unsafe {
-            Layout::from_size_align_unchecked(self.overall_size, GcHeader::<Id>::FIXED_ALIGNMENT)
+        unsafe { Layout::from_size_align_unchecked(self.overall_size, self.overall_align()) }
+    }
+
+    /// The alignment of the overall allocation,
+    /// which is larger than [`GcHeader::FIXED_ALIGNMENT`] for over-aligned values.
+    #[inline]
+    const fn overall_align(&self) -> usize {
+        if self.value_layout.align() > GcHeader::<Id>::FIXED_ALIGNMENT {
+            self.value_layout.align()
+        } else {
+            GcHeader::<Id>::FIXED_ALIGNMENT
+        }
+    }
+
+    /// The padding before the header in the overall allocation.
+    ///
+    /// This keeps the value at a fixed offset from the header,
+    /// and is nonzero only if the value is over-aligned.
+    #[inline]
+    pub const fn header_offset(&self) -> usize {
+        Self::header_offset_for(self.value_layout.align())
+    }
+
+    #[inline]
+    const fn header_offset_for(value_align: usize) -> usize {
+        if value_align > GcHeader::<Id>::FIXED_ALIGNMENT {
+            let misalignment = GcHeader::<Id>::REGULAR_VALUE_OFFSET % value_align;
+            (value_align - misalignment) % value_align
+        } else {
+            0
         }
     }
 
@@ -68,14 +101,29 @@ impl<Id: CollectorId> GcTypeLayout<Id> {
 
     #[track_caller]
     pub const fn from_value_layout(value_layout: Layout) -> Self {
-        assert!(
-            value_layout.align() <= GcHeader::<Id>::FIXED_ALIGNMENT,
-            "Alignment exceeds maximum",
-        );
-        let overall_layout = Self::compute_overall_layout(value_layout);
+        let align = value_layout.align();
+        let mut overall_size = if align <= GcHeader::<Id>::FIXED_ALIGNMENT {
+            Self::compute_overall_layout(value_layout).size()
+        } else {
+            // pad before the header, so the value remains at a fixed offset
+            let Some(unpadded_size) = (Self::header_offset_for(align)
+                + GcHeader::<Id>::REGULAR_VALUE_OFFSET)
+                .checked_add(value_layout.size())
+            else {
+                panic!("layout overflow")
+            };
+            let Some(padded_size) = unpadded_size.checked_next_multiple_of(align) else {
+                panic!("layout overflow")
+            };
+            padded_size
+        };
+        if cfg!(feature = "compact-header") && overall_size < GcHeader::<Id>::MIN_OVERALL_SIZE {
+            // the value must have room for a forwarding pointer
+            overall_size = GcHeader::<Id>::MIN_OVERALL_SIZE;
+        }
         GcTypeLayout {
             value_layout,
-            overall_size: overall_layout.size(),
+            overall_size,
             marker: PhantomData,
         }
     }
@@ -104,18 +152,81 @@ impl<Id: CollectorId> GcArrayTypeInfo<Id> {
             &*(GcTypeInfo::<Id>::new::<T>() as *const GcTypeInfo<Id> as *const GcArrayTypeInfo<Id>)
         }
     }
+
+    /// How to trace an array with these elements.
+    #[inline]
+    pub fn kind(&self) -> GcArrayKind {
+        self.element_type_info.array_kind
+    }
+
+    /// The type info for an array of [`NullCollect`] elements,
+    /// which is never traced regardless of [`Collect::NEEDS_COLLECT`].
+    ///
+    /// This avoids iterating over large arrays of primitives during collection.
+    #[inline]
+    pub const fn new_null_collect<T: NullCollect<Id>>() -> &'static Self {
+        assert!(std::mem::size_of::<Self>() == std::mem::size_of::<GcTypeInfo<Id>>());
+        unsafe {
+            &*(<GcTypeInitImpl as TypeIdInit<Id, T>>::NULL_TRACE_TYPE_INFO_REF
+                as *const GcTypeInfo<Id> as *const GcArrayTypeInfo<Id>)
+        }
+    }
+}
+
+/// How to trace the elements of an array.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum GcArrayKind {
+    /// Each element is traced by an indirect call to the `trace_func`.
+    Regular,
+    /// Each element is a [`Gc`](crate::Gc) pointer,
+    /// so the array can be traced as a flat vector of pointers.
+    GcPointers,
 }
 
 pub type TraceFuncPtr<Id> = unsafe fn(NonNull<()>, &mut CollectContext<Id>);
 
+/// A pointer to [`Collect::on_moved`], given the old and new locations of the value.
+pub type MovedFuncPtr = unsafe fn(NonNull<()>, NonNull<()>);
+
+/// The name of a type, used for debugging.
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum GcTypeName {
+    /// The name of a static rust type.
+    ///
+    /// This is lazily computed, because [`std::any::type_name`] is not a `const fn`.
+    Static(fn() -> &'static str),
+    /// The name of a type defined at runtime.
+    Dynamic(&'static str),
+}
+impl GcTypeName {
+    #[inline]
+    pub fn get(&self) -> &'static str {
+        match *self {
+            GcTypeName::Static(func) => func(),
+            GcTypeName::Dynamic(name) => name,
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub(crate) struct GcTypeInfo<Id: CollectorId> {
     pub(super) layout: GcTypeLayout<Id>,
     pub(super) drop_func: Option<unsafe fn(*mut ())>,
     pub(super) trace_func: Option<TraceFuncPtr<Id>>,
+    pub(super) moved_func: Option<MovedFuncPtr>,
+    pub(super) type_name: GcTypeName,
+    /// The kind of array with this type as its elements.
+    pub(super) array_kind: GcArrayKind,
+    /// Which generation new objects of this type are allocated in.
+    pub(super) placement: PlacementHint,
 }
 impl<Id: CollectorId> GcTypeInfo<Id> {
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name.get()
+    }
+
     #[inline]
     pub unsafe fn assume_array_info(&self) -> &'_ GcArrayTypeInfo<Id> {
         // Takes advantage of fact repr is identical
@@ -155,13 +266,34 @@ trait TypeIdInit<Id: CollectorId, T: Collect<Id>> {
         } else {
             None
         };
+        let moved_func = if T::NEEDS_MOVE_HOOK {
+            unsafe {
+                Some(std::mem::transmute::<_, MovedFuncPtr>(
+                    T::on_moved as unsafe fn(NonNull<T>, NonNull<T>),
+                ))
+            }
+        } else {
+            None
+        };
         GcTypeInfo {
             layout,
             drop_func,
             trace_func,
+            moved_func,
+            type_name: GcTypeName::Static(std::any::type_name::<T>),
+            array_kind: if T::IS_GC_POINTER.get() {
+                GcArrayKind::GcPointers
+            } else {
+                GcArrayKind::Regular
+            },
+            placement: T::PLACEMENT,
         }
     };
     const TYPE_INFO_REF: &'static GcTypeInfo<Id> = &Self::TYPE_INFO_INIT_VAL;
+    const NULL_TRACE_TYPE_INFO_REF: &'static GcTypeInfo<Id> = &GcTypeInfo {
+        trace_func: None,
+        ..Self::TYPE_INFO_INIT_VAL
+    };
 }
 struct GcTypeInitImpl;
 impl<Id: CollectorId, T: Collect<Id>> TypeIdInit<Id, T> for GcTypeInitImpl {}
@@ -235,10 +367,61 @@ pub struct GcStateBits {
     raw_mark_bits: GcRawMarkBits,
     #[bit(4, rw)]
     value_initialized: bool,
+    /// Indicates the object must not be moved by the collector.
+    ///
+    /// Pinned objects in the young generation are excluded from evacuation.
+    #[bit(5, rw)]
+    pinned: bool,
+    /// Indicates the object has been frozen, and must not be mutated.
+    ///
+    /// This is preserved when the object is promoted.
+    #[bit(6, rw)]
+    frozen: bool,
+    /// Indicates the object lives in the immortal space.
+    ///
+    /// Immortal objects are never marked, moved, or swept.
+    #[bit(7, rw)]
+    immortal: bool,
+    /// The number of collections a young object has survived.
+    ///
+    /// Once this reaches the [age threshold](crate::GcConfig::survivor_age_threshold),
+    /// the object is promoted to the old generation.
+    #[bits(8..=9, rw)]
+    age: u2,
+    /// Indicates everything reachable from the object has also been frozen.
+    ///
+    /// This is only set by a deep freeze, which skips these objects,
+    /// so a deep freeze after a shallow one still freezes the children.
+    #[bit(10, rw)]
+    deep_frozen: bool,
 }
+
+/// The number of low bits in the state reserved for [`GcStateBits`] fields.
+///
+/// With the `compact-header` feature, the remaining bits hold the type index.
+#[cfg(feature = "compact-header")]
+const TYPE_INDEX_SHIFT: u32 = u32::BITS - type_registry::INDEX_BITS;
+
+// every field must fit below the type index
+#[cfg(feature = "compact-header")]
+const _: () = {
+    let all_fields = GcStateBits::DEFAULT
+        .with_forwarded(true)
+        .with_generation(GenerationId::Old)
+        .with_array(true)
+        .with_raw_mark_bits(GcRawMarkBits::Green)
+        .with_value_initialized(true)
+        .with_pinned(true)
+        .with_frozen(true)
+        .with_immortal(true)
+        .with_age(u2::new(3))
+        .with_deep_frozen(true);
+    assert!(all_fields.raw_value() >> TYPE_INDEX_SHIFT == 0);
+};
 pub union HeaderMetadata<Id: CollectorId> {
     pub type_info: &'static GcTypeInfo<Id>,
     pub array_type_info: &'static GcArrayTypeInfo<Id>,
+    #[cfg_attr(feature = "compact-header", allow(dead_code))]
     pub forward_ptr: NonNull<GcHeader<Id>>,
 }
 pub union AllocInfo {
@@ -255,11 +438,23 @@ pub union AllocInfo {
     pub live_object_index: u32,
 }
 
+/// The header of every GC object.
+///
+/// With the `compact-header` feature, there is no metadata pointer.
+/// Instead, the upper bits of the state hold an index into a global type registry,
+/// and the forwarding pointer is stored in place of the (dead) value.
+/// Combined with a zero-sized id for singleton collectors,
+/// this shrinks the header from 16 bytes to 8 bytes.
 #[repr(C, align(8))]
 pub(crate) struct GcHeader<Id: CollectorId> {
     pub(super) state_bits: Cell<GcStateBits>,
     pub(super) alloc_info: AllocInfo,
+    #[cfg(not(feature = "compact-header"))]
     pub(super) metadata: HeaderMetadata<Id>,
+    /// A word of metadata controlled by the user,
+    /// which is preserved when the object moves.
+    #[cfg(feature = "user-word")]
+    pub(super) user_word: Cell<usize>,
     /// The id for the collector where this object is allocated.
     ///
     /// If the collector is a singleton (either global or thread-local),
@@ -271,16 +466,14 @@ pub(crate) struct GcHeader<Id: CollectorId> {
 }
 impl<Id: CollectorId> Debug for GcHeader<Id> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("GcHeader")
-            .field("state_bits", &self.state_bits.get())
-            .field(
-                "metadata",
-                if self.state_bits.get().forwarded() {
-                    unsafe { &self.metadata.forward_ptr }
-                } else {
-                    unsafe { &self.metadata.type_info }
-                },
-            )
+        let mut debug = f.debug_struct("GcHeader");
+        debug.field("state_bits", &self.state_bits.get());
+        if self.state_bits.get().forwarded() {
+            debug.field("forward_ptr", unsafe { &self.forward_ptr() });
+        } else {
+            debug.field("type_info", self.type_info());
+        }
+        debug
             .field("collector_id", &self.collector_id)
             .finish_non_exhaustive()
     }
@@ -291,31 +484,167 @@ impl<Id: CollectorId> GcHeader<Id> {
         self.state_bits.set(func(self.state_bits.get()));
     }
 
-    /// The fixed alignment for all GC types
+    /// The fixed alignment for all GC headers
     ///
-    /// Allocating a type with an alignment greater than this is an error.
+    /// Values with a greater alignment are padded before the header.
     pub const FIXED_ALIGNMENT: usize = 8;
     /// The fixed offset from the start of the GcHeader to a regular value
     pub const REGULAR_VALUE_OFFSET: usize = std::mem::size_of::<Self>();
     pub const ARRAY_VALUE_OFFSET: usize = std::mem::size_of::<GcArrayHeader<Id>>();
     pub const REGULAR_HEADER_LAYOUT: Layout = Layout::new::<Self>();
     pub const ARRAY_HEADER_LAYOUT: Layout = Layout::new::<GcArrayHeader<Id>>();
+    /// The minimum size of an allocation,
+    /// leaving room to store a forwarding pointer in place of the value.
+    ///
+    /// This is only used with the `compact-header` feature.
+    pub const MIN_OVERALL_SIZE: usize =
+        Self::REGULAR_VALUE_OFFSET + std::mem::size_of::<NonNull<Self>>();
+    /// Create a new header for an object with the specified metadata.
+    #[inline]
+    pub fn new(
+        state_bits: GcStateBits,
+        alloc_info: AllocInfo,
+        metadata: HeaderMetadata<Id>,
+        collector_id: Id,
+    ) -> Self {
+        #[cfg(feature = "compact-header")]
+        {
+            let type_index = type_registry::index_of(unsafe { metadata.type_info });
+            GcHeader {
+                state_bits: Cell::new(GcStateBits::new_with_raw_value(
+                    state_bits.raw_value() | (type_index << TYPE_INDEX_SHIFT),
+                )),
+                alloc_info,
+                #[cfg(feature = "user-word")]
+                user_word: Cell::new(0),
+                collector_id,
+            }
+        }
+        #[cfg(not(feature = "compact-header"))]
+        {
+            GcHeader {
+                state_bits: Cell::new(state_bits),
+                alloc_info,
+                metadata,
+                #[cfg(feature = "user-word")]
+                user_word: Cell::new(0),
+                collector_id,
+            }
+        }
+    }
 
     #[inline]
     pub fn id(&self) -> Id {
         self.collector_id
     }
 
+    #[inline]
+    pub fn is_pinned(&self) -> bool {
+        self.state_bits.get().pinned()
+    }
+
+    #[inline]
+    pub fn is_frozen(&self) -> bool {
+        self.state_bits.get().frozen()
+    }
+
+    #[inline]
+    pub fn is_deep_frozen(&self) -> bool {
+        self.state_bits.get().deep_frozen()
+    }
+
+    #[inline]
+    pub fn is_array(&self) -> bool {
+        self.state_bits.get().array()
+    }
+
+    #[inline]
+    pub fn is_immortal(&self) -> bool {
+        self.state_bits.get().immortal()
+    }
+
+    #[cfg(feature = "user-word")]
+    #[inline]
+    pub fn user_word(&self) -> usize {
+        self.user_word.get()
+    }
+
+    #[cfg(feature = "user-word")]
+    #[inline]
+    pub fn set_user_word(&self, value: usize) {
+        self.user_word.set(value);
+    }
+
+    /// The type info of this object, which must not be forwarded.
+    ///
+    /// See [`Self::resolve_type_info`] for forwarded objects.
+    #[inline]
+    pub fn type_info(&self) -> &'static GcTypeInfo<Id> {
+        debug_assert!(!self.state_bits.get().forwarded());
+        #[cfg(feature = "compact-header")]
+        unsafe {
+            type_registry::lookup(self.state_bits.get().raw_value() >> TYPE_INDEX_SHIFT)
+        }
+        #[cfg(not(feature = "compact-header"))]
+        unsafe {
+            self.metadata.type_info
+        }
+    }
+
+    /// The array type info of this object,
+    /// which must be a (non-forwarded) array.
+    #[inline]
+    pub fn array_type_info(&self) -> &'static GcArrayTypeInfo<Id> {
+        debug_assert!(self.state_bits.get().array());
+        unsafe { self.type_info().assume_array_info() }
+    }
+
+    /// The new location of this object, which must have been forwarded.
+    #[inline]
+    pub unsafe fn forward_ptr(&self) -> NonNull<GcHeader<Id>> {
+        debug_assert!(self.state_bits.get().forwarded());
+        #[cfg(feature = "compact-header")]
+        {
+            self.regular_value_ptr()
+                .cast::<NonNull<GcHeader<Id>>>()
+                .as_ptr()
+                .read()
+        }
+        #[cfg(not(feature = "compact-header"))]
+        {
+            self.metadata.forward_ptr
+        }
+    }
+
+    /// Set the new location of a forwarded object.
+    ///
+    /// With the `compact-header` feature, this overwrites the start of the value,
+    /// so it must be called after the value has been copied.
+    #[inline]
+    pub unsafe fn set_forward_ptr(header: NonNull<Self>, forward_ptr: NonNull<GcHeader<Id>>) {
+        #[cfg(feature = "compact-header")]
+        {
+            header
+                .cast::<u8>()
+                .add(Self::REGULAR_VALUE_OFFSET)
+                .cast::<NonNull<GcHeader<Id>>>()
+                .write(forward_ptr);
+        }
+        #[cfg(not(feature = "compact-header"))]
+        {
+            (*header.as_ptr()).metadata.forward_ptr = forward_ptr;
+        }
+    }
+
     #[inline]
     pub fn resolve_type_info(&self) -> &'static GcTypeInfo<Id> {
         unsafe {
             if self.state_bits.get().forwarded() {
-                let forward_ptr = self.metadata.forward_ptr;
-                let forward_header = forward_ptr.as_ref();
+                let forward_header = self.forward_ptr().as_ref();
                 debug_assert!(!forward_header.state_bits.get().forwarded());
-                forward_header.metadata.type_info
+                forward_header.type_info()
             } else {
-                self.metadata.type_info
+                self.type_info()
             }
         }
     }
@@ -334,9 +663,35 @@ impl<Id: CollectorId> GcHeader<Id> {
         &*(self as *const Self as *const GcArrayHeader<Id>)
     }
 
+    /// The start and layout of the underlying allocation.
+    ///
+    /// For over-aligned values (or arrays of over-aligned elements),
+    /// the allocation starts with padding before the header.
+    #[inline]
+    pub unsafe fn allocation(&self) -> (NonNull<u8>, Layout) {
+        let header_ptr = NonNull::from(self).cast::<u8>();
+        if self.state_bits.get().array() {
+            let layout_info = self.assume_array_header().layout_info();
+            (
+                header_ptr.sub(layout_info.header_offset()),
+                layout_info.overall_layout(),
+            )
+        } else {
+            let layout = &self.type_info().layout;
+            (
+                header_ptr.sub(layout.header_offset()),
+                layout.overall_layout(),
+            )
+        }
+    }
+
+    /// Invoke the object's destructor,
+    /// dispatching to [`GcArrayHeader::invoke_destructor`] for arrays.
     #[inline]
     pub unsafe fn invoke_destructor(&self) {
-        if let Some(drop_func) = self.resolve_type_info().drop_func {
+        if self.state_bits.get().array() {
+            self.assume_array_header().invoke_destructor();
+        } else if let Some(drop_func) = self.resolve_type_info().drop_func {
             drop_func(self.regular_value_ptr().as_ptr() as *mut ());
         }
     }
@@ -350,6 +705,26 @@ pub struct GcArrayHeader<Id: CollectorId> {
 }
 
 impl<Id: CollectorId> GcArrayHeader<Id> {
+    /// The length of the array in elements
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len_elements
+    }
+
+    #[inline]
+    pub fn main_header(&self) -> &'_ GcHeader<Id> {
+        &self.main_header
+    }
+
+    /// Get the header of an array from a pointer to its first element.
+    ///
+    /// ## Safety
+    /// The pointer must be the value pointer of a valid array.
+    #[inline]
+    pub unsafe fn from_value_ptr<'a>(ptr: NonNull<u8>) -> &'a Self {
+        &*(ptr.as_ptr().sub(GcHeader::<Id>::ARRAY_VALUE_OFFSET) as *const Self)
+    }
+
     #[inline]
     fn resolve_type_info(&self) -> &'static GcArrayTypeInfo<Id> {
         unsafe {
@@ -470,28 +845,52 @@ impl<Id: CollectorId> GcArrayLayoutInfo<Id> {
         }
     }
 
-    // See Layout::max_size_for_align
-    const MAX_VALUE_SIZE: usize = ((isize::MAX as usize) - GcHeader::<Id>::FIXED_ALIGNMENT - 1)
-        - GcHeader::<Id>::ARRAY_VALUE_OFFSET;
+    /// The alignment of the overall allocation,
+    /// which is larger than [`GcHeader::FIXED_ALIGNMENT`] for over-aligned elements.
+    #[inline]
+    const fn overall_align_for(element_align: usize) -> usize {
+        if element_align > GcHeader::<Id>::FIXED_ALIGNMENT {
+            element_align
+        } else {
+            GcHeader::<Id>::FIXED_ALIGNMENT
+        }
+    }
+
+    /// The padding needed before the header,
+    /// so that elements with the specified alignment are properly aligned.
+    ///
+    /// This is zero unless the elements are over-aligned.
+    #[inline]
+    const fn header_offset_for(element_align: usize) -> usize {
+        if element_align > GcHeader::<Id>::FIXED_ALIGNMENT {
+            let misalignment = GcHeader::<Id>::ARRAY_VALUE_OFFSET % element_align;
+            (element_align - misalignment) % element_align
+        } else {
+            0
+        }
+    }
 
     #[cfg_attr(not(debug_assertions), inline)]
     pub const fn new(
         element_layout: Layout,
         len_elements: usize,
     ) -> Result<Self, GcArrayLayoutError> {
-        if element_layout.align() > GcHeader::<Id>::FIXED_ALIGNMENT {
-            return Err(GcArrayLayoutError::InvalidElementAlign);
-        }
         if LayoutExt(element_layout).pad_to_align().size() != element_layout.size() {
             return Err(GcArrayLayoutError::ElementMissingPadding);
         }
         let Some(repeated_value_size) = element_layout.size().checked_mul(len_elements) else {
             return Err(GcArrayLayoutError::ArraySizeOverflow);
         };
-        if repeated_value_size >= Self::MAX_VALUE_SIZE {
+        // See Layout::max_size_for_align
+        let max_value_size = (isize::MAX as usize)
+            - Self::overall_align_for(element_layout.align())
+            - 1
+            - Self::header_offset_for(element_layout.align())
+            - GcHeader::<Id>::ARRAY_VALUE_OFFSET;
+        if repeated_value_size >= max_value_size {
             return Err(GcArrayLayoutError::ArraySizeOverflow);
         }
-        if cfg!(debug_assertions) {
+        if cfg!(debug_assertions) && element_layout.align() <= GcHeader::<Id>::FIXED_ALIGNMENT {
             // double check above calculations
             match Layout::from_size_align(repeated_value_size, GcHeader::<Id>::FIXED_ALIGNMENT) {
                 Ok(repeated_value) => {
@@ -542,6 +941,14 @@ impl<Id: CollectorId> GcArrayLayoutInfo<Id> {
         }
     }
 
+    /// The padding before the header in the overall allocation.
+    ///
+    /// This is nonzero only if the elements are over-aligned.
+    #[inline]
+    pub const fn header_offset(&self) -> usize {
+        Self::header_offset_for(self.element_layout.align())
+    }
+
     #[inline]
     pub fn overall_layout(&self) -> Layout {
         let value_layout = self.value_layout();
@@ -549,14 +956,16 @@ impl<Id: CollectorId> GcArrayLayoutInfo<Id> {
             Layout::from_size_align_unchecked(
                 value_layout
                     .size()
-                    .unchecked_add(GcHeader::<Id>::ARRAY_VALUE_OFFSET),
-                GcHeader::<Id>::FIXED_ALIGNMENT,
+                    .unchecked_add(GcHeader::<Id>::ARRAY_VALUE_OFFSET)
+                    .unchecked_add(self.header_offset()),
+                Self::overall_align_for(value_layout.align()),
             )
             .pad_to_align()
         }
     }
 }
-#[derive(Debug, thiserror::Error)]
+/// The reason an array layout could not be computed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
 #[non_exhaustive]
 pub enum GcArrayLayoutError {
     #[error("Invalid element alignment")]
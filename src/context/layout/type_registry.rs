@@ -0,0 +1,91 @@
+//! A global registry assigning a small index to each [`GcTypeInfo`].
+//!
+//! This is used by the `compact-header` feature,
+//! which stores a type index in the header instead of a metadata pointer.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Mutex;
+
+use crate::context::layout::GcTypeInfo;
+use crate::CollectorId;
+
+/// The number of bits available for a type index.
+pub const INDEX_BITS: u32 = 21;
+const CHUNK_BITS: u32 = 12;
+const CHUNK_SIZE: usize = 1 << CHUNK_BITS;
+const NUM_CHUNKS: usize = 1 << (INDEX_BITS - CHUNK_BITS);
+
+type Chunk = [AtomicPtr<()>; CHUNK_SIZE];
+
+/// Lookup tables from index to type info.
+///
+/// Chunks are allocated lazily and never freed,
+/// so reads are lock-free.
+static CHUNKS: [AtomicPtr<Chunk>; NUM_CHUNKS] = {
+    const EMPTY_CHUNK: AtomicPtr<Chunk> = AtomicPtr::new(std::ptr::null_mut());
+    [EMPTY_CHUNK; NUM_CHUNKS]
+};
+/// The index assigned to each type info, keyed by address.
+static INDEXES: Mutex<BTreeMap<usize, u32>> = Mutex::new(BTreeMap::new());
+
+thread_local! {
+    /// Avoid locking the global registry on every allocation.
+    static CACHED_INDEXES: RefCell<HashMap<usize, u32>> = RefCell::new(HashMap::new());
+}
+
+/// Get the index of the specified type, registering it if needed.
+///
+/// ## Panics
+/// If more than `2^21` distinct types are registered.
+#[inline]
+pub fn index_of<Id: CollectorId>(type_info: &'static GcTypeInfo<Id>) -> u32 {
+    let ptr = NonNull::from(type_info).cast::<()>();
+    let address = crate::utils::addr(ptr.as_ptr());
+    CACHED_INDEXES
+        .with_borrow_mut(|cache| *cache.entry(address).or_insert_with(|| register_slow(ptr)))
+}
+
+#[cold]
+fn register_slow(ptr: NonNull<()>) -> u32 {
+    // the address is only used as a key,
+    // so the registry stores the original pointer to preserve its provenance
+    let address = crate::utils::addr(ptr.as_ptr());
+    let mut indexes = INDEXES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(&index) = indexes.get(&address) {
+        return index;
+    }
+    let index = u32::try_from(indexes.len())
+        .ok()
+        .filter(|&index| index < (1 << INDEX_BITS))
+        .expect("Exhausted all compact type indexes");
+    let chunk_slot = &CHUNKS[index as usize / CHUNK_SIZE];
+    let mut chunk = chunk_slot.load(Ordering::Acquire);
+    if chunk.is_null() {
+        const EMPTY_SLOT: AtomicPtr<()> = AtomicPtr::new(std::ptr::null_mut());
+        // leaked, as readers never lock
+        chunk = Box::into_raw(Box::new([EMPTY_SLOT; CHUNK_SIZE]));
+        chunk_slot.store(chunk, Ordering::Release);
+    }
+    unsafe {
+        (*chunk)[index as usize % CHUNK_SIZE].store(ptr.as_ptr(), Ordering::Release);
+    }
+    indexes.insert(address, index);
+    index
+}
+
+/// Lookup the type info with the specified index.
+///
+/// ## Safety
+/// The index must have been returned by [`index_of`] for a type with the same collector id.
+#[inline]
+pub unsafe fn lookup<Id: CollectorId>(index: u32) -> &'static GcTypeInfo<Id> {
+    let chunk = CHUNKS[index as usize / CHUNK_SIZE].load(Ordering::Acquire);
+    debug_assert!(!chunk.is_null(), "Unregistered type index {index}");
+    let ptr = (*chunk)[index as usize % CHUNK_SIZE].load(Ordering::Acquire);
+    NonNull::new_unchecked(ptr)
+        .cast::<GcTypeInfo<Id>>()
+        .as_ref()
+}
@@ -0,0 +1,69 @@
+//! The mutation log, which records mutations performed while marking is in progress.
+//!
+//! This implements a snapshot-at-the-beginning (SATB) barrier.
+//! Without it, moving a pointer out of an unmarked object into an already-marked one
+//! would hide the target from the marker.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::ptr::NonNull;
+
+use crate::context::layout::GcHeader;
+use crate::{CollectContext, CollectorId};
+
+pub(crate) struct MutationLog<Id: CollectorId> {
+    /// Objects whose pointers were overwritten, which must be kept alive.
+    deleted: RefCell<Vec<NonNull<GcHeader<Id>>>>,
+    /// Objects which were mutated in an unknown way, whose children must be rescanned.
+    mutated: RefCell<Vec<NonNull<GcHeader<Id>>>>,
+}
+impl<Id: CollectorId> MutationLog<Id> {
+    pub fn new() -> Self {
+        MutationLog {
+            deleted: RefCell::new(Vec::new()),
+            mutated: RefCell::new(Vec::new()),
+        }
+    }
+
+    #[inline]
+    pub fn log_deleted(&self, header: NonNull<GcHeader<Id>>) {
+        self.deleted.borrow_mut().push(header);
+    }
+
+    #[inline]
+    pub fn log_mutated(&self, header: NonNull<GcHeader<Id>>) {
+        self.mutated.borrow_mut().push(header);
+    }
+
+    /// Remove objects which are about to be freed without a collection.
+    pub fn forget(&self, discarded: &HashSet<NonNull<GcHeader<Id>>>) {
+        self.deleted
+            .borrow_mut()
+            .retain(|header| !discarded.contains(header));
+        self.mutated
+            .borrow_mut()
+            .retain(|header| !discarded.contains(header));
+    }
+
+    /// Mark everything in the log, returning `true` if there was anything to mark.
+    ///
+    /// The gray queue must be drained afterwards.
+    ///
+    /// ## Safety
+    /// Must only be called during the marking phase of a collection.
+    pub unsafe fn drain(&self, context: &mut CollectContext<'_, Id>) -> bool {
+        let deleted = std::mem::take(&mut *self.deleted.borrow_mut());
+        let mutated = std::mem::take(&mut *self.mutated.borrow_mut());
+        for &header in &deleted {
+            let _ = context.collect_any_gcheader(header);
+        }
+        for &header in &mutated {
+            // an already marked object would otherwise be skipped
+            let header = context.collect_any_gcheader(header);
+            if let Some(trace_func) = header.as_ref().type_info().trace_func {
+                context.trace_children(header, trace_func);
+            }
+        }
+        !deleted.is_empty() || !mutated.is_empty()
+    }
+}
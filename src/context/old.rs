@@ -8,6 +8,15 @@ use crate::context::layout::{AllocInfo, GcHeader, GcMarkBits};
 use crate::context::{CollectorState, GenerationId};
 use crate::CollectorId;
 
+mod pages;
+mod size_class;
+#[cfg(feature = "concurrent-sweep")]
+mod sweeper;
+
+pub use self::pages::OldPage;
+pub use self::size_class::SizeClassStats;
+use self::size_class::SizeClasses;
+
 mod fallback {
     use allocator_api2::alloc::AllocError;
     use std::alloc::Layout;
@@ -49,126 +58,383 @@ type HeapAllocator = zerogc_next_mimalloc_semisafe::heap::MimallocHeap;
 
 const DROP_NEEDS_EXPLICIT_FREE: bool = cfg!(any(miri, feature = "debug-alloc"));
 
-enum ObjectFreeCondition<'a, Id: CollectorId> {
-    /// Free the object if it has not been marked.
-    ///
-    /// Used to sweep objects.
-    Unmarked { state: &'a CollectorState<Id> },
-    /// Unconditionally free the object.
-    ///
-    /// Used to destroy the
-    Always,
+/// Free memory from the heap without access to the heap itself,
+/// which is possible from any thread.
+#[cfg(all(feature = "concurrent-sweep", any(miri, feature = "debug-alloc")))]
+unsafe fn deallocate_detached(ptr: NonNull<u8>, layout: Layout) {
+    allocator_api2::alloc::dealloc(ptr.as_ptr(), layout)
+}
+#[cfg(all(feature = "concurrent-sweep", not(any(miri, feature = "debug-alloc"))))]
+unsafe fn deallocate_detached(ptr: NonNull<u8>, layout: Layout) {
+    MimallocHeap::deallocate_detached(ptr, layout)
+}
+
+/// An entry in the table of old-generation objects.
+///
+/// This records everything needed to free the object,
+/// so sweeping only needs to touch a dead object's memory to run its destructor.
+struct OldObject<Id: CollectorId> {
+    header: NonNull<GcHeader<Id>>,
+    allocation_ptr: NonNull<u8>,
+    /// The layout of the allocation, which is the block of a size class for small objects.
+    overall_layout: Layout,
+    needs_drop: bool,
+    /// The epoch of the heap which the object was allocated from.
+    heap_epoch: u32,
+}
+
+/// A heap which was replaced by compaction, but still contains pinned objects.
+struct RetiredHeap {
+    heap: HeapAllocator,
+    epoch: u32,
+    /// The number of objects which still occupy the heap.
+    objects: usize,
+}
+
+/// A side table of mark bits for the old generation,
+/// indexed by each object's `live_object_index`.
+///
+/// Each word covers a page of 64 consecutive object slots,
+/// so the sweep can skip pages where every object survived
+/// and clear the bits a word at a time.
+struct MarkBitmap {
+    words: Vec<u64>,
+}
+impl MarkBitmap {
+    const BITS_PER_WORD: usize = u64::BITS as usize;
+
+    /// Ensure the bitmap has room for the specified number of objects.
+    #[inline]
+    fn reserve_objects(&mut self, num_objects: usize) {
+        let needed_words = num_objects.div_ceil(Self::BITS_PER_WORD);
+        if needed_words > self.words.len() {
+            self.words.resize(needed_words, 0);
+        }
+    }
+
+    #[inline]
+    fn set(&mut self, index: usize) {
+        self.words[index / Self::BITS_PER_WORD] |= 1 << (index % Self::BITS_PER_WORD);
+    }
 }
 
 pub struct OldGenerationSpace<Id: CollectorId> {
     // TODO: Add allocation count wrapper?
-    heap: HeapAllocator,
-    live_objects: UnsafeCell<Vec<Option<NonNull<GcHeader<Id>>>>>,
+    heap: UnsafeCell<HeapAllocator>,
+    objects: UnsafeCell<Vec<Option<OldObject<Id>>>>,
+    /// Indexes into `objects` which are currently unused.
+    free_indexes: UnsafeCell<Vec<u32>>,
+    size_classes: UnsafeCell<SizeClasses>,
+    mark_bitmap: UnsafeCell<MarkBitmap>,
     collector_id: Id,
     allocated_bytes: Cell<usize>,
+    /// The fresh heap which live objects are evacuated into during compaction.
+    ///
+    /// Objects promoted during compaction are allocated here as well.
+    compaction_heap: UnsafeCell<Option<HeapAllocator>>,
+    /// Identifies `heap`, incremented whenever compaction replaces it.
+    heap_epoch: Cell<u32>,
+    /// Heaps which were replaced by compaction, but still contain pinned objects.
+    ///
+    /// Each is freed along with its last object.
+    retired_heaps: UnsafeCell<Vec<RetiredHeap>>,
+    /// Frees dead objects in the background, spawned by the first sweep.
+    #[cfg(feature = "concurrent-sweep")]
+    sweeper: UnsafeCell<Option<sweeper::BackgroundSweeper>>,
 }
 impl<Id: CollectorId> OldGenerationSpace<Id> {
     pub unsafe fn new(id: Id) -> Self {
         OldGenerationSpace {
-            heap: HeapAllocator::new(),
-            live_objects: UnsafeCell::new(Vec::new()),
+            heap: UnsafeCell::new(HeapAllocator::new()),
+            objects: UnsafeCell::new(Vec::new()),
+            free_indexes: UnsafeCell::new(Vec::new()),
+            size_classes: UnsafeCell::new(SizeClasses::new()),
+            mark_bitmap: UnsafeCell::new(MarkBitmap { words: Vec::new() }),
             collector_id: id,
             allocated_bytes: Cell::new(0),
+            compaction_heap: UnsafeCell::new(None),
+            heap_epoch: Cell::new(0),
+            retired_heaps: UnsafeCell::new(Vec::new()),
+            #[cfg(feature = "concurrent-sweep")]
+            sweeper: UnsafeCell::new(None),
         }
     }
 
-    pub unsafe fn sweep(&mut self, state: &CollectorState<Id>) {
-        self.free_live_objects(ObjectFreeCondition::Unmarked { state });
+    /// Record that the specified object has been marked by the current collection.
+    ///
+    /// The mark bits in the header must also be updated,
+    /// but the sweep only consults the bitmap.
+    #[inline]
+    pub unsafe fn mark(&self, header: NonNull<GcHeader<Id>>) {
+        debug_assert_eq!(
+            header.as_ref().state_bits.get().generation(),
+            GenerationId::Old
+        );
+        let index = header.as_ref().alloc_info.live_object_index as usize;
+        debug_assert!(matches!(
+            (&*self.objects.get())[index],
+            Some(OldObject { header: existing, .. }) if existing == header
+        ));
+        (*self.mark_bitmap.get()).set(index);
+    }
+
+    /// Begin a compacting collection,
+    /// where unpinned live objects are evacuated into a fresh heap.
+    ///
+    /// The evacuated objects are densely packed in the order they are traced,
+    /// so the fragmented pages of the old heap can be released.
+    ///
+    /// ## Safety
+    /// Must only be called at the start of a collection.
+    pub unsafe fn begin_compaction(&self) {
+        let compaction_heap = &mut *self.compaction_heap.get();
+        assert!(compaction_heap.is_none(), "Already compacting");
+        *compaction_heap = Some(HeapAllocator::new());
+    }
+
+    #[inline]
+    pub fn is_compacting(&self) -> bool {
+        unsafe { (*self.compaction_heap.get()).is_some() }
+    }
+
+    /// Finish compaction after sweeping,
+    /// making the compacted heap the target of future allocations.
+    ///
+    /// ## Safety
+    /// Must only be called at the end of a collection,
+    /// when nothing else is accessing this space.
+    pub unsafe fn finish_compaction(&self) {
+        let heap = (*self.compaction_heap.get())
+            .take()
+            .expect("Compaction is not in progress");
+        let old_heap = std::mem::replace(&mut *self.heap.get(), heap);
+        // free blocks would otherwise keep the pages of the old heap alive
+        for (ptr, layout) in (*self.size_classes.get()).drain_free() {
+            old_heap.deallocate(ptr, layout);
+        }
+        let epoch = self.heap_epoch.get();
+        self.heap_epoch.set(epoch.wrapping_add(1));
+        // only pinned objects and objects which failed to evacuate were left behind
+        let remaining = (*self.objects.get())
+            .iter()
+            .flatten()
+            .filter(|object| object.heap_epoch == epoch)
+            .count();
+        (*self.retired_heaps.get()).push(RetiredHeap {
+            heap: old_heap,
+            epoch,
+            objects: remaining,
+        });
+        self.prune_retired_heaps();
     }
 
-    unsafe fn free_live_objects(&mut self, cond: ObjectFreeCondition<'_, Id>) {
-        let mut next_index: u32 = 0;
-        self.live_objects.get_mut().retain(|func| {
-            if func.is_none() {
-                return false; // skip null objects, deallocated early
+    /// Free every retired heap whose objects have all been freed.
+    unsafe fn prune_retired_heaps(&self) {
+        let retired_heaps = &mut *self.retired_heaps.get();
+        if retired_heaps.iter().any(|retired| retired.objects == 0) {
+            // destroying a heap would race with any pending frees
+            #[cfg(feature = "concurrent-sweep")]
+            if let Some(sweeper) = (*self.sweeper.get()).as_ref() {
+                sweeper.wait_idle();
             }
-            let header = &mut *func.unwrap().as_ptr();
-            debug_assert_eq!(header.collector_id, self.collector_id);
-            debug_assert_eq!(header.state_bits.get().generation(), GenerationId::Old);
-            let should_free = match cond {
-                ObjectFreeCondition::Unmarked { state } => {
-                    let mark_bits = header.state_bits.get().raw_mark_bits().resolve(state);
-                    match mark_bits {
-                        GcMarkBits::White => true,  // should free
-                        GcMarkBits::Black => false, // should not free
-                    }
-                }
-                ObjectFreeCondition::Always => true, // always free
-            };
-            if should_free {
-                // unmarked (should free)
-                if cfg!(debug_assertions) {
-                    header.alloc_info.live_object_index = u32::MAX;
+            retired_heaps.retain(|retired| retired.objects > 0);
+        }
+    }
+
+    /// Run the destructors of every unmarked object, without freeing them,
+    /// so the following [sweep](Self::sweep) only deallocates.
+    ///
+    /// ## Safety
+    /// Must only be called at the end of a collection,
+    /// when nothing else is accessing this space.
+    pub unsafe fn run_destructors(&self, state: &CollectorState<Id>) {
+        let objects = &mut *self.objects.get();
+        for (word_index, &marked) in (*self.mark_bitmap.get()).words.iter().enumerate() {
+            let page_start = word_index * MarkBitmap::BITS_PER_WORD;
+            let mut unmarked = !marked;
+            while unmarked != 0 {
+                let index = page_start + unmarked.trailing_zeros() as usize;
+                unmarked &= unmarked - 1;
+                if index >= objects.len() {
+                    break;
                 }
-                let overall_layout = if header.state_bits.get().array() {
-                    header.assume_array_header().layout_info().overall_layout()
-                } else {
-                    header.metadata.type_info.layout.overall_layout()
+                let Some(object) = objects[index].as_mut() else {
+                    continue;
                 };
-                self.allocated_bytes.set(
-                    self.allocated_bytes
-                        .get()
-                        .checked_sub(overall_layout.size())
-                        .expect("allocated size underflow"),
-                );
-                // run destructors
-                if header.state_bits.get().array() {
-                    header.assume_array_header().invoke_destructor();
-                } else {
-                    header.invoke_destructor();
+                let state_bits = object.header.as_ref().state_bits.get();
+                if object.needs_drop && !state_bits.forwarded() {
+                    debug_assert_eq!(
+                        state_bits.raw_mark_bits().resolve(state),
+                        GcMarkBits::White,
+                        "Mark bitmap disagrees with header"
+                    );
+                    object.header.as_ref().invoke_destructor();
+                    object.needs_drop = false;
                 }
-                // deallocate memory
-                self.heap
-                    .deallocate(NonNull::from(header).cast(), overall_layout);
-                false
-            } else {
-                // marked (should not free)
-                header.alloc_info.live_object_index = next_index;
-                next_index += 1;
-                true
             }
-        });
-        assert_eq!(next_index as usize, self.live_objects.get_mut().len());
-        if cfg!(debug_assertions) {
-            // second pass to check indexes
-            for (index, live) in self.live_objects.get_mut().iter().enumerate() {
-                let live = live.expect("All `None` objects should be removed");
-                assert_eq!(live.as_ref().alloc_info.live_object_index as usize, index);
+        }
+    }
+
+    /// Free every unmarked object.
+    ///
+    /// ## Safety
+    /// Must only be called at the end of a collection,
+    /// when nothing else is accessing this space.
+    pub unsafe fn sweep(&self, state: &CollectorState<Id>) {
+        let objects = &mut *self.objects.get();
+        let free_indexes = &mut *self.free_indexes.get();
+        let size_classes = &mut *self.size_classes.get();
+        let heap = &*self.heap.get();
+        let heap_epoch = self.heap_epoch.get();
+        let retired_heaps = &mut *self.retired_heaps.get();
+        #[cfg(feature = "concurrent-sweep")]
+        let mut dead_allocations = Vec::new();
+        for (word_index, word) in (*self.mark_bitmap.get()).words.iter_mut().enumerate() {
+            let marked = std::mem::take(word);
+            if marked == u64::MAX {
+                continue; // every object in the page survived
+            }
+            let page_start = word_index * MarkBitmap::BITS_PER_WORD;
+            let mut unmarked = !marked;
+            while unmarked != 0 {
+                let index = page_start + unmarked.trailing_zeros() as usize;
+                unmarked &= unmarked - 1;
+                if index >= objects.len() {
+                    break;
+                }
+                if let Some(mut object) = objects[index].take() {
+                    if object.header.as_ref().state_bits.get().forwarded() {
+                        // evacuated by compaction, so the value now lives elsewhere
+                        object.needs_drop = false;
+                    } else {
+                        debug_assert_eq!(
+                            object
+                                .header
+                                .as_ref()
+                                .state_bits
+                                .get()
+                                .raw_mark_bits()
+                                .resolve(state),
+                            GcMarkBits::White,
+                            "Mark bitmap disagrees with header"
+                        );
+                    }
+                    free_indexes.push(index as u32);
+                    #[cfg(feature = "concurrent-sweep")]
+                    if !object.needs_drop
+                        && object.heap_epoch == heap_epoch
+                        && SizeClasses::block_layout(object.overall_layout).is_none()
+                    {
+                        // nothing can observe the object, so only deallocation is deferred.
+                        // small objects are cheaper to push onto a free list
+                        Self::release_bytes(&self.allocated_bytes, object.overall_layout);
+                        dead_allocations.push(sweeper::DeadAllocation::new(
+                            object.allocation_ptr,
+                            object.overall_layout,
+                        ));
+                        continue;
+                    }
+                    Self::free_object(
+                        heap,
+                        heap_epoch,
+                        retired_heaps,
+                        size_classes,
+                        &self.allocated_bytes,
+                        object,
+                    );
+                }
             }
         }
+        #[cfg(feature = "concurrent-sweep")]
+        (*self.sweeper.get())
+            .get_or_insert_with(sweeper::BackgroundSweeper::spawn)
+            .submit(dead_allocations);
+        for (ptr, layout) in size_classes.trim_free() {
+            heap.deallocate(ptr, layout);
+        }
+        self.prune_retired_heaps();
+    }
+
+    #[inline]
+    fn release_bytes(allocated_bytes: &Cell<usize>, layout: Layout) {
+        allocated_bytes.set(
+            allocated_bytes
+                .get()
+                .checked_sub(layout.size())
+                .expect("allocated size underflow"),
+        );
+    }
+
+    /// Run the destructor of an object and release its memory,
+    /// either to the free list of its size class or to the heap.
+    unsafe fn free_object(
+        heap: &HeapAllocator,
+        heap_epoch: u32,
+        retired_heaps: &mut [RetiredHeap],
+        size_classes: &mut SizeClasses,
+        allocated_bytes: &Cell<usize>,
+        object: OldObject<Id>,
+    ) {
+        Self::release_bytes(allocated_bytes, object.overall_layout);
+        if object.needs_drop {
+            object.header.as_ref().invoke_destructor();
+        }
+        Self::deallocate(heap, heap_epoch, retired_heaps, size_classes, &object);
+    }
+
+    /// Return the memory of an object to the heap it was allocated from.
+    ///
+    /// Objects in a retired heap are deallocated directly,
+    /// since a free block could outlive the heap.
+    /// The caller must [prune](Self::prune_retired_heaps) any heap which becomes empty.
+    unsafe fn deallocate(
+        heap: &HeapAllocator,
+        heap_epoch: u32,
+        retired_heaps: &mut [RetiredHeap],
+        size_classes: &mut SizeClasses,
+        object: &OldObject<Id>,
+    ) {
+        if object.heap_epoch == heap_epoch {
+            if !size_classes.release(object.allocation_ptr, object.overall_layout) {
+                heap.deallocate(object.allocation_ptr, object.overall_layout);
+            }
+            return;
+        }
+        let retired = retired_heaps
+            .iter_mut()
+            .find(|retired| retired.epoch == object.heap_epoch)
+            .expect("Object belongs to an unknown heap");
+        size_classes.record_dealloc(object.overall_layout);
+        retired
+            .heap
+            .deallocate(object.allocation_ptr, object.overall_layout);
+        retired.objects -= 1;
     }
 
     /// Destroy an object whose value has not been initialized
     #[cold]
     pub(super) unsafe fn destroy_uninit_object(&self, header: NonNull<GcHeader<Id>>) {
         assert!(!header.as_ref().state_bits.get().value_initialized());
-        let array = header.as_ref().state_bits.get().array();
-        let overall_layout = if array {
-            header
-                .as_ref()
-                .assume_array_header()
-                .layout_info()
-                .overall_layout()
-        } else {
-            header.as_ref().metadata.type_info.layout.overall_layout()
+        let live_object_index = header.as_ref().alloc_info.live_object_index;
+        let object = {
+            let objects = &mut *self.objects.get();
+            let obj_ref = &mut objects[live_object_index as usize];
+            assert_eq!(obj_ref.as_ref().map(|obj| obj.header), Some(header));
+            obj_ref.take().unwrap() // null out remaining reference
         };
-        {
-            let live_objects = &mut *self.live_objects.get();
-            let live_object_index = header.as_ref().alloc_info.live_object_index as usize;
-            let obj_ref = &mut live_objects[live_object_index];
-            assert_eq!(*obj_ref, Some(header));
-            *obj_ref = None; // null out remaining reference
-        }
-        self.heap.deallocate(header.cast(), overall_layout);
+        (*self.free_indexes.get()).push(live_object_index);
+        Self::deallocate(
+            &*self.heap.get(),
+            self.heap_epoch.get(),
+            &mut *self.retired_heaps.get(),
+            &mut *self.size_classes.get(),
+            &object,
+        );
+        self.prune_retired_heaps();
         self.allocated_bytes.set(
             self.allocated_bytes
                 .get()
-                .checked_sub(overall_layout.size())
+                .checked_sub(object.overall_layout.size())
                 .expect("dealloc size overflow"),
         )
     }
@@ -178,46 +444,178 @@ impl<Id: CollectorId> OldGenerationSpace<Id> {
         &self,
         target: &T,
     ) -> Result<NonNull<T::Header>, OldAllocError> {
+        if self.is_compacting() {
+            // objects promoted while compacting would otherwise be left in the old heap
+            return self.evacuate(target);
+        }
         let overall_layout = target.overall_layout();
-        let raw_ptr = match self.heap.allocate(overall_layout) {
+        let overall_layout = SizeClasses::block_layout(overall_layout).unwrap_or(overall_layout);
+        if let Some(block) = (*self.size_classes.get()).pop_free(overall_layout) {
+            let raw_ptr = NonNull::slice_from_raw_parts(block, overall_layout.size());
+            return self.init_object(raw_ptr, overall_layout, self.heap_epoch.get(), target);
+        }
+        let heap = &*self.heap.get();
+        let raw_ptr = match heap.allocate(overall_layout) {
             Ok(raw_ptr) => raw_ptr,
-            Err(allocator_api2::alloc::AllocError) => return Err(OldAllocError::OutOfMemory),
+            #[cfg(feature = "concurrent-sweep")]
+            Err(AllocError) if (*self.sweeper.get()).is_some() => {
+                // memory may be waiting to be freed by the sweeper
+                (*self.sweeper.get()).as_ref().unwrap().wait_idle();
+                heap.allocate(overall_layout)
+                    .map_err(|AllocError| OldAllocError::OutOfMemory)?
+            }
+            Err(AllocError) => return Err(OldAllocError::OutOfMemory),
         };
+        self.init_object(raw_ptr, overall_layout, self.heap_epoch.get(), target)
+    }
+
+    /// Allocate space in the compacted heap,
+    /// for an object evacuated or promoted by compaction.
+    ///
+    /// The caller must copy the value and state bits of the original object.
+    ///
+    /// ## Safety
+    /// Compaction must be in progress.
+    #[inline]
+    pub unsafe fn evacuate<T: super::RawAllocTarget<Id>>(
+        &self,
+        target: &T,
+    ) -> Result<NonNull<T::Header>, OldAllocError> {
+        let heap = (*self.compaction_heap.get())
+            .as_ref()
+            .expect("Compaction is not in progress");
+        // free blocks belong to the old heap, so they are never reused here
+        let overall_layout = target.overall_layout();
+        let overall_layout = SizeClasses::block_layout(overall_layout).unwrap_or(overall_layout);
+        let Ok(raw_ptr) = heap.allocate(overall_layout) else {
+            return Err(OldAllocError::OutOfMemory);
+        };
+        // the compacted heap replaces the current one once compaction finishes
+        let heap_epoch = self.heap_epoch.get().wrapping_add(1);
+        self.init_object(raw_ptr, overall_layout, heap_epoch, target)
+    }
+
+    #[inline(always)]
+    unsafe fn init_object<T: super::RawAllocTarget<Id>>(
+        &self,
+        raw_ptr: NonNull<[u8]>,
+        overall_layout: Layout,
+        heap_epoch: u32,
+        target: &T,
+    ) -> Result<NonNull<T::Header>, OldAllocError> {
+        (*self.size_classes.get()).record_alloc(overall_layout);
         self.allocated_bytes.set(
             self.allocated_bytes
                 .get()
                 .checked_add(overall_layout.size())
                 .expect("allocated size overflow"),
         );
-        let header_ptr = raw_ptr.cast::<T::Header>();
+        let header_ptr = raw_ptr
+            .cast::<u8>()
+            .add(target.header_offset())
+            .cast::<T::Header>();
+        let object = OldObject {
+            header: header_ptr.cast::<GcHeader<Id>>(),
+            allocation_ptr: raw_ptr.cast::<u8>(),
+            overall_layout,
+            needs_drop: target.needs_drop(),
+            heap_epoch,
+        };
         let live_object_index: u32;
         {
-            let live_objects = &mut *self.live_objects.get();
-            live_object_index = u32::try_from(live_objects.len()).unwrap();
-            live_objects.push(Some(header_ptr.cast::<GcHeader<Id>>()));
+            let objects = &mut *self.objects.get();
+            match (*self.free_indexes.get()).pop() {
+                Some(index) => {
+                    debug_assert!(objects[index as usize].is_none());
+                    objects[index as usize] = Some(object);
+                    live_object_index = index;
+                }
+                None => {
+                    live_object_index = u32::try_from(objects.len()).unwrap();
+                    objects.push(Some(object));
+                    (*self.mark_bitmap.get()).reserve_objects(objects.len());
+                }
+            }
+        }
+        let state_bits = target.init_state_bits(GenerationId::Old);
+        if state_bits.raw_mark_bits().resolve(target.collector_state()) == GcMarkBits::Black {
+            // allocated black during a collection, so the sweep must not free it
+            (*self.mark_bitmap.get()).set(live_object_index as usize);
         }
         target.init_header(
             header_ptr,
-            GcHeader {
-                state_bits: Cell::new(target.init_state_bits(GenerationId::Old)),
-                alloc_info: AllocInfo { live_object_index },
-                metadata: target.header_metadata(),
-                collector_id: self.collector_id,
-            },
+            GcHeader::new(
+                state_bits,
+                AllocInfo { live_object_index },
+                target.header_metadata(),
+                self.collector_id,
+            ),
         );
         Ok(header_ptr)
     }
 
+    /// Every object in this space, including dead objects which have not yet been swept.
+    #[cfg(any(feature = "classify-ptr", debug_assertions))]
+    pub unsafe fn objects(&self) -> impl Iterator<Item = NonNull<GcHeader<Id>>> + '_ {
+        (*self.objects.get())
+            .iter()
+            .flatten()
+            .map(|object| object.header)
+    }
+
+    /// The number of bytes occupied by objects, including dead objects which have not yet been swept.
     #[inline]
     pub fn allocated_bytes(&self) -> usize {
         self.allocated_bytes.get()
     }
+
+    /// The number of bytes in free blocks waiting to be reused,
+    /// which are not returned to the allocator.
+    #[inline]
+    pub fn free_list_bytes(&self) -> usize {
+        unsafe { (*self.size_classes.get()).free_bytes() }
+    }
+
+    /// Describe every page occupied by objects in this space, in address order.
+    ///
+    /// Must not be called during a collection.
+    pub unsafe fn iter_pages(&self) -> impl Iterator<Item = OldPage> {
+        pages::describe_pages(
+            (*self.objects.get())
+                .iter()
+                .flatten()
+                .map(|object| (object.allocation_ptr, object.overall_layout)),
+        )
+    }
+
+    pub fn size_class_stats(&self) -> Vec<SizeClassStats> {
+        unsafe { (*self.size_classes.get()).stats() }
+    }
 }
 impl<Id: CollectorId> Drop for OldGenerationSpace<Id> {
     fn drop(&mut self) {
+        // destroying the heap would race with any pending frees
+        #[cfg(feature = "concurrent-sweep")]
+        drop(self.sweeper.get_mut().take());
         if DROP_NEEDS_EXPLICIT_FREE {
-            unsafe {
-                self.free_live_objects(ObjectFreeCondition::Always);
+            let size_classes = self.size_classes.get_mut();
+            let heap_epoch = self.heap_epoch.get();
+            for object in self.objects.get_mut().drain(..).flatten() {
+                unsafe {
+                    Self::free_object(
+                        self.heap.get_mut(),
+                        heap_epoch,
+                        self.retired_heaps.get_mut(),
+                        size_classes,
+                        &self.allocated_bytes,
+                        object,
+                    );
+                }
+            }
+            for (ptr, layout) in size_classes.drain_free() {
+                unsafe {
+                    self.heap.get_mut().deallocate(ptr, layout);
+                }
             }
         }
     }
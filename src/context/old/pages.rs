@@ -0,0 +1,70 @@
+//! Describing the memory of the old generation page by page,
+//! for analyzing fragmentation outside the crate.
+
+use std::alloc::Layout;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::ptr::NonNull;
+
+/// A page of the old generation,
+/// given by [`GarbageCollector::iter_old_pages`](crate::GarbageCollector::iter_old_pages).
+///
+/// Pages are aligned windows of [`OldPage::SIZE`] bytes of the address space,
+/// matching the small pages of the default allocator.
+/// Only pages which contain part of an object are described.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OldPage {
+    /// The address of the start of the page.
+    pub start: usize,
+    /// The number of bytes in the page which are occupied by objects,
+    /// including dead objects which have not yet been swept.
+    pub used_bytes: usize,
+    /// The number of objects whose allocation starts in the page.
+    pub objects: usize,
+}
+impl OldPage {
+    /// The size of every page.
+    pub const SIZE: usize = 64 * 1024;
+
+    /// The range of addresses covered by the page.
+    #[inline]
+    pub fn address_range(&self) -> Range<usize> {
+        self.start..self.start + Self::SIZE
+    }
+
+    /// The number of bytes in the page which are not occupied by objects.
+    #[inline]
+    pub fn free_bytes(&self) -> usize {
+        Self::SIZE - self.used_bytes
+    }
+}
+
+/// Describe the pages occupied by the specified allocations, in address order.
+///
+/// Allocations spanning multiple pages count towards the used bytes of each one.
+pub(super) fn describe_pages(
+    allocations: impl Iterator<Item = (NonNull<u8>, Layout)>,
+) -> std::collections::btree_map::IntoValues<usize, OldPage> {
+    let mut pages = BTreeMap::<usize, OldPage>::new();
+    for (ptr, layout) in allocations {
+        let start = crate::utils::addr(ptr.as_ptr());
+        let end = start + layout.size();
+        let mut page_start = start - start % OldPage::SIZE;
+        let mut first = true;
+        while page_start < end {
+            let page = pages.entry(page_start).or_insert(OldPage {
+                start: page_start,
+                used_bytes: 0,
+                objects: 0,
+            });
+            page.used_bytes += end.min(page_start + OldPage::SIZE) - start.max(page_start);
+            if first {
+                page.objects += 1;
+                first = false;
+            }
+            page_start += OldPage::SIZE;
+        }
+    }
+    pages.into_values()
+}
@@ -0,0 +1,192 @@
+//! Segregating small old-generation objects by size.
+//!
+//! Each small object occupies a block of its size class,
+//! and the sweep pushes the blocks of dead objects onto a free list for their class.
+//! Allocating an object of the same class pops a block in constant time,
+//! without going through the underlying allocator.
+//! Each sweep returns the free blocks a class is unlikely to reuse to the allocator,
+//! and compaction returns all of them.
+//! Blocks in a heap retired by compaction are never reused.
+
+use std::alloc::Layout;
+use std::ptr::NonNull;
+
+use super::pages::OldPage;
+
+/// The alignment of every block.
+///
+/// Objects with a greater alignment are allocated directly.
+const BLOCK_ALIGN: usize = 16;
+
+/// The size of the blocks in each class, in increasing order.
+///
+/// Classes are spaced by 16 bytes up to 256, then by a quarter of each power of two.
+const BLOCK_SIZES: [usize; 28] = [
+    16, 32, 48, 64, 80, 96, 112, 128, 144, 160, 176, 192, 208, 224, 240, 256, // small
+    320, 384, 448, 512, // quarters of 512
+    640, 768, 896, 1024, // quarters of 1024
+    1280, 1536, 1792, 2048, // quarters of 2048
+];
+
+/// Statistics on one size class of the old generation,
+/// given by [`GarbageCollector::old_size_class_stats`](crate::GarbageCollector::old_size_class_stats).
+///
+/// The free blocks measure fragmentation,
+/// while the ratio of reused blocks to allocations measures how well they are recycled.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SizeClassStats {
+    /// The size of each block in the class, including the object's header.
+    pub block_size: usize,
+    /// The number of objects occupying a block,
+    /// including dead objects which have not yet been swept.
+    pub objects: usize,
+    /// The number of blocks waiting to be reused.
+    pub free_blocks: usize,
+    /// The total number of objects allocated in the class.
+    pub allocations: u64,
+    /// The number of allocations which reused a free block.
+    pub reused: u64,
+}
+
+#[derive(Default)]
+struct SizeClass {
+    free_blocks: Vec<NonNull<u8>>,
+    objects: usize,
+    allocations: u64,
+    reused: u64,
+}
+
+pub(super) struct SizeClasses {
+    classes: [SizeClass; BLOCK_SIZES.len()],
+    /// The total size of the free blocks of every class.
+    free_bytes: usize,
+}
+impl SizeClasses {
+    pub fn new() -> Self {
+        SizeClasses {
+            classes: std::array::from_fn(|_| SizeClass::default()),
+            free_bytes: 0,
+        }
+    }
+
+    /// The layout of the block for an object with the specified layout,
+    /// or `None` if the object is too large or over-aligned to have a size class.
+    ///
+    /// The layout of a block always maps back to the same block.
+    #[inline]
+    pub fn block_layout(layout: Layout) -> Option<Layout> {
+        let class = Self::class_of(layout)?;
+        Some(unsafe { Layout::from_size_align_unchecked(BLOCK_SIZES[class], BLOCK_ALIGN) })
+    }
+
+    #[inline]
+    fn class_of(layout: Layout) -> Option<usize> {
+        if layout.align() > BLOCK_ALIGN || layout.size() > BLOCK_SIZES[BLOCK_SIZES.len() - 1] {
+            return None;
+        }
+        Some(if layout.size() <= 256 {
+            layout.size().saturating_sub(1) / 16
+        } else {
+            BLOCK_SIZES.partition_point(|&size| size < layout.size())
+        })
+    }
+
+    /// Pop a free block for an object with the specified block layout.
+    #[inline]
+    pub fn pop_free(&mut self, block_layout: Layout) -> Option<NonNull<u8>> {
+        let class = &mut self.classes[Self::class_of(block_layout)?];
+        let block = class.free_blocks.pop()?;
+        class.reused += 1;
+        self.free_bytes -= block_layout.size();
+        Some(block)
+    }
+
+    /// Record that an object now occupies a block with the specified layout.
+    #[inline]
+    pub fn record_alloc(&mut self, block_layout: Layout) {
+        if let Some(class) = Self::class_of(block_layout) {
+            let class = &mut self.classes[class];
+            class.objects += 1;
+            class.allocations += 1;
+        }
+    }
+
+    /// Push the block of a dead object onto the free list of its class,
+    /// returning `false` if the block does not belong to a class
+    /// and must be deallocated instead.
+    #[inline]
+    pub fn release(&mut self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let Some(class_index) = Self::class_of(layout) else {
+            return false;
+        };
+        let class = &mut self.classes[class_index];
+        class.objects -= 1;
+        class.free_blocks.push(ptr);
+        self.free_bytes += BLOCK_SIZES[class_index];
+        true
+    }
+
+    /// Record that an object no longer occupies a block,
+    /// without keeping the block for reuse.
+    #[inline]
+    pub fn record_dealloc(&mut self, block_layout: Layout) {
+        if let Some(class) = Self::class_of(block_layout) {
+            self.classes[class].objects -= 1;
+        }
+    }
+
+    /// Remove every free block, so it can be returned to the allocator.
+    pub fn drain_free(&mut self) -> impl Iterator<Item = (NonNull<u8>, Layout)> + '_ {
+        self.free_bytes = 0;
+        self.classes
+            .iter_mut()
+            .zip(BLOCK_SIZES)
+            .flat_map(|(class, size)| {
+                let layout = Layout::from_size_align(size, BLOCK_ALIGN).unwrap();
+                class.free_blocks.drain(..).map(move |ptr| (ptr, layout))
+            })
+    }
+
+    /// Remove the free blocks which each class is unlikely to reuse,
+    /// so they can be returned to the allocator.
+    ///
+    /// Each class keeps as many free blocks as it has live objects,
+    /// or enough to fill a page if that is more.
+    pub fn trim_free(&mut self) -> impl Iterator<Item = (NonNull<u8>, Layout)> + '_ {
+        let free_bytes = &mut self.free_bytes;
+        self.classes
+            .iter_mut()
+            .zip(BLOCK_SIZES)
+            .flat_map(move |(class, size)| {
+                let keep = class.objects.max(OldPage::SIZE / size);
+                let excess = class.free_blocks.len().saturating_sub(keep);
+                *free_bytes -= excess * size;
+                let layout = Layout::from_size_align(size, BLOCK_ALIGN).unwrap();
+                class
+                    .free_blocks
+                    .drain(class.free_blocks.len() - excess..)
+                    .map(move |ptr| (ptr, layout))
+            })
+    }
+
+    /// The total size of the free blocks waiting to be reused.
+    #[inline]
+    pub fn free_bytes(&self) -> usize {
+        self.free_bytes
+    }
+
+    pub fn stats(&self) -> Vec<SizeClassStats> {
+        self.classes
+            .iter()
+            .zip(BLOCK_SIZES)
+            .map(|(class, block_size)| SizeClassStats {
+                block_size,
+                objects: class.objects,
+                free_blocks: class.free_blocks.len(),
+                allocations: class.allocations,
+                reused: class.reused,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,95 @@
+//! A background thread which frees dead objects,
+//! used by the `concurrent-sweep` feature.
+//!
+//! Despite the name of the feature, this only defers deallocation.
+//! Finding dead objects and running their destructors still happens during the pause.
+//! Dead objects are unreachable, so their memory can be released while the mutator runs.
+//! Only objects without destructors are handed to the sweeper,
+//! because destructors may not be safe to run on another thread.
+//! Objects in size classes are also kept,
+//! since pushing them onto a free list is cheaper than sending them to another thread.
+
+use std::alloc::Layout;
+use std::ptr::NonNull;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+/// The memory of a dead object, which is waiting to be freed.
+pub struct DeadAllocation {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+impl DeadAllocation {
+    #[inline]
+    pub fn new(ptr: NonNull<u8>, layout: Layout) -> Self {
+        DeadAllocation { ptr, layout }
+    }
+}
+/// The object is dead, so nothing else refers to its memory.
+unsafe impl Send for DeadAllocation {}
+
+pub struct BackgroundSweeper {
+    sender: Option<Sender<Vec<DeadAllocation>>>,
+    /// The number of batches which have been submitted but not yet freed.
+    pending_batches: Arc<(Mutex<usize>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+impl BackgroundSweeper {
+    pub fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel::<Vec<DeadAllocation>>();
+        let pending_batches = Arc::new((Mutex::new(0usize), Condvar::new()));
+        let thread = std::thread::Builder::new()
+            .name("zerogc-sweeper".into())
+            .spawn({
+                let pending_batches = Arc::clone(&pending_batches);
+                move || {
+                    for batch in receiver {
+                        for dead in batch {
+                            unsafe { super::deallocate_detached(dead.ptr, dead.layout) }
+                        }
+                        let (count, finished) = &*pending_batches;
+                        *count.lock().unwrap() -= 1;
+                        finished.notify_all();
+                    }
+                }
+            })
+            .expect("Failed to spawn sweeper thread");
+        BackgroundSweeper {
+            sender: Some(sender),
+            pending_batches,
+            thread: Some(thread),
+        }
+    }
+
+    /// Submit a batch of dead objects to be freed in the background.
+    pub fn submit(&self, batch: Vec<DeadAllocation>) {
+        if batch.is_empty() {
+            return;
+        }
+        *self.pending_batches.0.lock().unwrap() += 1;
+        self.sender
+            .as_ref()
+            .unwrap()
+            .send(batch)
+            .expect("Sweeper thread died");
+    }
+
+    /// Block until all submitted objects have been freed.
+    pub fn wait_idle(&self) {
+        let (count, finished) = &*self.pending_batches;
+        let guard = count.lock().unwrap();
+        drop(finished.wait_while(guard, |count| *count > 0).unwrap());
+    }
+}
+impl Drop for BackgroundSweeper {
+    fn drop(&mut self) {
+        // closing the channel stops the thread once remaining batches are freed
+        drop(self.sender.take());
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() && !std::thread::panicking() {
+                panic!("Sweeper thread panicked");
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Sharing GC pointers with the worker threads of a [`GarbageCollector::par_scope`](crate::GarbageCollector::par_scope).
+//!
+//! GC pointers are not `Send` or `Sync` by themselves,
+//! because the thread which owns the collector can mutate object headers at any time
+//! (for example by pinning the object, or growing an array in place).
+//! Within a parallel scope the collector is unreachable from every thread,
+//! so the objects are effectively immutable and can be shared.
+
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use crate::array::{GcArray, GcSlice, GcString};
+use crate::{CollectorId, Gc, GcField};
+
+/// GC pointers which can be shared by a [`GarbageCollector::par_scope`](crate::GarbageCollector::par_scope).
+///
+/// This is implemented for GC pointers whose values are `Sync`,
+/// and tuples of such pointers.
+///
+/// ## Safety
+/// Given that the collector is unreachable,
+/// it must be safe to access the pointers from multiple threads at once.
+pub unsafe trait ParShare {}
+unsafe impl<T: Sync, Id: CollectorId + Sync> ParShare for Gc<'_, T, Id> {}
+unsafe impl<T: Sync, Id: CollectorId + Sync> ParShare for GcArray<'_, T, Id> {}
+unsafe impl<T: Sync, Id: CollectorId + Sync> ParShare for GcSlice<'_, T, Id> {}
+unsafe impl<Id: CollectorId + Sync> ParShare for GcString<'_, Id> {}
+unsafe impl<T: Sync, F: Sync, Id: CollectorId + Sync> ParShare for GcField<'_, T, F, Id> {}
+macro_rules! impl_par_share_tuple {
+    ($($param:ident),*) => {
+        unsafe impl<$($param: ParShare),*> ParShare for ($($param,)*) {}
+    };
+}
+impl_par_share_tuple!(A);
+impl_par_share_tuple!(A, B);
+impl_par_share_tuple!(A, B, C);
+impl_par_share_tuple!(A, B, C, D);
+
+/// GC pointers shared with the worker threads of a [`GarbageCollector::par_scope`](crate::GarbageCollector::par_scope).
+///
+/// The lifetime is unique to each scope,
+/// so the pointers cannot escape after the collector becomes usable again.
+pub struct ParShared<'scope, P: 'scope> {
+    ptr: P,
+    // invariant, so the scope cannot be extended
+    marker: PhantomData<&'scope mut &'scope ()>,
+}
+impl<P: ParShare> ParShared<'_, P> {
+    /// Share the specified pointers.
+    ///
+    /// ## Safety
+    /// The collector must be unreachable for the duration of the scope.
+    #[inline]
+    pub(super) unsafe fn new(ptr: P) -> Self {
+        ParShared {
+            ptr,
+            marker: PhantomData,
+        }
+    }
+}
+/// Unreachable collectors make every [`ParShare`] pointer safe to share.
+unsafe impl<P: ParShare> Send for ParShared<'_, P> {}
+unsafe impl<P: ParShare> Sync for ParShared<'_, P> {}
+impl<P> Deref for ParShared<'_, P> {
+    type Target = P;
+
+    #[inline]
+    fn deref(&self) -> &P {
+        &self.ptr
+    }
+}
+impl<P: Copy> Copy for ParShared<'_, P> {}
+impl<P: Copy> Clone for ParShared<'_, P> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
@@ -0,0 +1,201 @@
+//! Counts of where GC memory is allocated, grouped by call site and type.
+//!
+//! Only available with the `profile` feature.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::panic::Location;
+use std::ptr::NonNull;
+use std::time::Instant;
+
+use crate::context::layout::GcHeader;
+use crate::CollectorId;
+
+#[derive(Copy, Clone, Default)]
+struct SiteCounters {
+    count: u64,
+    bytes: u64,
+}
+
+/// Records the call site of every allocation.
+pub(crate) struct AllocProfiler {
+    sites: RefCell<HashMap<(&'static Location<'static>, &'static str), SiteCounters>>,
+    started: RefCell<Instant>,
+}
+impl AllocProfiler {
+    pub fn new() -> Self {
+        AllocProfiler {
+            sites: RefCell::new(HashMap::new()),
+            started: RefCell::new(Instant::now()),
+        }
+    }
+
+    #[inline]
+    pub unsafe fn record_alloc<Id: CollectorId>(
+        &self,
+        header: NonNull<GcHeader<Id>>,
+        size: usize,
+        allocated_at: &'static Location<'static>,
+    ) {
+        let type_name = header.as_ref().type_info().type_name();
+        let mut sites = self.sites.borrow_mut();
+        let counters = sites.entry((allocated_at, type_name)).or_default();
+        counters.count += 1;
+        counters.bytes += size as u64;
+    }
+
+    pub fn report(&self) -> AllocProfile {
+        let mut sites = self
+            .sites
+            .borrow()
+            .iter()
+            .map(|(&(location, type_name), counters)| AllocSite {
+                location,
+                type_name,
+                count: counters.count,
+                bytes: counters.bytes,
+            })
+            .collect::<Vec<_>>();
+        sites.sort_by(|a, b| {
+            b.bytes
+                .cmp(&a.bytes)
+                .then_with(|| a.location.file().cmp(b.location.file()))
+                .then_with(|| a.location.line().cmp(&b.location.line()))
+                .then_with(|| a.location.column().cmp(&b.location.column()))
+                .then_with(|| a.type_name.cmp(b.type_name))
+        });
+        AllocProfile {
+            sites,
+            elapsed_micros: self.started.borrow().elapsed().as_micros(),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.sites.borrow_mut().clear();
+        *self.started.borrow_mut() = Instant::now();
+    }
+}
+
+/// The allocations made at a single call site for a single type.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AllocSite {
+    /// The location of the call to the allocation method.
+    pub location: &'static Location<'static>,
+    /// The name of the allocated type.
+    pub type_name: &'static str,
+    /// The number of objects allocated.
+    pub count: u64,
+    /// The number of bytes allocated, including object headers.
+    pub bytes: u64,
+}
+
+/// A report of where GC memory has been allocated,
+/// returned by [`GarbageCollector::alloc_profile`](crate::GarbageCollector::alloc_profile).
+///
+/// Allocations are attributed to the caller of the public allocation method,
+/// so wrappers need `#[track_caller]` to attribute allocations to their own callers.
+#[derive(Debug, Clone)]
+pub struct AllocProfile {
+    sites: Vec<AllocSite>,
+    elapsed_micros: u128,
+}
+impl AllocProfile {
+    /// The allocation sites, from the most bytes allocated to the least.
+    #[inline]
+    pub fn sites(&self) -> &[AllocSite] {
+        &self.sites
+    }
+
+    /// The total number of objects allocated.
+    pub fn total_count(&self) -> u64 {
+        self.sites.iter().map(|site| site.count).sum()
+    }
+
+    /// The total number of bytes allocated.
+    pub fn total_bytes(&self) -> u64 {
+        self.sites.iter().map(|site| site.bytes).sum()
+    }
+
+    /// Write the profile in the collapsed stack format used by `flamegraph.pl` and `inferno`.
+    ///
+    /// Each line is a stack of the call site and the allocated type,
+    /// weighted by the number of bytes allocated.
+    /// Semicolons in type names are replaced by commas,
+    /// since they separate the frames of a stack.
+    pub fn write_folded(&self, out: &mut impl Write) -> io::Result<()> {
+        for site in &self.sites {
+            writeln!(
+                out,
+                "{};{} {}",
+                site.location,
+                site.type_name.replace(';', ","),
+                site.bytes
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the profile as a JSON file for the [DHAT viewer](https://nnethercote.github.io/dh_view/dh_view.html).
+    ///
+    /// Each site is a program point with a single frame,
+    /// giving the allocated type and the call site.
+    /// Block lifetimes are not tracked.
+    pub fn write_dhat_json(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut frames = vec!["[root]".to_string()];
+        let mut program_points = String::new();
+        for (index, site) in self.sites.iter().enumerate() {
+            if index > 0 {
+                program_points.push_str(",\n");
+            }
+            frames.push(format!("{} ({})", site.type_name, site.location));
+            write!(
+                program_points,
+                "{{\"tb\":{},\"tbk\":{},\"fs\":[{}]}}",
+                site.bytes,
+                site.count,
+                frames.len() - 1
+            )
+            .unwrap();
+        }
+        let mut frame_table = String::new();
+        for (index, frame) in frames.iter().enumerate() {
+            if index > 0 {
+                frame_table.push_str(",\n");
+            }
+            write_json_string(&mut frame_table, frame);
+        }
+        let mut command = String::new();
+        write_json_string(
+            &mut command,
+            &std::env::args().collect::<Vec<_>>().join(" "),
+        );
+        writeln!(
+            out,
+            "{{\"dhatFileVersion\":2,\"mode\":\"zerogc\",\"verb\":\"Allocated\",\
+            \"bklt\":false,\"bkacc\":false,\"tu\":\"µs\",\"Mtu\":\"s\",\
+            \"cmd\":{command},\"pid\":{pid},\"te\":{elapsed},\n\
+            \"pps\":[\n{program_points}\n],\n\"ftbl\":[\n{frame_table}\n]}}",
+            pid = std::process::id(),
+            elapsed = self.elapsed_micros,
+        )
+    }
+}
+
+fn write_json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
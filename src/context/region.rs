@@ -0,0 +1,21 @@
+//! Regions, which are separate nurseries that can be discarded independently.
+//!
+//! These are intended for actor-style runtimes,
+//! where each actor allocates many short-lived objects
+//! which can be freed at once when it finishes handling a message.
+
+use crate::CollectorId;
+
+/// A separate nursery within the young generation,
+/// created by [`GarbageCollector::create_region`](crate::GarbageCollector::create_region).
+///
+/// Objects are allocated in a region [while it is active](crate::GarbageCollector::in_region).
+/// Like any other young object, those which survive a collection are evacuated out of the region,
+/// so every collection leaves every region empty.
+/// A region can also be [discarded](crate::GarbageCollector::discard_region) without tracing,
+/// if none of its objects are referenced from outside of it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GcRegion<Id: CollectorId> {
+    pub(super) index: usize,
+    pub(super) id: Id,
+}
@@ -0,0 +1,192 @@
+//! Recording the allocations and collections of a collector,
+//! so they can be replayed deterministically against a fresh collector.
+//!
+//! Only available with the `record-replay` feature.
+
+use std::alloc::Layout;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+
+use crate::context::layout::GcArrayLayoutInfo;
+use crate::context::CollectionReason;
+use crate::{CollectorId, GarbageCollector};
+
+/// An event recorded by [`GarbageCollector::start_recording`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TraceEvent {
+    /// An object was allocated.
+    Alloc {
+        /// The name of the allocated type.
+        type_name: Cow<'static, str>,
+        /// The size of the object, including its header.
+        size: usize,
+    },
+    /// A collection was performed.
+    Collect { reason: CollectionReason },
+}
+
+/// An error parsing a recorded [`AllocTrace`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum TraceParseError {
+    #[error("Failed to read trace")]
+    Io(#[from] io::Error),
+    #[error("Invalid trace event on line {line}")]
+    InvalidEvent { line: usize },
+}
+
+/// A sequence of allocations and collections recorded from a collector.
+///
+/// Only the sizes and types of objects are recorded, not their contents or liveness.
+/// Replaying with [`AllocTrace::replay`] leaves every object unreachable,
+/// while [`AllocTrace::replay_with`] lets the caller decide what to allocate and keep alive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AllocTrace {
+    events: Vec<TraceEvent>,
+}
+impl AllocTrace {
+    #[inline]
+    pub fn new() -> Self {
+        AllocTrace { events: Vec::new() }
+    }
+
+    /// The recorded events, in order.
+    #[inline]
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    #[inline]
+    pub fn push(&mut self, event: TraceEvent) {
+        self.events.push(event);
+    }
+
+    /// Write the trace in a line-based text format,
+    /// which can be read back by [`AllocTrace::read`].
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        for event in &self.events {
+            match *event {
+                TraceEvent::Alloc {
+                    ref type_name,
+                    size,
+                } => writeln!(out, "alloc {size} {type_name}")?,
+                TraceEvent::Collect { reason } => writeln!(out, "collect {reason:?}")?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a trace written by [`AllocTrace::write`].
+    pub fn read(input: impl BufRead) -> Result<Self, TraceParseError> {
+        let mut trace = AllocTrace::new();
+        for (index, line) in input.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event =
+                parse_event(&line).ok_or(TraceParseError::InvalidEvent { line: index + 1 })?;
+            trace.push(event);
+        }
+        Ok(trace)
+    }
+
+    /// Replay the trace against a collector,
+    /// allocating a zeroed byte array of the same size for each object.
+    ///
+    /// None of the replayed objects are kept alive.
+    pub fn replay<Id: CollectorId>(&self, collector: &mut GarbageCollector<Id>) {
+        let header_size = GcArrayLayoutInfo::<Id>::new(Layout::new::<u8>(), 0)
+            .unwrap()
+            .overall_layout()
+            .size();
+        self.replay_with(collector, |collector, _type_name, size| {
+            collector.alloc_bytes_zeroed(size.saturating_sub(header_size));
+        });
+    }
+
+    /// Replay the trace against a collector,
+    /// calling the closure to perform each recorded allocation.
+    ///
+    /// The closure is given the type name and size of the recorded object.
+    /// Objects which should survive collections must be rooted by the closure.
+    /// Collections are performed at exactly the recorded points, for the recorded reasons,
+    /// regardless of whether the replaying collector would otherwise collect.
+    pub fn replay_with<Id: CollectorId>(
+        &self,
+        collector: &mut GarbageCollector<Id>,
+        mut alloc: impl FnMut(&GarbageCollector<Id>, &str, usize),
+    ) {
+        for event in &self.events {
+            match *event {
+                TraceEvent::Alloc {
+                    ref type_name,
+                    size,
+                } => alloc(collector, type_name, size),
+                TraceEvent::Collect {
+                    reason: CollectionReason::Compaction,
+                } => collector.compact(),
+                TraceEvent::Collect { reason } => collector.collect_for(reason),
+            }
+        }
+    }
+}
+
+fn parse_event(line: &str) -> Option<TraceEvent> {
+    let (kind, rest) = line.split_once(' ')?;
+    match kind {
+        "alloc" => {
+            let (size, type_name) = rest.split_once(' ')?;
+            Some(TraceEvent::Alloc {
+                type_name: Cow::Owned(type_name.to_owned()),
+                size: size.parse().ok()?,
+            })
+        }
+        "collect" => {
+            let reason = CollectionReason::ALL
+                .into_iter()
+                .find(|reason| format!("{reason:?}") == rest.trim())?;
+            Some(TraceEvent::Collect { reason })
+        }
+        _ => None,
+    }
+}
+
+/// Records events while a recording is in progress.
+pub(crate) struct TraceRecorder {
+    trace: RefCell<Option<AllocTrace>>,
+}
+impl TraceRecorder {
+    pub fn new() -> Self {
+        TraceRecorder {
+            trace: RefCell::new(None),
+        }
+    }
+
+    #[inline]
+    pub fn record_alloc(&self, type_name: &'static str, size: usize) {
+        if let Some(ref mut trace) = *self.trace.borrow_mut() {
+            trace.push(TraceEvent::Alloc {
+                type_name: Cow::Borrowed(type_name),
+                size,
+            });
+        }
+    }
+
+    #[inline]
+    pub fn record_collect(&self, reason: CollectionReason) {
+        if let Some(ref mut trace) = *self.trace.borrow_mut() {
+            trace.push(TraceEvent::Collect { reason });
+        }
+    }
+
+    pub fn start(&self) {
+        *self.trace.borrow_mut() = Some(AllocTrace::new());
+    }
+
+    pub fn finish(&self) -> Option<AllocTrace> {
+        self.trace.borrow_mut().take()
+    }
+}
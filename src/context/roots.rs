@@ -0,0 +1,97 @@
+//! Enumerating the roots registered with a collector,
+//! for debuggers and for checking the rooting discipline of the host.
+
+use std::panic::Location;
+use std::ptr::NonNull;
+
+use crate::context::classify::HeapObject;
+use crate::context::layout::GcHeader;
+use crate::{CollectorId, GcError};
+
+/// How a root was registered with the collector.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RootKind {
+    /// A [`GcHandle`](crate::GcHandle), or another root owned by the host.
+    Handle,
+    /// A [`GcPin`](crate::GcPin), which also prevents the object from moving.
+    Pin,
+    /// A slot in a [`HandleScope`](crate::HandleScope).
+    HandleScope,
+}
+
+/// A root found by [`GarbageCollector::iter_roots`](crate::GarbageCollector::iter_roots).
+#[derive(Debug, Copy, Clone)]
+#[non_exhaustive]
+pub struct RootInfo {
+    /// How the root was registered.
+    pub kind: RootKind,
+    /// The object the root refers to.
+    pub object: HeapObject,
+    /// Where the root was created.
+    ///
+    /// This is only recorded with `debug_assertions`,
+    /// and never for the slots of a handle scope.
+    pub location: Option<&'static Location<'static>>,
+}
+impl RootInfo {
+    /// ## Safety
+    /// The header must be a live object.
+    pub(super) unsafe fn new<Id: CollectorId>(
+        kind: RootKind,
+        header: NonNull<GcHeader<Id>>,
+        location: Option<&'static Location<'static>>,
+    ) -> Self {
+        RootInfo {
+            kind,
+            object: HeapObject::from_header(header),
+            location,
+        }
+    }
+}
+
+/// The slots in the list of roots, given by [`GarbageCollector::root_slot_stats`](crate::GarbageCollector::root_slot_stats).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RootSlotStats {
+    /// Slots whose root is still alive.
+    pub live: usize,
+    /// Slots whose root has been dropped,
+    /// which are removed by the next collection or [compaction](crate::GarbageCollector::compact_roots).
+    pub stale: usize,
+}
+
+/// Whether the roots of a collector can still be used.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum RootState {
+    Alive,
+    /// The collector has been dropped, freeing every object.
+    CollectorDropped,
+    /// Tracing panicked, leaving the heap in an inconsistent state.
+    Poisoned,
+}
+impl RootState {
+    #[inline]
+    #[track_caller]
+    pub fn assert_usable(self) {
+        match self {
+            RootState::Alive => {}
+            RootState::CollectorDropped => {
+                panic!("Cannot use a root after its collector has been dropped")
+            }
+            RootState::Poisoned => {
+                panic!("Cannot use a root after its collector was poisoned by a tracing failure")
+            }
+        }
+    }
+
+    /// The error for using a root in this state, if it cannot be used.
+    #[inline]
+    pub fn error(self) -> Option<GcError> {
+        match self {
+            RootState::Alive => None,
+            RootState::CollectorDropped => Some(GcError::InvalidHandle),
+            RootState::Poisoned => Some(GcError::Poisoned),
+        }
+    }
+}
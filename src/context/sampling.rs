@@ -0,0 +1,76 @@
+//! Sampling every Nth allocation,
+//! which is cheap enough to leave enabled in production.
+//!
+//! Unlike the `profile` feature, this is always available,
+//! and only costs a counter decrement per allocation.
+
+use std::cell::Cell;
+use std::num::NonZeroU64;
+
+use crate::utils::CallbackSlot;
+
+/// An allocation chosen by the [sampler](crate::GarbageCollector::set_alloc_sampler).
+#[derive(Debug, Copy, Clone)]
+#[non_exhaustive]
+pub struct AllocSample {
+    /// The name of the allocated type.
+    ///
+    /// For arrays, this is the name of the element type.
+    pub type_name: &'static str,
+    /// The size of the allocation, including its header.
+    pub size: usize,
+    /// Whether the allocation is an array.
+    pub array: bool,
+}
+
+type SampleCallback = Box<dyn FnMut(AllocSample)>;
+
+pub(crate) struct AllocSampler {
+    /// The number of allocations between samples, or zero if sampling is disabled.
+    rate: Cell<u64>,
+    /// The number of allocations remaining until the next sample.
+    countdown: Cell<u64>,
+    callback: CallbackSlot<dyn FnMut(AllocSample)>,
+}
+impl AllocSampler {
+    pub fn new() -> Self {
+        AllocSampler {
+            rate: Cell::new(0),
+            countdown: Cell::new(0),
+            callback: CallbackSlot::new(),
+        }
+    }
+
+    pub fn set(&self, rate: NonZeroU64, callback: SampleCallback) {
+        self.rate.set(rate.get());
+        self.countdown.set(rate.get());
+        self.callback.set(Some(callback));
+    }
+
+    pub fn clear(&self) {
+        self.rate.set(0);
+        self.countdown.set(0);
+        self.callback.set(None);
+    }
+
+    /// Count an allocation, returning `true` if it should be sampled.
+    #[inline]
+    pub fn tick(&self) -> bool {
+        match self.countdown.get() {
+            0 => false, // disabled
+            1 => {
+                self.countdown.set(self.rate.get());
+                true
+            }
+            remaining => {
+                self.countdown.set(remaining - 1);
+                false
+            }
+        }
+    }
+
+    #[cold]
+    pub fn sample(&self, sample: AllocSample) {
+        self.callback.call(|callback| callback(sample));
+    }
+}
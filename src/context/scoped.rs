@@ -0,0 +1,56 @@
+//! A safe, closure-scoped entry point for creating a [`GarbageCollector`].
+
+use std::num::NonZeroU64;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::context::{GarbageCollector, GcConfig, SingletonStatus};
+use crate::CollectorId;
+
+/// A [`CollectorId`] which is guaranteed to be unique,
+/// used by [`GarbageCollector::scoped`].
+///
+/// Ids are never reused within a process.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[repr(C)]
+pub struct ScopedId(NonZeroU64);
+impl ScopedId {
+    fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        // even allocating an id every nanosecond would take centuries to overflow
+        assert!(id < u64::MAX, "Exhausted all scoped collector ids");
+        ScopedId(NonZeroU64::new(id).unwrap())
+    }
+}
+unsafe impl CollectorId for ScopedId {
+    const SINGLETON: Option<SingletonStatus> = None;
+
+    #[inline]
+    unsafe fn summon_singleton() -> Option<Self> {
+        None
+    }
+}
+
+impl GarbageCollector<ScopedId> {
+    /// Run the specified closure with a fresh garbage collector,
+    /// which is destroyed once the closure returns.
+    ///
+    /// This is entirely safe, because the collector is given a unique [`ScopedId`].
+    #[inline]
+    pub fn scoped<R>(func: impl FnOnce(&mut GarbageCollector<ScopedId>) -> R) -> R {
+        Self::scoped_with_config(GcConfig::default(), func)
+    }
+
+    /// Run the specified closure with a fresh garbage collector,
+    /// using the specified configuration.
+    ///
+    /// See [`Self::scoped`] for details.
+    pub fn scoped_with_config<R>(
+        config: GcConfig,
+        func: impl FnOnce(&mut GarbageCollector<ScopedId>) -> R,
+    ) -> R {
+        // SAFETY: The id is unique
+        let mut collector = unsafe { GarbageCollector::new_unchecked(ScopedId::next(), config) };
+        func(&mut collector)
+    }
+}
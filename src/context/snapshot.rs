@@ -0,0 +1,470 @@
+//! Snapshots of the heap, which can be saved and later restored.
+//!
+//! Only objects reachable from the registered snapshot roots are saved.
+//! Each object is encoded by the [`SnapshotType`] codec of its type,
+//! which refers to other objects by their index in the snapshot.
+//!
+//! Objects are written after all the objects they refer to,
+//! so they can be decoded in a single pass.
+//! As a consequence, snapshots cannot contain cycles.
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::ptr::NonNull;
+
+use crate::context::layout::GcHeader;
+use crate::{Collect, CollectorId, GarbageCollector, Gc, GcHandle};
+
+const MAGIC: [u8; 8] = *b"ZGCSNAP\0";
+const VERSION: u32 = 1;
+
+/// A type which can be saved in a heap snapshot.
+///
+/// Like [`GcHandle`], this is implemented for the `'static` version of a type,
+/// and its codec operates on [`Collect::Collected`].
+pub trait SnapshotType<Id: CollectorId>: Collect<Id> + 'static {
+    /// The name identifying this type within a snapshot.
+    ///
+    /// This must be unique among the types registered with a collector.
+    const TYPE_NAME: &'static str;
+
+    fn encode<'gc>(
+        value: &Self::Collected<'gc>,
+        encoder: &mut SnapshotEncoder<'_, 'gc, Id>,
+    ) -> Result<(), SnapshotError>;
+
+    fn decode<'gc>(
+        decoder: &mut SnapshotDecoder<'_, 'gc, Id>,
+    ) -> Result<Self::Collected<'gc>, SnapshotError>;
+}
+
+/// An error saving or restoring a heap snapshot.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum SnapshotError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid snapshot: {0}")]
+    InvalidFormat(&'static str),
+    #[error("Unsupported snapshot version {version}")]
+    UnsupportedVersion { version: u32 },
+    #[error("Type {name:?} is not registered")]
+    UnregisteredType { name: String },
+    #[error("Type already registered with name {name:?}")]
+    DuplicateName { name: String },
+    #[error("Expected object of type {expected:?}, but got {actual:?}")]
+    TypeMismatch {
+        expected: &'static str,
+        actual: &'static str,
+    },
+    /// An object refers back to itself, which cannot be represented in a snapshot.
+    #[error("Cyclic reference to object")]
+    Cycle,
+}
+
+type DecodeFunc<Id> =
+    for<'a, 'gc> fn(&mut SnapshotDecoder<'a, 'gc, Id>) -> Result<NonNull<()>, SnapshotError>;
+type RootFunc<Id> = unsafe fn(&GarbageCollector<Id>, NonNull<()>) -> RootEntry<Id>;
+
+fn decode_object<T: SnapshotType<Id>, Id: CollectorId>(
+    decoder: &mut SnapshotDecoder<'_, '_, Id>,
+) -> Result<NonNull<()>, SnapshotError> {
+    let value = T::decode(decoder)?;
+    let ptr = decoder.collector.alloc(value);
+    Ok(unsafe { ptr.as_raw_ptr().cast() })
+}
+
+/// ## Safety
+/// The value must be a live object of type `T`.
+unsafe fn root_object<T: SnapshotType<Id>, Id: CollectorId>(
+    collector: &GarbageCollector<Id>,
+    value: NonNull<()>,
+) -> RootEntry<Id> {
+    let ptr = Gc::<'_, T::Collected<'_>, Id>::from_raw_ptr(value.cast());
+    RootEntry::new(collector.root_raw::<T>(NonNull::from(ptr.header())))
+}
+
+struct TypeEntry<Id: CollectorId> {
+    type_id: TypeId,
+    decode: DecodeFunc<Id>,
+    root: RootFunc<Id>,
+}
+
+struct RootEntry<Id: CollectorId> {
+    /// The [`GcHandle`], with its type erased.
+    handle: Box<dyn Any>,
+    encode:
+        for<'a, 'gc> fn(&dyn Any, &mut SnapshotEncoder<'a, 'gc, Id>) -> Result<u64, SnapshotError>,
+}
+impl<Id: CollectorId> RootEntry<Id> {
+    fn new<T: SnapshotType<Id>>(handle: GcHandle<T, Id>) -> Self {
+        fn encode<T: SnapshotType<Id>, Id: CollectorId>(
+            handle: &dyn Any,
+            encoder: &mut SnapshotEncoder<'_, '_, Id>,
+        ) -> Result<u64, SnapshotError> {
+            let handle = handle.downcast_ref::<GcHandle<T, Id>>().unwrap();
+            let ptr = handle.resolve(encoder.collector);
+            encoder.object_index::<T>(ptr)
+        }
+        RootEntry {
+            handle: Box::new(handle),
+            encode: encode::<T, Id>,
+        }
+    }
+}
+
+/// The snapshot types and roots registered with a collector.
+pub(crate) struct SnapshotRegistry<Id: CollectorId> {
+    types: RefCell<HashMap<&'static str, TypeEntry<Id>>>,
+    roots: RefCell<BTreeMap<String, RootEntry<Id>>>,
+}
+impl<Id: CollectorId> SnapshotRegistry<Id> {
+    pub fn new() -> Self {
+        SnapshotRegistry {
+            types: RefCell::new(HashMap::new()),
+            roots: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn register_type<T: SnapshotType<Id>>(&self) -> Result<(), SnapshotError> {
+        let mut types = self.types.borrow_mut();
+        if let Some(existing) = types.get(T::TYPE_NAME) {
+            return if existing.type_id == TypeId::of::<T>() {
+                Ok(())
+            } else {
+                Err(SnapshotError::DuplicateName {
+                    name: T::TYPE_NAME.into(),
+                })
+            };
+        }
+        types.insert(
+            T::TYPE_NAME,
+            TypeEntry {
+                type_id: TypeId::of::<T>(),
+                decode: decode_object::<T, Id>,
+                root: root_object::<T, Id>,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn register_root<T: SnapshotType<Id>>(&self, name: String, handle: GcHandle<T, Id>) {
+        self.roots.borrow_mut().insert(name, RootEntry::new(handle));
+    }
+
+    pub fn unregister_root(&self, name: &str) -> bool {
+        self.roots.borrow_mut().remove(name).is_some()
+    }
+
+    pub fn root<T: SnapshotType<Id>>(&self, name: &str) -> Option<GcHandle<T, Id>> {
+        self.roots
+            .borrow()
+            .get(name)?
+            .handle
+            .downcast_ref::<GcHandle<T, Id>>()
+            .cloned()
+    }
+
+    pub fn snapshot(
+        &self,
+        collector: &GarbageCollector<Id>,
+        out: &mut dyn Write,
+    ) -> Result<(), SnapshotError> {
+        let types = self.types.borrow();
+        let roots = self.roots.borrow();
+        let mut encoder = SnapshotEncoder {
+            collector,
+            types: &types,
+            indexes: HashMap::new(),
+            objects: Vec::new(),
+            buffer: Vec::new(),
+        };
+        let root_indexes = roots
+            .values()
+            .map(|root| (root.encode)(&*root.handle, &mut encoder))
+            .collect::<Result<Vec<u64>, _>>()?;
+        out.write_all(&MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&(encoder.objects.len() as u64).to_le_bytes())?;
+        for (type_name, payload) in &encoder.objects {
+            write_bytes(out, type_name.as_bytes())?;
+            write_bytes(out, payload)?;
+        }
+        out.write_all(&(roots.len() as u64).to_le_bytes())?;
+        for (name, index) in roots.keys().zip(root_indexes) {
+            write_bytes(out, name.as_bytes())?;
+            out.write_all(&index.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn restore(
+        &self,
+        collector: &GarbageCollector<Id>,
+        input: &mut dyn Read,
+    ) -> Result<(), SnapshotError> {
+        let types = self.types.borrow();
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(SnapshotError::InvalidFormat("Missing header"));
+        }
+        let version = u32::from_le_bytes(read_array(input)?);
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion { version });
+        }
+        let num_objects = u64::from_le_bytes(read_array(input)?);
+        let mut objects: Vec<DecodedObject> = Vec::new();
+        for _ in 0..num_objects {
+            let type_name = read_string(input)?;
+            let (&type_name, entry) =
+                types
+                    .get_key_value(&*type_name)
+                    .ok_or(SnapshotError::UnregisteredType {
+                        name: type_name.clone(),
+                    })?;
+            let payload = read_bytes(input)?;
+            let mut decoder = SnapshotDecoder {
+                collector,
+                objects: &objects,
+                payload: &payload,
+            };
+            let ptr = (entry.decode)(&mut decoder)?;
+            if !decoder.payload.is_empty() {
+                return Err(SnapshotError::InvalidFormat("Trailing bytes in object"));
+            }
+            objects.push(DecodedObject {
+                type_id: entry.type_id,
+                type_name,
+                ptr,
+            });
+        }
+        let num_roots = u64::from_le_bytes(read_array(input)?);
+        let mut roots = BTreeMap::new();
+        for _ in 0..num_roots {
+            let name = read_string(input)?;
+            let index = u64::from_le_bytes(read_array(input)?);
+            let object = usize::try_from(index)
+                .ok()
+                .and_then(|index| objects.get(index))
+                .ok_or(SnapshotError::InvalidFormat(
+                    "Root refers to unknown object",
+                ))?;
+            let entry = &types[object.type_name];
+            // SAFETY: The object was decoded by the codec for this type
+            roots.insert(name, unsafe { (entry.root)(collector, object.ptr) });
+        }
+        // only replace the roots once the entire snapshot is valid
+        self.roots.borrow_mut().extend(roots);
+        Ok(())
+    }
+}
+
+fn write_bytes(out: &mut dyn Write, bytes: &[u8]) -> std::io::Result<()> {
+    out.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    out.write_all(bytes)
+}
+
+fn read_array<const N: usize>(input: &mut dyn Read) -> std::io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_bytes(input: &mut dyn Read) -> Result<Vec<u8>, SnapshotError> {
+    let len = u64::from_le_bytes(read_array(input)?);
+    // avoid trusting the length for the initial allocation
+    let mut bytes = Vec::new();
+    input.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(SnapshotError::InvalidFormat("Unexpected end of snapshot"));
+    }
+    Ok(bytes)
+}
+
+fn read_string(input: &mut dyn Read) -> Result<String, SnapshotError> {
+    String::from_utf8(read_bytes(input)?).map_err(|_| SnapshotError::InvalidFormat("Invalid UTF-8"))
+}
+
+/// Encodes objects for a heap snapshot,
+/// passed to [`SnapshotType::encode`].
+///
+/// The value of the object is written using the [`Write`] implementation,
+/// or the helper methods.
+pub struct SnapshotEncoder<'a, 'gc, Id: CollectorId> {
+    collector: &'gc GarbageCollector<Id>,
+    types: &'a HashMap<&'static str, TypeEntry<Id>>,
+    /// The index of each object in the snapshot,
+    /// or `None` if the object is still being encoded.
+    indexes: HashMap<NonNull<GcHeader<Id>>, Option<u64>>,
+    objects: Vec<(&'static str, Vec<u8>)>,
+    /// The encoded value of the current object.
+    buffer: Vec<u8>,
+}
+impl<'a, 'gc, Id: CollectorId> SnapshotEncoder<'a, 'gc, Id> {
+    #[inline]
+    pub fn collector(&self) -> &'gc GarbageCollector<Id> {
+        self.collector
+    }
+
+    #[inline]
+    pub fn write_u64(&mut self, value: u64) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Write a byte slice, prefixed by its length.
+    #[inline]
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u64(bytes.len() as u64);
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Write a string, prefixed by its length.
+    #[inline]
+    pub fn write_str(&mut self, s: &str) {
+        self.write_bytes(s.as_bytes());
+    }
+
+    /// Write a reference to another object,
+    /// encoding the object first if it has not been encoded yet.
+    ///
+    /// Returns [`SnapshotError::Cycle`] if the object is currently being encoded.
+    pub fn write_gc<T: SnapshotType<Id>>(
+        &mut self,
+        ptr: Gc<'gc, T::Collected<'gc>, Id>,
+    ) -> Result<(), SnapshotError> {
+        let index = self.object_index::<T>(ptr)?;
+        self.write_u64(index);
+        Ok(())
+    }
+
+    fn object_index<T: SnapshotType<Id>>(
+        &mut self,
+        ptr: Gc<'gc, T::Collected<'gc>, Id>,
+    ) -> Result<u64, SnapshotError> {
+        let header = NonNull::from(ptr.header());
+        match self.indexes.get(&header) {
+            Some(&Some(index)) => return Ok(index),
+            Some(None) => return Err(SnapshotError::Cycle),
+            None => {}
+        }
+        match self.types.get(T::TYPE_NAME) {
+            Some(entry) if entry.type_id == TypeId::of::<T>() => {}
+            _ => {
+                return Err(SnapshotError::UnregisteredType {
+                    name: T::TYPE_NAME.into(),
+                })
+            }
+        }
+        self.indexes.insert(header, None);
+        let parent_buffer = std::mem::take(&mut self.buffer);
+        #[cfg(not(miri))]
+        let result = stacker::maybe_grow(
+            4096,       // 4KB
+            128 * 1024, // 128KB
+            || T::encode(&*ptr, self),
+        );
+        #[cfg(miri)]
+        let result = T::encode(&*ptr, self);
+        let payload = std::mem::replace(&mut self.buffer, parent_buffer);
+        result?;
+        let index = self.objects.len() as u64;
+        self.objects.push((T::TYPE_NAME, payload));
+        self.indexes.insert(header, Some(index));
+        Ok(index)
+    }
+}
+impl<Id: CollectorId> Write for SnapshotEncoder<'_, '_, Id> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.write(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+struct DecodedObject {
+    type_id: TypeId,
+    type_name: &'static str,
+    ptr: NonNull<()>,
+}
+
+/// Decodes objects from a heap snapshot,
+/// passed to [`SnapshotType::decode`].
+///
+/// The value of the object is read using the [`Read`] implementation,
+/// or the helper methods.
+/// The entire value must be consumed.
+pub struct SnapshotDecoder<'a, 'gc, Id: CollectorId> {
+    collector: &'gc GarbageCollector<Id>,
+    objects: &'a [DecodedObject],
+    /// The remaining bytes of the current object.
+    payload: &'a [u8],
+}
+impl<'a, 'gc, Id: CollectorId> SnapshotDecoder<'a, 'gc, Id> {
+    /// The collector which the object is being restored into.
+    #[inline]
+    pub fn collector(&self) -> &'gc GarbageCollector<Id> {
+        self.collector
+    }
+
+    #[inline]
+    pub fn read_u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(
+            self.take(8)?.try_into().expect("Length is 8"),
+        ))
+    }
+
+    /// Read a byte slice written by [`SnapshotEncoder::write_bytes`].
+    #[inline]
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], SnapshotError> {
+        let len = self.read_u64()?;
+        self.take(usize::try_from(len).unwrap_or(usize::MAX))
+    }
+
+    /// Read a string written by [`SnapshotEncoder::write_str`].
+    #[inline]
+    pub fn read_str(&mut self) -> Result<&'a str, SnapshotError> {
+        std::str::from_utf8(self.read_bytes()?)
+            .map_err(|_| SnapshotError::InvalidFormat("Invalid UTF-8"))
+    }
+
+    /// Read a reference written by [`SnapshotEncoder::write_gc`].
+    pub fn read_gc<T: SnapshotType<Id>>(
+        &mut self,
+    ) -> Result<Gc<'gc, T::Collected<'gc>, Id>, SnapshotError> {
+        let index = self.read_u64()?;
+        let object = usize::try_from(index)
+            .ok()
+            .and_then(|index| self.objects.get(index))
+            .ok_or(SnapshotError::InvalidFormat("Reference to unknown object"))?;
+        if object.type_id != TypeId::of::<T>() {
+            return Err(SnapshotError::TypeMismatch {
+                expected: T::TYPE_NAME,
+                actual: object.type_name,
+            });
+        }
+        // SAFETY: The object was decoded by the codec for this type
+        Ok(unsafe { Gc::from_raw_ptr(object.ptr.cast()) })
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if len > self.payload.len() {
+            return Err(SnapshotError::InvalidFormat("Unexpected end of object"));
+        }
+        let (bytes, remaining) = self.payload.split_at(len);
+        self.payload = remaining;
+        Ok(bytes)
+    }
+}
+impl<Id: CollectorId> Read for SnapshotDecoder<'_, '_, Id> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.payload.read(buf)
+    }
+}
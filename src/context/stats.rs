@@ -0,0 +1,270 @@
+//! Statistics on the activity of a [`GarbageCollector`](crate::GarbageCollector)
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// The reason a collection was performed.
+///
+/// There is no reason for allocation failures,
+/// because the collector never collects in the middle of an allocation:
+/// the objects being built would not be rooted.
+/// A young allocation which does not fit falls back to the old generation,
+/// and a failed allocation is reported as an error.
+/// A rejection by the heap limit is remembered,
+/// so the next call to [`GarbageCollector::collect`](crate::GarbageCollector::collect)
+/// performs a collection for [`CollectionReason::HeapLimit`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CollectionReason {
+    /// The size of a generation doubled since the previous collection.
+    SizeThreshold,
+    /// The [collection trigger](crate::GcConfig::collect_trigger_bytes) was exceeded.
+    Trigger,
+    /// An allocation was rejected by the [heap limit](crate::GcConfig::heap_limit)
+    /// since the previous collection.
+    HeapLimit,
+    /// Explicitly requested by [`GarbageCollector::force_collect`](crate::GarbageCollector::force_collect).
+    Explicit,
+    /// Requested by [`GarbageCollector::compact`](crate::GarbageCollector::compact).
+    Compaction,
+    /// Performed on leaving [`GarbageCollector::scoped_young`](crate::GarbageCollector::scoped_young),
+    /// which usually only evacuates the region of the scope.
+    ScopeExit,
+    /// The heap grew past the [soft limit](crate::GcConfig::soft_heap_limit)
+    /// since the previous collection.
+    SoftLimit,
+}
+impl CollectionReason {
+    /// All possible reasons, in the order used by [`CollectionReasonCounts`].
+    pub const ALL: [CollectionReason; 7] = [
+        CollectionReason::SizeThreshold,
+        CollectionReason::Trigger,
+        CollectionReason::HeapLimit,
+        CollectionReason::Explicit,
+        CollectionReason::Compaction,
+        CollectionReason::ScopeExit,
+        CollectionReason::SoftLimit,
+    ];
+
+    #[inline]
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The number of collections performed for each [`CollectionReason`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct CollectionReasonCounts {
+    counts: [u64; CollectionReason::ALL.len()],
+}
+impl CollectionReasonCounts {
+    /// The number of collections performed for the specified reason.
+    #[inline]
+    pub fn get(&self, reason: CollectionReason) -> u64 {
+        self.counts[reason.index()]
+    }
+
+    /// Iterate over the number of collections for each reason.
+    pub fn iter(&self) -> impl Iterator<Item = (CollectionReason, u64)> + '_ {
+        CollectionReason::ALL
+            .into_iter()
+            .map(|reason| (reason, self.get(reason)))
+    }
+
+    #[inline]
+    fn wrapping_sub(&self, earlier: &Self) -> Self {
+        CollectionReasonCounts {
+            counts: std::array::from_fn(|index| {
+                self.counts[index].wrapping_sub(earlier.counts[index])
+            }),
+        }
+    }
+}
+
+/// Running counters for collector activity.
+///
+/// All counters use wrapping arithmetic,
+/// so deltas remain correct even if a counter overflows.
+pub(crate) struct GcStatsCounters {
+    collections: Cell<u64>,
+    allocated_objects: Cell<u64>,
+    allocated_bytes: Cell<u64>,
+    promoted_objects: Cell<u64>,
+    promoted_bytes: Cell<u64>,
+    collections_by_reason: [Cell<u64>; CollectionReason::ALL.len()],
+    last_collection_reason: Cell<Option<CollectionReason>>,
+}
+impl GcStatsCounters {
+    pub fn new() -> Self {
+        GcStatsCounters {
+            collections: Cell::new(0),
+            allocated_objects: Cell::new(0),
+            allocated_bytes: Cell::new(0),
+            promoted_objects: Cell::new(0),
+            promoted_bytes: Cell::new(0),
+            collections_by_reason: Default::default(),
+            last_collection_reason: Cell::new(None),
+        }
+    }
+
+    #[inline]
+    fn bump(cell: &Cell<u64>, amount: usize) {
+        cell.set(cell.get().wrapping_add(amount as u64));
+    }
+
+    #[inline]
+    pub fn record_alloc(&self, size: usize) {
+        Self::bump(&self.allocated_objects, 1);
+        Self::bump(&self.allocated_bytes, size);
+    }
+
+    #[inline]
+    pub fn record_promotion(&self, size: usize) {
+        Self::bump(&self.promoted_objects, 1);
+        Self::bump(&self.promoted_bytes, size);
+    }
+
+    #[inline]
+    pub fn collections(&self) -> u64 {
+        self.collections.get()
+    }
+
+    #[inline]
+    pub fn record_collection(&self, reason: CollectionReason) {
+        Self::bump(&self.collections, 1);
+        Self::bump(&self.collections_by_reason[reason.index()], 1);
+        self.last_collection_reason.set(Some(reason));
+    }
+
+    pub fn reset(&self) {
+        for counter in [
+            &self.collections,
+            &self.allocated_objects,
+            &self.allocated_bytes,
+            &self.promoted_objects,
+            &self.promoted_bytes,
+        ]
+        .into_iter()
+        .chain(&self.collections_by_reason)
+        {
+            counter.set(0);
+        }
+        self.last_collection_reason.set(None);
+    }
+
+    pub fn snapshot(&self) -> GcStats {
+        GcStats {
+            collections: self.collections(),
+            allocated_objects: self.allocated_objects.get(),
+            allocated_bytes: self.allocated_bytes.get(),
+            promoted_objects: self.promoted_objects.get(),
+            promoted_bytes: self.promoted_bytes.get(),
+            collections_by_reason: CollectionReasonCounts {
+                counts: std::array::from_fn(|index| self.collections_by_reason[index].get()),
+            },
+            last_collection_reason: self.last_collection_reason.get(),
+            timestamp: Instant::now(),
+        }
+    }
+}
+
+/// A point-in-time snapshot of the collector's statistics.
+///
+/// Counters are cumulative since the collector was created
+/// (or since the last call to [`reset_stats`](crate::GarbageCollector::reset_stats)).
+/// Use [`GcStats::delta`] to compute the activity in an interval.
+#[derive(Debug, Copy, Clone)]
+#[non_exhaustive]
+pub struct GcStats {
+    /// The number of completed collections.
+    pub collections: u64,
+    /// The number of objects allocated.
+    pub allocated_objects: u64,
+    /// The number of bytes allocated, including object headers.
+    pub allocated_bytes: u64,
+    /// The number of objects promoted from the young generation to the old generation.
+    pub promoted_objects: u64,
+    /// The number of bytes promoted to the old generation, including object headers.
+    pub promoted_bytes: u64,
+    /// The number of completed collections, broken down by their reason.
+    pub collections_by_reason: CollectionReasonCounts,
+    /// The reason for the most recent collection,
+    /// or `None` if no collection has occurred.
+    pub last_collection_reason: Option<CollectionReason>,
+    /// The time the snapshot was taken.
+    pub timestamp: Instant,
+}
+impl GcStats {
+    /// Compute the change in statistics since an `earlier` snapshot.
+    ///
+    /// Counter wraparound is handled correctly,
+    /// but the result is meaningless if the statistics were reset in between.
+    #[inline]
+    pub fn delta(&self, earlier: &GcStats) -> GcStatsDelta {
+        GcStatsDelta {
+            collections: self.collections.wrapping_sub(earlier.collections),
+            allocated_objects: self
+                .allocated_objects
+                .wrapping_sub(earlier.allocated_objects),
+            allocated_bytes: self.allocated_bytes.wrapping_sub(earlier.allocated_bytes),
+            promoted_objects: self.promoted_objects.wrapping_sub(earlier.promoted_objects),
+            promoted_bytes: self.promoted_bytes.wrapping_sub(earlier.promoted_bytes),
+            collections_by_reason: self
+                .collections_by_reason
+                .wrapping_sub(&earlier.collections_by_reason),
+            elapsed: self.timestamp.saturating_duration_since(earlier.timestamp),
+        }
+    }
+}
+
+/// The change in collector statistics over an interval.
+///
+/// Created by [`GcStats::delta`]
+/// or [`GarbageCollector::stats_delta`](crate::GarbageCollector::stats_delta).
+#[derive(Debug, Copy, Clone)]
+#[non_exhaustive]
+pub struct GcStatsDelta {
+    pub collections: u64,
+    pub allocated_objects: u64,
+    pub allocated_bytes: u64,
+    pub promoted_objects: u64,
+    pub promoted_bytes: u64,
+    pub collections_by_reason: CollectionReasonCounts,
+    /// The time elapsed over the interval.
+    pub elapsed: Duration,
+}
+impl GcStatsDelta {
+    #[inline]
+    fn per_second(&self, count: u64) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            count as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// The number of objects allocated per second over the interval.
+    #[inline]
+    pub fn allocations_per_sec(&self) -> f64 {
+        self.per_second(self.allocated_objects)
+    }
+
+    /// The number of bytes allocated per second over the interval.
+    #[inline]
+    pub fn allocated_bytes_per_sec(&self) -> f64 {
+        self.per_second(self.allocated_bytes)
+    }
+
+    /// The number of objects promoted per second over the interval.
+    #[inline]
+    pub fn promotions_per_sec(&self) -> f64 {
+        self.per_second(self.promoted_objects)
+    }
+
+    /// The number of collections per second over the interval.
+    #[inline]
+    pub fn collections_per_sec(&self) -> f64 {
+        self.per_second(self.collections)
+    }
+}
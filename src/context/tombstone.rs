@@ -0,0 +1,157 @@
+//! Summaries of recently freed objects, to help debug stale pointers.
+//!
+//! Only available with the `debug-tombstones` feature.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::panic::Location;
+use std::ptr::NonNull;
+
+use crate::context::ephemeron::is_marked;
+use crate::context::layout::GcHeader;
+use crate::context::CollectorState;
+use crate::CollectorId;
+
+/// Information about a live object, recorded when it was allocated.
+#[derive(Copy, Clone)]
+struct AllocRecord {
+    type_name: &'static str,
+    size: usize,
+    allocated_at: &'static Location<'static>,
+}
+
+/// A summary of an object which has been freed.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Tombstone {
+    /// The address of the object's header.
+    pub address: usize,
+    /// The size of the object, including its header.
+    pub size: usize,
+    /// The name of the object's type.
+    pub type_name: &'static str,
+    /// The location where the object was allocated.
+    pub allocated_at: &'static Location<'static>,
+    /// The number of the collection which freed the object,
+    /// as given by [`GcStats::collections`](crate::GcStats::collections).
+    pub freed_in_gc: u64,
+}
+impl Tombstone {
+    /// Check if the specified address falls within the freed object.
+    #[inline]
+    pub fn contains(&self, address: usize) -> bool {
+        address >= self.address && address - self.address < self.size
+    }
+}
+impl Display for Tombstone {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({} bytes at {:#x}) was freed in GC #{}, allocated at {}",
+            self.type_name, self.size, self.address, self.freed_in_gc, self.allocated_at
+        )
+    }
+}
+
+/// Tracks live objects in order to record tombstones once they die.
+pub(crate) struct TombstoneTracker<Id: CollectorId> {
+    live: RefCell<HashMap<NonNull<GcHeader<Id>>, AllocRecord>>,
+    tombstones: RefCell<VecDeque<Tombstone>>,
+    capacity: usize,
+}
+impl<Id: CollectorId> TombstoneTracker<Id> {
+    pub fn new(capacity: usize) -> Self {
+        TombstoneTracker {
+            live: RefCell::new(HashMap::new()),
+            tombstones: RefCell::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    #[inline]
+    pub unsafe fn record_alloc(
+        &self,
+        header: NonNull<GcHeader<Id>>,
+        size: usize,
+        allocated_at: &'static Location<'static>,
+    ) {
+        let type_name = header.as_ref().type_info().type_name();
+        self.live.borrow_mut().insert(
+            header,
+            AllocRecord {
+                type_name,
+                size,
+                allocated_at,
+            },
+        );
+    }
+
+    /// Record a tombstone for an object freed outside of a collection.
+    pub fn record_early_free(&self, header: NonNull<GcHeader<Id>>, gc_number: u64) {
+        if let Some(record) = self.live.borrow_mut().remove(&header) {
+            self.push_tombstone(header, record, gc_number);
+        }
+    }
+
+    fn push_tombstone(&self, header: NonNull<GcHeader<Id>>, record: AllocRecord, gc_number: u64) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut tombstones = self.tombstones.borrow_mut();
+        if tombstones.len() >= self.capacity {
+            tombstones.pop_front();
+        }
+        tombstones.push_back(Tombstone {
+            address: crate::utils::addr(header.as_ptr()),
+            size: record.size,
+            type_name: record.type_name,
+            allocated_at: record.allocated_at,
+            freed_in_gc: gc_number,
+        });
+    }
+
+    /// Record tombstones for all objects which were not marked,
+    /// and update the addresses of all objects which were moved.
+    ///
+    /// ## Safety
+    /// Must be called after marking, but before sweeping.
+    pub unsafe fn process_marked(&self, state: &CollectorState<Id>, gc_number: u64) {
+        let mut live = self.live.borrow_mut();
+        let mut survivors = HashMap::with_capacity(live.len());
+        for (header, record) in live.drain() {
+            let state_bits = header.as_ref().state_bits.get();
+            if state_bits.forwarded() {
+                // young objects are evacuated, as are old objects during compaction
+                survivors.insert(header.as_ref().forward_ptr(), record);
+            } else if is_marked(header, state) {
+                survivors.insert(header, record);
+            } else {
+                self.push_tombstone(header, record, gc_number);
+            }
+        }
+        *live = survivors;
+    }
+
+    /// Record tombstones for objects freed without a collection.
+    pub fn record_discarded(&self, discarded: &HashSet<NonNull<GcHeader<Id>>>, gc_number: u64) {
+        for &header in discarded {
+            self.record_early_free(header, gc_number);
+        }
+    }
+
+    /// Find the most recent tombstone containing the specified address.
+    pub fn find(&self, address: usize) -> Option<Tombstone> {
+        self.tombstones
+            .borrow()
+            .iter()
+            .rev()
+            .find(|tombstone| tombstone.contains(address))
+            .cloned()
+    }
+
+    /// All retained tombstones, from oldest to newest.
+    pub fn all(&self) -> Vec<Tombstone> {
+        self.tombstones.borrow().iter().cloned().collect()
+    }
+}
@@ -0,0 +1,76 @@
+//! A facade for allocating many objects of the same type,
+//! like the nodes of a syntax tree.
+
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+
+use crate::context::RegularAlloc;
+use crate::{Collect, CollectorId, GarbageCollector, Gc};
+
+/// Allocates objects of a single type,
+/// created by [`GarbageCollector::typed`].
+///
+/// The type's metadata and layout are resolved once when the facade is created,
+/// instead of on every allocation.
+/// Otherwise, allocating through the facade is equivalent to
+/// [`GarbageCollector::alloc`] and [`GarbageCollector::alloc_many`].
+pub struct TypedAlloc<'gc, T: Collect<Id>, Id: CollectorId> {
+    collector: &'gc GarbageCollector<Id>,
+    target: RegularAlloc<'gc, Id>,
+    marker: PhantomData<fn(T) -> T>,
+}
+impl<'gc, T: Collect<Id>, Id: CollectorId> TypedAlloc<'gc, T, Id> {
+    /// The target must be for values of type `T`.
+    #[inline]
+    pub(super) fn new(collector: &'gc GarbageCollector<Id>, target: RegularAlloc<'gc, Id>) -> Self {
+        TypedAlloc {
+            collector,
+            target,
+            marker: PhantomData,
+        }
+    }
+
+    /// Allocate a GC object.
+    ///
+    /// ## Panics
+    /// If allocation fails.
+    #[inline]
+    #[track_caller]
+    pub fn alloc(&self, value: T) -> Gc<'gc, T, Id> {
+        unsafe {
+            let header = self.collector.alloc_raw(&self.target);
+            self.collector.init_alloc(header, || value)
+        }
+    }
+
+    /// Allocate a GC object for each of the values, returning them in order.
+    ///
+    /// If the iterator reports its exact length,
+    /// the objects are allocated in a single block like [`GarbageCollector::alloc_many`].
+    ///
+    /// ## Panics
+    /// If allocation fails.
+    #[track_caller]
+    pub fn alloc_iter(&self, values: impl IntoIterator<Item = T>) -> Vec<Gc<'gc, T, Id>> {
+        let values = values.into_iter();
+        let count = match values.size_hint() {
+            (lower, Some(upper)) if lower == upper => lower,
+            _ => 0,
+        };
+        unsafe { self.collector.alloc_many_in(&self.target, values, count) }
+    }
+}
+impl<T: Collect<Id>, Id: CollectorId> Clone for TypedAlloc<'_, T, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T: Collect<Id>, Id: CollectorId> Copy for TypedAlloc<'_, T, Id> {}
+impl<T: Collect<Id>, Id: CollectorId> Debug for TypedAlloc<'_, T, Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedAlloc")
+            .field("type_name", &std::any::type_name::<T>())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,50 @@
+//! Verifying the invariants of the heap after each collection.
+//!
+//! This catches objects whose pointers were never traced,
+//! which are left pointing to the old location of an evacuated object,
+//! or into young-generation memory which has since been reset.
+//!
+//! Only enabled with `debug_assertions`, since it walks the entire heap.
+
+use std::collections::{HashSet, VecDeque};
+use std::ptr::NonNull;
+
+use crate::context::classify::heap_objects;
+use crate::context::layout::GcHeader;
+use crate::{CollectContext, CollectorId, GarbageCollector};
+
+/// Check that every pointer in every live object refers to another live object.
+///
+/// ## Safety
+/// Must be called after a collection has finished sweeping.
+pub(super) unsafe fn verify_heap<Id: CollectorId>(collector: &GarbageCollector<Id>) {
+    let objects = heap_objects(collector).collect::<Vec<_>>();
+    let mut context = CollectContext {
+        garbage_collector: collector,
+        id: collector.collector_id,
+        freezing: false,
+        gray_queue: VecDeque::new(),
+        verify_live: Some(objects.iter().copied().collect()),
+    };
+    for header in objects {
+        if let Some(trace_func) = header.as_ref().type_info().trace_func {
+            context.trace_children(header, trace_func);
+        }
+    }
+}
+
+/// Check a pointer found while verifying the heap, returning it unchanged.
+pub(super) unsafe fn verify_pointer<Id: CollectorId>(
+    live: &HashSet<NonNull<GcHeader<Id>>>,
+    header: NonNull<GcHeader<Id>>,
+) -> NonNull<GcHeader<Id>> {
+    assert!(
+        live.contains(&header),
+        "Found stale pointer to {header:?}, which is not a live object"
+    );
+    assert!(
+        !header.as_ref().state_bits.get().forwarded(),
+        "Pointer to forwarded object {header:?} was never updated"
+    );
+    header
+}
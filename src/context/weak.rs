@@ -0,0 +1,130 @@
+//! Weak handles, which refer to an object without keeping it alive.
+
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use crate::context::ephemeron::{is_marked, EphemeronTable};
+use crate::context::layout::GcHeader;
+use crate::context::CollectorState;
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector, Gc, GcHandle};
+
+struct WeakCell<Id: CollectorId> {
+    /// The target object, or `None` if it has died.
+    header: Cell<Option<NonNull<GcHeader<Id>>>>,
+}
+impl<Id: CollectorId> EphemeronTable<Id> for WeakCell<Id> {
+    unsafe fn trace_reachable(&self, _context: &mut CollectContext<'_, Id>) -> bool {
+        false // never keeps the target alive
+    }
+
+    unsafe fn sweep(&self, state: &CollectorState<Id>) {
+        if let Some(header) = self.header.get() {
+            if !is_marked(header, state) {
+                self.header.set(None);
+            } else if header.as_ref().state_bits.get().forwarded() {
+                self.header.set(Some(header.as_ref().forward_ptr()));
+            }
+        }
+    }
+
+    fn forget(&self, discarded: &HashSet<NonNull<GcHeader<Id>>>) {
+        if self
+            .header
+            .get()
+            .is_some_and(|header| discarded.contains(&header))
+        {
+            self.header.set(None);
+        }
+    }
+}
+
+/// A handle which refers to an object without keeping it alive,
+/// created by [`GcHandle::downgrade`].
+///
+/// Once the object dies, the handle is cleared.
+/// This is useful for observer lists, which should not extend the life of their entries.
+pub struct WeakGcHandle<T: Collect<Id>, Id: CollectorId> {
+    cell: Rc<WeakCell<Id>>,
+    id: Id,
+    marker: PhantomData<T>,
+}
+impl<T: Collect<Id>, Id: CollectorId> WeakGcHandle<T, Id> {
+    /// Check if the object has died, clearing the handle.
+    #[inline]
+    pub fn is_cleared(&self) -> bool {
+        self.cell.header.get().is_none()
+    }
+
+    /// Resolve this handle into a [`Gc`] smart-pointer,
+    /// returning `None` if the object has died.
+    #[inline]
+    pub fn resolve<'gc>(
+        &self,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> Option<Gc<'gc, T::Collected<'gc>, Id>> {
+        assert_eq!(self.id, collector.id());
+        self.cell
+            .header
+            .get()
+            .map(|header| unsafe { Gc::from_raw_ptr(header.as_ref().regular_value_ptr().cast()) })
+    }
+
+    /// Upgrade this into a strong [`GcHandle`],
+    /// returning `None` if the object has died.
+    #[inline]
+    pub fn upgrade(&self, collector: &GarbageCollector<Id>) -> Option<GcHandle<T, Id>> {
+        assert_eq!(self.id, collector.id());
+        self.cell
+            .header
+            .get()
+            .map(|header| unsafe { collector.root_raw(header) })
+    }
+
+    /// Register a callback to be notified once the object dies and this handle is cleared.
+    ///
+    /// Like [finalizers](GarbageCollector::add_finalizer),
+    /// notifications are queued by the collection which finds the object dead,
+    /// and delivered by [`GarbageCollector::run_pending_finalizers`],
+    /// so the callback is free to allocate.
+    /// This is useful for invalidating external caches keyed by GC objects.
+    ///
+    /// If the object has already died, the notification is queued immediately.
+    /// The notification is skipped if this handle has been dropped before it is delivered.
+    pub fn with_notifier(
+        self,
+        collector: &GarbageCollector<Id>,
+        callback: impl FnOnce(&GarbageCollector<Id>) + 'static,
+    ) -> Self {
+        assert_eq!(self.id, collector.id());
+        let cell = Rc::downgrade(&self.cell);
+        let notifier = Box::new(move |collector: &GarbageCollector<Id>| {
+            if cell.strong_count() > 0 {
+                callback(collector);
+            }
+        });
+        match self.cell.header.get() {
+            Some(header) => collector.finalizers.register(header, notifier),
+            None => collector.finalizers.push_pending(notifier),
+        }
+        self
+    }
+}
+
+impl<T: Collect<Id>, Id: CollectorId> WeakGcHandle<T, Id> {
+    /// Create a weak handle to the object with the specified header,
+    /// registering it with the collector.
+    pub(crate) fn new(collector: &GarbageCollector<Id>, header: NonNull<GcHeader<Id>>) -> Self {
+        let cell = Rc::new(WeakCell {
+            header: Cell::new(Some(header)),
+        });
+        collector.register_ephemerons(Rc::downgrade(&cell) as _);
+        WeakGcHandle {
+            cell,
+            id: collector.id(),
+            marker: PhantomData,
+        }
+    }
+}
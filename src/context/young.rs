@@ -7,11 +7,17 @@ use std::mem::ManuallyDrop;
 use std::ptr::NonNull;
 
 use crate::context::alloc::{ArenaAlloc, CountingAlloc};
-use crate::context::layout::{AllocInfo, GcHeader, GcMarkBits};
-use crate::context::{CollectorState, GenerationId};
+#[cfg(feature = "jit-layout")]
+use crate::context::jit_layout::AllocationCursor;
+use crate::context::layout::{AllocInfo, GcArrayHeader, GcArrayLayoutInfo, GcHeader, GcMarkBits};
+use crate::context::{CollectorState, GcConfig, GenerationId};
 use crate::utils::Alignment;
 use crate::{CollectorId, Gc};
 
+use self::queue::DestructionQueue;
+
+mod queue;
+
 struct YoungAlloc {
     #[cfg(feature = "debug-alloc")]
     group: ArenaAlloc<allocator_api2::alloc::Global>,
@@ -19,16 +25,20 @@ struct YoungAlloc {
     bump: Bump,
 }
 impl YoungAlloc {
-    pub fn new() -> Self {
+    /// Create an allocator whose first chunk has the specified capacity.
+    pub fn new(chunk_size: usize) -> Self {
         #[cfg(feature = "debug-alloc")]
         {
+            let _ = chunk_size; // each allocation is separate
             YoungAlloc {
                 group: ArenaAlloc::new(allocator_api2::alloc::Global),
             }
         }
         #[cfg(not(feature = "debug-alloc"))]
         {
-            YoungAlloc { bump: Bump::new() }
+            YoungAlloc {
+                bump: Bump::with_capacity(chunk_size),
+            }
         }
     }
     fn alloc_impl(&self) -> impl Allocator + '_ {
@@ -64,35 +74,129 @@ unsafe impl Allocator for YoungAlloc {
 
 /// A young-generation object-space
 ///
-/// If copying is in progress,
-/// there may be two young generations for a single collector.
+/// New objects are allocated in the eden arena.
+/// Objects which survive a collection are copied into a survivor space,
+/// until they reach the age threshold and are promoted to the old generation.
+/// There are two survivor spaces, which swap roles after every collection.
 ///
 /// The design of the allocator is heavily based on [`bumpalo`](https://crates.io/crates/bumpalo)
 pub struct YoungGenerationSpace<Id: CollectorId> {
-    alloc: CountingAlloc<YoungAlloc>,
+    /// The eden arena, where new objects are allocated.
+    alloc: UnsafeCell<CountingAlloc<YoungAlloc>>,
+    /// The survivor space containing objects which survived the previous collection.
+    survivor_from: UnsafeCell<CountingAlloc<YoungAlloc>>,
+    /// The survivor space which objects are copied into during a collection.
+    ///
+    /// This is empty outside of a collection.
+    survivor_to: UnsafeCell<CountingAlloc<YoungAlloc>>,
     /// A set of objects which need destructors to be run.
-    destruction_queue: UnsafeCell<Vec<Option<NonNull<GcHeader<Id>>>>>,
+    destruction_queue: UnsafeCell<DestructionQueue<Id>>,
+    /// The destruction queue for objects copied into `survivor_to`,
+    /// which replaces the current queue once the collection finishes.
+    survivor_destruction_queue: UnsafeCell<DestructionQueue<Id>>,
+    /// Every object allocated in the eden arena,
+    /// used to find the object containing an arbitrary address.
+    ///
+    /// Like the survivor lists, this is only [`tracked`] with the `classify-ptr` feature
+    /// or `debug_assertions`, keeping it off the fast path of allocation.
+    eden_objects: UnsafeCell<Vec<NonNull<GcHeader<Id>>>>,
+    /// Every object in `survivor_from`.
+    survivor_from_objects: UnsafeCell<Vec<NonNull<GcHeader<Id>>>>,
+    /// Every object copied into `survivor_to`.
+    survivor_to_objects: UnsafeCell<Vec<NonNull<GcHeader<Id>>>>,
+    /// Pinned objects which survived a previous collection.
+    ///
+    /// These are excluded from evacuation,
+    /// so they remain in place within a retired arena.
+    retained_pinned: UnsafeCell<Vec<NonNull<GcHeader<Id>>>>,
+    /// Pinned objects which were marked during the current collection.
+    marked_pinned: UnsafeCell<Vec<NonNull<GcHeader<Id>>>>,
+    /// Whether the current arena contains a marked pinned object,
+    /// and needs to be retired instead of reset.
+    retire_current_arena: Cell<bool>,
+    /// Whether `survivor_from` contains a marked pinned object.
+    retire_survivor_arena: Cell<bool>,
+    /// Additional eden arenas, which can be discarded independently.
+    regions: UnsafeCell<Vec<Region<Id>>>,
+    /// The index of the region new objects are allocated in,
+    /// or `None` to allocate in the main eden arena.
+    active_region: Cell<Option<usize>>,
+    /// Arenas which are kept alive because they contain pinned objects.
+    ///
+    /// These are freed once there are no more retained pinned objects.
+    retired_arenas: UnsafeCell<Vec<YoungAlloc>>,
+    /// The initial capacity of each arena.
+    chunk_size: usize,
+    /// The largest object which can be allocated in this space.
+    size_limit: usize,
+    /// The age at which surviving objects are promoted.
+    age_threshold: u8,
+    /// The region of the eden arena which generated code allocates from directly.
+    ///
+    /// Boxed so the addresses given to generated code remain stable.
+    #[cfg(feature = "jit-layout")]
+    jit_buffer: Box<JitBuffer>,
+    /// The region of the eden arena which arrays are allocated from,
+    /// so the newest one can be [extended in place](Self::try_extend_in_place).
+    array_buffer: ArrayBuffer,
     collector_id: Id,
 }
 impl<Id: CollectorId> YoungGenerationSpace<Id> {
-    pub unsafe fn new(id: Id) -> Self {
+    pub unsafe fn new(id: Id, config: &GcConfig) -> Self {
         #[cfg(not(feature = "debug-alloc"))]
         let bump = ManuallyDrop::new(Box::new(Bump::new()));
         YoungGenerationSpace {
-            alloc: CountingAlloc::new(YoungAlloc::new()),
-            destruction_queue: UnsafeCell::new(Vec::new()),
+            alloc: UnsafeCell::new(CountingAlloc::new(YoungAlloc::new(config.young_chunk_size))),
+            survivor_from: UnsafeCell::new(CountingAlloc::new(YoungAlloc::new(
+                config.young_chunk_size,
+            ))),
+            survivor_to: UnsafeCell::new(CountingAlloc::new(YoungAlloc::new(
+                config.young_chunk_size,
+            ))),
+            destruction_queue: UnsafeCell::new(DestructionQueue::new()),
+            survivor_destruction_queue: UnsafeCell::new(DestructionQueue::new()),
+            eden_objects: UnsafeCell::new(Vec::new()),
+            survivor_from_objects: UnsafeCell::new(Vec::new()),
+            survivor_to_objects: UnsafeCell::new(Vec::new()),
+            retained_pinned: UnsafeCell::new(Vec::new()),
+            marked_pinned: UnsafeCell::new(Vec::new()),
+            retire_current_arena: Cell::new(false),
+            retire_survivor_arena: Cell::new(false),
+            regions: UnsafeCell::new(Vec::new()),
+            active_region: Cell::new(None),
+            retired_arenas: UnsafeCell::new(Vec::new()),
+            chunk_size: config.young_chunk_size,
+            size_limit: config.young_object_limit,
+            age_threshold: config.survivor_age_threshold,
+            #[cfg(feature = "jit-layout")]
+            jit_buffer: Box::new(JitBuffer {
+                cursor: Cell::new(std::ptr::null_mut()),
+                limit: Cell::new(std::ptr::null_mut()),
+            }),
+            array_buffer: ArrayBuffer::new(),
             collector_id: id,
         }
     }
 
-    /// The maximum size to allocate in the young generation.
+    /// The value of `nontrivial_drop_index` for objects which don't need to be dropped.
+    pub const NO_DROP_INDEX: u32 = u32::MAX;
+    /// The value of `nontrivial_drop_index` for pinned objects retained from a previous collection.
     ///
-    /// Anything larger than this is immediately sent to the old generation.
-    pub const SIZE_LIMIT: usize = 1024;
+    /// These objects are not in the destruction queue,
+    /// and are instead tracked by `retained_pinned`.
+    pub const RETAINED_INDEX: u32 = u32::MAX - 2;
 
-    pub unsafe fn sweep(&mut self, state: &CollectorState<Id>) {
-        for &element in self.destruction_queue.get_mut().iter() {
-            if let Some(header) = element {
+    /// Run the destructors of dead objects, without freeing their memory.
+    ///
+    /// This must be followed by [`Self::sweep`].
+    ///
+    /// ## Safety
+    /// Must only be called at the end of a collection,
+    /// once all live objects have been evacuated.
+    pub unsafe fn run_destructors(&self, state: &CollectorState<Id>) {
+        for header in (*self.destruction_queue.get()).iter() {
+            // failed initialization leaves objects in the queue, but they must not be dropped
+            if header.as_ref().state_bits.get().value_initialized() {
                 debug_assert_eq!(
                     header
                         .as_ref()
@@ -106,8 +210,94 @@ impl<Id: CollectorId> YoungGenerationSpace<Id> {
                 header.as_ref().invoke_destructor();
             }
         }
-        self.destruction_queue.get_mut().clear();
-        self.alloc.as_inner_mut().reset();
+        // retained objects which were not marked again are dead
+        for &header in (*self.retained_pinned.get()).iter() {
+            let state_bits = header.as_ref().state_bits.get();
+            if !state_bits.forwarded()
+                && state_bits.raw_mark_bits().resolve(state) == GcMarkBits::White
+            {
+                header.as_ref().invoke_destructor();
+            }
+        }
+    }
+
+    /// Reset the arenas, freeing every dead object at once.
+    ///
+    /// ## Safety
+    /// Must only be called at the end of a collection, after [`Self::run_destructors`],
+    /// when nothing else is accessing this space.
+    pub unsafe fn sweep(&self) {
+        // objects which survived this collection replace the dead queue
+        *self.destruction_queue.get() = std::mem::take(&mut *self.survivor_destruction_queue.get());
+        (*self.eden_objects.get()).clear();
+        *self.survivor_from_objects.get() = std::mem::take(&mut *self.survivor_to_objects.get());
+        let retained_pinned = &mut *self.retained_pinned.get();
+        *retained_pinned = std::mem::take(&mut *self.marked_pinned.get());
+        #[cfg(feature = "jit-layout")]
+        self.jit_buffer.clear();
+        self.array_buffer.clear();
+        let retired_arenas = &mut *self.retired_arenas.get();
+        let alloc = &mut *self.alloc.get();
+        // a newly pinned object may be in any region, so they are all retired together
+        let retire_current = self.retire_current_arena.replace(false);
+        if retire_current {
+            let retired = std::mem::replace(alloc.as_inner_mut(), YoungAlloc::new(self.chunk_size));
+            retired_arenas.push(retired);
+        } else {
+            alloc.as_inner_mut().reset();
+        }
+        alloc.reset_count();
+        for region in (*self.regions.get()).iter_mut() {
+            region.objects.get_mut().clear();
+            if retire_current {
+                let retired = std::mem::replace(
+                    &mut region.alloc,
+                    CountingAlloc::new(YoungAlloc::new(self.chunk_size)),
+                );
+                retired_arenas.push(retired.into_inner());
+            } else {
+                region.alloc.as_inner_mut().reset();
+                region.alloc.reset_count();
+            }
+        }
+        // the previous survivors are dead or copied, so the from-space becomes the next to-space
+        let survivor_to = &mut *self.survivor_to.get();
+        std::mem::swap(&mut *self.survivor_from.get(), survivor_to);
+        if self.retire_survivor_arena.replace(false) {
+            let retired = std::mem::replace(
+                survivor_to,
+                CountingAlloc::new(YoungAlloc::new(self.chunk_size)),
+            );
+            retired_arenas.push(retired.into_inner());
+        } else {
+            survivor_to.as_inner_mut().reset();
+            survivor_to.reset_count();
+        }
+        if retained_pinned.is_empty() {
+            retired_arenas.clear();
+        }
+    }
+
+    /// Retain a marked object in place, excluding it from evacuation.
+    ///
+    /// This is used for pinned objects,
+    /// and for objects which could not be evacuated because memory ran out.
+    /// Either way, the object is tracked with the retained pinned objects.
+    pub unsafe fn retain_in_place(&self, header: NonNull<GcHeader<Id>>) {
+        let drop_index = header.as_ref().alloc_info.nontrivial_drop_index;
+        if drop_index != Self::RETAINED_INDEX {
+            // newly retained, so it lives in the current arena
+            if drop_index != Self::NO_DROP_INDEX {
+                (*self.destruction_queue.get()).remove(drop_index);
+            }
+            (*header.as_ptr()).alloc_info.nontrivial_drop_index = Self::RETAINED_INDEX;
+            if header.as_ref().state_bits.get().age().value() > 0 {
+                self.retire_survivor_arena.set(true);
+            } else {
+                self.retire_current_arena.set(true);
+            }
+        }
+        (*self.marked_pinned.get()).push(header);
     }
 
     #[inline]
@@ -131,11 +321,13 @@ impl<Id: CollectorId> YoungGenerationSpace<Id> {
             GenerationId::Young
         );
         let drop_index = header.as_ref().alloc_info.nontrivial_drop_index;
-        if drop_index == u32::MAX {
+        if drop_index == Self::NO_DROP_INDEX {
             debug_assert!(header.as_ref().resolve_type_info().drop_func.is_none());
+        } else if drop_index == Self::RETAINED_INDEX {
+            // no longer pinned, but tracked by `retained_pinned` instead of the queue
         } else {
             debug_assert!(header.as_ref().resolve_type_info().drop_func.is_some());
-            (*self.destruction_queue.get())[drop_index as usize] = None;
+            (*self.destruction_queue.get()).remove(drop_index);
             if cfg!(debug_assertions) {
                 (*header.as_ptr()).alloc_info.nontrivial_drop_index = u32::MAX - 1;
             }
@@ -147,51 +339,475 @@ impl<Id: CollectorId> YoungGenerationSpace<Id> {
         &self,
         target: &T,
     ) -> Result<NonNull<T::Header>, YoungAllocError> {
-        let overall_layout = target.overall_layout();
-        if overall_layout.size() > Self::SIZE_LIMIT {
+        if target.overall_layout().size() > self.size_limit {
+            return Err(YoungAllocError::SizeExceedsLimit);
+        }
+        if let Some(index) = self.active_region.get() {
+            let region = &(&*self.regions.get())[index];
+            return self.alloc_in(
+                &region.alloc,
+                &self.destruction_queue,
+                Some(&region.objects),
+                target,
+            );
+        }
+        if T::ARRAY {
+            return self.alloc_array(target);
+        }
+        self.alloc_in(
+            &*self.alloc.get(),
+            &self.destruction_queue,
+            tracked(&self.eden_objects),
+            target,
+        )
+    }
+
+    /// Allocate space for an object evacuated from a region into the main eden arena,
+    /// regardless of the active region.
+    ///
+    /// The caller must copy the value and state bits of the original object.
+    #[inline]
+    pub unsafe fn alloc_eden<T: super::RawAllocTarget<Id>>(
+        &self,
+        target: &T,
+    ) -> Result<NonNull<T::Header>, YoungAllocError> {
+        self.alloc_in(
+            &*self.alloc.get(),
+            &self.destruction_queue,
+            tracked(&self.eden_objects),
+            target,
+        )
+    }
+
+    /// Allocate space for a surviving object in the to-space.
+    ///
+    /// The caller must copy the value and state bits of the original object.
+    #[inline]
+    pub unsafe fn alloc_survivor<T: super::RawAllocTarget<Id>>(
+        &self,
+        target: &T,
+    ) -> Result<NonNull<T::Header>, YoungAllocError> {
+        self.alloc_in(
+            &*self.survivor_to.get(),
+            &self.survivor_destruction_queue,
+            tracked(&self.survivor_to_objects),
+            target,
+        )
+    }
+
+    /// Allocate `count` objects of the same type from a single contiguous block,
+    /// returning their headers in address order.
+    ///
+    /// Fails with [`YoungAllocError::SizeExceedsLimit`] if the block would not fit in a chunk.
+    pub unsafe fn alloc_batch<T: super::RawAllocTarget<Id>>(
+        &self,
+        target: &T,
+        count: usize,
+    ) -> Result<Vec<NonNull<T::Header>>, YoungAllocError> {
+        let layout = target.overall_layout().pad_to_align();
+        if layout.size() > self.size_limit {
             return Err(YoungAllocError::SizeExceedsLimit);
         }
-        let Ok(raw_ptr) = self.alloc.allocate(overall_layout) else {
+        let block_layout = match layout.size().checked_mul(count) {
+            Some(size) if size <= self.chunk_size => {
+                Layout::from_size_align_unchecked(size, layout.align())
+            }
+            _ => return Err(YoungAllocError::SizeExceedsLimit),
+        };
+        let (arena, objects) = match self.active_region.get() {
+            Some(index) => {
+                let region = &(&*self.regions.get())[index];
+                (&region.alloc, Some(&region.objects))
+            }
+            None => (&*self.alloc.get(), tracked(&self.eden_objects)),
+        };
+        let Ok(block) = arena.allocate(block_layout) else {
             return Err(YoungAllocError::OutOfMemory);
         };
-        let header_ptr = raw_ptr.cast::<T::Header>();
+        Ok((0..count)
+            .map(|index| {
+                let raw_ptr = block.cast::<u8>().add(index * layout.size());
+                self.init_in(raw_ptr, &self.destruction_queue, objects, target)
+            })
+            .collect())
+    }
+
+    /// Allocate an array from the array buffer of the eden arena,
+    /// refilling the buffer if it is exhausted.
+    unsafe fn alloc_array<T: super::RawAllocTarget<Id>>(
+        &self,
+        target: &T,
+    ) -> Result<NonNull<T::Header>, YoungAllocError> {
+        let layout = target.overall_layout();
+        let arena = &*self.alloc.get();
+        let raw_ptr = match self.array_buffer.bump(layout) {
+            Some(res) => res,
+            None => {
+                // the remainder of the old buffer is abandoned, like the end of a bumpalo chunk
+                let size = layout
+                    .size()
+                    .saturating_add(layout.align())
+                    .max(self.chunk_size / 4)
+                    .next_multiple_of(GcHeader::<Id>::FIXED_ALIGNMENT);
+                let buffer_layout = Layout::from_size_align(size, GcHeader::<Id>::FIXED_ALIGNMENT)
+                    .map_err(|_| YoungAllocError::SizeExceedsLimit)?;
+                let Ok(buffer) = arena.as_inner().allocate(buffer_layout) else {
+                    return Err(YoungAllocError::OutOfMemory);
+                };
+                self.array_buffer.refill(buffer.cast(), size);
+                self.array_buffer.bump(layout).unwrap()
+            }
+        };
+        arena.count_bytes(self.array_buffer.cursor.get() as usize - raw_ptr.as_ptr() as usize);
+        Ok(self.init_in(
+            raw_ptr,
+            &self.destruction_queue,
+            tracked(&self.eden_objects),
+            target,
+        ))
+    }
+
+    #[inline(always)]
+    unsafe fn alloc_in<T: super::RawAllocTarget<Id>>(
+        &self,
+        arena: &CountingAlloc<YoungAlloc>,
+        destruction_queue: &UnsafeCell<DestructionQueue<Id>>,
+        objects: Option<&UnsafeCell<Vec<NonNull<GcHeader<Id>>>>>,
+        target: &T,
+    ) -> Result<NonNull<T::Header>, YoungAllocError> {
+        let Ok(raw_ptr) = arena.allocate(target.overall_layout()) else {
+            return Err(YoungAllocError::OutOfMemory);
+        };
+        Ok(self.init_in(raw_ptr.cast(), destruction_queue, objects, target))
+    }
+
+    /// Initialize the header of an object at the start of freshly allocated memory,
+    /// and register it with this space.
+    ///
+    /// The object is added to the list of objects, if one is given.
+    #[inline(always)]
+    unsafe fn init_in<T: super::RawAllocTarget<Id>>(
+        &self,
+        raw_ptr: NonNull<u8>,
+        destruction_queue: &UnsafeCell<DestructionQueue<Id>>,
+        objects: Option<&UnsafeCell<Vec<NonNull<GcHeader<Id>>>>>,
+        target: &T,
+    ) -> NonNull<T::Header> {
+        let header_ptr = raw_ptr.add(target.header_offset()).cast::<T::Header>();
+        if let Some(objects) = objects {
+            (*objects.get()).push(header_ptr.cast::<GcHeader<Id>>());
+        }
         let drop_index = if target.needs_drop() {
-            let index = (*self.destruction_queue.get()).len();
-            (*self.destruction_queue.get()).push(Some(header_ptr.cast::<GcHeader<Id>>()));
-            assert!(index < u32::MAX as usize);
-            index as u32
+            (*destruction_queue.get()).push(header_ptr.cast::<GcHeader<Id>>())
         } else {
-            u32::MAX
+            Self::NO_DROP_INDEX
         };
         target.init_header(
             header_ptr,
-            GcHeader {
-                state_bits: Cell::new(target.init_state_bits(GenerationId::Young)),
-                alloc_info: AllocInfo {
+            GcHeader::new(
+                target.init_state_bits(GenerationId::Young),
+                AllocInfo {
                     nontrivial_drop_index: drop_index,
                 },
-                metadata: target.header_metadata(),
-                collector_id: self.collector_id,
-            },
+                target.header_metadata(),
+                self.collector_id,
+            ),
         );
-        Ok(header_ptr)
+        header_ptr
+    }
+
+    /// Attempt to grow a young array to `new_len` elements without moving it,
+    /// returning `true` on success.
+    ///
+    /// Arrays in the eden arena are allocated upwards from the array buffer.
+    /// If the array is the newest allocation in the buffer,
+    /// its end is the buffer's cursor, which is bumped to make room for the new elements.
+    /// Otherwise, growth only succeeds within the padding at the end of the existing allocation,
+    /// and the caller must reallocate.
+    ///
+    /// ## Safety
+    /// The header must be a valid young array allocated by this space.
+    /// On success, the caller must initialize the new elements
+    /// before the array is next accessed or collected.
+    pub unsafe fn try_extend_in_place(
+        &self,
+        header: NonNull<GcArrayHeader<Id>>,
+        new_len: usize,
+    ) -> bool {
+        let main_header = &header.as_ref().main_header;
+        debug_assert_eq!(
+            main_header.state_bits.get().generation(),
+            GenerationId::Young
+        );
+        debug_assert!(main_header.state_bits.get().array());
+        let old_len = header.as_ref().len_elements;
+        if new_len <= old_len {
+            return new_len == old_len;
+        }
+        let old_layout = header.as_ref().layout_info();
+        let Ok(new_layout) = GcArrayLayoutInfo::<Id>::new(old_layout.element_layout(), new_len)
+        else {
+            return false;
+        };
+        let old_size = old_layout.overall_layout().size();
+        let new_size = new_layout.overall_layout().size();
+        if new_size > old_size {
+            let start = header.cast::<u8>().as_ptr().sub(old_layout.header_offset());
+            if start != self.array_buffer.last.get() || new_size > self.size_limit {
+                return false;
+            }
+            debug_assert_eq!(start.add(old_size), self.array_buffer.cursor.get());
+            if new_size > self.array_buffer.limit.get() as usize - start as usize {
+                return false;
+            }
+            self.array_buffer.cursor.set(start.add(new_size));
+            (*self.alloc.get()).count_bytes(new_size - old_size);
+        }
+        (*header.as_ptr()).len_elements = new_len;
+        true
+    }
+
+    /// The cursor and limit of the region generated code allocates from,
+    /// which is empty until [refilled](Self::refill_allocation_cursor).
+    ///
+    /// ## Safety
+    /// Generated code must follow the contract of [`AllocationCursor`].
+    #[cfg(feature = "jit-layout")]
+    #[inline]
+    pub unsafe fn allocation_cursor(&self) -> AllocationCursor {
+        AllocationCursor {
+            cursor: NonNull::new_unchecked(self.jit_buffer.cursor.as_ptr()),
+            limit: NonNull::new_unchecked(self.jit_buffer.limit.as_ptr()),
+        }
+    }
+
+    /// Reserve a fresh region of the eden arena for generated code to allocate from,
+    /// abandoning the remainder of the current region.
+    ///
+    /// Returns the size of the new region.
+    #[cfg(feature = "jit-layout")]
+    pub unsafe fn refill_allocation_cursor(&self) -> Result<usize, YoungAllocError> {
+        let size = self
+            .size_limit
+            .next_multiple_of(GcHeader::<Id>::FIXED_ALIGNMENT);
+        let layout = Layout::from_size_align(size, GcHeader::<Id>::FIXED_ALIGNMENT)
+            .map_err(|_| YoungAllocError::SizeExceedsLimit)?;
+        let Ok(region) = (*self.alloc.get()).allocate(layout) else {
+            return Err(YoungAllocError::OutOfMemory);
+        };
+        let start = region.cast::<u8>().as_ptr();
+        self.jit_buffer.cursor.set(start);
+        self.jit_buffer.limit.set(start.add(size));
+        Ok(size)
+    }
+
+    /// The address which generated code will allocate from next.
+    ///
+    /// This only advances when generated code allocates.
+    #[cfg(feature = "jit-layout")]
+    #[inline]
+    pub fn jit_cursor(&self) -> *mut u8 {
+        self.jit_buffer.cursor.get()
+    }
+
+    /// Create an empty region, returning its index.
+    pub fn create_region(&self) -> usize {
+        let regions = unsafe { &mut *self.regions.get() };
+        regions.push(Region {
+            alloc: CountingAlloc::new(YoungAlloc::new(self.chunk_size)),
+            objects: UnsafeCell::new(Vec::new()),
+        });
+        regions.len() - 1
+    }
+
+    /// Set the region which new objects are allocated in,
+    /// returning the previously active region.
+    #[inline]
+    pub fn set_active_region(&self, index: Option<usize>) -> Option<usize> {
+        self.active_region.replace(index)
+    }
+
+    /// The region which new objects are allocated in.
+    #[inline]
+    pub fn active_region(&self) -> Option<usize> {
+        self.active_region.get()
+    }
+
+    /// The objects allocated in the specified region since it was last emptied.
+    pub unsafe fn region_objects(&self, index: usize) -> &'_ [NonNull<GcHeader<Id>>] {
+        &*(&*self.regions.get())[index].objects.get()
+    }
+
+    #[inline]
+    pub fn region_allocated_bytes(&self, index: usize) -> usize {
+        unsafe { (&*self.regions.get())[index].alloc.allocated_bytes() }
+    }
+
+    /// Free every object in the specified region at once, without tracing.
+    ///
+    /// ## Safety
+    /// No object in the region may be reachable from outside of it,
+    /// and this must not be called during a collection.
+    pub unsafe fn discard_region(&self, index: usize) {
+        let region = &mut (&mut *self.regions.get())[index];
+        let destruction_queue = &mut *self.destruction_queue.get();
+        for header in region.objects.get_mut().drain(..) {
+            if header.as_ref().state_bits.get().forwarded() {
+                continue; // evacuated, which already removed it from the queue
+            }
+            debug_assert!(!header.as_ref().is_pinned());
+            let drop_index = header.as_ref().alloc_info.nontrivial_drop_index;
+            if drop_index != Self::NO_DROP_INDEX {
+                destruction_queue.remove(drop_index);
+                if header.as_ref().state_bits.get().value_initialized() {
+                    header.as_ref().invoke_destructor();
+                }
+            }
+        }
+        region.alloc.as_inner_mut().reset();
+        region.alloc.reset_count();
+    }
+
+    /// Every object in this space, including dead objects which have not yet been swept.
+    ///
+    /// Must not be called during a collection.
+    #[cfg(any(feature = "classify-ptr", debug_assertions))]
+    pub unsafe fn objects(&self) -> impl Iterator<Item = NonNull<GcHeader<Id>>> + '_ {
+        (*self.eden_objects.get())
+            .iter()
+            .chain((*self.survivor_from_objects.get()).iter())
+            .chain((*self.retained_pinned.get()).iter())
+            .chain(
+                (*self.regions.get())
+                    .iter()
+                    .flat_map(|region| (*region.objects.get()).iter()),
+            )
+            .copied()
     }
 
     #[inline]
     pub fn allocated_bytes(&self) -> usize {
-        self.alloc.allocated_bytes()
+        // the arenas are only replaced while sweeping, which never overlaps this
+        unsafe {
+            (*self.alloc.get()).allocated_bytes()
+                + (*self.survivor_from.get()).allocated_bytes()
+                + (*self.survivor_to.get()).allocated_bytes()
+                + (*self.regions.get())
+                    .iter()
+                    .map(|region| region.alloc.allocated_bytes())
+                    .sum::<usize>()
+        }
+    }
+
+    /// The age at which surviving objects are promoted to the old generation.
+    #[inline]
+    pub fn age_threshold(&self) -> u8 {
+        self.age_threshold
     }
 }
 impl<Id: CollectorId> Drop for YoungGenerationSpace<Id> {
     fn drop(&mut self) {
         // drop all pending objects
         for header in self.destruction_queue.get_mut().iter() {
-            if let Some(header) = header {
-                unsafe { header.as_ref().invoke_destructor() }
+            if unsafe { !header.as_ref().state_bits.get().value_initialized() } {
+                continue; // failed initialization
+            }
+            unsafe { header.as_ref().invoke_destructor() }
+        }
+        for header in self.retained_pinned.get_mut().iter() {
+            unsafe {
+                if !header.as_ref().state_bits.get().forwarded() {
+                    header.as_ref().invoke_destructor()
+                }
             }
         }
     }
 }
+/// The specified list of objects, if every object in the heap is tracked.
+///
+/// Regions always track their objects, since discarding them requires it.
+#[inline(always)]
+fn tracked<Id: CollectorId>(
+    objects: &UnsafeCell<Vec<NonNull<GcHeader<Id>>>>,
+) -> Option<&UnsafeCell<Vec<NonNull<GcHeader<Id>>>>> {
+    cfg!(any(feature = "classify-ptr", debug_assertions)).then_some(objects)
+}
+
+/// An additional eden arena, whose objects can be discarded all at once.
+///
+/// Objects in a region share the destruction queue of the main eden arena,
+/// so they are evacuated and swept by a collection just like any other young object.
+struct Region<Id: CollectorId> {
+    alloc: CountingAlloc<YoungAlloc>,
+    /// Every object allocated in this region.
+    objects: UnsafeCell<Vec<NonNull<GcHeader<Id>>>>,
+}
+
+/// A region of the eden arena, which generated code bumps upwards from `cursor` to `limit`.
+#[cfg(feature = "jit-layout")]
+struct JitBuffer {
+    cursor: Cell<*mut u8>,
+    limit: Cell<*mut u8>,
+}
+#[cfg(feature = "jit-layout")]
+impl JitBuffer {
+    /// Empty the region once the arena has been reset,
+    /// so the next allocation takes the slow path.
+    fn clear(&self) {
+        self.cursor.set(std::ptr::null_mut());
+        self.limit.set(std::ptr::null_mut());
+    }
+}
+
+/// A region of the eden arena, which arrays are bumped upwards into from `cursor` to `limit`.
+///
+/// The arena itself bumps downwards, so the end of its newest allocation abuts the previous one.
+/// Bumping upwards leaves room after the newest array, which it can grow into.
+struct ArrayBuffer {
+    cursor: Cell<*mut u8>,
+    limit: Cell<*mut u8>,
+    /// The start of the newest array, which is the only one that can grow.
+    last: Cell<*mut u8>,
+}
+impl ArrayBuffer {
+    fn new() -> Self {
+        ArrayBuffer {
+            cursor: Cell::new(std::ptr::null_mut()),
+            limit: Cell::new(std::ptr::null_mut()),
+            last: Cell::new(std::ptr::null_mut()),
+        }
+    }
+
+    /// Allocate the specified layout from the buffer,
+    /// returning `None` if it doesn't fit.
+    fn bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let cursor = self.cursor.get();
+        let start = (cursor as usize).checked_next_multiple_of(layout.align())?;
+        let end = start.checked_add(layout.size())?;
+        if cursor.is_null() || end > self.limit.get() as usize {
+            return None;
+        }
+        let start = cursor.wrapping_add(start - cursor as usize);
+        self.cursor.set(cursor.wrapping_add(end - cursor as usize));
+        self.last.set(start);
+        NonNull::new(start)
+    }
+
+    /// Replace the buffer with a fresh block of `size` bytes.
+    fn refill(&self, block: NonNull<u8>, size: usize) {
+        self.cursor.set(block.as_ptr());
+        self.limit.set(block.as_ptr().wrapping_add(size));
+        self.last.set(std::ptr::null_mut());
+    }
+
+    /// Empty the buffer once the arena has been reset.
+    fn clear(&self) {
+        self.cursor.set(std::ptr::null_mut());
+        self.limit.set(std::ptr::null_mut());
+        self.last.set(std::ptr::null_mut());
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum YoungAllocError {
     #[error("Out of memory (young-gen)")]
@@ -0,0 +1,69 @@
+//! The queue of young objects whose destructors must run if they die.
+//!
+//! Objects are removed from the queue when they are evacuated,
+//! so by the end of a collection most entries are usually empty.
+//! Entries are grouped into fixed-size chunks, each counting its remaining objects,
+//! so sweeping can skip chunks which were emptied entirely.
+
+use std::ptr::NonNull;
+
+use crate::context::layout::GcHeader;
+use crate::CollectorId;
+
+/// The number of entries in each chunk.
+const CHUNK_SIZE: usize = 256;
+
+pub struct DestructionQueue<Id: CollectorId> {
+    /// The entries of every chunk, indexed by `nontrivial_drop_index`.
+    entries: Vec<Option<NonNull<GcHeader<Id>>>>,
+    /// The number of entries in each chunk which have not been removed.
+    chunk_counts: Vec<u32>,
+}
+impl<Id: CollectorId> DestructionQueue<Id> {
+    #[inline]
+    pub const fn new() -> Self {
+        DestructionQueue {
+            entries: Vec::new(),
+            chunk_counts: Vec::new(),
+        }
+    }
+
+    /// Add an object to the queue, returning its index.
+    #[inline]
+    pub fn push(&mut self, header: NonNull<GcHeader<Id>>) -> u32 {
+        let index = self.entries.len();
+        assert!(index < u32::MAX as usize);
+        if index.is_multiple_of(CHUNK_SIZE) {
+            self.chunk_counts.push(0);
+        }
+        self.entries.push(Some(header));
+        *self.chunk_counts.last_mut().unwrap() += 1;
+        index as u32
+    }
+
+    /// Remove the object with the specified index in constant time.
+    #[inline]
+    pub fn remove(&mut self, index: u32) {
+        let index = index as usize;
+        let removed = self.entries[index].take();
+        debug_assert!(removed.is_some(), "Entry {index} already removed");
+        self.chunk_counts[index / CHUNK_SIZE] -= 1;
+    }
+
+    /// Iterate over the objects remaining in the queue, in the order they were added.
+    ///
+    /// Chunks whose objects have all been removed are skipped without being scanned.
+    pub fn iter(&self) -> impl Iterator<Item = NonNull<GcHeader<Id>>> + '_ {
+        self.entries
+            .chunks(CHUNK_SIZE)
+            .zip(self.chunk_counts.iter())
+            .filter(|&(_, &count)| count > 0)
+            .flat_map(|(chunk, _)| chunk.iter().flatten().copied())
+    }
+}
+impl<Id: CollectorId> Default for DestructionQueue<Id> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,166 @@
+//! A copy-on-write pointer to GC data.
+//!
+//! The collector does not count references,
+//! so it cannot tell whether a [`Gc`] pointer is unique like [`Rc::make_mut`](std::rc::Rc::make_mut) does.
+//! Instead, a [`GcCow`] remembers whether it allocated its object itself,
+//! and whether the pointer has been shared since.
+
+use std::cell::Cell;
+use std::fmt::{Debug, Formatter};
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+use crate::{Collect, CollectContext, CollectorId, GarbageCollector, Gc};
+
+/// A pointer to GC data, which is cloned into a fresh allocation before the first mutation.
+///
+/// A `GcCow` created from an existing [`Gc`] pointer starts out shared.
+/// Calling [`GcCow::make_mut`] clones the value into an allocation owned by the `GcCow`,
+/// which can then be mutated in place until the pointer is shared again,
+/// either by [`GcCow::share`] or by cloning the `GcCow`.
+pub struct GcCow<'gc, T, Id: CollectorId> {
+    ptr: Gc<'gc, T, Id>,
+    /// Whether the object was allocated by this `GcCow` and has never been shared.
+    owned: Cell<bool>,
+}
+impl<'gc, T: Collect<Id>, Id: CollectorId> GcCow<'gc, T, Id> {
+    /// Wrap a pointer which may be shared.
+    #[inline]
+    pub fn new(ptr: Gc<'gc, T, Id>) -> Self {
+        GcCow {
+            ptr,
+            owned: Cell::new(false),
+        }
+    }
+
+    /// Allocate a value which is owned by the new `GcCow`,
+    /// so it can be mutated without copying.
+    #[inline]
+    pub fn alloc(collector: &'gc GarbageCollector<Id>, value: T) -> Self {
+        GcCow {
+            ptr: collector.alloc(value),
+            owned: Cell::new(true),
+        }
+    }
+
+    /// Check if the object is owned by this `GcCow`,
+    /// so that [`GcCow::make_mut`] will not need to copy it.
+    #[inline]
+    pub fn is_owned(&self) -> bool {
+        self.owned.get()
+    }
+
+    /// Get the underlying pointer.
+    ///
+    /// This shares the object, so the next call to [`GcCow::make_mut`] will copy it.
+    #[inline]
+    pub fn share(&self) -> Gc<'gc, T, Id> {
+        self.owned.set(false);
+        self.ptr
+    }
+
+    /// Get a mutable reference to the value,
+    /// cloning it into a fresh allocation if it is shared.
+    ///
+    /// The write barrier is performed when the returned guard is dropped.
+    /// An owned object which has been [frozen](Gc::freeze) is also copied.
+    #[track_caller]
+    pub fn make_mut<'a>(
+        &'a mut self,
+        collector: &'gc GarbageCollector<Id>,
+    ) -> GcCowMut<'a, 'gc, T, Id>
+    where
+        T: Clone,
+    {
+        if !self.owned.get() || self.ptr.is_frozen(collector) {
+            self.ptr = collector.alloc(T::clone(&self.ptr));
+            self.owned.set(true);
+        }
+        if T::NEEDS_COLLECT {
+            collector.record_mutation(NonNull::from(self.ptr.header()));
+        }
+        GcCowMut {
+            // SAFETY: An owned object is only reachable through this `GcCow`,
+            // which is borrowed mutably for the lifetime of the guard
+            value: unsafe { &mut *self.ptr.as_raw_ptr().as_ptr() },
+            ptr: self.ptr,
+            collector,
+        }
+    }
+}
+unsafe impl<'gc, Id: CollectorId, T: Collect<Id>> Collect<Id> for GcCow<'gc, T, Id> {
+    type Collected<'newgc> = GcCow<'newgc, T::Collected<'newgc>, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        // moving the object does not share it
+        <Gc<'gc, T, Id> as Collect<Id>>::collect_inplace(
+            NonNull::from(&mut (*target.as_ptr()).ptr),
+            context,
+        );
+    }
+}
+impl<'gc, T, Id: CollectorId> Deref for GcCow<'gc, T, Id> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.ptr
+    }
+}
+impl<'gc, T, Id: CollectorId> Clone for GcCow<'gc, T, Id> {
+    /// Clone the pointer, sharing the object between both copies.
+    #[inline]
+    fn clone(&self) -> Self {
+        self.owned.set(false);
+        GcCow {
+            ptr: self.ptr,
+            owned: Cell::new(false),
+        }
+    }
+}
+impl<'gc, T: Collect<Id>, Id: CollectorId> From<Gc<'gc, T, Id>> for GcCow<'gc, T, Id> {
+    #[inline]
+    fn from(ptr: Gc<'gc, T, Id>) -> Self {
+        Self::new(ptr)
+    }
+}
+impl<'gc, T: Debug, Id: CollectorId> Debug for GcCow<'gc, T, Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+/// A mutable borrow of the value of a [`GcCow`],
+/// which performs the write barrier when dropped.
+pub struct GcCowMut<'a, 'gc, T: Collect<Id>, Id: CollectorId> {
+    value: &'a mut T,
+    ptr: Gc<'gc, T, Id>,
+    collector: &'gc GarbageCollector<Id>,
+}
+impl<T: Collect<Id>, Id: CollectorId> Deref for GcCowMut<'_, '_, T, Id> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+impl<T: Collect<Id>, Id: CollectorId> DerefMut for GcCowMut<'_, '_, T, Id> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+impl<T: Collect<Id>, Id: CollectorId> Drop for GcCowMut<'_, '_, T, Id> {
+    #[inline]
+    fn drop(&mut self) {
+        if T::NEEDS_COLLECT {
+            unsafe {
+                let field = NonNull::from(&mut *self.value);
+                self.collector.write_barrier(self.ptr, field);
+            }
+        }
+    }
+}
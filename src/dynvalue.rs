@@ -0,0 +1,221 @@
+//! A NaN-boxed dynamic value, the standard representation for dynamic-language VMs.
+//!
+//! A [`GcValue`] is a single word, which stores small integers, booleans, doubles,
+//! and `null` inline, and heap objects as [`DynGc`] pointers.
+//! Only the pointer case is traced by the collector.
+//!
+//! The encoding follows JavaScriptCore:
+//! - Pointers have the upper 16 bits clear, relying on 48-bit virtual addresses.
+//! - Integers have the upper 16 bits set, with the value in the low 32 bits.
+//! - Doubles are offset by `2^49`, so their upper 16 bits are never all clear or all set.
+//! - `null`, `false`, and `true` are small constants, which are never valid pointers.
+//!
+//! Only available with the `dynvalue` feature on 64-bit targets.
+
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+use crate::context::dynamic::DynGc;
+use crate::{Collect, CollectContext, CollectorId};
+
+const INT_TAG: u64 = 0xFFFF_0000_0000_0000;
+const DOUBLE_OFFSET: u64 = 1 << 49;
+const POINTER_MASK: u64 = 0xFFFF_0000_0000_0000;
+const NULL_BITS: u64 = 0x02;
+const FALSE_BITS: u64 = 0x06;
+const TRUE_BITS: u64 = 0x07;
+
+/// The unpacked contents of a [`GcValue`].
+#[derive(Debug, Copy, Clone)]
+pub enum GcValueKind<'gc, Id: CollectorId> {
+    Null,
+    Bool(bool),
+    Int(i32),
+    Double(f64),
+    Object(DynGc<'gc, Id>),
+}
+
+/// A dynamically typed value, packed into a single word.
+///
+/// See the [module documentation](self) for the encoding.
+pub struct GcValue<'gc, Id: CollectorId> {
+    /// The encoded bits, stored as a pointer to preserve the provenance of objects.
+    ///
+    /// Only the pointer case is ever dereferenced.
+    bits: *mut u8,
+    marker: PhantomData<DynGc<'gc, Id>>,
+}
+impl<'gc, Id: CollectorId> GcValue<'gc, Id> {
+    #[inline]
+    fn from_bits(bits: u64) -> Self {
+        #[cfg(feature = "strict-provenance")]
+        let bits = std::ptr::without_provenance_mut(bits as usize);
+        #[cfg(not(feature = "strict-provenance"))]
+        let bits = bits as usize as *mut u8;
+        GcValue {
+            bits,
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn bits(&self) -> u64 {
+        crate::utils::addr(self.bits) as u64
+    }
+
+    #[inline]
+    pub fn null() -> Self {
+        Self::from_bits(NULL_BITS)
+    }
+
+    #[inline]
+    pub fn from_bool(value: bool) -> Self {
+        Self::from_bits(if value { TRUE_BITS } else { FALSE_BITS })
+    }
+
+    #[inline]
+    pub fn from_i32(value: i32) -> Self {
+        Self::from_bits(INT_TAG | (value as u32 as u64))
+    }
+
+    /// Create a value from a double.
+    ///
+    /// All NaNs are canonicalized, so their payload is not preserved.
+    #[inline]
+    pub fn from_f64(value: f64) -> Self {
+        let value = if value.is_nan() { f64::NAN } else { value };
+        Self::from_bits(value.to_bits().wrapping_add(DOUBLE_OFFSET))
+    }
+
+    /// Create a value from an object pointer.
+    ///
+    /// ## Panics
+    /// If the address of the object does not fit in 48 bits.
+    #[inline]
+    pub fn from_object(object: DynGc<'gc, Id>) -> Self {
+        let ptr = object.as_ptr().as_ptr();
+        assert_eq!(
+            crate::utils::addr(ptr) as u64 & POINTER_MASK,
+            0,
+            "Object address exceeds 48 bits"
+        );
+        GcValue {
+            bits: ptr,
+            marker: PhantomData,
+        }
+    }
+
+    /// Unpack the contents of this value.
+    #[inline]
+    pub fn kind(&self) -> GcValueKind<'gc, Id> {
+        let bits = self.bits();
+        if bits & INT_TAG == INT_TAG {
+            GcValueKind::Int(bits as u32 as i32)
+        } else if bits & POINTER_MASK != 0 {
+            GcValueKind::Double(f64::from_bits(bits.wrapping_sub(DOUBLE_OFFSET)))
+        } else {
+            match bits {
+                NULL_BITS => GcValueKind::Null,
+                FALSE_BITS => GcValueKind::Bool(false),
+                TRUE_BITS => GcValueKind::Bool(true),
+                _ => GcValueKind::Object(unsafe {
+                    DynGc::from_raw_ptr(NonNull::new_unchecked(self.bits))
+                }),
+            }
+        }
+    }
+
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        self.bits() == NULL_BITS
+    }
+
+    #[inline]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.kind() {
+            GcValueKind::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_i32(&self) -> Option<i32> {
+        match self.kind() {
+            GcValueKind::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.kind() {
+            GcValueKind::Double(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_object(&self) -> Option<DynGc<'gc, Id>> {
+        match self.kind() {
+            GcValueKind::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+}
+unsafe impl<'gc, Id: CollectorId> Collect<Id> for GcValue<'gc, Id> {
+    type Collected<'newgc> = GcValue<'newgc, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        if let Some(mut object) = target.as_ref().as_object() {
+            <DynGc<'gc, Id> as Collect<Id>>::collect_inplace(NonNull::from(&mut object), context);
+            target
+                .cast::<GcValue<'_, Id>>()
+                .write(GcValue::from_object(object));
+        }
+    }
+}
+impl<'gc, Id: CollectorId> Copy for GcValue<'gc, Id> {}
+impl<'gc, Id: CollectorId> Clone for GcValue<'gc, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'gc, Id: CollectorId> Debug for GcValue<'gc, Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.kind() {
+            GcValueKind::Null => f.write_str("null"),
+            GcValueKind::Bool(value) => Debug::fmt(&value, f),
+            GcValueKind::Int(value) => Debug::fmt(&value, f),
+            GcValueKind::Double(value) => Debug::fmt(&value, f),
+            GcValueKind::Object(object) => Debug::fmt(&object, f),
+        }
+    }
+}
+impl<'gc, Id: CollectorId> From<bool> for GcValue<'gc, Id> {
+    #[inline]
+    fn from(value: bool) -> Self {
+        Self::from_bool(value)
+    }
+}
+impl<'gc, Id: CollectorId> From<i32> for GcValue<'gc, Id> {
+    #[inline]
+    fn from(value: i32) -> Self {
+        Self::from_i32(value)
+    }
+}
+impl<'gc, Id: CollectorId> From<f64> for GcValue<'gc, Id> {
+    #[inline]
+    fn from(value: f64) -> Self {
+        Self::from_f64(value)
+    }
+}
+impl<'gc, Id: CollectorId> From<DynGc<'gc, Id>> for GcValue<'gc, Id> {
+    #[inline]
+    fn from(object: DynGc<'gc, Id>) -> Self {
+        Self::from_object(object)
+    }
+}
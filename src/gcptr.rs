@@ -2,7 +2,9 @@ use std::marker::PhantomData;
 use std::ops::Deref;
 use std::ptr::NonNull;
 
+use crate::collect::IsGcPointer;
 use crate::context::layout::{GcHeader, GcTypeInfo};
+use crate::context::GcPin;
 use crate::{Collect, CollectContext, CollectorId, GarbageCollector};
 
 pub struct Gc<'gc, T, Id: CollectorId> {
@@ -32,23 +34,208 @@ impl<'gc, T: Collect<Id>, Id: CollectorId> Gc<'gc, T, Id> {
         GcTypeInfo::new::<Self>()
     }
 
+    /// Pin this object, preventing it from moving until the guard is dropped.
+    ///
+    /// See [`GarbageCollector::pin`] for details.
+    #[inline]
+    pub fn pin(self, collector: &'gc GarbageCollector<Id>) -> GcPin<T::Collected<'static>, Id> {
+        collector.pin(self)
+    }
+
+    /// Freeze this object, marking it as immutable.
+    ///
+    /// Mutating APIs provided by the collector will panic on a frozen object,
+    /// including the [cells](crate::cell), the elements of arrays, the collections,
+    /// and [`DynGc::set_slot`](crate::context::dynamic::DynGc::set_slot).
+    /// The [write barriers](GarbageCollector::write_barrier) also panic,
+    /// so cells implemented by unsafe code are checked after each store.
+    /// Freezing is permanent, and is preserved when the object moves.
+    ///
+    /// Freezing only forbids mutation.
+    /// Frozen objects are still traced by every collection,
+    /// since their children are only kept alive by being marked,
+    /// and there is no incremental marking whose re-tracing could be skipped.
+    /// They can only be shared with other threads by a [`GarbageCollector::par_scope`].
+    ///
+    /// This only freezes the object itself, see [`Gc::freeze_deep`] to freeze its children.
+    #[inline]
+    pub fn freeze(self, collector: &'gc GarbageCollector<Id>) -> Self {
+        assert_eq!(self.id(), collector.id());
+        unsafe {
+            collector.freeze_raw(NonNull::from(self.header()), false);
+        }
+        self
+    }
+
+    /// Freeze this object and everything reachable from it.
+    ///
+    /// Objects which were already frozen by an earlier deep freeze are skipped,
+    /// as their children are already frozen.
+    /// Objects frozen by a shallow [`Gc::freeze`] still have their children frozen.
+    pub fn freeze_deep(self, collector: &'gc GarbageCollector<Id>) -> Self {
+        assert_eq!(self.id(), collector.id());
+        unsafe {
+            collector.freeze_raw(NonNull::from(self.header()), true);
+        }
+        self
+    }
+
+    /// Check if this object has been [frozen](Gc::freeze).
+    #[inline]
+    pub fn is_frozen(&self, collector: &'gc GarbageCollector<Id>) -> bool {
+        assert_eq!(self.id(), collector.id());
+        self.header().is_frozen()
+    }
+
+    #[inline]
+    pub fn is_pinned(&self) -> bool {
+        self.header().is_pinned()
+    }
+
+    /// The number of [guards](GcPin) currently pinning this object.
+    ///
+    /// This is zero for objects which are pinned permanently,
+    /// like those from [`GarbageCollector::alloc_pinned`].
+    #[inline]
+    pub fn pin_count(&self, collector: &'gc GarbageCollector<Id>) -> usize {
+        assert_eq!(self.id(), collector.id());
+        collector.pin_count_raw(NonNull::from(self.header()))
+    }
+
+    /// The word of user-controlled metadata stored in the object's header,
+    /// which is zero until [set](Gc::set_user_data).
+    ///
+    /// This is only available with the `user-word` feature,
+    /// and is preserved when the object moves.
+    /// Like [`Gc::is_pinned`], this requires the collector
+    /// because the word could be concurrently modified.
+    #[cfg(feature = "user-word")]
+    #[inline]
+    pub fn user_data(&self, collector: &'gc GarbageCollector<Id>) -> usize {
+        assert_eq!(self.id(), collector.id());
+        self.header().user_word()
+    }
+
+    /// Set the word of user-controlled metadata stored in the object's header.
+    ///
+    /// This is useful for language runtimes which need per-object metadata,
+    /// like a hash code, shape pointer, or lock word.
+    /// The collector never interprets the value,
+    /// so it must not be used to store GC pointers.
+    ///
+    /// Unlike other mutations, this is permitted on [frozen](Gc::freeze) objects.
+    #[cfg(feature = "user-word")]
+    #[inline]
+    pub fn set_user_data(&self, collector: &'gc GarbageCollector<Id>, value: usize) {
+        assert_eq!(self.id(), collector.id());
+        self.header().set_user_word(value);
+    }
+
+    /// The [collection epoch](GarbageCollector::collection_count) in which
+    /// the address of this object is known to be valid.
+    ///
+    /// A cache keyed by the address of the object's header
+    /// can record this alongside the address,
+    /// and treat the entry as stale once the collector's epoch has changed.
+    #[inline]
+    pub fn header_epoch(&self, collector: &'gc GarbageCollector<Id>) -> u64 {
+        assert_eq!(self.id(), collector.id());
+        // the pointer is tied to the borrow of the collector, so no collection has happened since
+        collector.collection_count()
+    }
+
+    /// A hash code for the identity of this object,
+    /// which remains stable even when the object moves.
+    ///
+    /// Because objects move, their address cannot be used as a hash code.
+    /// Instead, a unique hash is lazily assigned on first use and tracked by the collector.
+    #[inline]
+    pub fn identity_hash(&self, collector: &'gc GarbageCollector<Id>) -> u64 {
+        assert_eq!(self.id(), collector.id());
+        collector.identity_hash_raw(NonNull::from(self.header()))
+    }
+
+    /// Project this pointer to one of the value's fields.
+    ///
+    /// Unlike a plain reference, the resulting [`GcField`] can be traced,
+    /// so it remains valid after the object moves.
+    ///
+    /// ## Panics
+    /// If the closure returns a reference outside the value.
+    #[inline]
+    pub fn project<F: Collect<Id>>(
+        self,
+        func: impl for<'a> FnOnce(&'a T) -> &'a F,
+    ) -> GcField<'gc, T, F, Id> {
+        let base = crate::utils::addr(self.ptr.as_ptr());
+        let field = crate::utils::addr(func(&*self));
+        assert!(
+            field >= base && field - base + size_of::<F>() <= size_of::<T>(),
+            "Projection must return a field of the value"
+        );
+        GcField {
+            base: self,
+            offset: field - base,
+            marker: PhantomData,
+        }
+    }
+
     #[inline(always)]
     pub unsafe fn as_raw_ptr(&self) -> NonNull<T> {
         self.ptr
     }
 
-    #[inline(always)]
-    pub unsafe fn from_raw_ptr(ptr: NonNull<T>) -> Self {
-        Gc {
-            ptr,
-            marker: PhantomData,
-            collect_marker: PhantomData,
+    /// Convert this pointer into a raw pointer to its value,
+    /// which can be stored in foreign or intrusive data structures.
+    ///
+    /// The collector can still move or free the object,
+    /// so the raw pointer is only valid until the next collection.
+    /// To keep it valid, the object must be [pinned](Gc::pin),
+    /// or the raw pointer must be converted back and traced before collecting.
+    ///
+    /// Use [`Gc::from_raw`] to convert it back.
+    #[inline]
+    pub fn into_raw(self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    crate::utils::unsafe_api! {
+        /// Create a pointer from the raw value pointer.
+        ///
+        /// Only public with the `unsafe-api` feature.
+        ///
+        /// ## Safety
+        /// The pointer must refer to a live object of type `T`,
+        /// which is valid for the lifetime `'gc`.
+        #[inline(always)]
+        pub unsafe fn from_raw_ptr(ptr: NonNull<T>) -> Self {
+            Gc {
+                ptr,
+                marker: PhantomData,
+                collect_marker: PhantomData,
+            }
+        }
+    }
+
+    crate::utils::unsafe_api! {
+        /// Reconstruct a pointer from the result of [`Gc::into_raw`].
+        ///
+        /// Only public with the `unsafe-api` feature.
+        ///
+        /// ## Safety
+        /// The pointer must have been returned by [`Gc::into_raw`] for an object of type `T`,
+        /// and the object must not have been moved or freed since.
+        /// The same requirements as [`Gc::from_raw_ptr`] apply.
+        #[inline]
+        pub unsafe fn from_raw(ptr: *const T) -> Self {
+            Self::from_raw_ptr(NonNull::new_unchecked(ptr as *mut T))
         }
     }
 }
 unsafe impl<'gc, Id: CollectorId, T: Collect<Id>> Collect<Id> for Gc<'gc, T, Id> {
     type Collected<'newgc> = Gc<'newgc, T::Collected<'newgc>, Id>;
     const NEEDS_COLLECT: bool = true;
+    const IS_GC_POINTER: IsGcPointer = IsGcPointer::YES;
 
     #[inline]
     unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
@@ -74,3 +261,142 @@ impl<'gc, T, Id: CollectorId> Clone for Gc<'gc, T, Id> {
         *self
     }
 }
+
+/// A read-only view of the header of a GC object,
+/// recovered from a raw pointer to its value.
+#[repr(transparent)]
+pub struct GcObjectHeader<Id: CollectorId>(GcHeader<Id>);
+impl<Id: CollectorId> GcObjectHeader<Id> {
+    /// Recover the header of the object containing the specified value.
+    ///
+    /// This allows inspecting an object which has been erased to a raw pointer,
+    /// without knowing its type.
+    ///
+    /// ## Safety
+    /// The pointer must have been returned by [`Gc::into_raw`] (possibly cast to another type),
+    /// and the object must not have been moved or freed since.
+    #[inline]
+    pub unsafe fn from_value_ptr<'a>(ptr: *const ()) -> &'a Self {
+        &*(ptr
+            .cast::<u8>()
+            .sub(GcHeader::<Id>::REGULAR_VALUE_OFFSET)
+            .cast::<Self>())
+    }
+
+    /// Recover the header of an array from a pointer to its first element.
+    ///
+    /// ## Safety
+    /// The pointer must have been returned by [`GcArray::as_ptr`](crate::array::GcArray::as_ptr)
+    /// (possibly cast to another type),
+    /// and the array must not have been moved or freed since.
+    #[inline]
+    pub unsafe fn from_array_value_ptr<'a>(ptr: *const ()) -> &'a Self {
+        &*(ptr
+            .cast::<u8>()
+            .sub(GcHeader::<Id>::ARRAY_VALUE_OFFSET)
+            .cast::<Self>())
+    }
+
+    /// The id of the collector which owns the object.
+    #[inline]
+    pub fn id(&self) -> Id {
+        match unsafe { Id::summon_singleton() } {
+            None => self.0.id(),
+            Some(id) => id,
+        }
+    }
+
+    /// Check if the object is currently [pinned](Gc::pin),
+    /// meaning it will not be moved by the collector.
+    #[inline]
+    pub fn is_pinned(&self) -> bool {
+        self.0.is_pinned()
+    }
+
+    /// Check if the object has been [frozen](Gc::freeze).
+    #[inline]
+    pub fn is_frozen(&self) -> bool {
+        self.0.is_frozen()
+    }
+
+    /// Check if the object is an array.
+    #[inline]
+    pub fn is_array(&self) -> bool {
+        self.0.is_array()
+    }
+
+    /// A pointer to the value of the object,
+    /// which is the first element of an array.
+    #[inline]
+    pub fn value_ptr(&self) -> NonNull<()> {
+        if self.is_array() {
+            unsafe { self.0.assume_array_header().array_value_ptr().cast() }
+        } else {
+            self.0.regular_value_ptr().cast()
+        }
+    }
+}
+
+/// A pointer to a field of a GC object,
+/// created by [`Gc::project`].
+///
+/// This stores the base object and the offset of the field,
+/// so the address of the field is re-derived on every access.
+/// Tracing updates the base pointer,
+/// so the projection remains valid after the object moves.
+pub struct GcField<'gc, T, F, Id: CollectorId> {
+    base: Gc<'gc, T, Id>,
+    offset: usize,
+    marker: PhantomData<*const F>,
+}
+impl<'gc, T, F, Id: CollectorId> GcField<'gc, T, F, Id> {
+    /// The object containing the field.
+    #[inline]
+    pub fn base(&self) -> Gc<'gc, T, Id> {
+        self.base
+    }
+
+    /// The offset of the field in bytes, relative to the start of the value.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+unsafe impl<'gc, Id: CollectorId, T: Collect<Id>, F: Collect<Id>> Collect<Id>
+    for GcField<'gc, T, F, Id>
+{
+    type Collected<'newgc> = GcField<'newgc, T::Collected<'newgc>, F::Collected<'newgc>, Id>;
+    const NEEDS_COLLECT: bool = true;
+
+    #[inline]
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, Id>) {
+        <Gc<'gc, T, Id> as Collect<Id>>::collect_inplace(
+            NonNull::from(&mut (*target.as_ptr()).base),
+            context,
+        )
+    }
+}
+impl<'gc, T, F, Id: CollectorId> Deref for GcField<'gc, T, F, Id> {
+    type Target = F;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe {
+            &*self
+                .base
+                .ptr
+                .as_ptr()
+                .cast::<u8>()
+                .add(self.offset)
+                .cast::<F>()
+        }
+    }
+}
+impl<'gc, T, F, Id: CollectorId> Copy for GcField<'gc, T, F, Id> {}
+
+impl<'gc, T, F, Id: CollectorId> Clone for GcField<'gc, T, F, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
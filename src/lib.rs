@@ -1,11 +1,27 @@
 #![doc = include_str!("../README.md")]
 
+pub mod array;
+pub mod cell;
 pub mod collect;
+pub mod collections;
 pub mod context;
+pub mod cow;
+#[cfg(all(feature = "dynvalue", target_pointer_width = "64"))]
+pub mod dynvalue;
 mod gcptr;
+#[cfg(feature = "serde")]
+pub mod serde;
 pub(crate) mod utils;
 
-pub use self::collect::{Collect, NullCollect};
-pub use self::context::{CollectContext, CollectorId, GarbageCollector};
-
-pub use self::gcptr::Gc;
+pub use self::array::{GcArray, GcBytes, GcSlice, GcString};
+pub use self::cell::{GcCell, GcOnceCell, GcRefCell};
+pub use self::collect::{Collect, NullCollect, PlacementHint};
+pub use self::collections::{GcBTreeMap, GcHashMap, GcPersistentMap, GcPersistentVec};
+pub use self::context::{
+    BrandedGc, CollectContext, CollectionReason, CollectorId, DropOrder, GarbageCollector,
+    GcAnyHandle, GcConfig, GcError, GcExpiring, GcHandle, GcInterner, GcPin, GcRegion, GcScope,
+    GcStats, GcStatsDelta, GcWeakMap, Guardian, HandleScope, ScopedHandle, ScopedId, SnapshotError,
+    SnapshotType, Symbol, TraceFailurePolicy, TryReserveError, TypedAlloc, WeakGcHandle,
+};
+pub use self::cow::GcCow;
+pub use self::gcptr::{Gc, GcField, GcObjectHeader};
@@ -0,0 +1,601 @@
+//! Integration with [`serde`], enabled by the `serde` feature.
+//!
+//! Serializing a [`Gc`] or [`GcArray`] simply serializes its value.
+//! Deserializing requires a collector to allocate into,
+//! so GC types implement [`GcDeserialize`] instead of [`Deserialize`](serde::Deserialize),
+//! which receives a [`GcDeserializeContext`] wrapping the collector.
+//! A [`GcDeserializeSeed`] adapts this to serde's [`DeserializeSeed`] API,
+//! for use within hand-written visitors.
+//!
+//! ## Shared pointers
+//! By default, an object referenced by multiple pointers is serialized once for each pointer,
+//! and deserialized as separate copies.
+//! Wrapping the root in [`SerializeShared`] instead serializes each pointer as an `(id, Option<value>)` pair,
+//! where the value is only present the first time the object is encountered.
+//! These pointers must be deserialized with [`GcDeserializeContext::with_preserve_shared`],
+//! which reconstructs the aliasing of the original graph.
+//!
+//! Cycles are not supported by either format.
+//! A cycle through a [`GcRefCell`] serializes as a reference back to an object
+//! which is still being deserialized, so deserializing it fails with an error.
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt::Formatter;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+use serde::de::{DeserializeSeed, Error as _, SeqAccess, Visitor};
+use serde::ser::Error as _;
+use serde::{Deserializer, Serialize, Serializer};
+
+use crate::{Collect, CollectorId, GarbageCollector, Gc, GcArray, GcRefCell};
+
+#[doc(hidden)]
+pub use serde as __serde;
+
+thread_local! {
+    /// The ids assigned to objects by the active [`SerializeShared`], keyed by address.
+    static SHARED_IDS: RefCell<Option<HashMap<usize, u64>>> = const { RefCell::new(None) };
+}
+
+/// Serialize a GC object, which is located at the specified address.
+fn serialize_object<T: Serialize + ?Sized, S: Serializer>(
+    addr: usize,
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let shared = SHARED_IDS.with(|ids| {
+        ids.borrow_mut().as_mut().map(|ids| {
+            let next_id = ids.len() as u64;
+            match ids.entry(addr) {
+                Entry::Occupied(entry) => (*entry.get(), false),
+                Entry::Vacant(entry) => (*entry.insert(next_id), true),
+            }
+        })
+    });
+    match shared {
+        None => value.serialize(serializer),
+        Some((id, first)) => (id, first.then_some(value)).serialize(serializer),
+    }
+}
+
+impl<'gc, T: Serialize, Id: CollectorId> Serialize for Gc<'gc, T, Id> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_object(crate::utils::addr::<T>(&**self), &**self, serializer)
+    }
+}
+
+impl<'gc, T: Serialize, Id: CollectorId> Serialize for GcArray<'gc, T, Id> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_object(
+            crate::utils::addr(self.as_ptr().as_ptr()),
+            self.as_slice(),
+            serializer,
+        )
+    }
+}
+
+/// Serializes the current value.
+///
+/// Fails if the value is currently mutably borrowed.
+impl<T: Serialize> Serialize for GcRefCell<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.try_borrow() {
+            Ok(value) => value.serialize(serializer),
+            Err(cause) => Err(S::Error::custom(cause)),
+        }
+    }
+}
+
+/// Serialize a value, preserving which of its GC pointers are shared.
+///
+/// Each object is serialized only once,
+/// and later pointers to the same object refer back to it by id.
+/// The result must be deserialized using [`GcDeserializeContext::with_preserve_shared`].
+pub struct SerializeShared<'a, T: ?Sized>(pub &'a T);
+impl<T: Serialize + ?Sized> Serialize for SerializeShared<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let active = SHARED_IDS.with(|ids| {
+            let mut ids = ids.borrow_mut();
+            let active = ids.is_some();
+            if !active {
+                *ids = Some(HashMap::new());
+            }
+            active
+        });
+        if active {
+            // nested within another call, so ids must be shared with it
+            return self.0.serialize(serializer);
+        }
+        let _guard = scopeguard::guard((), |()| {
+            SHARED_IDS.with(|ids| *ids.borrow_mut() = None);
+        });
+        self.0.serialize(serializer)
+    }
+}
+
+/// A type which can be deserialized directly into the GC heap.
+///
+/// Types which do not contain GC pointers can implement this
+/// by delegating to [`Deserialize`](serde::Deserialize) with [`gc_deserialize_delegate!`](crate::gc_deserialize_delegate).
+pub trait GcDeserialize<'gc, 'de, Id: CollectorId>: Sized {
+    fn deserialize_gc<D: Deserializer<'de>>(
+        context: &GcDeserializeContext<'gc, Id>,
+        deserializer: D,
+    ) -> Result<Self, D::Error>;
+}
+
+/// Implement [`GcDeserialize`](crate::serde::GcDeserialize) for a list of types,
+/// by delegating to their implementation of [`Deserialize`](serde::Deserialize).
+///
+/// This is appropriate for types which do not contain GC pointers.
+///
+/// ```
+/// # use zerogc_next::gc_deserialize_delegate;
+/// #[derive(serde::Deserialize)]
+/// struct Span(u32, u32);
+/// gc_deserialize_delegate!(Span);
+/// ```
+#[macro_export]
+macro_rules! gc_deserialize_delegate {
+    ($($target:ty),* $(,)?) => {
+        $(
+            impl<'gc, 'de, Id: $crate::CollectorId> $crate::serde::GcDeserialize<'gc, 'de, Id> for $target {
+                #[inline]
+                fn deserialize_gc<D: $crate::serde::__serde::Deserializer<'de>>(
+                    _context: &$crate::serde::GcDeserializeContext<'gc, Id>,
+                    deserializer: D,
+                ) -> Result<Self, D::Error> {
+                    <Self as $crate::serde::__serde::Deserialize<'de>>::deserialize(deserializer)
+                }
+            }
+        )*
+    };
+}
+
+gc_deserialize_delegate!(
+    (),
+    bool,
+    char,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    f32,
+    f64,
+    String
+);
+
+/// An object which has been assigned an id by [`SerializeShared`].
+struct SharedObject {
+    /// The type of pointer, with its lifetimes erased.
+    type_id: TypeId,
+    /// The address of the object,
+    /// or `None` if it is still being deserialized.
+    ptr: Option<NonNull<()>>,
+}
+
+/// The state of a deserialization into the GC heap.
+pub struct GcDeserializeContext<'gc, Id: CollectorId> {
+    collector: &'gc GarbageCollector<Id>,
+    /// The objects which have been deserialized so far, by their id,
+    /// or `None` if shared pointers are not preserved.
+    shared: Option<RefCell<HashMap<u64, SharedObject>>>,
+}
+impl<'gc, Id: CollectorId> GcDeserializeContext<'gc, Id> {
+    #[inline]
+    pub fn new(collector: &'gc GarbageCollector<Id>) -> Self {
+        GcDeserializeContext {
+            collector,
+            shared: None,
+        }
+    }
+
+    /// Set whether GC pointers were serialized by [`SerializeShared`],
+    /// so that shared pointers are preserved.
+    ///
+    /// Defaults to `false`.
+    #[inline]
+    pub fn with_preserve_shared(mut self, preserve_shared: bool) -> Self {
+        self.shared = preserve_shared.then(Default::default);
+        self
+    }
+
+    #[inline]
+    pub fn preserve_shared(&self) -> bool {
+        self.shared.is_some()
+    }
+
+    #[inline]
+    pub fn collector(&self) -> &'gc GarbageCollector<Id> {
+        self.collector
+    }
+
+    /// A seed which deserializes a value of type `T` using this context.
+    #[inline]
+    pub fn seed<T>(&self) -> GcDeserializeSeed<'_, 'gc, T, Id> {
+        GcDeserializeSeed {
+            context: self,
+            marker: PhantomData,
+        }
+    }
+
+    /// Deserialize a value of type `T`.
+    #[inline]
+    pub fn deserialize<'de, T: GcDeserialize<'gc, 'de, Id>, D: Deserializer<'de>>(
+        &self,
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        T::deserialize_gc(self, deserializer)
+    }
+}
+
+/// A [`DeserializeSeed`] which deserializes a [`GcDeserialize`] type,
+/// created by [`GcDeserializeContext::seed`].
+pub struct GcDeserializeSeed<'a, 'gc, T, Id: CollectorId> {
+    context: &'a GcDeserializeContext<'gc, Id>,
+    marker: PhantomData<fn() -> T>,
+}
+impl<'a, 'gc, T, Id: CollectorId> GcDeserializeSeed<'a, 'gc, T, Id> {
+    #[inline]
+    pub fn context(&self) -> &'a GcDeserializeContext<'gc, Id> {
+        self.context
+    }
+}
+impl<'gc, T, Id: CollectorId> Clone for GcDeserializeSeed<'_, 'gc, T, Id> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'gc, T, Id: CollectorId> Copy for GcDeserializeSeed<'_, 'gc, T, Id> {}
+impl<'de, 'gc, T: GcDeserialize<'gc, 'de, Id>, Id: CollectorId> DeserializeSeed<'de>
+    for GcDeserializeSeed<'_, 'gc, T, Id>
+{
+    type Value = T;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<T, D::Error> {
+        T::deserialize_gc(self.context, deserializer)
+    }
+}
+
+/// Deserializes an optional value using the wrapped seed.
+struct OptionSeed<S>(S);
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for OptionSeed<S> {
+    type Value = Option<S::Value>;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_option(self)
+    }
+}
+impl<'de, S: DeserializeSeed<'de>> Visitor<'de> for OptionSeed<S> {
+    type Value = Option<S::Value>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("an optional value")
+    }
+
+    #[inline]
+    fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    #[inline]
+    fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    #[inline]
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.0.deserialize(deserializer).map(Some)
+    }
+}
+
+impl<'gc, 'de, T: GcDeserialize<'gc, 'de, Id>, Id: CollectorId> GcDeserialize<'gc, 'de, Id>
+    for Option<T>
+{
+    #[inline]
+    fn deserialize_gc<D: Deserializer<'de>>(
+        context: &GcDeserializeContext<'gc, Id>,
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        OptionSeed(context.seed::<T>()).deserialize(deserializer)
+    }
+}
+
+/// Deserializes a sequence of values using the wrapped seed.
+struct VecSeed<'a, 'gc, T, Id: CollectorId>(GcDeserializeSeed<'a, 'gc, T, Id>);
+impl<'de, 'gc, T: GcDeserialize<'gc, 'de, Id>, Id: CollectorId> Visitor<'de>
+    for VecSeed<'_, 'gc, T, Id>
+{
+    type Value = Vec<T>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("a sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<T>, A::Error> {
+        // limit preallocation, like serde does
+        let mut result = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(element) = seq.next_element_seed(self.0)? {
+            result.push(element);
+        }
+        Ok(result)
+    }
+}
+
+impl<'gc, 'de, T: GcDeserialize<'gc, 'de, Id>, Id: CollectorId> GcDeserialize<'gc, 'de, Id>
+    for Vec<T>
+{
+    #[inline]
+    fn deserialize_gc<D: Deserializer<'de>>(
+        context: &GcDeserializeContext<'gc, Id>,
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(VecSeed(context.seed::<T>()))
+    }
+}
+
+impl<'gc, 'de, T: GcDeserialize<'gc, 'de, Id>, Id: CollectorId> GcDeserialize<'gc, 'de, Id>
+    for Box<T>
+{
+    #[inline]
+    fn deserialize_gc<D: Deserializer<'de>>(
+        context: &GcDeserializeContext<'gc, Id>,
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        T::deserialize_gc(context, deserializer).map(Box::new)
+    }
+}
+
+impl<'gc, 'de, T: GcDeserialize<'gc, 'de, Id>, Id: CollectorId> GcDeserialize<'gc, 'de, Id>
+    for GcRefCell<T>
+{
+    #[inline]
+    fn deserialize_gc<D: Deserializer<'de>>(
+        context: &GcDeserializeContext<'gc, Id>,
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        T::deserialize_gc(context, deserializer).map(GcRefCell::new)
+    }
+}
+
+/// A pointer to a GC object, which may be shared.
+trait SharedPointer<'gc, 'de, Id: CollectorId>: Sized {
+    /// The deserialized value, before it is allocated.
+    type Value;
+
+    fn type_id() -> TypeId;
+
+    fn deserialize_value<D: Deserializer<'de>>(
+        context: &GcDeserializeContext<'gc, Id>,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error>;
+
+    fn alloc(collector: &'gc GarbageCollector<Id>, value: Self::Value) -> Self;
+
+    fn as_raw(&self) -> NonNull<()>;
+
+    /// ## Safety
+    /// The pointer must have come from [`Self::as_raw`] for the same type.
+    unsafe fn from_raw(ptr: NonNull<()>) -> Self;
+
+    fn deserialize_pointer<D: Deserializer<'de>>(
+        context: &GcDeserializeContext<'gc, Id>,
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        if context.shared.is_some() {
+            deserializer.deserialize_tuple(
+                2,
+                SharedVisitor {
+                    context,
+                    marker: PhantomData,
+                },
+            )
+        } else {
+            let value = Self::deserialize_value(context, deserializer)?;
+            Ok(Self::alloc(context.collector, value))
+        }
+    }
+}
+
+/// Deserializes the value of a shared pointer, using the wrapped context.
+struct ValueSeed<'a, 'gc, P, Id: CollectorId> {
+    context: &'a GcDeserializeContext<'gc, Id>,
+    marker: PhantomData<fn() -> P>,
+}
+impl<'de, 'gc, P: SharedPointer<'gc, 'de, Id>, Id: CollectorId> DeserializeSeed<'de>
+    for ValueSeed<'_, 'gc, P, Id>
+{
+    type Value = P::Value;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<P::Value, D::Error> {
+        P::deserialize_value(self.context, deserializer)
+    }
+}
+
+/// Visits the `(id, Option<value>)` pair written by [`SerializeShared`].
+struct SharedVisitor<'a, 'gc, P, Id: CollectorId> {
+    context: &'a GcDeserializeContext<'gc, Id>,
+    marker: PhantomData<fn() -> P>,
+}
+impl<'de, 'gc, P: SharedPointer<'gc, 'de, Id>, Id: CollectorId> Visitor<'de>
+    for SharedVisitor<'_, 'gc, P, Id>
+{
+    type Value = P;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("a shared object id with an optional value")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<P, A::Error> {
+        let shared = self.context.shared.as_ref().unwrap();
+        let id: u64 = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        let type_id = P::type_id();
+        let previous = match shared.borrow_mut().entry(id) {
+            Entry::Occupied(entry) => Some((entry.get().type_id, entry.get().ptr)),
+            Entry::Vacant(entry) => {
+                entry.insert(SharedObject { type_id, ptr: None });
+                None
+            }
+        };
+        let value_seed = OptionSeed(ValueSeed::<P, Id> {
+            context: self.context,
+            marker: PhantomData,
+        });
+        let value = seq
+            .next_element_seed(value_seed)?
+            .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+        match (previous, value) {
+            (None, Some(value)) => {
+                let ptr = P::alloc(self.context.collector, value);
+                shared.borrow_mut().get_mut(&id).unwrap().ptr = Some(ptr.as_raw());
+                Ok(ptr)
+            }
+            (None, None) => Err(A::Error::custom(format_args!(
+                "Reference to undefined shared object {id}"
+            ))),
+            (Some(_), Some(_)) => Err(A::Error::custom(format_args!(
+                "Duplicate definition of shared object {id}"
+            ))),
+            (Some((_, None)), None) => Err(A::Error::custom(format_args!(
+                "Cyclic reference to shared object {id}"
+            ))),
+            (Some((previous_type, Some(ptr))), None) => {
+                if previous_type == type_id {
+                    // SAFETY: Came from the same type of pointer
+                    Ok(unsafe { P::from_raw(ptr) })
+                } else {
+                    Err(A::Error::custom(format_args!(
+                        "Shared object {id} has a different type"
+                    )))
+                }
+            }
+        }
+    }
+}
+
+impl<'gc, 'de, T, Id: CollectorId> SharedPointer<'gc, 'de, Id> for Gc<'gc, T, Id>
+where
+    T: GcDeserialize<'gc, 'de, Id> + Collect<Id>,
+    T::Collected<'static>: 'static,
+{
+    type Value = T;
+
+    #[inline]
+    fn type_id() -> TypeId {
+        TypeId::of::<Gc<'static, T::Collected<'static>, Id>>()
+    }
+
+    #[inline]
+    fn deserialize_value<D: Deserializer<'de>>(
+        context: &GcDeserializeContext<'gc, Id>,
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        T::deserialize_gc(context, deserializer)
+    }
+
+    #[inline]
+    fn alloc(collector: &'gc GarbageCollector<Id>, value: T) -> Self {
+        collector.alloc(value)
+    }
+
+    #[inline]
+    fn as_raw(&self) -> NonNull<()> {
+        unsafe { self.as_raw_ptr().cast() }
+    }
+
+    #[inline]
+    unsafe fn from_raw(ptr: NonNull<()>) -> Self {
+        Gc::from_raw_ptr(ptr.cast())
+    }
+}
+
+impl<'gc, 'de, T, Id: CollectorId> SharedPointer<'gc, 'de, Id> for GcArray<'gc, T, Id>
+where
+    T: GcDeserialize<'gc, 'de, Id> + Collect<Id>,
+    T::Collected<'static>: 'static,
+{
+    type Value = Vec<T>;
+
+    #[inline]
+    fn type_id() -> TypeId {
+        TypeId::of::<GcArray<'static, T::Collected<'static>, Id>>()
+    }
+
+    #[inline]
+    fn deserialize_value<D: Deserializer<'de>>(
+        context: &GcDeserializeContext<'gc, Id>,
+        deserializer: D,
+    ) -> Result<Vec<T>, D::Error> {
+        Vec::<T>::deserialize_gc(context, deserializer)
+    }
+
+    #[inline]
+    fn alloc(collector: &'gc GarbageCollector<Id>, value: Vec<T>) -> Self {
+        let mut elements = value.into_iter();
+        collector.alloc_array_with(elements.len(), |_| elements.next().unwrap())
+    }
+
+    #[inline]
+    fn as_raw(&self) -> NonNull<()> {
+        self.as_ptr().cast()
+    }
+
+    #[inline]
+    unsafe fn from_raw(ptr: NonNull<()>) -> Self {
+        GcArray::from_raw_ptr(ptr.cast())
+    }
+}
+
+/// Allocates the value in the GC heap.
+///
+/// The `'static` bound on [`Collect::Collected`] is used to check the types of shared pointers.
+impl<'gc, 'de, T, Id: CollectorId> GcDeserialize<'gc, 'de, Id> for Gc<'gc, T, Id>
+where
+    T: GcDeserialize<'gc, 'de, Id> + Collect<Id>,
+    T::Collected<'static>: 'static,
+{
+    #[inline]
+    fn deserialize_gc<D: Deserializer<'de>>(
+        context: &GcDeserializeContext<'gc, Id>,
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        Self::deserialize_pointer(context, deserializer)
+    }
+}
+
+/// Allocates the elements in the GC heap.
+///
+/// The `'static` bound on [`Collect::Collected`] is used to check the types of shared pointers.
+impl<'gc, 'de, T, Id: CollectorId> GcDeserialize<'gc, 'de, Id> for GcArray<'gc, T, Id>
+where
+    T: GcDeserialize<'gc, 'de, Id> + Collect<Id>,
+    T::Collected<'static>: 'static,
+{
+    #[inline]
+    fn deserialize_gc<D: Deserializer<'de>>(
+        context: &GcDeserializeContext<'gc, Id>,
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        Self::deserialize_pointer(context, deserializer)
+    }
+}
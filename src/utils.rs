@@ -1,4 +1,5 @@
 use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::Cell;
 use std::fmt::Display;
 use std::mem::ManuallyDrop;
 use std::panic::Location;
@@ -7,6 +8,23 @@ mod layout_helpers;
 
 pub use self::layout_helpers::{Alignment, LayoutExt};
 
+/// Get the address of a pointer, for comparisons and hashing.
+///
+/// With the `strict-provenance` feature, this uses [`pointer::addr`],
+/// which never exposes the provenance of the pointer.
+/// An address must never be cast back into a pointer.
+#[inline(always)]
+pub fn addr<T: ?Sized>(ptr: *const T) -> usize {
+    #[cfg(feature = "strict-provenance")]
+    {
+        ptr.addr()
+    }
+    #[cfg(not(feature = "strict-provenance"))]
+    {
+        ptr as *const () as usize
+    }
+}
+
 enum AbortReason<M: Display> {
     Message(M),
     FailedAbort,
@@ -156,3 +174,78 @@ fn mismatch_transmute_sizes(src: TransmuteTypeInfo, dst: TransmuteTypeInfo) -> !
     );
     unreachable!() // sizes actually match
 }
+
+/// A user callback, which is taken out of the slot while it runs
+/// in case it reenters the collector.
+///
+/// If the callback replaces or removes itself while running,
+/// it is not restored afterwards.
+pub struct CallbackSlot<F: ?Sized>(Cell<SlotState<F>>);
+enum SlotState<F: ?Sized> {
+    Empty,
+    Set(Box<F>),
+    Running,
+}
+impl<F: ?Sized> CallbackSlot<F> {
+    #[inline]
+    pub const fn new() -> Self {
+        CallbackSlot(Cell::new(SlotState::Empty))
+    }
+
+    /// Replace the callback, or remove it if `None`.
+    #[inline]
+    pub fn set(&self, callback: Option<Box<F>>) {
+        self.0.set(match callback {
+            Some(callback) => SlotState::Set(callback),
+            None => SlotState::Empty,
+        });
+    }
+
+    /// Invoke the callback, if there is one and it is not already running.
+    #[inline]
+    pub fn call(&self, func: impl FnOnce(&mut F)) {
+        match self.0.replace(SlotState::Running) {
+            SlotState::Set(mut callback) => {
+                func(&mut callback);
+                match self.0.replace(SlotState::Empty) {
+                    SlotState::Running => self.0.set(SlotState::Set(callback)),
+                    // replaced or removed by the callback
+                    replacement => self.0.set(replacement),
+                }
+            }
+            state => self.0.set(state),
+        }
+    }
+}
+
+/// Hint that the specified memory will soon be read.
+///
+/// This is a no-op on architectures without a stable prefetch intrinsic.
+#[inline(always)]
+pub fn prefetch_read<T>(ptr: *const T) {
+    #[cfg(target_arch = "x86_64")]
+    #[allow(unused_unsafe)] // safe on newer compilers, as sse is always enabled
+    unsafe {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(ptr.cast::<i8>());
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = ptr;
+}
+
+/// Declare an item which is only public with the `unsafe-api` feature.
+///
+/// Without the feature, the item remains usable within this crate.
+macro_rules! unsafe_api {
+    ($(#[$attr:meta])* pub $($item:tt)*) => {
+        $(#[$attr])*
+        #[cfg(feature = "unsafe-api")]
+        pub $($item)*
+
+        $(#[$attr])*
+        #[cfg(not(feature = "unsafe-api"))]
+        #[allow(dead_code)]
+        pub(crate) $($item)*
+    };
+}
+pub(crate) use unsafe_api;
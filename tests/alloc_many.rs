@@ -0,0 +1,112 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use zerogc_next::{Collect, CollectContext, CollectorId, GarbageCollector};
+
+struct Named(u32, Rc<RefCell<Vec<u32>>>);
+impl Drop for Named {
+    fn drop(&mut self) {
+        self.1.borrow_mut().push(self.0);
+    }
+}
+unsafe impl<Id: CollectorId> Collect<Id> for Named {
+    type Collected<'newgc> = Named;
+    const NEEDS_COLLECT: bool = false;
+    unsafe fn collect_inplace(_: std::ptr::NonNull<Self>, _: &mut CollectContext<'_, Id>) {}
+}
+
+/// An iterator which reports the wrong length.
+struct Misreported<I> {
+    values: I,
+    len: usize,
+}
+impl<I: Iterator> Iterator for Misreported<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        self.values.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+impl<I: Iterator> ExactSizeIterator for Misreported<I> {}
+
+#[test]
+fn alloc_many_contiguous() {
+    GarbageCollector::scoped(|gc| {
+        let objs = gc.alloc_many((0..100usize).map(|i| i as u64 * 2));
+        assert_eq!(objs.len(), 100);
+        for (i, o) in objs.iter().enumerate() {
+            assert_eq!(**o, i as u64 * 2);
+        }
+        let addr = |i: usize| &*objs[i] as *const u64 as usize;
+        let stride = addr(1) - addr(0);
+        assert!((1..100).all(|i| addr(i) - addr(i - 1) == stride));
+        assert!(gc.alloc_many(Vec::<u64>::new()).is_empty());
+    });
+}
+
+#[test]
+fn alloc_many_drops_and_survives() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    GarbageCollector::scoped(|gc| {
+        let objs = gc.alloc_many((0..10).map(|i| Named(i, log.clone())));
+        let keep = gc.root(objs[3]);
+        gc.force_collect();
+        assert_eq!(log.borrow().len(), 9);
+        assert_eq!(keep.resolve(gc).0, 3);
+    });
+    assert_eq!(log.borrow().len(), 10);
+}
+
+#[test]
+fn alloc_many_overreported_length() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    GarbageCollector::scoped(|gc| {
+        let values = Misreported {
+            values: (0..3).map(|i| Named(i, log.clone())),
+            len: 10,
+        };
+        let objs = gc.alloc_many(values);
+        assert_eq!(objs.len(), 3);
+        let keep = gc.root(objs[2]);
+        // the unused objects of the block are never dropped
+        gc.force_collect();
+        assert_eq!(*log.borrow(), [0, 1]);
+        assert_eq!(keep.resolve(gc).0, 2);
+    });
+    assert_eq!(*log.borrow(), [0, 1, 2]);
+}
+
+#[test]
+fn alloc_many_underreported_length() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    GarbageCollector::scoped(|gc| {
+        let values = Misreported {
+            values: (0..10).map(|i| Named(i, log.clone())),
+            len: 3,
+        };
+        let objs = gc.alloc_many(values);
+        assert_eq!(objs.len(), 10);
+        for (i, o) in objs.iter().enumerate() {
+            assert_eq!(o.0, i as u32);
+        }
+        let keep = gc.root(objs[9]);
+        gc.force_collect();
+        assert_eq!(log.borrow().len(), 9);
+        assert_eq!(keep.resolve(gc).0, 9);
+    });
+    assert_eq!(log.borrow().len(), 10);
+}
+
+#[test]
+fn alloc_many_fallback_large() {
+    GarbageCollector::scoped(|gc| {
+        let objs = gc.alloc_many((0..200_000usize).map(|i| i as u64));
+        assert_eq!(objs.len(), 200_000);
+        assert_eq!(*objs[199_999], 199_999);
+        gc.force_collect();
+    });
+}
@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use zerogc_next::{GarbageCollector, Gc, GcBTreeMap, ScopedId};
+
+type Map<'gc> = GcBTreeMap<'gc, u64, Gc<'gc, u64, ScopedId>, ScopedId>;
+
+/// A xorshift generator, so the operations are reproducible.
+struct Rng(u64);
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+fn entries(map: &Map<'_>) -> Vec<(u64, u64)> {
+    map.iter().map(|(k, v)| (k, *v)).collect()
+}
+
+#[test]
+fn split_and_merge_boundaries() {
+    GarbageCollector::scoped(|gc| {
+        // a node holds up to eleven entries, so these sizes cross one and two splits
+        for size in [1, 10, 11, 12, 22, 23, 24, 121, 122, 200] {
+            let map = gc.alloc(Map::new());
+            for i in 0..size {
+                GcBTreeMap::insert(&map, gc, i, gc.alloc(i));
+            }
+            assert_eq!(map.len(), size as usize);
+            assert_eq!(entries(&map), (0..size).map(|i| (i, i)).collect::<Vec<_>>());
+            // removing from either end forces nodes to borrow from their siblings and merge
+            let mut expected: BTreeMap<u64, u64> = (0..size).map(|i| (i, i)).collect();
+            let mut low = 0;
+            let mut high = size;
+            while low < high {
+                let key = if low % 2 == 0 {
+                    low += 1;
+                    low - 1
+                } else {
+                    high -= 1;
+                    high
+                };
+                assert_eq!(GcBTreeMap::remove(&map, gc, &key).map(|v| *v), Some(key));
+                expected.remove(&key);
+                assert_eq!(
+                    entries(&map),
+                    expected.clone().into_iter().collect::<Vec<_>>()
+                );
+            }
+            assert!(map.is_empty());
+            assert!(map.iter().next().is_none());
+        }
+    });
+}
+
+#[test]
+fn matches_std() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    GarbageCollector::scoped(|gc| {
+        let map = gc.alloc(Map::new());
+        let mut expected = BTreeMap::new();
+        for _ in 0..5000 {
+            let key = rng.next() % 300;
+            if rng.next() % 3 < 2 {
+                let value = rng.next();
+                let old = GcBTreeMap::insert(&map, gc, key, gc.alloc(value)).map(|v| *v);
+                assert_eq!(old, expected.insert(key, value));
+            } else {
+                let old = GcBTreeMap::remove(&map, gc, &key).map(|v| *v);
+                assert_eq!(old, expected.remove(&key));
+            }
+            assert_eq!(map.len(), expected.len());
+        }
+        for key in 0..300 {
+            assert_eq!(map.get(&key).map(|v| **v), expected.get(&key).copied());
+        }
+        assert_eq!(entries(&map), expected.into_iter().collect::<Vec<_>>());
+    });
+}
+
+#[test]
+fn range_bounds() {
+    GarbageCollector::scoped(|gc| {
+        let map = gc.alloc(GcBTreeMap::<u64, u64, ScopedId>::new());
+        // only even keys, so bounds fall both on and between entries
+        let expected: BTreeMap<u64, u64> = (0..100).map(|i| (i * 2, i)).collect();
+        for (&k, &v) in &expected {
+            GcBTreeMap::insert(&map, gc, k, v);
+        }
+        let bounds = |key: u64| [Bound::Included(key), Bound::Excluded(key), Bound::Unbounded];
+        for start in [0, 1, 2, 21, 22, 100, 197, 198, 199, 250] {
+            for end in [0, 1, 2, 21, 22, 100, 197, 198, 199, 250] {
+                for start_bound in bounds(start) {
+                    for end_bound in bounds(end) {
+                        let range = (start_bound, end_bound);
+                        let valid = match range {
+                            (Bound::Excluded(s), Bound::Excluded(e)) => s < e,
+                            (
+                                Bound::Included(s) | Bound::Excluded(s),
+                                Bound::Included(e) | Bound::Excluded(e),
+                            ) => s <= e,
+                            _ => true,
+                        };
+                        if !valid {
+                            continue;
+                        }
+                        let actual: Vec<_> = map.range(range).collect();
+                        let wanted: Vec<_> = expected.range(range).map(|(&k, &v)| (k, v)).collect();
+                        assert_eq!(actual, wanted, "{range:?}");
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[test]
+#[should_panic(expected = "Range start is greater than range end")]
+fn range_reversed() {
+    GarbageCollector::scoped(|gc| {
+        let map = gc.alloc(GcBTreeMap::<u64, u64, ScopedId>::new());
+        let (start, end) = (5, 3);
+        let _ = map.range(start..end);
+    });
+}
+
+#[test]
+fn traced_after_collection() {
+    GarbageCollector::scoped(|gc| {
+        let map = gc.root(gc.alloc(Map::new()));
+        for round in 0..4u64 {
+            {
+                let map = map.resolve(gc);
+                for i in 0..500 {
+                    let key = round * 500 + i;
+                    GcBTreeMap::insert(&map, gc, key, gc.alloc(key * 3));
+                }
+                for i in (0..500).step_by(3) {
+                    GcBTreeMap::remove(&map, gc, &(round * 500 + i));
+                }
+            }
+            // the nodes and values are only reachable through the map
+            gc.force_collect();
+        }
+        let map = map.resolve(gc);
+        for key in 0..2000u64 {
+            let present = (key % 500) % 3 != 0;
+            assert_eq!(map.get(&key).map(|v| **v), present.then_some(key * 3));
+        }
+        let range: Vec<u64> = map.range(10..20).map(|(k, _)| k).collect();
+        assert_eq!(range, vec![10, 11, 13, 14, 16, 17, 19]);
+        GcBTreeMap::clear(&map, gc);
+        assert!(map.is_empty());
+    });
+}
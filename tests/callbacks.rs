@@ -0,0 +1,46 @@
+use std::cell::Cell;
+use std::num::NonZeroU64;
+use std::rc::Rc;
+
+use zerogc_next::{GarbageCollector, GcConfig};
+
+#[test]
+fn error_hook_clears_itself() {
+    let config = GcConfig::default().with_heap_limit(1);
+    GarbageCollector::scoped_with_config(config, |gc| {
+        let calls = Rc::new(Cell::new(0));
+        let gc_ptr: *const GarbageCollector<_> = &*gc;
+        let counter = calls.clone();
+        gc.set_error_hook(move |_| {
+            counter.set(counter.get() + 1);
+            unsafe { (*gc_ptr).clear_error_hook() };
+        });
+        assert!(gc.try_alloc(0u64).is_err());
+        assert!(gc.try_alloc(0u64).is_err());
+        assert_eq!(calls.get(), 1);
+    });
+}
+
+#[test]
+fn sampler_replaces_itself() {
+    GarbageCollector::scoped(|gc| {
+        let first = Rc::new(Cell::new(0));
+        let second = Rc::new(Cell::new(0));
+        let gc_ptr: *const GarbageCollector<_> = &*gc;
+        let (first_counter, second_counter) = (first.clone(), second.clone());
+        gc.set_alloc_sampler(NonZeroU64::MIN, move |_| {
+            first_counter.set(first_counter.get() + 1);
+            let second_counter = second_counter.clone();
+            unsafe {
+                (*gc_ptr).set_alloc_sampler(NonZeroU64::MIN, move |_| {
+                    second_counter.set(second_counter.get() + 1);
+                });
+            }
+        });
+        for i in 0..3u64 {
+            gc.alloc(i);
+        }
+        assert_eq!(first.get(), 1);
+        assert_eq!(second.get(), 2);
+    });
+}
@@ -0,0 +1,54 @@
+#[cfg(feature = "classify-ptr")]
+use zerogc_next::context::HeapSpace;
+use zerogc_next::{GarbageCollector, GcConfig};
+
+#[test]
+fn compaction_keeps_pinned_objects() {
+    let config = GcConfig::default().with_survivor_age_threshold(0);
+    GarbageCollector::scoped_with_config(config, |gc| {
+        let old: Vec<_> = (0..50u64)
+            .map(|i| gc.root(gc.alloc(i.to_string())))
+            .collect();
+        gc.force_collect(); // promote
+        let old_addrs: Vec<_> = old
+            .iter()
+            .map(|h| &*h.resolve(gc) as *const String)
+            .collect();
+        let pinned = gc.alloc_pinned(String::from("pinned"));
+        let pinned_addr = &*pinned as *const String;
+        let pin = gc.pin(pinned);
+        // promoted by the compaction itself
+        let young: Vec<_> = (0..50u64)
+            .map(|i| gc.root(gc.alloc(format!("young {i}"))))
+            .collect();
+        gc.compact();
+        assert!(std::ptr::eq(&*pin.resolve(gc), pinned_addr));
+        assert_eq!(*pin.resolve(gc), "pinned");
+        for (h, &addr) in old.iter().zip(&old_addrs) {
+            assert!(!std::ptr::eq(&*h.resolve(gc), addr));
+        }
+        // the pinned object was the last one in the retired heap
+        drop(pin);
+        gc.force_collect();
+        gc.compact();
+        gc.force_collect();
+        for (i, h) in old.iter().enumerate() {
+            assert_eq!(*h.resolve(gc), i.to_string());
+        }
+        for (i, h) in young.iter().enumerate() {
+            let s = h.resolve(gc);
+            assert_eq!(*s, format!("young {i}"));
+            #[cfg(feature = "classify-ptr")]
+            assert_eq!(
+                gc.classify_ptr(&*s as *const String).unwrap().space,
+                HeapSpace::Old
+            );
+        }
+        // allocating after the retired heap is freed
+        let more: Vec<_> = (0..100u64).map(|i| gc.root(gc.alloc_pinned(i))).collect();
+        gc.force_collect();
+        for (i, h) in more.iter().enumerate() {
+            assert_eq!(*h.resolve(gc), i as u64);
+        }
+    });
+}
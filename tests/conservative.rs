@@ -0,0 +1,69 @@
+#![cfg(feature = "conservative-roots")]
+use std::cell::Cell;
+use std::rc::Rc;
+
+use zerogc_next::{Collect, CollectContext, CollectorId, GarbageCollector};
+
+/// Counts how many times it has been dropped.
+struct Dropper(Rc<Cell<u32>>);
+impl Drop for Dropper {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+unsafe impl<Id: CollectorId> Collect<Id> for Dropper {
+    type Collected<'newgc> = Dropper;
+    const NEEDS_COLLECT: bool = false;
+    unsafe fn collect_inplace(_: std::ptr::NonNull<Self>, _: &mut CollectContext<'_, Id>) {}
+}
+
+#[test]
+fn range_keeps_objects_alive() {
+    GarbageCollector::scoped(|gc| {
+        let drops = Rc::new(Cell::new(0));
+        let buffer: Box<[usize]> = vec![0usize; 4].into_boxed_slice();
+        let id = unsafe {
+            gc.register_conservative_range(buffer.as_ptr().cast(), std::mem::size_of_val(&*buffer))
+        };
+        let ptr = unsafe { gc.alloc(Dropper(drops.clone())).as_raw_ptr() };
+        // only an interior pointer is stored, which must still keep the object alive
+        unsafe {
+            (buffer.as_ptr() as *mut usize)
+                .add(1)
+                .write(ptr.as_ptr() as usize + 1)
+        };
+        let _unrooted = gc.alloc(Dropper(drops.clone()));
+        for _ in 0..4 {
+            gc.force_collect();
+        }
+        // the referenced object survives and is pinned in place
+        assert_eq!(drops.get(), 1);
+        assert!(gc.contains_ptr(ptr.as_ptr()));
+        assert!(gc.unregister_conservative_range(id));
+        assert!(!gc.unregister_conservative_range(id));
+        gc.force_collect();
+        assert_eq!(drops.get(), 2);
+        assert!(!gc.contains_ptr(ptr.as_ptr()));
+        // a second collection must not free the object again
+        gc.force_collect();
+        assert_eq!(drops.get(), 2);
+        drop(buffer);
+    });
+}
+
+#[test]
+fn stack_keeps_objects_alive() {
+    let base = 0u8;
+    GarbageCollector::scoped(|gc| {
+        let stack_id = unsafe { gc.register_conservative_stack(&base) };
+        let ptr = unsafe { gc.alloc(42u64).as_raw_ptr() };
+        let addr = std::hint::black_box(ptr.as_ptr() as usize);
+        for _ in 0..4 {
+            gc.force_collect();
+        }
+        assert!(gc.contains_ptr(addr as *const u64));
+        assert_eq!(unsafe { *(addr as *const u64) }, 42);
+        assert!(gc.unregister_conservative_range(stack_id));
+        std::hint::black_box(addr);
+    });
+}
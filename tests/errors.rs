@@ -0,0 +1,97 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use zerogc_next::context::GcArrayLayoutError;
+use zerogc_next::{
+    Collect, CollectContext, CollectionReason, CollectorId, GarbageCollector, GcConfig, GcError,
+    TryReserveError,
+};
+
+/// A large object without any GC pointers.
+struct Block {
+    _data: [u64; 4096],
+}
+unsafe impl<Id: CollectorId> Collect<Id> for Block {
+    type Collected<'newgc> = Block;
+    const NEEDS_COLLECT: bool = false;
+    unsafe fn collect_inplace(_: std::ptr::NonNull<Self>, _: &mut CollectContext<'_, Id>) {}
+}
+
+#[test]
+fn invalid_array_layout() {
+    GarbageCollector::scoped(|gc| {
+        let error = gc.try_reserve_array::<u64>(usize::MAX).unwrap_err();
+        assert_eq!(
+            GcError::from(error),
+            GcError::InvalidLayout(GcArrayLayoutError::ArraySizeOverflow)
+        );
+    });
+}
+
+#[test]
+fn try_reserve_containers() {
+    let config = GcConfig::default().with_heap_limit(1 << 20);
+    GarbageCollector::scoped_with_config(config, |gc| {
+        let string = gc.alloc_str("hello");
+        let array = gc.alloc_array_copied(&[1u64, 2, 3]);
+        assert!(string.try_reserve(gc, 16).is_ok());
+        assert!(array.try_reserve(gc, 16).is_ok());
+        assert!(matches!(
+            string.try_reserve(gc, 2 << 20),
+            Err(TryReserveError::HeapLimitExceeded { .. })
+        ));
+        assert!(matches!(
+            array.try_reserve(gc, usize::MAX),
+            Err(TryReserveError::InvalidLayout(_))
+        ));
+    });
+}
+
+#[test]
+fn pinned_respects_heap_limit() {
+    let config = GcConfig::default().with_heap_limit(1 << 20);
+    GarbageCollector::scoped_with_config(config, |gc| {
+        let before = gc.stats_snapshot();
+        gc.alloc_pinned(0u64);
+        assert_eq!(gc.stats_delta(&before).allocated_objects, 1);
+        // pinned objects bypass the young generation, but not the heap limit
+        let mut count = 0;
+        while catch_unwind(AssertUnwindSafe(|| {
+            gc.alloc_pinned(Block { _data: [0; 4096] })
+        }))
+        .is_ok()
+        {
+            count += 1;
+            assert!(count < 64);
+        }
+        assert_eq!(
+            gc.pending_collection_reason(),
+            Some(CollectionReason::HeapLimit)
+        );
+    });
+}
+
+#[test]
+fn immortal_respects_heap_limit() {
+    let config = GcConfig::default().with_heap_limit(1 << 20);
+    GarbageCollector::scoped_with_config(config, |gc| {
+        let headroom = gc.heap_headroom().unwrap();
+        gc.alloc_immortal(Block { _data: [0; 4096] });
+        // immortal objects are never freed, so they permanently count against the limit
+        assert!(gc.heap_headroom().unwrap() <= headroom - std::mem::size_of::<Block>());
+        gc.force_collect();
+        assert!(gc.heap_headroom().unwrap() <= headroom - std::mem::size_of::<Block>());
+        let mut count = 0;
+        while catch_unwind(AssertUnwindSafe(|| {
+            gc.alloc_immortal(Block { _data: [0; 4096] })
+        }))
+        .is_ok()
+        {
+            count += 1;
+            assert!(count < 64);
+        }
+        assert_eq!(
+            gc.pending_collection_reason(),
+            Some(CollectionReason::HeapLimit)
+        );
+    });
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "fault-injection")]
+use zerogc_next::context::AllocFailStage;
+use zerogc_next::{GarbageCollector, GcConfig};
+
+#[test]
+fn compaction_leaves_object_in_place() {
+    let config = GcConfig::default().with_survivor_age_threshold(0);
+    GarbageCollector::scoped_with_config(config, |gc| {
+        let old: Vec<_> = (0..10u64)
+            .map(|i| gc.root(gc.alloc(i.to_string())))
+            .collect();
+        gc.force_collect(); // promote
+        let addrs: Vec<_> = old
+            .iter()
+            .map(|h| &*h.resolve(gc) as *const String)
+            .collect();
+        gc.inject_alloc_failures(AllocFailStage::Evacuation, 1);
+        gc.compact();
+        assert_eq!(gc.remaining_alloc_failures(), 0);
+        let moved = old
+            .iter()
+            .zip(&addrs)
+            .filter(|&(h, &addr)| !std::ptr::eq(&*h.resolve(gc), addr))
+            .count();
+        assert_eq!(moved, 9);
+        for _ in 0..3 {
+            gc.force_collect();
+            gc.compact();
+        }
+        for (i, h) in old.iter().enumerate() {
+            assert_eq!(*h.resolve(gc), i.to_string());
+        }
+    });
+}
+
+#[test]
+fn young_object_left_in_place() {
+    GarbageCollector::scoped(|gc| {
+        let young: Vec<_> = (0..10u64)
+            .map(|i| gc.root(gc.alloc(i.to_string())))
+            .collect();
+        let addrs: Vec<_> = young
+            .iter()
+            .map(|h| &*h.resolve(gc) as *const String)
+            .collect();
+        gc.inject_alloc_failures(AllocFailStage::Evacuation, 1);
+        gc.force_collect();
+        assert_eq!(gc.remaining_alloc_failures(), 0);
+        let moved = young
+            .iter()
+            .zip(&addrs)
+            .filter(|&(h, &addr)| !std::ptr::eq(&*h.resolve(gc), addr))
+            .count();
+        assert_eq!(moved, 9);
+        // the object is evacuated normally by later collections
+        for round in 0..4 {
+            for i in 0..100u64 {
+                gc.alloc(format!("garbage {round} {i}"));
+            }
+            gc.force_collect();
+        }
+        for (i, h) in young.iter().enumerate() {
+            assert_eq!(*h.resolve(gc), i.to_string());
+        }
+    });
+}
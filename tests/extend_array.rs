@@ -0,0 +1,52 @@
+use zerogc_next::{GarbageCollector, GcConfig};
+
+#[test]
+fn newest_array_grows_in_place() {
+    GarbageCollector::scoped(|gc| {
+        let array = gc.alloc_array_with(1, |i| i as u64);
+        let ptr = array.as_ptr();
+        for len in 2..=100 {
+            assert!(gc.try_extend_array_with(array, len, |i| i as u64));
+            // regular objects are allocated separately from arrays
+            gc.alloc(len as u64);
+        }
+        assert_eq!(array.as_ptr(), ptr);
+        assert_eq!(array.len(), 100);
+        assert!(array.iter().enumerate().all(|(i, &x)| x == i as u64));
+        // only the newest array can grow past its padding
+        let other = gc.alloc_array_with(1, |_| 0u64);
+        assert!(!gc.try_extend_array_with(array, 101, |i| i as u64));
+        assert_eq!(array.len(), 100);
+        assert!(gc.try_extend_array_with(other, 2, |_| 1u64));
+        assert_eq!(&*other, &[0, 1]);
+    });
+}
+
+#[test]
+fn growth_limited_to_young_objects() {
+    let config = GcConfig::default().with_young_object_limit(256);
+    GarbageCollector::scoped_with_config(config, |gc| {
+        let array = gc.alloc_array_with(1, |_| 0u8);
+        let mut len = 1;
+        while gc.try_extend_array_with(array, len + 1, |_| 0u8) {
+            len += 1;
+        }
+        assert!(len > 200 && len < 256, "{len}");
+        assert_eq!(array.len(), len);
+    });
+}
+
+#[test]
+fn evacuated_array_is_not_extended() {
+    GarbageCollector::scoped(|gc| {
+        let holder = gc.root(gc.alloc(gc.alloc_array_with(4, |i| i as u64)));
+        gc.force_collect();
+        let array = *holder.resolve(gc);
+        assert!(!gc.try_extend_array_with(array, 16, |i| i as u64));
+        assert_eq!(&*array, &[0, 1, 2, 3]);
+        // the buffer was emptied with the rest of the arena
+        let fresh = gc.alloc_array_with(1, |_| 7u64);
+        assert!(gc.try_extend_array_with(fresh, 8, |_| 7u64));
+        assert_eq!(&*fresh, &[7; 8]);
+    });
+}
@@ -0,0 +1,47 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use zerogc_next::GarbageCollector;
+
+#[test]
+fn run_once_after_death() {
+    GarbageCollector::scoped(|gc| {
+        let log = Rc::new(RefCell::new(Vec::<u32>::new()));
+        let kept = gc.root(gc.alloc(1u32));
+        for i in 0..3u32 {
+            let obj = gc.alloc(i + 10);
+            let log = log.clone();
+            gc.add_finalizer(obj, move |gc| {
+                // finalizers may allocate
+                let buf = gc.alloc(vec![i]);
+                log.borrow_mut().push(buf[0]);
+            });
+        }
+        let kept_log = log.clone();
+        gc.add_finalizer(kept.resolve(gc), move |_| kept_log.borrow_mut().push(99));
+        assert_eq!(gc.run_pending_finalizers(), 0);
+        gc.force_collect();
+        assert_eq!(gc.pending_finalizers(), 3);
+        assert!(log.borrow().is_empty());
+        assert_eq!(gc.run_pending_finalizers(), 3);
+        assert_eq!(*log.borrow(), vec![0, 1, 2]);
+        // a second collection must not queue the same finalizers again
+        gc.force_collect();
+        assert_eq!(gc.pending_finalizers(), 0);
+        assert_eq!(gc.run_pending_finalizers(), 0);
+        assert_eq!(log.borrow().len(), 3);
+        // the surviving object is promoted and moved, but stays registered
+        for _ in 0..5 {
+            gc.force_collect();
+        }
+        gc.compact();
+        assert_eq!(gc.run_pending_finalizers(), 0);
+        drop(kept);
+        gc.force_collect();
+        assert_eq!(gc.run_pending_finalizers(), 1);
+        assert_eq!(log.borrow().last(), Some(&99));
+        gc.force_collect();
+        assert_eq!(gc.run_pending_finalizers(), 0);
+        assert_eq!(log.borrow().len(), 4);
+    });
+}
@@ -0,0 +1,78 @@
+use std::cell::Cell;
+use std::ptr::NonNull;
+
+use zerogc_next::{Collect, CollectContext, GarbageCollector, Gc, GcCell, GcRefCell, ScopedId};
+
+/// A node whose successor can be changed to form a cycle.
+struct Node<'gc> {
+    next: Cell<Option<Gc<'gc, Node<'gc>, ScopedId>>>,
+}
+unsafe impl<'gc> Collect<ScopedId> for Node<'gc> {
+    type Collected<'newgc> = Node<'newgc>;
+    const NEEDS_COLLECT: bool = true;
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, ScopedId>) {
+        context.trace_option(NonNull::new_unchecked((*target.as_ptr()).next.as_ptr()));
+    }
+}
+
+#[test]
+#[should_panic(expected = "frozen")]
+fn frozen_cell() {
+    GarbageCollector::scoped(|gc| {
+        let value = gc.alloc(1u32);
+        let cell = gc.alloc(GcCell::new(value)).freeze(gc);
+        GcCell::set(&cell, gc, gc.alloc(2u32));
+    });
+}
+
+#[test]
+#[should_panic(expected = "frozen")]
+fn frozen_ref_cell() {
+    GarbageCollector::scoped(|gc| {
+        let cell = gc.alloc(GcRefCell::new(gc.alloc(1u32))).freeze(gc);
+        *GcRefCell::borrow_mut(&cell, gc) = gc.alloc(2u32);
+    });
+}
+
+#[test]
+fn freeze_deep() {
+    GarbageCollector::scoped(|gc| {
+        let inner = gc.alloc(GcCell::new(gc.alloc(1u32)));
+        let outer = gc.alloc(GcCell::new(inner)).freeze_deep(gc);
+        assert!(outer.is_frozen(gc));
+        assert!(inner.is_frozen(gc));
+        let outer = gc.root(outer);
+        gc.force_collect();
+        let outer = outer.resolve(gc);
+        assert!(outer.is_frozen(gc));
+        assert_eq!(*outer.get().get(), 1);
+    });
+}
+
+#[test]
+fn freeze_deep_after_shallow() {
+    GarbageCollector::scoped(|gc| {
+        let inner = gc.alloc(GcCell::new(gc.alloc(1u32)));
+        let outer = gc.alloc(GcCell::new(inner)).freeze(gc);
+        assert!(!inner.is_frozen(gc));
+        outer.freeze_deep(gc);
+        assert!(inner.is_frozen(gc));
+    });
+}
+
+#[test]
+fn freeze_deep_cycle() {
+    GarbageCollector::scoped(|gc| {
+        let first = gc.alloc(Node {
+            next: Cell::new(None),
+        });
+        let second = gc.alloc(Node {
+            next: Cell::new(Some(first)),
+        });
+        // no collection happens, so the cycle needs no write barrier
+        first.next.set(Some(second));
+        first.freeze_deep(gc);
+        assert!(first.is_frozen(gc));
+        assert!(second.is_frozen(gc));
+    });
+}
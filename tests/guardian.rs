@@ -0,0 +1,42 @@
+use zerogc_next::{GarbageCollector, Gc, Guardian, ScopedId};
+
+type Obj = Vec<Gc<'static, String, ScopedId>>;
+
+#[test]
+fn resurrect_once() {
+    GarbageCollector::scoped(|gc| {
+        let guardian = Guardian::<Obj, ScopedId>::new(gc);
+        let kept = gc.root(gc.alloc(vec![gc.alloc(String::from("kept"))]));
+        guardian.register(gc, kept.resolve(gc));
+        for i in 0..3u32 {
+            let obj = gc.alloc(vec![gc.alloc(format!("child{i}"))]);
+            guardian.register(gc, obj);
+        }
+        gc.force_collect();
+        assert_eq!(guardian.pending(), 3);
+        assert_eq!(guardian.registered(), 1);
+        // queued objects stay alive, along with everything they reference
+        for _ in 0..4 {
+            gc.force_collect();
+        }
+        gc.compact();
+        assert_eq!(guardian.pending(), 3);
+        let mut seen = Vec::new();
+        while let Some(obj) = guardian.pop(gc) {
+            seen.push((*obj[0]).clone());
+        }
+        seen.sort();
+        assert_eq!(seen, vec!["child0", "child1", "child2"]);
+        // the retrieved objects are unreachable again, but are not resurrected twice
+        gc.force_collect();
+        assert_eq!(guardian.pending(), 0);
+        assert_eq!(guardian.registered(), 1);
+        drop(kept);
+        gc.force_collect();
+        assert_eq!(guardian.registered(), 0);
+        let kept = guardian.pop(gc).unwrap();
+        assert_eq!(*kept[0], "kept");
+        gc.force_collect();
+        assert!(guardian.pop(gc).is_none());
+    });
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use zerogc_next::{GarbageCollector, Gc, GcHashMap, ScopedId};
+
+type Map<'gc> = GcHashMap<'gc, u64, Gc<'gc, u64, ScopedId>, ScopedId>;
+
+/// A xorshift generator, so the operations are reproducible.
+struct Rng(u64);
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[test]
+fn insert_overwrite_remove() {
+    GarbageCollector::scoped(|gc| {
+        let map = gc.alloc(Map::new());
+        assert!(map.is_empty());
+        assert_eq!(map.capacity(), 0);
+        for i in 0..6u64 {
+            assert!(GcHashMap::insert(&map, gc, i, gc.alloc(i)).is_none());
+        }
+        // six entries fit in the smallest table
+        assert_eq!(map.capacity(), 6);
+        let old = GcHashMap::insert(&map, gc, 3, gc.alloc(30)).unwrap();
+        assert_eq!(*old, 3);
+        assert_eq!(map.len(), 6);
+        assert_eq!(**map.get(&3).unwrap(), 30);
+        assert_eq!(*GcHashMap::remove(&map, gc, &3).unwrap(), 30);
+        assert!(GcHashMap::remove(&map, gc, &3).is_none());
+        assert!(!map.contains_key(&3));
+        assert_eq!(map.len(), 5);
+    });
+}
+
+#[test]
+fn matches_std_across_wraparound() {
+    // the buckets are chosen by a random hasher,
+    // so many small tables are needed for clusters to wrap around the end of the table
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    GarbageCollector::scoped(|gc| {
+        for _ in 0..200 {
+            let map = gc.alloc(Map::new());
+            let mut expected = HashMap::new();
+            for _ in 0..100 {
+                let key = rng.next() % 12;
+                if rng.next() & 1 == 0 && expected.len() < 6 {
+                    let value = rng.next();
+                    let old = GcHashMap::insert(&map, gc, key, gc.alloc(value)).map(|v| *v);
+                    assert_eq!(old, expected.insert(key, value));
+                } else {
+                    let old = GcHashMap::remove(&map, gc, &key).map(|v| *v);
+                    assert_eq!(old, expected.remove(&key));
+                }
+                // the table never grows past eight buckets
+                assert!(map.capacity() <= 6);
+                assert_eq!(map.len(), expected.len());
+                for key in 0..12 {
+                    assert_eq!(map.get(&key).map(|v| **v), expected.get(&key).copied());
+                }
+            }
+        }
+    });
+}
+
+#[test]
+fn resize() {
+    GarbageCollector::scoped(|gc| {
+        let map = gc.alloc(Map::new());
+        for i in 0..1000u64 {
+            GcHashMap::insert(&map, gc, i, gc.alloc(i * 2));
+            assert!(map.capacity() >= map.len());
+        }
+        assert_eq!(map.len(), 1000);
+        assert_eq!(map.capacity(), 1536);
+        for i in 0..1000u64 {
+            assert_eq!(**map.get(&i).unwrap(), i * 2);
+        }
+        let mut entries: Vec<_> = map.iter().map(|(k, v)| (k, *v)).collect();
+        entries.sort();
+        assert!(entries
+            .iter()
+            .enumerate()
+            .all(|(i, &(k, v))| k == i as u64 && v == k * 2));
+        let sized = GcHashMap::<u64, u64, ScopedId>::with_capacity(gc, 100);
+        assert!(sized.capacity() >= 100);
+    });
+}
+
+#[test]
+fn clear() {
+    GarbageCollector::scoped(|gc| {
+        let map = gc.alloc(Map::new());
+        for i in 0..20u64 {
+            GcHashMap::insert(&map, gc, i, gc.alloc(i));
+        }
+        GcHashMap::clear(&map, gc);
+        assert!(map.is_empty());
+        assert_eq!(map.capacity(), 0);
+        assert!(map.get(&1).is_none());
+        GcHashMap::insert(&map, gc, 1, gc.alloc(2));
+        assert_eq!(**map.get(&1).unwrap(), 2);
+    });
+}
+
+#[test]
+fn survives_collection() {
+    GarbageCollector::scoped(|gc| {
+        let map = gc.root(gc.alloc(Map::new()));
+        for round in 0..4u64 {
+            {
+                let map = map.resolve(gc);
+                for i in 0..500 {
+                    let key = round * 500 + i;
+                    GcHashMap::insert(&map, gc, key, gc.alloc(key * 2));
+                }
+                for i in (0..500).step_by(3) {
+                    GcHashMap::remove(&map, gc, &(round * 500 + i));
+                }
+            }
+            // the values are only reachable through the map
+            gc.force_collect();
+        }
+        let map = map.resolve(gc);
+        for key in 0..2000u64 {
+            let present = (key % 500) % 3 != 0;
+            assert_eq!(map.get(&key).map(|v| **v), present.then_some(key * 2));
+        }
+    });
+}
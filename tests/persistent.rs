@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use zerogc_next::{
+    Collect, CollectContext, CollectorId, GarbageCollector, Gc, GcPersistentMap, GcPersistentVec,
+    ScopedId,
+};
+
+type Vector<'gc> = GcPersistentVec<'gc, Gc<'gc, u64, ScopedId>, ScopedId>;
+
+fn contents(vec: &Vector<'_>) -> Vec<u64> {
+    vec.iter().map(|v| **v).collect()
+}
+
+/// A key whose hash ignores all but its lowest bits, so distinct keys collide.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Collided(u64);
+impl Hash for Collided {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0 % 4).hash(state);
+    }
+}
+unsafe impl<Id: CollectorId> Collect<Id> for Collided {
+    type Collected<'newgc> = Collided;
+    const NEEDS_COLLECT: bool = false;
+    unsafe fn collect_inplace(_: std::ptr::NonNull<Self>, _: &mut CollectContext<'_, Id>) {}
+}
+
+#[test]
+fn vec_old_versions_unchanged() {
+    GarbageCollector::scoped(|gc| {
+        let mut versions = vec![Vector::new()];
+        for i in 0..100u64 {
+            let next = versions.last().unwrap().push(gc, gc.alloc(i));
+            versions.push(next);
+        }
+        let full = *versions.last().unwrap();
+        let updated = full.set(gc, 40, gc.alloc(1000));
+        let (popped, last) = full.pop(gc).unwrap();
+        assert_eq!(**last, 99);
+        for (len, version) in versions.iter().enumerate() {
+            assert_eq!(contents(version), (0..len as u64).collect::<Vec<_>>());
+        }
+        assert_eq!(**full.get(40).unwrap(), 40);
+        assert_eq!(**updated.get(40).unwrap(), 1000);
+        assert_eq!(updated.len(), 100);
+        assert_eq!(contents(&popped), (0..99).collect::<Vec<_>>());
+    });
+}
+
+#[test]
+fn vec_level_boundaries() {
+    GarbageCollector::scoped(|gc| {
+        // a node holds 32 slots, so these lengths fill a level or spill into a new one
+        for len in [31, 32, 33, 64, 65, 1023, 1024, 1025, 1057] {
+            let mut vec = Vector::new();
+            for i in 0..len {
+                vec = vec.push(gc, gc.alloc(i));
+            }
+            assert_eq!(vec.len(), len as usize);
+            assert_eq!(contents(&vec), (0..len).collect::<Vec<_>>());
+            assert_eq!(**vec.first().unwrap(), 0);
+            assert_eq!(**vec.last().unwrap(), len - 1);
+            assert!(vec.get(len as usize).is_none());
+            let set = vec.set(gc, len as usize - 1, gc.alloc(0));
+            assert_eq!(**set.last().unwrap(), 0);
+            assert_eq!(**vec.last().unwrap(), len - 1);
+            // popping back across the boundary removes the extra level
+            let mut popped = vec;
+            for expected in (0..len).rev() {
+                let (rest, value) = popped.pop(gc).unwrap();
+                assert_eq!(**value, expected);
+                assert_eq!(rest.len(), expected as usize);
+                popped = rest;
+                if expected % 31 == 0 {
+                    assert_eq!(contents(&popped), (0..expected).collect::<Vec<_>>());
+                }
+            }
+            assert!(popped.pop(gc).is_none());
+            // pushing onto a popped vector must not disturb the original
+            let regrown = vec.pop(gc).unwrap().0.push(gc, gc.alloc(7));
+            assert_eq!(**regrown.last().unwrap(), 7);
+            assert_eq!(contents(&vec), (0..len).collect::<Vec<_>>());
+        }
+    });
+}
+
+#[test]
+fn vec_survives_collection() {
+    GarbageCollector::scoped(|gc| {
+        let mut vec = Vector::new();
+        for i in 0..33u64 {
+            vec = vec.push(gc, gc.alloc(i));
+        }
+        let old = gc.root(gc.alloc(vec));
+        let new = gc.root(gc.alloc(vec.set(gc, 32, gc.alloc(100)).push(gc, gc.alloc(33))));
+        gc.force_collect();
+        let old = *old.resolve(gc);
+        let new = *new.resolve(gc);
+        assert_eq!(contents(&old), (0..33).collect::<Vec<_>>());
+        let mut expected: Vec<u64> = (0..34).collect();
+        expected[32] = 100;
+        assert_eq!(contents(&new), expected);
+    });
+}
+
+#[test]
+fn map_old_versions_unchanged() {
+    GarbageCollector::scoped(|gc| {
+        let mut versions = vec![GcPersistentMap::<u64, u64, ScopedId>::new()];
+        for i in 0..100u64 {
+            let next = versions.last().unwrap().insert(gc, i, i * 2);
+            versions.push(next);
+        }
+        let full = versions.last().unwrap().clone();
+        let overwritten = full.insert(gc, 40, 1000);
+        let (removed, value) = full.remove(gc, &40).unwrap();
+        assert_eq!(*value, 80);
+        assert!(removed.remove(gc, &40).is_none());
+        for (len, version) in versions.iter().enumerate() {
+            assert_eq!(version.len(), len);
+            for key in 0..100u64 {
+                let expected = (key < len as u64).then_some(key * 2);
+                assert_eq!(version.get(&key).copied(), expected);
+            }
+        }
+        assert_eq!(overwritten.len(), 100);
+        assert_eq!(overwritten.get(&40), Some(&1000));
+        assert_eq!(removed.len(), 99);
+        assert!(!removed.contains_key(&40));
+        assert_eq!(full.get(&40), Some(&80));
+    });
+}
+
+#[test]
+fn map_node_boundaries() {
+    GarbageCollector::scoped(|gc| {
+        // a node has 32 slots, so these sizes fill the root or force nested nodes
+        for len in [31, 32, 33, 1024, 1025] {
+            let mut map = GcPersistentMap::<u64, u64, ScopedId>::new();
+            for i in 0..len {
+                map = map.insert(gc, i, i);
+            }
+            assert_eq!(map.len(), len as usize);
+            let mut entries: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+            entries.sort();
+            assert_eq!(entries, (0..len).map(|i| (i, i)).collect::<Vec<_>>());
+            let mut shrunk = map.clone();
+            for i in 0..len {
+                let (rest, value) = shrunk.remove(gc, &i).unwrap();
+                assert_eq!(*value, i);
+                shrunk = rest;
+            }
+            assert!(shrunk.is_empty());
+            assert!(shrunk.iter().next().is_none());
+            assert_eq!(map.len(), len as usize);
+            assert!((0..len).all(|i| map.get(&i) == Some(&i)));
+        }
+    });
+}
+
+#[test]
+fn map_hash_collisions() {
+    GarbageCollector::scoped(|gc| {
+        let mut map = GcPersistentMap::<Collided, u64, ScopedId>::new();
+        let mut expected = HashMap::new();
+        for i in 0..40u64 {
+            map = map.insert(gc, Collided(i), i);
+            expected.insert(i, i);
+        }
+        let before = map.clone();
+        for i in (0..40u64).step_by(3) {
+            let (rest, value) = map.remove(gc, &Collided(i)).unwrap();
+            assert_eq!(*value, i);
+            map = rest;
+            expected.remove(&i);
+        }
+        map = map.insert(gc, Collided(1), 100);
+        expected.insert(1, 100);
+        assert_eq!(map.len(), expected.len());
+        for i in 0..40u64 {
+            assert_eq!(map.get(&Collided(i)).copied(), expected.get(&i).copied());
+            assert_eq!(before.get(&Collided(i)), Some(&i));
+        }
+    });
+}
+
+#[test]
+fn map_survives_collection() {
+    GarbageCollector::scoped(|gc| {
+        let mut map = GcPersistentMap::<u64, Gc<'_, u64, ScopedId>, ScopedId>::new();
+        for i in 0..33u64 {
+            map = map.insert(gc, i, gc.alloc(i));
+        }
+        let old = gc.root(gc.alloc(map.clone()));
+        let new = gc.root(gc.alloc(map.remove(gc, &0).unwrap().0.insert(gc, 1, gc.alloc(100))));
+        gc.force_collect();
+        let old = old.resolve(gc);
+        let new = new.resolve(gc);
+        for i in 0..33u64 {
+            assert_eq!(old.get(&i).map(|v| **v), Some(i));
+        }
+        assert!(!new.contains_key(&0));
+        assert_eq!(new.get(&1).map(|v| **v), Some(100));
+        assert_eq!(new.get(&32).map(|v| **v), Some(32));
+    });
+}
@@ -0,0 +1,25 @@
+use zerogc_next::{GarbageCollector, GcObjectHeader};
+
+#[test]
+fn header_from_value_ptr() {
+    GarbageCollector::scoped(|gc| {
+        let value = gc.alloc_pinned(7u64);
+        let raw = value.into_raw();
+        let header = unsafe { GcObjectHeader::<zerogc_next::ScopedId>::from_value_ptr(raw.cast()) };
+        assert!(header.is_pinned());
+        assert!(!header.is_array());
+        assert_eq!(header.value_ptr().as_ptr().cast_const(), raw.cast());
+    });
+}
+
+#[test]
+fn header_from_array_value_ptr() {
+    GarbageCollector::scoped(|gc| {
+        let array = gc.alloc_array_copied(&[1u32, 2, 3]);
+        let raw = array.as_ptr().as_ptr().cast_const();
+        let header =
+            unsafe { GcObjectHeader::<zerogc_next::ScopedId>::from_array_value_ptr(raw.cast()) };
+        assert!(header.is_array());
+        assert_eq!(header.value_ptr().as_ptr().cast_const(), raw.cast());
+    });
+}
@@ -0,0 +1,90 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use zerogc_next::{Collect, CollectContext, CollectorId, GarbageCollector};
+
+struct Dropper(Rc<Cell<u32>>);
+impl Drop for Dropper {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+unsafe impl<Id: CollectorId> Collect<Id> for Dropper {
+    type Collected<'newgc> = Dropper;
+    const NEEDS_COLLECT: bool = false;
+    unsafe fn collect_inplace(_: std::ptr::NonNull<Self>, _: &mut CollectContext<'_, Id>) {}
+}
+
+#[test]
+fn regions() {
+    let drops = Rc::new(Cell::new(0));
+    GarbageCollector::scoped(|gc| {
+        let region = gc.create_region();
+        let other = gc.create_region();
+        let kept = gc.alloc(Dropper(drops.clone()));
+        gc.in_region(region, || {
+            for _ in 0..10 {
+                gc.alloc(Dropper(drops.clone()));
+                gc.alloc(5u64);
+            }
+            gc.in_region(other, || gc.alloc(Dropper(drops.clone())));
+        });
+        assert!(gc.region_allocated_bytes(region) > 0);
+        assert!(gc.region_allocated_bytes(other) > 0);
+        let h = gc.root(kept);
+        unsafe { gc.discard_region(region) };
+        assert_eq!(drops.get(), 10);
+        assert_eq!(gc.region_allocated_bytes(region), 0);
+        // the region can be reused
+        let survivor = gc.in_region(region, || gc.alloc(7u64));
+        let sh = gc.root(survivor);
+        gc.force_collect();
+        assert_eq!(drops.get(), 11);
+        assert_eq!(gc.region_allocated_bytes(region), 0);
+        assert_eq!(*sh.resolve(gc), 7);
+        let _ = h.resolve(gc);
+        let pinned = gc.in_region(other, || gc.pin(gc.alloc(9u32)));
+        gc.force_collect();
+        assert_eq!(*pinned.resolve(gc), 9);
+    });
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "rooted")]
+fn discard_rooted() {
+    GarbageCollector::scoped(|gc| {
+        let region = gc.create_region();
+        let h = gc.in_region(region, || gc.root(gc.alloc(1u64)));
+        unsafe { gc.discard_region(region) };
+        drop(h);
+    });
+}
+
+#[test]
+fn discard_forgets_side_tables() {
+    use zerogc_next::{GcWeakMap, Guardian};
+    let finalized = Rc::new(Cell::new(false));
+    GarbageCollector::scoped(|gc| {
+        let region = gc.create_region();
+        let map = GcWeakMap::<u64, u64, _>::new(gc);
+        let guardian = Guardian::<u64, _>::new(gc);
+        let weak = gc.in_region(region, || {
+            let obj = gc.alloc(1u64);
+            map.insert(gc, obj, 2);
+            guardian.register(gc, obj);
+            let f = finalized.clone();
+            gc.add_finalizer(obj, move |_| f.set(true));
+            gc.root(obj).downgrade(gc)
+        });
+        assert!(!weak.is_cleared());
+        assert_eq!(map.len(), 1);
+        unsafe { gc.discard_region(region) };
+        assert!(weak.is_cleared());
+        assert_eq!(map.len(), 0);
+        assert_eq!(guardian.registered(), 0);
+        gc.force_collect();
+        assert!(guardian.pop(gc).is_none());
+        assert_eq!(gc.run_pending_finalizers(), 1);
+    });
+    assert!(finalized.get());
+}
@@ -0,0 +1,120 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use zerogc_next::{Collect, CollectContext, CollectionReason, CollectorId, GarbageCollector};
+
+struct Dropper(Rc<Cell<u32>>);
+impl Drop for Dropper {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+unsafe impl<Id: CollectorId> Collect<Id> for Dropper {
+    type Collected<'newgc> = Dropper;
+    const NEEDS_COLLECT: bool = false;
+    unsafe fn collect_inplace(_: std::ptr::NonNull<Self>, _: &mut CollectContext<'_, Id>) {}
+}
+
+#[test]
+fn scoped_young() {
+    let drops = Rc::new(Cell::new(0));
+    GarbageCollector::scoped(|gc| {
+        let outer = gc.root(gc.alloc(3u64));
+        let before = gc.stats_snapshot();
+        let (kept, n) = gc.scoped_young(|gc| {
+            for _ in 0..20 {
+                gc.alloc(Dropper(drops.clone()));
+            }
+            let k = gc.alloc(Dropper(drops.clone()));
+            (gc.root(k), *outer.resolve(gc))
+        });
+        assert_eq!(n, 3);
+        assert_eq!(drops.get(), 20);
+        let _ = kept.resolve(gc);
+        let delta = gc.stats_delta(&before);
+        assert_eq!(delta.collections_by_reason.get(CollectionReason::ScopeExit), 1);
+        // nothing allocated, no collection
+        gc.scoped_young(|_| ());
+        assert_eq!(gc.stats_delta(&before).collections_by_reason.get(CollectionReason::ScopeExit), 1);
+        drop(kept);
+        gc.force_collect();
+        assert_eq!(drops.get(), 21);
+        assert_eq!(*outer.resolve(gc), 3);
+    });
+}
+
+type Slot<'gc, Id> = zerogc_next::GcCell<zerogc_next::Gc<'gc, Dropper, Id>>;
+
+#[test]
+fn scoped_young_evacuates_region_only() {
+    let drops = Rc::new(Cell::new(0));
+    let uncounted = Rc::new(Cell::new(0));
+    GarbageCollector::scoped(|gc| {
+        // garbage outside the region, which a full collection would free
+        gc.alloc(Dropper(drops.clone()));
+        let placeholder = gc.alloc(Dropper(uncounted.clone()));
+        let slot = gc.root(gc.alloc(Slot::new(placeholder)));
+        let before = gc.stats_snapshot();
+        let (pinned_holder, weak_dead, weak_live) = gc.scoped_young(|gc| {
+            let slot = slot.resolve(gc);
+            let stored = gc.alloc(Dropper(drops.clone()));
+            zerogc_next::GcCell::set(&slot, gc, stored);
+            // a new object outside the region refers into it
+            let held = gc.alloc(Dropper(drops.clone()));
+            let holder = gc.alloc_pinned(Slot::new(held));
+            let dead = gc.alloc(Dropper(drops.clone()));
+            (gc.root(holder), gc.root(dead).downgrade(gc), gc.root(stored).downgrade(gc))
+        });
+        assert_eq!(drops.get(), 1, "only the dead region object is freed");
+        assert!(weak_dead.is_cleared());
+        assert!(!weak_live.is_cleared());
+        let delta = gc.stats_delta(&before);
+        assert_eq!(delta.collections_by_reason.get(CollectionReason::ScopeExit), 1);
+        let stored = slot.resolve(gc).get();
+        assert!(std::ptr::eq(&*stored, &*weak_live.resolve(gc).unwrap()));
+        let _ = pinned_holder.resolve(gc).get();
+        // evacuated objects are collected normally afterwards
+        drop(pinned_holder);
+        let placeholder = gc.alloc(Dropper(uncounted.clone()));
+        zerogc_next::GcCell::set(&slot.resolve(gc), gc, placeholder);
+        gc.force_collect();
+        assert_eq!(drops.get(), 4);
+        assert!(weak_live.is_cleared());
+    });
+}
+
+#[test]
+fn scoped_young_pinned_falls_back() {
+    let drops = Rc::new(Cell::new(0));
+    GarbageCollector::scoped(|gc| {
+        gc.alloc(Dropper(drops.clone()));
+        let pin = gc.scoped_young(|gc| gc.pin(gc.alloc(Dropper(drops.clone()))));
+        // the entire heap was collected
+        assert_eq!(drops.get(), 1);
+        drop(pin);
+        gc.force_collect();
+        assert_eq!(drops.get(), 2);
+    });
+}
+
+#[test]
+fn scoped_young_nested_collection() {
+    let drops = Rc::new(Cell::new(0));
+    let uncounted = Rc::new(Cell::new(0));
+    GarbageCollector::scoped(|gc| {
+        let placeholder = gc.alloc(Dropper(uncounted.clone()));
+        let slot = gc.root(gc.alloc(Slot::new(placeholder)));
+        let kept = gc.scoped_young(|gc| {
+            let first = gc.root(gc.alloc(Dropper(drops.clone())));
+            unsafe { gc.force_collect_shared() };
+            let second = gc.alloc(Dropper(drops.clone()));
+            zerogc_next::GcCell::set(&slot.resolve(gc), gc, second);
+            gc.alloc(Dropper(drops.clone()));
+            first
+        });
+        assert_eq!(drops.get(), 1);
+        let _ = kept.resolve(gc);
+        let _ = slot.resolve(gc).get();
+        gc.force_collect();
+        assert_eq!(drops.get(), 1);
+    });
+}
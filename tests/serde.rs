@@ -0,0 +1,146 @@
+#![cfg(feature = "serde")]
+use std::ptr::NonNull;
+
+use serde::de::{SeqAccess, Visitor};
+use serde::{Deserializer, Serialize, Serializer};
+use zerogc_next::serde::{GcDeserialize, GcDeserializeContext, SerializeShared};
+use zerogc_next::{Collect, CollectContext, GarbageCollector, Gc, GcRefCell, ScopedId};
+
+type LinkPtr<'gc> = Gc<'gc, GcRefCell<Link<'gc>>, ScopedId>;
+
+/// A node in a linked list, whose successor can be changed to form a cycle.
+struct Link<'gc> {
+    value: u64,
+    next: Option<LinkPtr<'gc>>,
+}
+unsafe impl<'gc> Collect<ScopedId> for Link<'gc> {
+    type Collected<'newgc> = Link<'newgc>;
+    const NEEDS_COLLECT: bool = true;
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, ScopedId>) {
+        context.trace_option(NonNull::from(&mut (*target.as_ptr()).next));
+    }
+}
+impl Serialize for Link<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.value, self.next).serialize(serializer)
+    }
+}
+impl<'gc, 'de> GcDeserialize<'gc, 'de, ScopedId> for Link<'gc> {
+    fn deserialize_gc<D: Deserializer<'de>>(
+        context: &GcDeserializeContext<'gc, ScopedId>,
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        struct LinkVisitor<'a, 'gc>(&'a GcDeserializeContext<'gc, ScopedId>);
+        impl<'de, 'gc> Visitor<'de> for LinkVisitor<'_, 'gc> {
+            type Value = Link<'gc>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a link")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Link<'gc>, A::Error> {
+                let value = seq.next_element()?.unwrap();
+                let next = seq.next_element_seed(self.0.seed())?.unwrap();
+                Ok(Link { value, next })
+            }
+        }
+        deserializer.deserialize_tuple(2, LinkVisitor(context))
+    }
+}
+
+fn link<'gc>(
+    gc: &'gc GarbageCollector<ScopedId>,
+    value: u64,
+    next: Option<LinkPtr<'gc>>,
+) -> LinkPtr<'gc> {
+    gc.alloc(GcRefCell::new(Link { value, next }))
+}
+
+fn values(mut link: Option<LinkPtr<'_>>, limit: usize) -> Vec<u64> {
+    let mut result = Vec::new();
+    while let Some(current) = link {
+        if result.len() == limit {
+            break;
+        }
+        result.push(current.borrow().value);
+        link = current.borrow().next;
+    }
+    result
+}
+
+#[test]
+fn roundtrip() {
+    GarbageCollector::scoped(|gc| {
+        let list = link(gc, 1, Some(link(gc, 2, Some(link(gc, 3, None)))));
+        let json = serde_json::to_string(&list).unwrap();
+        assert_eq!(json, "[1,[2,[3,null]]]");
+        let context = GcDeserializeContext::new(gc);
+        let numbers: Gc<'_, Vec<u64>, ScopedId> = context
+            .deserialize(&mut serde_json::Deserializer::from_str("[4,5,6]"))
+            .unwrap();
+        assert_eq!(*numbers, vec![4, 5, 6]);
+        let copy: LinkPtr<'_> = context
+            .deserialize(&mut serde_json::Deserializer::from_str(&json))
+            .unwrap();
+        let copy = gc.root(copy);
+        // the copy is only reachable through its root
+        gc.force_collect();
+        assert_eq!(values(Some(copy.resolve(gc)), 10), vec![1, 2, 3]);
+    });
+}
+
+#[test]
+fn shared_pointers() {
+    GarbageCollector::scoped(|gc| {
+        let tail = link(gc, 3, None);
+        let lists = vec![link(gc, 1, Some(tail)), link(gc, 2, Some(tail)), tail];
+        let shared = serde_json::to_string(&SerializeShared(&lists)).unwrap();
+        assert_eq!(shared, "[[0,[1,[1,[3,null]]]],[2,[2,[1,null]]],[1,null]]");
+        // without preserving sharing, each pointer gets its own copy
+        let unshared = serde_json::to_string(&lists).unwrap();
+        let copy: Vec<LinkPtr<'_>> = GcDeserializeContext::new(gc)
+            .deserialize(&mut serde_json::Deserializer::from_str(&unshared))
+            .unwrap();
+        assert_eq!(values(Some(copy[0]), 10), vec![1, 3]);
+        assert!(!std::ptr::eq(&*copy[0].borrow().next.unwrap(), &*copy[2]));
+        let context = GcDeserializeContext::new(gc).with_preserve_shared(true);
+        let copy: Vec<LinkPtr<'_>> = context
+            .deserialize(&mut serde_json::Deserializer::from_str(&shared))
+            .unwrap();
+        let copy = gc.root(gc.alloc(copy));
+        gc.force_collect();
+        let copy = copy.resolve(gc);
+        assert_eq!(values(Some(copy[0]), 10), vec![1, 3]);
+        assert_eq!(values(Some(copy[1]), 10), vec![2, 3]);
+        let tail = copy[2];
+        assert!(std::ptr::eq(&*copy[0].borrow().next.unwrap(), &*tail));
+        assert!(std::ptr::eq(&*copy[1].borrow().next.unwrap(), &*tail));
+    });
+}
+
+#[test]
+fn cycle_rejected() {
+    GarbageCollector::scoped(|gc| {
+        let first = link(gc, 1, None);
+        let second = link(gc, 2, Some(first));
+        GcRefCell::borrow_mut(&first, gc).next = Some(second);
+        assert_eq!(values(Some(first), 4), vec![1, 2, 1, 2]);
+        // the back edge is written as a reference, so serializing terminates
+        let shared = serde_json::to_string(&SerializeShared(&first)).unwrap();
+        assert_eq!(shared, "[0,[1,[1,[2,[0,null]]]]]");
+        let context = GcDeserializeContext::new(gc).with_preserve_shared(true);
+        let error = context
+            .deserialize::<LinkPtr<'_>, _>(&mut serde_json::Deserializer::from_str(&shared))
+            .err()
+            .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("Cyclic reference to shared object 0"),
+            "{error}"
+        );
+        // a borrowed cell cannot be serialized
+        let _borrow = GcRefCell::borrow_mut(&second, gc);
+        assert!(serde_json::to_string(&first).is_err());
+    });
+}
@@ -0,0 +1,77 @@
+use zerogc_next::{GarbageCollector, GcConfig};
+
+#[test]
+fn should_collect_tracks_pending() {
+    GarbageCollector::scoped(|gc| {
+        assert!(!gc.should_collect());
+        let mut n = 0;
+        while gc.pending_collection_reason().is_none() {
+            gc.alloc(n as u64);
+            n += 1;
+            assert!(n < 1_000_000);
+        }
+        assert!(gc.should_collect());
+        gc.collect();
+        assert!(gc.pending_collection_reason().is_none());
+        assert!(!gc.should_collect());
+        for _ in 0..10 {
+            while !gc.should_collect() {
+                assert!(gc.pending_collection_reason().is_none());
+                gc.alloc(1u64);
+            }
+            gc.collect();
+            assert!(!gc.should_collect());
+        }
+    });
+}
+
+#[test]
+fn should_collect_trigger() {
+    GarbageCollector::scoped_with_config(GcConfig::default().with_collect_trigger(256), |gc| {
+        assert!(!gc.should_collect());
+        for i in 0..64u64 {
+            gc.alloc(i);
+        }
+        assert!(gc.collection_recommended());
+        assert!(gc.should_collect());
+        gc.collect();
+        assert!(!gc.should_collect());
+    });
+}
+
+#[test]
+fn should_collect_pinned_and_immortal() {
+    GarbageCollector::scoped(|gc| {
+        let mut n = 0;
+        while gc.pending_collection_reason().is_none() {
+            gc.alloc_pinned(n as u64);
+            n += 1;
+            assert!(n < 1_000_000);
+        }
+        assert!(gc.should_collect());
+        gc.collect();
+        assert!(!gc.should_collect());
+    });
+    GarbageCollector::scoped_with_config(GcConfig::default().with_collect_trigger(256), |gc| {
+        for i in 0..64u64 {
+            gc.alloc_immortal(i);
+        }
+        assert!(gc.collection_recommended());
+        assert!(gc.should_collect());
+    });
+}
+
+#[test]
+fn heap_size_shrinks_after_collect() {
+    GarbageCollector::scoped_with_config(GcConfig::default().with_heap_limit(1 << 20), |gc| {
+        let headroom = gc.heap_headroom().unwrap();
+        for _ in 0..100 {
+            for i in 0..100u64 {
+                gc.alloc(i);
+            }
+            gc.force_collect();
+        }
+        // the eden arena is emptied by each collection, so its bytes must not accumulate
+        assert_eq!(gc.heap_headroom(), Some(headroom));
+    });
+}
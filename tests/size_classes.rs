@@ -0,0 +1,57 @@
+use zerogc_next::{GarbageCollector, GcConfig};
+
+#[test]
+fn free_blocks_are_reused() {
+    let config = GcConfig::default().with_survivor_age_threshold(0);
+    GarbageCollector::scoped_with_config(config, |gc| {
+        let stats = gc.old_size_class_stats();
+        assert!(stats.windows(2).all(|w| w[0].block_size < w[1].block_size));
+        let roots: Vec<_> = (0..100u64)
+            .map(|i| gc.root(gc.alloc(i.to_string())))
+            .collect();
+        gc.force_collect(); // promote
+        let objects: usize = gc.old_size_class_stats().iter().map(|c| c.objects).sum();
+        assert!(objects >= 100);
+        drop(roots);
+        gc.force_collect();
+        let free: usize = gc
+            .old_size_class_stats()
+            .iter()
+            .map(|c| c.free_blocks)
+            .sum();
+        assert!(free >= 100);
+        // pinned objects are allocated directly in the old generation
+        let pinned: Vec<_> = (0..50u64).map(|i| gc.alloc_pinned(i.to_string())).collect();
+        for (i, p) in pinned.iter().enumerate() {
+            assert_eq!(**p, i.to_string());
+        }
+        let reused: u64 = gc.old_size_class_stats().iter().map(|c| c.reused).sum();
+        assert!(reused >= 50);
+        // compaction returns every free block to the allocator
+        gc.compact();
+        let free: usize = gc
+            .old_size_class_stats()
+            .iter()
+            .map(|c| c.free_blocks)
+            .sum();
+        assert_eq!(free, 0);
+    });
+}
+
+#[test]
+fn sweep_trims_free_blocks() {
+    let config = GcConfig::default().with_survivor_age_threshold(0);
+    GarbageCollector::scoped_with_config(config, |gc| {
+        let roots: Vec<_> = (0..20_000u64).map(|i| gc.root(gc.alloc(i))).collect();
+        gc.force_collect(); // promote
+        drop(roots);
+        gc.force_collect();
+        for class in gc.old_size_class_stats() {
+            let page_blocks = zerogc_next::context::OldPage::SIZE / class.block_size;
+            assert!(
+                class.free_blocks <= class.objects.max(page_blocks),
+                "{class:?}"
+            );
+        }
+    });
+}
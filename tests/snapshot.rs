@@ -0,0 +1,132 @@
+use std::cell::Cell;
+use std::ptr::NonNull;
+
+use zerogc_next::context::{SnapshotDecoder, SnapshotEncoder};
+use zerogc_next::{
+    Collect, CollectContext, GarbageCollector, Gc, ScopedId, SnapshotError, SnapshotType,
+};
+
+/// A node in a graph, whose edge can be redirected to form a cycle.
+struct Node<'gc> {
+    value: u64,
+    name: String,
+    next: Cell<Option<Gc<'gc, Node<'gc>, ScopedId>>>,
+}
+unsafe impl<'gc> Collect<ScopedId> for Node<'gc> {
+    type Collected<'newgc> = Node<'newgc>;
+    const NEEDS_COLLECT: bool = true;
+    unsafe fn collect_inplace(target: NonNull<Self>, context: &mut CollectContext<'_, ScopedId>) {
+        context.trace_option(NonNull::new_unchecked((*target.as_ptr()).next.as_ptr()));
+    }
+}
+impl SnapshotType<ScopedId> for Node<'static> {
+    const TYPE_NAME: &'static str = "Node";
+
+    fn encode<'gc>(
+        value: &Node<'gc>,
+        encoder: &mut SnapshotEncoder<'_, 'gc, ScopedId>,
+    ) -> Result<(), SnapshotError> {
+        encoder.write_u64(value.value);
+        encoder.write_str(&value.name);
+        match value.next.get() {
+            None => encoder.write_u64(0),
+            Some(next) => {
+                encoder.write_u64(1);
+                encoder.write_gc::<Node<'static>>(next)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn decode<'gc>(
+        decoder: &mut SnapshotDecoder<'_, 'gc, ScopedId>,
+    ) -> Result<Node<'gc>, SnapshotError> {
+        let value = decoder.read_u64()?;
+        let name = decoder.read_str()?.to_owned();
+        let next = match decoder.read_u64()? {
+            0 => None,
+            _ => Some(decoder.read_gc::<Node<'static>>()?),
+        };
+        Ok(Node {
+            value,
+            name,
+            next: Cell::new(next),
+        })
+    }
+}
+
+fn node<'gc>(
+    gc: &'gc GarbageCollector<ScopedId>,
+    value: u64,
+    name: &str,
+    next: Option<Gc<'gc, Node<'gc>, ScopedId>>,
+) -> Gc<'gc, Node<'gc>, ScopedId> {
+    gc.alloc(Node {
+        value,
+        name: name.into(),
+        next: Cell::new(next),
+    })
+}
+
+#[test]
+fn roundtrip_shared() {
+    let bytes = GarbageCollector::scoped(|gc| {
+        gc.register_snapshot_type::<Node<'static>>().unwrap();
+        let shared = node(gc, 1, "shared", None);
+        let b = gc.root(node(gc, 2, "b", Some(shared)));
+        let c = gc.root(node(gc, 3, "c", Some(shared)));
+        gc.register_snapshot_root("b", b);
+        gc.register_snapshot_root("c", c);
+        gc.register_snapshot_root("also shared", gc.root(shared));
+        gc.force_collect();
+        let mut out = Vec::new();
+        gc.snapshot(&mut out).unwrap();
+        out
+    });
+    GarbageCollector::scoped(|gc| {
+        assert!(matches!(
+            gc.restore(&mut &bytes[..]),
+            Err(SnapshotError::UnregisteredType { .. })
+        ));
+        gc.register_snapshot_type::<Node<'static>>().unwrap();
+        gc.restore(&mut &bytes[..]).unwrap();
+        // the restored objects are only reachable through the snapshot roots
+        gc.force_collect();
+        let b = gc.snapshot_root::<Node<'static>>("b").unwrap().resolve(gc);
+        let c = gc.snapshot_root::<Node<'static>>("c").unwrap().resolve(gc);
+        let shared = gc
+            .snapshot_root::<Node<'static>>("also shared")
+            .unwrap()
+            .resolve(gc);
+        assert_eq!((b.value, &*b.name, c.value, &*c.name), (2, "b", 3, "c"));
+        assert_eq!((shared.value, &*shared.name), (1, "shared"));
+        assert!(shared.next.get().is_none());
+        // the shared object is restored once, and both nodes still point to it
+        assert!(std::ptr::eq(&*b.next.get().unwrap(), &*shared));
+        assert!(std::ptr::eq(&*c.next.get().unwrap(), &*shared));
+        assert!(gc.snapshot_root::<Node<'static>>("missing").is_none());
+        assert!(matches!(
+            gc.restore(&mut &bytes[..bytes.len() - 3]),
+            Err(SnapshotError::Io(_))
+        ));
+    });
+}
+
+#[test]
+fn cycle_rejected() {
+    GarbageCollector::scoped(|gc| {
+        gc.register_snapshot_type::<Node<'static>>().unwrap();
+        let first = node(gc, 1, "first", None);
+        let second = node(gc, 2, "second", Some(first));
+        // both objects are young, so no write barrier is needed
+        first.next.set(Some(second));
+        gc.register_snapshot_root("first", gc.root(first));
+        let mut out = Vec::new();
+        assert!(matches!(gc.snapshot(&mut out), Err(SnapshotError::Cycle)));
+        assert!(out.is_empty());
+        // breaking the cycle allows the graph to be saved
+        second.next.set(None);
+        gc.snapshot(&mut out).unwrap();
+        assert!(!out.is_empty());
+    });
+}
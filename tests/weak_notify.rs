@@ -0,0 +1,50 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use zerogc_next::GarbageCollector;
+
+#[test]
+fn notified_once() {
+    GarbageCollector::scoped(|gc| {
+        let hits = Rc::new(Cell::new(0));
+        let alive = gc.root(gc.alloc(5u32));
+        let other = gc.root(gc.alloc(6u32));
+        let alive_hits = hits.clone();
+        let weak = alive.downgrade(gc).with_notifier(gc, move |gc| {
+            // notifications may allocate
+            gc.alloc(1u8);
+            alive_hits.set(alive_hits.get() + 1)
+        });
+        let dropped_hits = hits.clone();
+        let dropped = other
+            .downgrade(gc)
+            .with_notifier(gc, move |_| dropped_hits.set(dropped_hits.get() + 100));
+        gc.force_collect();
+        assert_eq!(gc.run_pending_finalizers(), 0);
+        assert_eq!(weak.resolve(gc).map(|v| *v), Some(5));
+        drop(alive);
+        drop(other);
+        drop(dropped);
+        gc.force_collect();
+        assert!(weak.is_cleared());
+        assert!(weak.upgrade(gc).is_none());
+        // the notification of a dropped handle is skipped when delivered
+        assert_eq!(gc.pending_finalizers(), 2);
+        gc.run_pending_finalizers();
+        assert_eq!(hits.get(), 1);
+        // a second collection must not notify again
+        gc.force_collect();
+        assert_eq!(gc.pending_finalizers(), 0);
+        assert_eq!(gc.run_pending_finalizers(), 0);
+        assert_eq!(hits.get(), 1);
+        // registering on a cleared handle queues the notification immediately
+        let late_hits = hits.clone();
+        let weak = weak.with_notifier(gc, move |_| late_hits.set(late_hits.get() + 10));
+        assert_eq!(gc.pending_finalizers(), 1);
+        gc.run_pending_finalizers();
+        assert_eq!(hits.get(), 11);
+        gc.force_collect();
+        assert_eq!(gc.run_pending_finalizers(), 0);
+        drop(weak);
+    });
+}